@@ -1,11 +1,12 @@
-use async_graphql::{EmptySubscription, Schema};
+use async_graphql::Schema;
 use reqwest_middleware::ClientWithMiddleware;
 use tinyboards_db::{models::auth::Secret, utils::DbPool};
 use tinyboards_utils::{
+    rate_limit::RateLimitCell,
     settings::{structs::Settings, SETTINGS},
 };
 
-use crate::{Mutation, Query, storage::StorageBackend};
+use crate::{events::EventBus, storage::StorageBackend, subscriptions::Subscription, Mutation, Query};
 
 /// The global context for the application
 pub struct TinyBoardsContext {
@@ -14,8 +15,9 @@ pub struct TinyBoardsContext {
     settings: Settings,
     master_key: Secret,
     storage: StorageBackend,
-    //rate_limit_cell: RateLimitCell,
-    schema: Schema<Query, Mutation, EmptySubscription>,
+    events: EventBus,
+    rate_limit_cell: RateLimitCell,
+    schema: Schema<Query, Mutation, Subscription>,
 }
 
 impl TinyBoardsContext {
@@ -25,8 +27,9 @@ impl TinyBoardsContext {
         settings: Settings,
         master_key: Secret,
         storage: StorageBackend,
-        //rate_limit_cell: RateLimitCell,
-        schema: Schema<Query, Mutation, EmptySubscription>,
+        events: EventBus,
+        rate_limit_cell: RateLimitCell,
+        schema: Schema<Query, Mutation, Subscription>,
     ) -> TinyBoardsContext {
         TinyBoardsContext {
             pool,
@@ -34,7 +37,8 @@ impl TinyBoardsContext {
             settings,
             master_key,
             storage,
-            //rate_limit_cell,
+            events,
+            rate_limit_cell,
             schema,
         }
     }
@@ -59,11 +63,15 @@ impl TinyBoardsContext {
         &self.storage
     }
 
-    /*pub fn rate_limit_cell(&self) -> &RateLimitCell {
-        &&self.rate_limit_cell
-    }*/
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub fn rate_limit_cell(&self) -> &RateLimitCell {
+        &self.rate_limit_cell
+    }
 
-    pub fn schema(&self) -> &Schema<Query, Mutation, EmptySubscription> {
+    pub fn schema(&self) -> &Schema<Query, Mutation, Subscription> {
         &self.schema
     }
 }
@@ -76,7 +84,8 @@ impl Clone for TinyBoardsContext {
             settings: self.settings.clone(),
             master_key: self.master_key.clone(),
             storage: self.storage.clone(),
-            //rate_limit_cell: self.rate_limit_cell.clone(),
+            events: self.events.clone(),
+            rate_limit_cell: self.rate_limit_cell.clone(),
             schema: self.schema.clone(),
         }
     }
@@ -0,0 +1,146 @@
+use async_graphql::Context;
+use futures::stream::{self, Stream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+/// How many events a slow subscriber may fall behind before it starts skipping.
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// Something that happened which live subscribers may care about.
+///
+/// Events only carry ids; subscription resolvers load the current state from the
+/// database so clients never see data they wouldn't get from a normal query.
+#[derive(Clone, Debug)]
+pub enum LiveEvent {
+    NotificationCreated {
+        recipient_id: Uuid,
+        notification_id: Uuid,
+    },
+    MessageSent {
+        recipient_id: Uuid,
+        message_id: Uuid,
+    },
+    /// Notifications or messages were marked read or deleted by `user_id`.
+    UnreadCountsChanged {
+        user_id: Uuid,
+    },
+    CommentCreated {
+        post_id: Uuid,
+        comment_id: Uuid,
+    },
+    /// Votes or reactions on a post, or on one of its comments, changed.
+    ScoreChanged {
+        post_id: Uuid,
+        comment_id: Option<Uuid>,
+    },
+    /// Background processing of an upload finished, successfully or not.
+    UploadProcessed {
+        user_id: Uuid,
+        upload_id: Uuid,
+    },
+}
+
+/// In-process fan-out of [`LiveEvent`]s from mutations to GraphQL subscriptions.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<LiveEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: LiveEvent) {
+        // An error only means nobody is subscribed right now.
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> impl Stream<Item = LiveEvent> + Send + 'static {
+        stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Live event subscriber lagged, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+/// Publish an event from a resolver. Schemas built without an event bus
+/// (e.g. in tests) simply drop it.
+pub(crate) fn publish(ctx: &Context<'_>, event: LiveEvent) {
+    if let Some(events) = ctx.data_opt::<EventBus>() {
+        events.publish(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, StreamExt};
+
+    fn comment_created(post_id: Uuid) -> LiveEvent {
+        LiveEvent::CommentCreated {
+            post_id,
+            comment_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn test_every_subscriber_gets_every_event() {
+        let events = EventBus::new();
+        let mut first = Box::pin(events.subscribe());
+        let mut second = Box::pin(events.subscribe());
+        let post_ids = [Uuid::new_v4(), Uuid::new_v4()];
+
+        for post_id in post_ids {
+            events.publish(comment_created(post_id));
+        }
+
+        for subscriber in [&mut first, &mut second] {
+            for expected in post_ids {
+                assert!(matches!(
+                    block_on(subscriber.next()),
+                    Some(LiveEvent::CommentCreated { post_id, .. }) if post_id == expected
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_subscribers_only_see_events_published_after_subscribing() {
+        let events = EventBus::new();
+        // Nobody is listening yet, so this is dropped rather than buffered.
+        events.publish(comment_created(Uuid::new_v4()));
+
+        let mut late = Box::pin(events.subscribe());
+        let post_id = Uuid::new_v4();
+        events.publish(comment_created(post_id));
+
+        assert!(matches!(
+            block_on(late.next()),
+            Some(LiveEvent::CommentCreated { post_id: seen, .. }) if seen == post_id
+        ));
+    }
+
+    #[test]
+    fn test_stream_ends_when_the_bus_is_dropped() {
+        let events = EventBus::new();
+        let mut subscriber = Box::pin(events.subscribe());
+        drop(events);
+
+        assert!(block_on(subscriber.next()).is_none());
+    }
+}
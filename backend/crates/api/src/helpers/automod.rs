@@ -0,0 +1,454 @@
+//! Runs AutoModerator rules on new posts. Rule matching itself lives in
+//! `tinyboards_utils::automod`; this module loads what the rules look at
+//! and carries out their actions as the AutoModerator account.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use std::collections::{hash_map::Entry, HashMap};
+use tinyboards_db::{
+    enums::{DbApprovalStatus, DbFlairType, DbModerationAction, DbPostType, DbReportStatus},
+    models::{
+        comment::comments::{CommentInsertForm, CommentUpdateForm},
+        flair::PostFlairInsertForm,
+        board::board_mods::ModPerms,
+        moderator::{
+            automod_rule::{AutomodRule as DbAutomodRule, AUTOMOD_USER_ID},
+            moderation_log::ModerationLogInsertForm,
+        },
+        post::{
+            post_report::PostReportInsertForm,
+            posts::{Post as DbPost, PostUpdateForm},
+        },
+        user::user::{AdminPerms, User},
+    },
+    schema::{
+        automod_rules, comments, flair_templates, moderation_log, post_flairs, post_reports,
+        posts, user_aggregates, user_flairs, users,
+    },
+    utils::DbPool,
+};
+use tinyboards_utils::{
+    automod::{AutomodActions, AutomodConditions, AutomodSubject},
+    settings::structs::Settings,
+    slug::generate_slug,
+    TinyBoardsError,
+};
+use uuid::Uuid;
+
+use crate::events::EventBus;
+use crate::helpers::{notifications::create_post_reply_notification, validation::require_mod_or_admin};
+
+/// A stored rule with its conditions and actions parsed.
+pub struct ParsedRule {
+    pub rule: DbAutomodRule,
+    pub conditions: AutomodConditions,
+    pub actions: AutomodActions,
+}
+
+impl From<DbAutomodRule> for ParsedRule {
+    fn from(rule: DbAutomodRule) -> Self {
+        Self {
+            conditions: serde_json::from_value(rule.conditions.clone()).unwrap_or_default(),
+            actions: serde_json::from_value(rule.actions.clone()).unwrap_or_default(),
+            rule,
+        }
+    }
+}
+
+/// What rules know about a post's author.
+pub struct Author {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub reputation: i64,
+    pub flair_ids: Vec<Uuid>,
+}
+
+/// Check that the user can manage a board's rules, or the site-wide rules
+/// when `board_id` is `None`.
+pub async fn require_rule_manager(
+    user: &User,
+    pool: &DbPool,
+    board_id: Option<Uuid>,
+) -> Result<(), TinyBoardsError> {
+    match board_id {
+        Some(board_id) => {
+            require_mod_or_admin(user, pool, board_id, ModPerms::Config, Some(AdminPerms::Content)).await
+        }
+        None if user.has_permission(AdminPerms::Content) => Ok(()),
+        None => Err(TinyBoardsError::from_message(
+            403,
+            "Only admins can manage site-wide AutoModerator rules",
+        )),
+    }
+}
+
+/// Enabled rules for posts in a board: site-wide rules first, then the
+/// board's own, each in display order.
+pub async fn enabled_rules(
+    conn: &mut diesel_async::AsyncPgConnection,
+    board_id: Uuid,
+) -> Result<Vec<ParsedRule>, TinyBoardsError> {
+    let rules: Vec<DbAutomodRule> = automod_rules::table
+        .filter(automod_rules::is_enabled.eq(true))
+        .filter(
+            automod_rules::board_id
+                .is_null()
+                .or(automod_rules::board_id.eq(board_id)),
+        )
+        .order((
+            automod_rules::board_id.is_not_null(),
+            automod_rules::display_order,
+            automod_rules::created_at,
+        ))
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(rules.into_iter().map(ParsedRule::from).collect())
+}
+
+pub async fn load_author(
+    conn: &mut diesel_async::AsyncPgConnection,
+    user_id: Uuid,
+    board_id: Uuid,
+) -> Result<Author, TinyBoardsError> {
+    let (name, created_at): (String, DateTime<Utc>) = users::table
+        .find(user_id)
+        .select((users::name, users::created_at))
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Author not found".into()))?;
+
+    let reputation = user_aggregates::table
+        .filter(user_aggregates::user_id.eq(user_id))
+        .select(user_aggregates::post_score + user_aggregates::comment_score)
+        .first::<i64>(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+        .unwrap_or(0);
+
+    let flair_ids: Vec<Uuid> = user_flairs::table
+        .filter(user_flairs::user_id.eq(user_id))
+        .filter(user_flairs::board_id.eq(board_id))
+        .filter(user_flairs::is_approved.eq(true))
+        .select(user_flairs::flair_template_id)
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(Author {
+        name,
+        created_at,
+        reputation,
+        flair_ids,
+    })
+}
+
+fn post_type_str(post_type: DbPostType) -> &'static str {
+    match post_type {
+        DbPostType::Text => "text",
+        DbPostType::Link => "link",
+        DbPostType::Image => "image",
+        DbPostType::Video => "video",
+    }
+}
+
+/// The post as rules see it. Account age is measured at posting time, so
+/// dry runs over older posts give the same answer a live run would have.
+pub fn subject<'a>(post: &'a DbPost, author: &'a Author) -> AutomodSubject<'a> {
+    AutomodSubject {
+        title: &post.title,
+        body: &post.body,
+        url: post.url.as_deref(),
+        post_type: post_type_str(post.post_type),
+        account_age_days: (post.created_at - author.created_at).num_days(),
+        reputation: author.reputation,
+        author_flair_ids: &author.flair_ids,
+    }
+}
+
+/// A rule that matched a post in a dry run.
+pub struct DryRunMatch<'a> {
+    pub post: &'a DbPost,
+    pub rule: &'a ParsedRule,
+    pub author_name: String,
+}
+
+/// Check `posts` against `rules` without acting on them.
+pub async fn dry_run<'a>(
+    conn: &mut diesel_async::AsyncPgConnection,
+    posts: &'a [DbPost],
+    rules: &'a [ParsedRule],
+) -> Result<Vec<DryRunMatch<'a>>, TinyBoardsError> {
+    let mut authors: HashMap<(Uuid, Uuid), Author> = HashMap::new();
+    let mut matches = Vec::new();
+
+    for post in posts {
+        let key = (post.creator_id, post.board_id);
+        if let Entry::Vacant(entry) = authors.entry(key) {
+            entry.insert(load_author(conn, post.creator_id, post.board_id).await?);
+        }
+        let author = &authors[&key];
+        let subject = subject(post, author);
+
+        for rule in rules {
+            // Site-wide rules apply everywhere, board rules only in their board
+            let applies = rule.rule.board_id.is_none_or(|id| id == post.board_id);
+            if applies && rule.conditions.matches(&subject) {
+                matches.push(DryRunMatch {
+                    post,
+                    rule,
+                    author_name: author.name.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+/// Run the enabled rules on a new post and carry out the actions of every
+/// rule that matches. Each action is logged to the moderation log as the
+/// AutoModerator account with the rule's name as the reason.
+///
+/// Call it in the transaction that inserts the post, so a post a rule
+/// removes or filters is never visible, and a post the rules couldn't run
+/// on isn't created. Returns the ids of the replies AutoModerator posted,
+/// for `notify_replies` once the post is committed.
+pub async fn run_for_post(
+    pool: &DbPool,
+    conn: &mut diesel_async::AsyncPgConnection,
+    settings: &Settings,
+    post: &mut DbPost,
+) -> Result<Vec<Uuid>, TinyBoardsError> {
+    let rules = enabled_rules(conn, post.board_id).await?;
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let author = load_author(conn, post.creator_id, post.board_id).await?;
+    let matched: Vec<&ParsedRule> = rules
+        .iter()
+        .filter(|rule| rule.conditions.matches(&subject(post, &author)))
+        .collect();
+
+    let mut replies = Vec::new();
+    for rule in matched {
+        if let Some(comment_id) = apply_rule(pool, conn, settings, post, rule).await? {
+            replies.push(comment_id);
+        }
+    }
+
+    Ok(replies)
+}
+
+/// Tell the author about AutoModerator's replies to their post.
+pub async fn notify_replies(
+    pool: &DbPool,
+    events: Option<&EventBus>,
+    post: &DbPost,
+    comment_ids: &[Uuid],
+) {
+    for &comment_id in comment_ids {
+        let _ = create_post_reply_notification(
+            pool,
+            events,
+            post.creator_id,
+            post.id,
+            comment_id,
+            AUTOMOD_USER_ID,
+        )
+        .await;
+    }
+}
+
+/// Carry out one matched rule's actions on the post and log them. Returns
+/// the id of the reply it posted, if any.
+async fn apply_rule(
+    pool: &DbPool,
+    conn: &mut diesel_async::AsyncPgConnection,
+    settings: &Settings,
+    post: &mut DbPost,
+    rule: &ParsedRule,
+) -> Result<Option<Uuid>, TinyBoardsError> {
+    let post_id = post.id;
+    let actions = &rule.actions;
+    let log = |action_type: DbModerationAction, metadata: serde_json::Value| ModerationLogInsertForm {
+        moderator_id: AUTOMOD_USER_ID,
+        action_type,
+        target_type: "post".to_string(),
+        target_id: post_id,
+        board_id: Some(post.board_id),
+        reason: Some(rule.rule.name.clone()),
+        metadata: Some(serde_json::json!({ "automod_rule_id": rule.rule.id, "details": metadata })),
+        expires_at: None,
+    };
+    let mut entries = Vec::new();
+    let mut update = PostUpdateForm::default();
+
+    if actions.remove && !post.is_removed {
+        update.is_removed = Some(true);
+        post.is_removed = true;
+        entries.push(log(DbModerationAction::RemovePost, serde_json::Value::Null));
+    } else if actions.filter && !post.is_removed {
+        // Approving it from the queue restores it
+        update.is_removed = Some(true);
+        update.approval_status = Some(DbApprovalStatus::Pending);
+        post.is_removed = true;
+        entries.push(log(DbModerationAction::FilterPost, serde_json::Value::Null));
+    }
+
+    if actions.lock && !post.is_locked {
+        update.is_locked = Some(true);
+        post.is_locked = true;
+        entries.push(log(DbModerationAction::LockPost, serde_json::Value::Null));
+    }
+
+    if actions.mark_nsfw && !post.is_nsfw {
+        update.is_nsfw = Some(true);
+        post.is_nsfw = true;
+        entries.push(log(DbModerationAction::MarkNsfw, serde_json::Value::Null));
+    }
+
+    if !entries.is_empty() {
+        diesel::update(posts::table.find(post_id))
+            .set(&update)
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    }
+
+    if let Some(template_id) = actions.set_flair_id {
+        if apply_flair(conn, post, template_id).await? {
+            entries.push(log(
+                DbModerationAction::FlairPost,
+                serde_json::json!({ "flair_template_id": template_id }),
+            ));
+        }
+    }
+
+    if let Some(ref reason) = actions.report {
+        diesel::insert_into(post_reports::table)
+            .values(&PostReportInsertForm {
+                id: Uuid::new_v4(),
+                creator_id: AUTOMOD_USER_ID,
+                post_id,
+                original_post_title: post.title.clone(),
+                original_post_url: post.url.clone(),
+                original_post_body: Some(post.body.clone()),
+                reason: reason.clone(),
+                status: DbReportStatus::Pending,
+            })
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        entries.push(log(DbModerationAction::ReportPost, serde_json::Value::Null));
+    }
+
+    let mut comment_id = None;
+    if let Some(ref body) = actions.comment {
+        let id = post_comment(pool, conn, settings, post, body).await?;
+        comment_id = Some(id);
+        entries.push(log(
+            DbModerationAction::CommentPost,
+            serde_json::json!({ "comment_id": id }),
+        ));
+    }
+
+    diesel::insert_into(moderation_log::table)
+        .values(&entries)
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(comment_id)
+}
+
+/// Replace the post's flair. Returns false when the template can't be used
+/// on the post anymore, e.g. it was deleted or belongs to another board.
+async fn apply_flair(
+    conn: &mut diesel_async::AsyncPgConnection,
+    post: &DbPost,
+    template_id: Uuid,
+) -> Result<bool, TinyBoardsError> {
+    let usable: bool = flair_templates::table
+        .find(template_id)
+        .filter(flair_templates::board_id.eq(post.board_id))
+        .filter(flair_templates::flair_type.eq(DbFlairType::Post))
+        .filter(flair_templates::is_active.eq(true))
+        .count()
+        .get_result::<i64>(conn)
+        .await
+        .map(|c| c > 0)
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    if !usable {
+        return Ok(false);
+    }
+
+    diesel::delete(post_flairs::table.filter(post_flairs::post_id.eq(post.id)))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    diesel::insert_into(post_flairs::table)
+        .values(&PostFlairInsertForm {
+            post_id: post.id,
+            flair_template_id: template_id,
+            custom_text: None,
+            custom_text_color: None,
+            custom_background_color: None,
+            assigned_by: AUTOMOD_USER_ID,
+            is_original_author: false,
+        })
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(true)
+}
+
+/// Reply to the post as AutoModerator, distinguished as a moderator.
+async fn post_comment(
+    pool: &DbPool,
+    conn: &mut diesel_async::AsyncPgConnection,
+    settings: &Settings,
+    post: &DbPost,
+    body: &str,
+) -> Result<Uuid, TinyBoardsError> {
+    let body_html =
+        crate::utils::emoji::process_content_with_emojis(body, pool, Some(post.board_id), settings, None)
+            .await?;
+    let comment_id = Uuid::new_v4();
+
+    diesel::insert_into(comments::table)
+        .values(&CommentInsertForm {
+            id: comment_id,
+            body: body.to_string(),
+            body_html,
+            slug: generate_slug(body, Some(60)),
+            creator_id: AUTOMOD_USER_ID,
+            post_id: post.id,
+            parent_id: None,
+            board_id: post.board_id,
+            language_id: None,
+            level: 0,
+            approval_status: DbApprovalStatus::Approved,
+            quoted_comment_id: None,
+        })
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    diesel::update(comments::table.find(comment_id))
+        .set(&CommentUpdateForm {
+            distinguished_as: Some(Some("mod".to_string())),
+            ..Default::default()
+        })
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(comment_id)
+}
@@ -1,6 +1,17 @@
-use crate::{DbPool, storage::StorageBackend};
-use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use crate::{
+    DbPool,
+    helpers::files::upload::make_thumbnail_key,
+    storage::{
+        maintenance::{media_key_from_url, UPLOAD_IS_UNREFERENCED},
+        StorageBackend,
+    },
+};
+use diesel::{
+    dsl::exists,
+    prelude::*,
+    sql_types::{Array, Nullable, Text},
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use std::collections::HashSet;
 use tinyboards_db::{
     models::upload::{ContentUpload, ContentUploadInsertForm, Upload},
@@ -20,20 +31,174 @@ pub fn extract_image_urls_from_html(html: &str) -> Vec<String> {
         .collect()
 }
 
-/// Delete a file from storage
-/// Extracts the storage key from the file path and deletes via StorageBackend
-pub async fn delete_from_storage(
+/// The files behind an upload record: the original and, once processed, the
+/// optimized copy. Each also has a thumbnail next to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Queryable, QueryableByName)]
+pub struct StoredFiles {
+    #[diesel(sql_type = Text)]
+    pub file_path: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub optimized_url: Option<String>,
+}
+
+impl From<&Upload> for StoredFiles {
+    fn from(upload: &Upload) -> Self {
+        Self {
+            file_path: upload.file_path.clone(),
+            optimized_url: upload.optimized_url.clone(),
+        }
+    }
+}
+
+/// Take a lock on a stored file for the rest of the transaction
+///
+/// Uploads reuse an existing blob with the same content, so finding the blob
+/// and inserting a record for it has to hold this lock, as does deciding a
+/// blob is unreferenced and deleting it.
+pub(crate) async fn lock_stored_file(
+    conn: &mut AsyncPgConnection,
     file_path: &str,
+) -> Result<(), TinyBoardsError> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind::<Text, _>(file_path)
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(format!("Failed to lock {}: {}", file_path, e)))?;
+    Ok(())
+}
+
+/// Delete an upload's files from storage once nothing references them
+///
+/// Uploads with identical content share one blob (see `upload_file_opendal`),
+/// and optimized copies are named by their content, so each file and its
+/// thumbnail are only removed when no upload record points at it any more.
+/// Call after deleting the caller's own upload record. Returns whether the
+/// original was deleted.
+pub async fn delete_from_storage(
+    conn: &mut AsyncPgConnection,
+    files: &StoredFiles,
+    storage: &StorageBackend,
+) -> Result<bool, String> {
+    // The blob is deleted under the file's lock (see `lock_stored_file`), so
+    // a concurrent upload either lands first and keeps it, or writes it again
+    conn.transaction::<_, TinyBoardsError, _>(|conn| {
+        delete_unreferenced_files(conn, files, storage).scope_boxed()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+async fn delete_unreferenced_files(
+    conn: &mut AsyncPgConnection,
+    files: &StoredFiles,
     storage: &StorageBackend,
-) -> Result<(), String> {
+) -> Result<bool, TinyBoardsError> {
+    let file_path = files.file_path.as_str();
+    lock_stored_file(conn, file_path).await?;
+    let remaining: i64 = uploads::table
+        .filter(uploads::file_path.eq(file_path))
+        .count()
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(format!("Failed to count upload references: {}", e)))?;
+
     let key = file_path
         .split("/media/")
         .last()
         .unwrap_or(file_path);
 
+    let deleted = if remaining > 0 {
+        tracing::debug!("Keeping {}: still referenced by {} upload(s)", file_path, remaining);
+        false
+    } else {
+        delete_with_thumbnail(storage, key).await.map_err(TinyBoardsError::Internal)?;
+        true
+    };
+
+    // Videos are served as uploaded, so their optimized URL is the original's
+    let optimized = files
+        .optimized_url
+        .as_deref()
+        .and_then(|url| media_key_from_url(url).map(|key| (url, key)));
+    if let Some((optimized_url, optimized_key)) = optimized.filter(|(_, k)| k != key) {
+        let still_used: bool = diesel::select(exists(
+            uploads::table.filter(
+                uploads::optimized_url
+                    .eq(optimized_url)
+                    .or(uploads::upload_url.eq(optimized_url)),
+            ),
+        ))
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(format!("Failed to check optimized file references: {}", e)))?;
+
+        if !still_used {
+            delete_with_thumbnail(storage, &optimized_key).await.map_err(TinyBoardsError::Internal)?;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Thumbnails share their file's lifetime; deleting a missing key is a no-op
+async fn delete_with_thumbnail(storage: &StorageBackend, key: &str) -> Result<(), String> {
     storage.delete(key)
         .await
-        .map_err(|e| format!("Failed to delete file: {}", e))
+        .map_err(|e| format!("Failed to delete file: {}", e))?;
+    storage.delete(&make_thumbnail_key(key))
+        .await
+        .map_err(|e| format!("Failed to delete thumbnail: {}", e))
+}
+
+/// Drop one content link to an upload
+///
+/// The upload record is deleted once no post or comment links to it, and its
+/// blob once no upload record references it.
+async fn release_upload(
+    conn: &mut AsyncPgConnection,
+    content_upload: &ContentUpload,
+    storage: &StorageBackend,
+) -> Result<(), TinyBoardsError> {
+    diesel::delete(content_uploads::table.find(content_upload.id))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let other_links: i64 = content_uploads::table
+        .filter(content_uploads::upload_id.eq(content_upload.upload_id))
+        .count()
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    if other_links > 0 {
+        return Ok(());
+    }
+
+    let upload: Option<Upload> = uploads::table
+        .find(content_upload.upload_id)
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let upload = match upload {
+        Some(upload) => upload,
+        None => return Ok(()),
+    };
+
+    tracing::info!("Deleting upload: {} ({})", upload.file_name, upload.upload_url);
+
+    diesel::delete(uploads::table.find(upload.id))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    if let Err(e) = delete_from_storage(conn, &StoredFiles::from(&upload), storage).await {
+        tracing::error!("Failed to delete file from storage: {}", e);
+    }
+
+    Ok(())
 }
 
 /// Clean up orphaned uploads when editing post/comment content
@@ -41,8 +206,7 @@ pub async fn delete_from_storage(
 /// 1. Gets all current uploads linked to the content
 /// 2. Extracts image URLs from the new HTML
 /// 3. Finds uploads that are no longer referenced
-/// 4. Deletes orphaned files from storage
-/// 5. Deletes orphaned upload records from database
+/// 4. Releases them (see `release_upload`)
 pub async fn cleanup_orphaned_uploads(
     pool: &DbPool,
     content_id: Uuid,
@@ -55,14 +219,16 @@ pub async fn cleanup_orphaned_uploads(
         .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
     // Get current uploads for this content
-    let current_uploads: Vec<ContentUpload> = if is_post {
+    let current_uploads: Vec<(ContentUpload, Upload)> = if is_post {
         content_uploads::table
+            .inner_join(uploads::table)
             .filter(content_uploads::post_id.eq(content_id))
             .load(conn)
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?
     } else {
         content_uploads::table
+            .inner_join(uploads::table)
             .filter(content_uploads::comment_id.eq(content_id))
             .load(conn)
             .await
@@ -74,34 +240,10 @@ pub async fn cleanup_orphaned_uploads(
         .into_iter()
         .collect();
 
-    // Find and delete orphaned uploads
-    for content_upload in current_uploads {
-        let upload: Upload = uploads::table
-            .find(content_upload.upload_id)
-            .first(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-
-        let upload_url_str = upload.upload_url.to_string();
-
-        if !referenced_urls.contains(&upload_url_str) {
-            tracing::info!("Deleting orphaned upload: {} ({})", upload.file_name, upload_url_str);
-
-            if let Err(e) = delete_from_storage(&upload.file_path, storage).await {
-                tracing::error!("Failed to delete file from storage: {}", e);
-            }
-
-            // Delete upload record
-            diesel::delete(uploads::table.find(upload.id))
-                .execute(conn)
-                .await
-                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-
-            // Delete content_upload junction entry
-            diesel::delete(content_uploads::table.find(content_upload.id))
-                .execute(conn)
-                .await
-                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    for (content_upload, upload) in current_uploads {
+        if !referenced_urls.contains(&upload.upload_url) {
+            tracing::info!("Releasing orphaned upload: {} ({})", upload.file_name, upload.upload_url);
+            release_upload(conn, &content_upload, storage).await?;
         }
     }
 
@@ -125,30 +267,9 @@ pub async fn delete_post_files(
         .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
     for content_upload in content_uploads_list {
-        let upload: Upload = uploads::table
-            .find(content_upload.upload_id)
-            .first(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-
-        tracing::info!("Deleting post file: {} ({})", upload.file_name, upload.upload_url);
-
-        if let Err(e) = delete_from_storage(&upload.file_path, storage).await {
-            tracing::error!("Failed to delete file from storage: {}", e);
-        }
-
-        diesel::delete(uploads::table.find(upload.id))
-            .execute(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        release_upload(conn, &content_upload, storage).await?;
     }
 
-    // Delete all content_upload entries for this post
-    diesel::delete(content_uploads::table.filter(content_uploads::post_id.eq(post_id)))
-        .execute(conn)
-        .await
-        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-
     Ok(())
 }
 
@@ -169,30 +290,88 @@ pub async fn delete_comment_files(
         .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
     for content_upload in content_uploads_list {
-        let upload: Upload = uploads::table
-            .find(content_upload.upload_id)
-            .first(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        release_upload(conn, &content_upload, storage).await?;
+    }
 
-        tracing::info!("Deleting comment file: {} ({})", upload.file_name, upload.upload_url);
+    Ok(())
+}
 
-        if let Err(e) = delete_from_storage(&upload.file_path, storage).await {
-            tracing::error!("Failed to delete file from storage: {}", e);
-        }
+/// Upload records linked to any of the given posts or comments
+pub async fn linked_upload_ids(
+    conn: &mut AsyncPgConnection,
+    post_ids: &[Uuid],
+    comment_ids: &[Uuid],
+) -> Result<Vec<Uuid>, TinyBoardsError> {
+    content_uploads::table
+        .filter(
+            content_uploads::post_id
+                .eq_any(post_ids)
+                .or(content_uploads::comment_id.eq_any(comment_ids)),
+        )
+        .select(content_uploads::upload_id)
+        .distinct()
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
 
-        diesel::delete(uploads::table.find(upload.id))
-            .execute(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+/// Delete the upload records among `upload_ids`, and those stored by URL in
+/// `urls`, that nothing references any more
+///
+/// Meant for purges: run it in the purge's transaction after the purged rows
+/// are gone, then pass the returned paths to `delete_purged_files` once the
+/// transaction has committed.
+pub async fn delete_released_uploads(
+    conn: &mut AsyncPgConnection,
+    upload_ids: &[Uuid],
+    urls: &[String],
+) -> Result<Vec<StoredFiles>, TinyBoardsError> {
+    if upload_ids.is_empty() && urls.is_empty() {
+        return Ok(Vec::new());
     }
 
-    diesel::delete(content_uploads::table.filter(content_uploads::comment_id.eq(comment_id)))
-        .execute(conn)
+    let query = format!(
+        "DELETE FROM uploads u WHERE (u.id = ANY($1) OR u.upload_url = ANY($2)) AND {} RETURNING u.file_path, u.optimized_url",
+        UPLOAD_IS_UNREFERENCED
+    );
+
+    diesel::sql_query(query)
+        .bind::<Array<diesel::sql_types::Uuid>, _>(upload_ids)
+        .bind::<Array<Text>, _>(urls)
+        .load(conn)
         .await
-        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
 
-    Ok(())
+/// Remove the files of purged uploads from storage
+///
+/// Blobs that another upload record still points at are kept (see
+/// `delete_from_storage`). Failures are logged rather than returned, since
+/// the purge itself has already committed. Returns the number of blobs deleted.
+pub async fn delete_purged_files(
+    pool: &DbPool,
+    files: &[StoredFiles],
+    storage: &StorageBackend,
+) -> usize {
+    let conn = &mut match get_conn(pool).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!("Failed to get a connection to delete purged files: {:?}", e);
+            return 0;
+        }
+    };
+
+    let unique_files: HashSet<&StoredFiles> = files.iter().collect();
+    let mut deleted = 0;
+    for stored in unique_files {
+        match delete_from_storage(conn, stored, storage).await {
+            Ok(true) => deleted += 1,
+            Ok(false) => {}
+            Err(e) => tracing::error!("Failed to delete purged file {}: {}", stored.file_path, e),
+        }
+    }
+
+    deleted
 }
 
 /// Link uploads found in HTML content to a post or comment
@@ -216,9 +395,16 @@ pub async fn link_content_uploads(
         .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
     for (position, url) in image_urls.iter().enumerate() {
-        // Try to find the upload by URL
+        // Try to find the upload by URL. Identical files share a URL, so prefer
+        // an upload that isn't linked to other content yet.
         let upload_result: Result<Upload, _> = uploads::table
             .filter(uploads::upload_url.eq(url))
+            .order((
+                exists(
+                    content_uploads::table.filter(content_uploads::upload_id.eq(uploads::id)),
+                ),
+                uploads::created_at.desc(),
+            ))
             .first(conn)
             .await;
 
@@ -281,4 +467,140 @@ mod tests {
         let urls = extract_image_urls_from_html(html);
         assert_eq!(urls.len(), 0);
     }
+
+    async fn store(storage: &StorageBackend, keys: &[&str]) {
+        for key in keys {
+            storage.write(key, b"data".to_vec()).await.unwrap();
+        }
+    }
+
+    async fn stored(storage: &StorageBackend, keys: &[&str]) -> Vec<bool> {
+        let mut found = Vec::new();
+        for key in keys {
+            found.push(storage.exists(key).await.unwrap());
+        }
+        found
+    }
+
+    async fn delete_record(conn: &mut AsyncPgConnection, upload: &Upload) {
+        diesel::delete(uploads::table.find(upload.id))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_from_storage_removes_the_optimized_copy() {
+        let Some(db) = crate::test_utils::TestDb::new().await else { return };
+        let owner = db.user(0).await;
+        let original = format!("images/{}.png", Uuid::new_v4());
+        let optimized = format!("images/{}.webp", Uuid::new_v4());
+        let keys = [
+            original.as_str(),
+            &make_thumbnail_key(&original),
+            optimized.as_str(),
+            &make_thumbnail_key(&optimized),
+        ];
+        store(&db.storage, &keys).await;
+
+        // Two records share the blob, as identical uploads do
+        let first = db.upload(&owner, &original, Some(&optimized)).await;
+        let second = db.upload(&owner, &original, Some(&optimized)).await;
+        let conn = &mut *db.conn().await;
+
+        delete_record(conn, &first).await;
+        assert!(!delete_from_storage(conn, &StoredFiles::from(&first), &db.storage).await.unwrap());
+        assert_eq!(stored(&db.storage, &keys).await, [true; 4]);
+
+        delete_record(conn, &second).await;
+        assert!(delete_from_storage(conn, &StoredFiles::from(&second), &db.storage).await.unwrap());
+        assert_eq!(stored(&db.storage, &keys).await, [false; 4]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_from_storage_keeps_a_shared_optimized_copy() {
+        let Some(db) = crate::test_utils::TestDb::new().await else { return };
+        let owner = db.user(0).await;
+        let (png, jpg) = (format!("images/{}.png", Uuid::new_v4()), format!("images/{}.jpg", Uuid::new_v4()));
+        // Different originals can optimize to the same bytes
+        let optimized = format!("images/{}.webp", Uuid::new_v4());
+        let optimized_thumb = make_thumbnail_key(&optimized);
+        store(&db.storage, &[&png, &jpg, &optimized, &optimized_thumb]).await;
+
+        let from_png = db.upload(&owner, &png, Some(&optimized)).await;
+        db.upload(&owner, &jpg, Some(&optimized)).await;
+        let conn = &mut *db.conn().await;
+
+        delete_record(conn, &from_png).await;
+        assert!(delete_from_storage(conn, &StoredFiles::from(&from_png), &db.storage).await.unwrap());
+        assert_eq!(
+            stored(&db.storage, &[&png, &jpg, &optimized, &optimized_thumb]).await,
+            [false, true, true, true]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_from_storage_handles_videos_served_as_uploaded() {
+        let Some(db) = crate::test_utils::TestDb::new().await else { return };
+        let owner = db.user(0).await;
+        let video = format!("videos/{}.mp4", Uuid::new_v4());
+        let poster = make_thumbnail_key(&video);
+        store(&db.storage, &[&video, &poster]).await;
+
+        let upload = db.upload(&owner, &video, Some(&video)).await;
+        let conn = &mut *db.conn().await;
+
+        delete_record(conn, &upload).await;
+        assert!(delete_from_storage(conn, &StoredFiles::from(&upload), &db.storage).await.unwrap());
+        assert_eq!(stored(&db.storage, &[&video, &poster]).await, [false, false]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_from_storage_waits_for_an_upload_reusing_the_blob() {
+        let Some(db) = crate::test_utils::TestDb::new().await else { return };
+        let owner = db.user(0).await;
+        let key = format!("images/{}.png", Uuid::new_v4());
+        store(&db.storage, &[&key]).await;
+        let old = db.upload(&owner, &key, None).await;
+        delete_record(&mut *db.conn().await, &old).await;
+
+        // An identical upload finds the blob and records it, but hasn't
+        // committed yet when the last old record's files are deleted
+        let (key, url) = (key.as_str(), db.storage.get_public_url(&key));
+        let (found, found_rx) = tokio::sync::oneshot::channel();
+        let owner_id = owner.id;
+        let upload = async {
+            let conn = &mut *db.conn().await;
+            conn.transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    lock_stored_file(conn, key).await?;
+                    diesel::insert_into(uploads::table)
+                        .values((
+                            uploads::user_id.eq(owner_id),
+                            uploads::original_name.eq(key),
+                            uploads::file_name.eq(key),
+                            uploads::file_path.eq(key),
+                            uploads::upload_url.eq(url),
+                        ))
+                        .execute(conn)
+                        .await?;
+                    found.send(()).unwrap();
+                    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+            .unwrap();
+        };
+        let delete = async {
+            found_rx.await.unwrap();
+            let conn = &mut *db.conn().await;
+            delete_from_storage(conn, &StoredFiles::from(&old), &db.storage).await.unwrap()
+        };
+
+        let ((), deleted) = tokio::join!(upload, delete);
+        assert!(!deleted);
+        assert_eq!(stored(&db.storage, &[key]).await, [true]);
+    }
 }
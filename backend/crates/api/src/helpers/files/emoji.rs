@@ -114,6 +114,10 @@ pub async fn upload_emoji_file(
         thumbnail_url: None,
         optimized_url: None,
         processing_status: None,
+        content_hash: None,
+        width: None,
+        height: None,
+        duration_seconds: None,
     };
 
     diesel::insert_into(uploads::table)
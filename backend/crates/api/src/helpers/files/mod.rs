@@ -1,4 +1,3 @@
 pub mod upload;
 pub mod emoji;
-pub mod maintenance;
 pub mod cleanup;
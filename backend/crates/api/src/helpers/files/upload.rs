@@ -1,10 +1,14 @@
-use crate::{DbPool, Settings, storage::StorageBackend};
-use crate::storage::image_processing::{process_upload as process_image, ImageProcessingSettings};
+use crate::{DbPool, Settings, storage::{processing_queue, StorageBackend}};
+use crate::helpers::files::cleanup::lock_stored_file;
+use crate::storage::image_processing::{strip_jpeg_metadata, ImageProcessingSettings};
+use crate::storage::transcoding::{process_media, TranscodeSettings};
 use async_graphql::*;
-use diesel_async::RunQueryDsl;
+use diesel::prelude::*;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use sha2::{Digest, Sha256};
 use std::io::Read;
 use tinyboards_db::{
-    models::upload::UploadInsertForm,
+    models::upload::{Upload as DbUpload, UploadInsertForm},
     schema::uploads,
     utils::get_conn,
 };
@@ -13,7 +17,8 @@ use tinyboards_utils::{
     utils::{
         generate_secure_filename, is_acceptable_file_type,
         validate_file_size, validate_file_content, get_file_path_for_type,
-        get_storage_key_for_type, ensure_upload_directories, format_file_size
+        get_storage_key_for_type, ensure_upload_directories, format_file_size,
+        get_file_type_extended, is_video_type,
     },
 };
 use tokio::fs::File;
@@ -137,7 +142,11 @@ pub async fn upload_file(
         size_bytes: size,
         thumbnail_url: None,
         optimized_url: None,
-        processing_status: Some("pending".to_string()),
+        processing_status: Some("done".to_string()),
+        content_hash: None,
+        width: None,
+        height: None,
+        duration_seconds: None,
     };
 
     let conn = &mut get_conn(pool).await?;
@@ -151,17 +160,13 @@ pub async fn upload_file(
     Ok(upload_url)
 }
 
-pub async fn upload_file_opendal(
-    upload: Upload,
-    file_name: Option<String>,
-    for_user_id: Uuid,
+/// Read an upload into memory and check its type, size and content.
+/// Returns the original file name, the declared content type and the bytes.
+async fn read_validated_upload(
+    upload: &Upload,
     max_size_mb: Option<u32>,
     ctx: &Context<'_>,
-) -> Result<Url> {
-    let _settings = ctx.data::<Settings>()?.as_ref();
-    let pool = ctx.data::<DbPool>()?;
-    let storage = ctx.data::<StorageBackend>()?;
-
+) -> Result<(String, String, Vec<u8>)> {
     let upload_value = upload.value(ctx)?;
     let original_file_name = upload_value.filename.clone();
     let content_type = upload_value.content_type.clone().unwrap_or_default();
@@ -198,101 +203,300 @@ pub async fn upload_file_opendal(
         TinyBoardsError::from_message(400, &e)
     })?;
 
+    Ok((original_file_name, content_type, all_bytes))
+}
+
+/// Upload a file whose URL is stored right away (avatars, board images, post
+/// images), so images are processed before returning. The work runs on the
+/// blocking pool, or in ffmpeg for animated GIFs. Videos are stored as
+/// uploaded and queued for their poster frame, since their URL doesn't
+/// change.
+pub async fn upload_file_opendal(
+    upload: Upload,
+    file_name: Option<String>,
+    for_user_id: Uuid,
+    max_size_mb: Option<u32>,
+    ctx: &Context<'_>,
+) -> Result<Url> {
+    let pool = ctx.data::<DbPool>()?;
+    let storage = ctx.data::<StorageBackend>()?;
+    let settings = ctx.data::<Settings>()?.as_ref();
+
+    let (original_file_name, content_type, all_bytes) =
+        read_validated_upload(&upload, max_size_mb, ctx).await?;
+
     // Determine the actual content type and bytes to store.
     // For images, run through the processing pipeline (resize, strip EXIF, convert).
     let is_image = IMAGE_MIME_TYPES.contains(&content_type.as_str());
-
-    let (store_bytes, store_mime, thumbnail_bytes) = if is_image {
-        let img_settings = ImageProcessingSettings::default();
-        let processed = process_image(&all_bytes, &content_type, &img_settings)
-            .map_err(|e| -> async_graphql::Error { e.into() })?;
+    let is_video = is_video_type(&content_type);
+
+    let (store_bytes, store_mime, thumbnail_bytes, dimensions, duration_seconds) = if is_image {
+        let original_size = all_bytes.len();
+        let processed = process_media(
+            all_bytes,
+            content_type.clone(),
+            &TranscodeSettings::from(&settings.media),
+        )
+        .await
+        .map_err(|e| -> async_graphql::Error { e.into() })?;
 
         tracing::info!(
-            "Image processed: {}x{}, {} -> {} bytes",
-            processed.width, processed.height,
-            processed.original_size, processed.processed_size
+            "Image processed: {}x{}, {} -> {} bytes ({})",
+            processed.width.unwrap_or(0), processed.height.unwrap_or(0),
+            original_size, processed.data.len(), processed.mime_type
         );
 
-        (processed.data, processed.mime_type, processed.thumbnail_data)
+        (
+            processed.data,
+            processed.mime_type,
+            processed.thumbnail_data,
+            (processed.width, processed.height),
+            processed.duration_seconds,
+        )
     } else {
-        (all_bytes, content_type.clone(), None)
+        (all_bytes, content_type.clone(), None, (None, None), None)
     };
 
-    // Generate secure filename using the (possibly updated) mime type
-    let generated_file_name = generate_secure_filename(
-        file_name.or(Some(original_file_name.clone())),
-        &store_mime
-    );
+    // Name the blob after its content so identical files are stored once.
+    // The requested/original name only picks the subdirectory (e.g. avatars/).
+    let content_hash = hash_content(&store_bytes);
+    let generated_file_name = format!("{}.{}", content_hash, get_file_type_extended(&store_mime));
+    let name_hint = file_name.unwrap_or_else(|| original_file_name.clone());
 
     // Determine storage key (subdirectory + filename)
-    let storage_key = get_storage_key_for_type(&generated_file_name, &store_mime, &original_file_name);
-
-    // Store the processed file
+    let storage_key = get_storage_key_for_type(&generated_file_name, &store_mime, &name_hint);
     let final_size = store_bytes.len() as i64;
-    storage.write(&storage_key, store_bytes).await
-        .map_err(|e| -> async_graphql::Error { e.into() })?;
-
-    // Store thumbnail if one was generated
-    let mut thumb_url = None;
-    if let Some(thumb_bytes) = thumbnail_bytes {
-        let thumb_key = make_thumbnail_key(&storage_key);
-        match storage.write(&thumb_key, thumb_bytes).await {
-            Ok(()) => {
-                thumb_url = Some(storage.get_public_url(&thumb_key));
-            }
-            Err(e) => {
-                // Log but don't fail the upload if the thumbnail write fails
-                tracing::error!("Failed to write thumbnail {}: {}", thumb_key, e);
-            }
-        }
-    }
 
     // Generate public URL
     let upload_url = Url::parse(&storage.get_public_url(&storage_key))?;
 
-    // Image processing is currently synchronous — it completes before the response
-    // is sent. Both image and non-image uploads are marked "complete" immediately.
-    // The processing_status column exists to support future async processing: if
-    // upload latency becomes an issue, processing can be moved to a background task
-    // with the initial status set to "pending" and updated on completion/failure.
-    let (optimized_url, processing_status) = if is_image {
-        (Some(upload_url.to_string()), Some("complete".to_string()))
+    // Processing already happened above, so the stored file is the optimized one.
+    let optimized_url = if is_image {
+        Some(upload_url.to_string())
     } else {
-        (None, Some("complete".to_string()))
+        None
     };
 
-    // Save to database
-    let upload_form = UploadInsertForm {
-        user_id: for_user_id,
-        original_name: original_file_name.clone(),
-        file_name: generated_file_name,
-        file_path: storage_key.clone(),
-        upload_url: upload_url.to_string(),
-        size_bytes: final_size,
-        thumbnail_url: thumb_url,
-        optimized_url,
-        processing_status,
-    };
-
-    let conn = &mut get_conn(pool).await?;
-    diesel::insert_into(uploads::table)
-        .values(&upload_form)
-        .execute(conn)
-        .await
-        .map_err(|e| TinyBoardsError::Database(format!("Failed to save upload record: {}", e)))?;
+    let mut conn = get_conn(pool).await?;
+    let conn = &mut *conn;
+    let (storage_key, upload_url_string) = (&storage_key, upload_url.to_string());
+    conn.transaction::<_, TinyBoardsError, _>(|conn| {
+        async move {
+            // Hold the file's lock until the record is in, so the blob can't
+            // be deleted between finding it and referencing it
+            lock_stored_file(conn, storage_key).await?;
+            let existing = find_stored_upload(conn, storage, storage_key).await?;
+
+            let thumb_url = match existing {
+                Some(ref upload) => {
+                    tracing::info!("Deduplicated upload {} -> {}", original_file_name, storage_key);
+                    upload.thumbnail_url.clone()
+                }
+                None => {
+                    // Store the processed file
+                    storage.write(storage_key, store_bytes).await?;
+
+                    // Store thumbnail if one was generated
+                    let mut thumb_url = None;
+                    if let Some(thumb_bytes) = thumbnail_bytes {
+                        let thumb_key = make_thumbnail_key(storage_key);
+                        match storage.write(&thumb_key, thumb_bytes).await {
+                            Ok(()) => {
+                                thumb_url = Some(storage.get_public_url(&thumb_key));
+                            }
+                            Err(e) => {
+                                // Log but don't fail the upload if the thumbnail write fails
+                                tracing::error!("Failed to write thumbnail {}: {}", thumb_key, e);
+                            }
+                        }
+                    }
+                    thumb_url
+                }
+            };
+
+            // A video already processed under another upload shares its poster
+            let (processing_status, dimensions, duration_seconds) = match existing {
+                Some(upload) if is_video && upload.processing_status == "done" => {
+                    ("done", (upload.width, upload.height), upload.duration_seconds)
+                }
+                _ if is_video => ("pending", (None, None), None),
+                _ => (
+                    "done",
+                    (dimensions.0.map(|w| w as i32), dimensions.1.map(|h| h as i32)),
+                    duration_seconds,
+                ),
+            };
+
+            // Save to database
+            let upload_form = UploadInsertForm {
+                user_id: for_user_id,
+                original_name: original_file_name.clone(),
+                file_name: generated_file_name,
+                file_path: storage_key.clone(),
+                upload_url: upload_url_string,
+                size_bytes: final_size,
+                thumbnail_url: thumb_url,
+                optimized_url,
+                processing_status: Some(processing_status.to_string()),
+                content_hash: Some(content_hash),
+                width: dimensions.0,
+                height: dimensions.1,
+                duration_seconds,
+            };
+
+            let upload_id: Uuid = diesel::insert_into(uploads::table)
+                .values(&upload_form)
+                .returning(uploads::id)
+                .get_result(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(format!("Failed to save upload record: {}", e)))?;
+
+            if processing_status == "pending" {
+                processing_queue::enqueue(conn, upload_id).await?;
+            }
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
 
     tracing::info!("File uploaded: {} ({} bytes)", storage_key, final_size);
 
     Ok(upload_url)
 }
 
-/// Generate a thumbnail storage key from the original key.
-/// Example: "images/abc123.webp" -> "images/abc123_thumb.webp"
-fn make_thumbnail_key(key: &str) -> String {
-    match key.rsplit_once('.') {
-        Some((base, ext)) => format!("{}_thumb.{}", base, ext),
-        None => format!("{}_thumb", key),
+/// Store a file as uploaded and return without waiting for processing.
+/// Images and videos are queued for the background worker, which fills in
+/// `optimized_url`/`thumbnail_url` and the dimensions and moves
+/// `processing_status` from pending to done (or failed). JPEG metadata is stripped up front since the
+/// original is served until then.
+pub async fn upload_file_queued(
+    upload: Upload,
+    for_user_id: Uuid,
+    max_size_mb: Option<u32>,
+    ctx: &Context<'_>,
+) -> Result<DbUpload> {
+    let pool = ctx.data::<DbPool>()?;
+    let storage = ctx.data::<StorageBackend>()?;
+
+    let (original_file_name, content_type, mut all_bytes) =
+        read_validated_upload(&upload, max_size_mb, ctx).await?;
+
+    let is_media = IMAGE_MIME_TYPES.contains(&content_type.as_str()) || is_video_type(&content_type);
+    if content_type == "image/jpeg" && ImageProcessingSettings::default().strip_exif {
+        if let Some(stripped) = strip_jpeg_metadata(&all_bytes) {
+            all_bytes = stripped;
+        }
     }
+
+    let content_hash = hash_content(&all_bytes);
+    let generated_file_name = format!("{}.{}", content_hash, get_file_type_extended(&content_type));
+    let storage_key = get_storage_key_for_type(&generated_file_name, &content_type, &original_file_name);
+    let size = all_bytes.len() as i64;
+
+    let mut conn = get_conn(pool).await?;
+    let conn = &mut *conn;
+    let storage_key = &storage_key;
+    let upload: DbUpload = conn
+        .transaction::<_, TinyBoardsError, _>(|conn| {
+            async move {
+                // Hold the file's lock until the record is in, so the blob
+                // can't be deleted between finding it and referencing it
+                lock_stored_file(conn, storage_key).await?;
+                let existing = find_stored_upload(conn, storage, storage_key).await?;
+
+                // An identical file that's already processed can share its results
+                let (optimized_url, thumbnail_url, processing_status, dimensions, duration_seconds) = match existing {
+                    Some(upload) if upload.processing_status == "done" => {
+                        tracing::info!("Deduplicated upload {} -> {}", original_file_name, storage_key);
+                        (
+                            upload.optimized_url,
+                            upload.thumbnail_url,
+                            "done",
+                            (upload.width, upload.height),
+                            upload.duration_seconds,
+                        )
+                    }
+                    existing => {
+                        if existing.is_none() {
+                            storage.write(storage_key, all_bytes).await?;
+                        }
+                        (None, None, if is_media { "pending" } else { "done" }, (None, None), None)
+                    }
+                };
+
+                let upload_form = UploadInsertForm {
+                    user_id: for_user_id,
+                    original_name: original_file_name,
+                    file_name: generated_file_name,
+                    file_path: storage_key.clone(),
+                    upload_url: storage.get_public_url(storage_key),
+                    size_bytes: size,
+                    thumbnail_url,
+                    optimized_url,
+                    processing_status: Some(processing_status.to_string()),
+                    content_hash: Some(content_hash),
+                    width: dimensions.0,
+                    height: dimensions.1,
+                    duration_seconds,
+                };
+
+                let upload: DbUpload = diesel::insert_into(uploads::table)
+                    .values(&upload_form)
+                    .get_result(conn)
+                    .await
+                    .map_err(|e| TinyBoardsError::Database(format!("Failed to save upload record: {}", e)))?;
+
+                if upload.processing_status == "pending" {
+                    processing_queue::enqueue(conn, upload.id).await?;
+                }
+                Ok(upload)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    tracing::info!("File uploaded: {} ({} bytes, {})", storage_key, size, upload.processing_status);
+
+    Ok(upload)
+}
+
+/// An upload row for this storage key whose blob is still present, so the
+/// blob can be reused instead of written again. The storage check covers
+/// blobs that were removed out from under the row. Call it holding the
+/// file's lock, and insert the new row before releasing it.
+async fn find_stored_upload(
+    conn: &mut diesel_async::AsyncPgConnection,
+    storage: &StorageBackend,
+    storage_key: &str,
+) -> Result<Option<DbUpload>, TinyBoardsError> {
+    let existing: Option<DbUpload> = uploads::table
+        .filter(uploads::file_path.eq(storage_key))
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(match existing {
+        Some(upload) if storage.exists(storage_key).await.unwrap_or(false) => Some(upload),
+        _ => None,
+    })
+}
+
+/// Hex-encoded SHA-256 of the bytes that end up in storage.
+pub(crate) fn hash_content(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Generate a thumbnail storage key from the original key. Thumbnails are
+/// always WebP, including those of GIFs and video posters.
+/// Example: "images/abc123.png" -> "images/abc123_thumb.webp"
+pub(crate) fn make_thumbnail_key(key: &str) -> String {
+    let base = match key.rsplit_once('.') {
+        Some((base, ext)) if !ext.contains('/') => base,
+        _ => key,
+    };
+    format!("{}_thumb.webp", base)
 }
 
 /// Placeholder — file deletion is not yet implemented in the rewritten codebase.
@@ -301,3 +505,26 @@ pub async fn delete_file(_pool: &DbPool, _upload_url: &str) -> Result<(), TinyBo
     // TODO: implement using uploads::table query once the old trait-based methods are removed
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_content() {
+        assert_eq!(
+            hash_content(b"hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(hash_content(b"hello"), hash_content(b"hello"));
+        assert_ne!(hash_content(b"hello"), hash_content(b"hello!"));
+    }
+
+    #[test]
+    fn test_make_thumbnail_key() {
+        assert_eq!(make_thumbnail_key("avatars/abc.webp"), "avatars/abc_thumb.webp");
+        assert_eq!(make_thumbnail_key("images/abc.gif"), "images/abc_thumb.webp");
+        assert_eq!(make_thumbnail_key("videos/abc.mp4"), "videos/abc_thumb.webp");
+        assert_eq!(make_thumbnail_key("abc"), "abc_thumb.webp");
+    }
+}
@@ -1,5 +1,8 @@
+pub mod automod;
 pub mod files;
 pub mod flair;
+pub mod modmail;
 pub mod notifications;
 pub mod permissions;
+pub mod removal_reasons;
 pub mod validation;
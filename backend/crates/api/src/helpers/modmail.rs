@@ -0,0 +1,158 @@
+//! Shared pieces of modmail: who can read a thread, and adding messages.
+//! Modmail messages are rows in `private_messages` addressed to the board,
+//! with `thread_id` set.
+
+use async_graphql::ID;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use tinyboards_db::{
+    models::{
+        board::board_mods::{BoardModerator, ModPerms},
+        message::{
+            message::{PrivateMessage as DbPrivateMessage, PrivateMessageInsertForm},
+            modmail_thread::ModmailThread as DbModmailThread,
+        },
+        site::site::Site,
+        user::user::{AdminPerms, User},
+    },
+    schema::{board_moderators, modmail_threads, private_messages, site},
+    utils::DbPool,
+};
+use tinyboards_utils::{settings::structs::Settings, TinyBoardsError};
+use uuid::Uuid;
+
+use crate::utils::emoji::process_content_with_emojis;
+
+const MAX_BODY_LENGTH: usize = 10000;
+
+/// What kind of message is being added to a thread.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// From the user who started the thread
+    FromUser,
+    /// From a moderator, shown to the user as coming from the board
+    FromBoard,
+    /// Internal note, only shown to moderators
+    ModNote,
+}
+
+/// Whether `user` can read and answer a board's modmail: an accepted
+/// moderator with the Users permission, or an admin who manages users.
+pub async fn can_moderate(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    board_id: Uuid,
+) -> Result<bool, TinyBoardsError> {
+    if user.has_permission(AdminPerms::Users) {
+        return Ok(true);
+    }
+
+    let moderator: Option<BoardModerator> = board_moderators::table
+        .filter(board_moderators::board_id.eq(board_id))
+        .filter(board_moderators::user_id.eq(user.id))
+        .filter(board_moderators::is_invite_accepted.eq(true))
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(moderator.is_some_and(|m| m.has_permission(ModPerms::Users)))
+}
+
+pub async fn find_thread(
+    conn: &mut AsyncPgConnection,
+    thread_id: &ID,
+) -> Result<DbModmailThread, TinyBoardsError> {
+    let thread_uuid: Uuid = thread_id
+        .parse()
+        .map_err(|_| TinyBoardsError::NotFound("Invalid thread ID".into()))?;
+
+    modmail_threads::table
+        .find(thread_uuid)
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Thread not found".into()))
+}
+
+/// Load a thread `user` can read, and whether they read it as one of the
+/// board's moderators. The user who started a thread always reads it as
+/// themselves, even if they moderate the board.
+pub async fn load_thread(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    thread_id: &ID,
+) -> Result<(DbModmailThread, bool), TinyBoardsError> {
+    let thread = find_thread(conn, thread_id).await?;
+
+    if thread.user_id == user.id {
+        return Ok((thread, false));
+    }
+    if can_moderate(conn, user, thread.board_id).await? {
+        return Ok((thread, true));
+    }
+
+    Err(TinyBoardsError::NotFound("Thread not found".into()))
+}
+
+/// Load a thread for a moderator acting on it.
+pub async fn load_thread_as_moderator(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    thread_id: &ID,
+) -> Result<DbModmailThread, TinyBoardsError> {
+    match load_thread(conn, user, thread_id).await? {
+        (thread, true) => Ok(thread),
+        (_, false) => Err(TinyBoardsError::from_message(
+            403,
+            "You can't act on your own thread as a moderator",
+        )),
+    }
+}
+
+/// Add a message to a thread.
+pub async fn insert_message(
+    pool: &DbPool,
+    conn: &mut AsyncPgConnection,
+    settings: &Settings,
+    thread: &DbModmailThread,
+    author_id: Uuid,
+    body: &str,
+    kind: MessageKind,
+) -> Result<DbPrivateMessage, TinyBoardsError> {
+    if body.trim().is_empty() {
+        return Err(TinyBoardsError::from_message(400, "Message body cannot be empty"));
+    }
+    if body.len() > MAX_BODY_LENGTH {
+        return Err(TinyBoardsError::from_message(400, "Message too long"));
+    }
+
+    let site_config: Site = site::table
+        .first(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    let emoji_limit = if site_config.emoji_enabled {
+        site_config.max_emojis_per_comment.map(|limit| limit as usize)
+    } else {
+        Some(0)
+    };
+
+    let body_html =
+        process_content_with_emojis(body, pool, Some(thread.board_id), settings, emoji_limit).await?;
+
+    diesel::insert_into(private_messages::table)
+        .values(&PrivateMessageInsertForm {
+            creator_id: author_id,
+            recipient_id: None,
+            recipient_board_id: Some(thread.board_id),
+            subject: None,
+            body: body.to_string(),
+            body_html,
+            is_read: false,
+            is_sender_hidden: kind == MessageKind::FromBoard,
+            thread_id: Some(thread.id),
+            is_mod_note: kind == MessageKind::ModNote,
+        })
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
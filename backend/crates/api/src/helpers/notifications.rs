@@ -9,15 +9,40 @@ use tinyboards_db::{
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::events::{EventBus, LiveEvent};
+
+/// Insert a notification and let live subscribers of the recipient know about it.
+pub(crate) async fn insert_notification(
+    pool: &DbPool,
+    events: Option<&EventBus>,
+    form: NotificationInsertForm,
+) -> Result<(), TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+
+    let notification_id: Uuid = diesel::insert_into(notifications::table)
+        .values(&form)
+        .returning(notifications::id)
+        .get_result(conn)
+        .await?;
+
+    if let Some(events) = events {
+        events.publish(LiveEvent::NotificationCreated {
+            recipient_id: form.recipient_user_id,
+            notification_id,
+        });
+    }
+
+    Ok(())
+}
+
 /// Create a notification for a comment reply
 pub async fn create_comment_reply_notification(
     pool: &DbPool,
+    events: Option<&EventBus>,
     recipient_user_id: Uuid,
     comment_id: Uuid,
     actor_user_id: Uuid,
 ) -> Result<(), TinyBoardsError> {
-    let conn = &mut get_conn(pool).await?;
-
     let form = NotificationInsertForm {
         kind: DbNotificationKind::CommentReply,
         recipient_user_id,
@@ -26,25 +51,20 @@ pub async fn create_comment_reply_notification(
         message_id: None,
         is_read: false,
         actor_user_id: Some(actor_user_id),
+        body: None,
     };
 
-    diesel::insert_into(notifications::table)
-        .values(&form)
-        .execute(conn)
-        .await?;
-
-    Ok(())
+    insert_notification(pool, events, form).await
 }
 
 /// Create a notification for a mention in a comment
 pub async fn create_comment_mention_notification(
     pool: &DbPool,
+    events: Option<&EventBus>,
     recipient_user_id: Uuid,
     comment_id: Uuid,
     actor_user_id: Uuid,
 ) -> Result<(), TinyBoardsError> {
-    let conn = &mut get_conn(pool).await?;
-
     let form = NotificationInsertForm {
         kind: DbNotificationKind::Mention,
         recipient_user_id,
@@ -53,25 +73,20 @@ pub async fn create_comment_mention_notification(
         message_id: None,
         is_read: false,
         actor_user_id: Some(actor_user_id),
+        body: None,
     };
 
-    diesel::insert_into(notifications::table)
-        .values(&form)
-        .execute(conn)
-        .await?;
-
-    Ok(())
+    insert_notification(pool, events, form).await
 }
 
 /// Create a notification for a mention in a post
 pub async fn create_post_mention_notification(
     pool: &DbPool,
+    events: Option<&EventBus>,
     recipient_user_id: Uuid,
     post_id: Uuid,
     actor_user_id: Uuid,
 ) -> Result<(), TinyBoardsError> {
-    let conn = &mut get_conn(pool).await?;
-
     let form = NotificationInsertForm {
         kind: DbNotificationKind::Mention,
         recipient_user_id,
@@ -80,26 +95,21 @@ pub async fn create_post_mention_notification(
         message_id: None,
         is_read: false,
         actor_user_id: Some(actor_user_id),
+        body: None,
     };
 
-    diesel::insert_into(notifications::table)
-        .values(&form)
-        .execute(conn)
-        .await?;
-
-    Ok(())
+    insert_notification(pool, events, form).await
 }
 
 /// Create a notification for a post reply (comment on post)
 pub async fn create_post_reply_notification(
     pool: &DbPool,
+    events: Option<&EventBus>,
     recipient_user_id: Uuid,
     post_id: Uuid,
     comment_id: Uuid,
     actor_user_id: Uuid,
 ) -> Result<(), TinyBoardsError> {
-    let conn = &mut get_conn(pool).await?;
-
     let form = NotificationInsertForm {
         kind: DbNotificationKind::PostReply,
         recipient_user_id,
@@ -108,14 +118,32 @@ pub async fn create_post_reply_notification(
         message_id: None,
         is_read: false,
         actor_user_id: Some(actor_user_id),
+        body: None,
     };
 
-    diesel::insert_into(notifications::table)
-        .values(&form)
-        .execute(conn)
-        .await?;
+    insert_notification(pool, events, form).await
+}
 
-    Ok(())
+/// Create a notification for a reply from a board's moderators to a modmail
+/// thread. There's no actor, since the reply comes from the board.
+pub async fn create_modmail_reply_notification(
+    pool: &DbPool,
+    events: Option<&EventBus>,
+    recipient_user_id: Uuid,
+    message_id: Uuid,
+) -> Result<(), TinyBoardsError> {
+    let form = NotificationInsertForm {
+        kind: DbNotificationKind::PrivateMessage,
+        recipient_user_id,
+        comment_id: None,
+        post_id: None,
+        message_id: Some(message_id),
+        is_read: false,
+        actor_user_id: None,
+        body: None,
+    };
+
+    insert_notification(pool, events, form).await
 }
 
 /// Extract @mentions from text
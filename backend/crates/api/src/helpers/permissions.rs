@@ -1,4 +1,9 @@
-use async_graphql::Context;
+use std::sync::Arc;
+
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextResolve, ResolveInfo},
+    Context, Guard, ServerError, ServerResult, Value,
+};
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
 use tinyboards_db::{
@@ -9,10 +14,11 @@ use tinyboards_db::{
     schema::board_moderators,
     utils::get_conn,
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
-use crate::{DbPool, LoggedInUser};
+use crate::{DbPool, LoggedInUser, TokenScope};
 
 /// Get the logged-in user from GraphQL context. Returns error if not authenticated.
 pub fn require_auth<'a>(ctx: &'a Context<'_>) -> Result<&'a User, TinyBoardsError> {
@@ -112,3 +118,105 @@ pub async fn require_board_mod_or_admin<'a>(
 pub fn optional_auth<'a>(ctx: &'a Context<'_>) -> Option<&'a User> {
     ctx.data::<LoggedInUser>().ok().and_then(|l| l.inner())
 }
+
+/// Fail if the request was made with a personal access token whose scope is
+/// below `required`. Login sessions always pass. For fields that only
+/// sometimes act as a moderator, like deleting a post that isn't yours;
+/// moderation-only fields use `TokenScopeGuard`.
+pub fn check_token_scope(ctx: &Context<'_>, required: ApiTokenScope) -> Result<(), TinyBoardsError> {
+    check_scope(ctx.data_opt::<TokenScope>(), required)
+}
+
+fn check_scope(scope: Option<&TokenScope>, required: ApiTokenScope) -> Result<(), TinyBoardsError> {
+    match scope.and_then(TokenScope::inner) {
+        Some(scope) if scope < required => Err(TinyBoardsError::Forbidden(format!(
+            "This API token needs the {} scope",
+            required.as_str()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Guard for fields a personal access token may only use with at least the
+/// given scope, e.g. `#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]`
+/// on moderation mutations. Admin powers don't need a guard: below the admin
+/// scope the user is loaded as a non-admin.
+pub struct TokenScopeGuard(pub ApiTokenScope);
+
+impl Guard for TokenScopeGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        Ok(check_token_scope(ctx, self.0)?)
+    }
+}
+
+/// Guard for fields that change the account itself (settings, deletion,
+/// exports, sessions). Personal access tokens can't use them whatever their
+/// scope, so a leaked token can't take over or destroy the account.
+pub struct LoginSessionGuard;
+
+impl Guard for LoginSessionGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        Ok(check_login_session(ctx.data_opt::<TokenScope>())?)
+    }
+}
+
+fn check_login_session(scope: Option<&TokenScope>) -> Result<(), TinyBoardsError> {
+    match scope.and_then(TokenScope::inner) {
+        Some(_) => Err(TinyBoardsError::Forbidden(
+            "API tokens can't change account settings. Log in to do this".to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Schema extension requiring the post scope for every mutation, so
+/// read-only tokens can only query and subscribe.
+pub struct TokenScopeExtension;
+
+impl ExtensionFactory for TokenScopeExtension {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(TokenScopeExtension)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for TokenScopeExtension {
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        if info.parent_type == "Mutation" {
+            check_scope(ctx.data_opt::<TokenScope>(), ApiTokenScope::Post)
+                .map_err(|e| ServerError::new(e.to_string(), None))?;
+        }
+        next.run(ctx, info).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_scope_includes_lower_scopes() {
+        let moderate = TokenScope::from(Some(ApiTokenScope::Moderate));
+        assert!(check_scope(Some(&moderate), ApiTokenScope::Post).is_ok());
+        assert!(check_scope(Some(&moderate), ApiTokenScope::Moderate).is_ok());
+        assert!(check_scope(Some(&moderate), ApiTokenScope::Admin).is_err());
+        assert!(check_scope(Some(&TokenScope::from(None)), ApiTokenScope::Admin).is_ok());
+    }
+
+    #[test]
+    fn test_account_changes_need_a_login_session() {
+        assert!(check_login_session(None).is_ok());
+        assert!(check_login_session(Some(&TokenScope::from(None))).is_ok());
+        for scope in [ApiTokenScope::Read, ApiTokenScope::Post, ApiTokenScope::Admin] {
+            assert!(matches!(
+                check_login_session(Some(&TokenScope::from(Some(scope)))),
+                Err(TinyBoardsError::Forbidden(_))
+            ));
+        }
+    }
+}
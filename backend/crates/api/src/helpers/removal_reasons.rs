@@ -0,0 +1,290 @@
+//! Removal reasons: loading the one a moderator picked and telling the
+//! author about it when their post or comment is removed.
+
+use async_graphql::ID;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use tinyboards_db::{
+    enums::{DbApprovalStatus, DbNotificationKind},
+    models::{
+        board::board_mods::ModPerms,
+        comment::comments::{Comment as DbComment, CommentInsertForm, CommentUpdateForm},
+        message::message::{PrivateMessage as DbPrivateMessage, PrivateMessageInsertForm},
+        moderator::removal_reason::RemovalReason as DbRemovalReason,
+        notification::notifications::NotificationInsertForm,
+        user::user::{AdminPerms, User},
+    },
+    schema::{boards, comments, notifications, private_messages, removal_reasons, users},
+    utils::DbPool,
+};
+use tinyboards_utils::{
+    removal_reason::{render_removal_message, RemovalMessageContext},
+    settings::structs::Settings,
+    slug::generate_slug,
+    TinyBoardsError,
+};
+use uuid::Uuid;
+
+use crate::events::LiveEvent;
+use crate::helpers::validation::require_mod_or_admin;
+use crate::structs::removal_reason::RemovalMessageDelivery;
+use crate::utils::emoji::process_content_with_emojis;
+
+/// The post or comment a removal reason is being given for.
+pub struct RemovedContent<'a> {
+    pub board_id: Uuid,
+    pub post_id: Uuid,
+    /// Set when a comment was removed rather than a post
+    pub comment: Option<&'a DbComment>,
+    pub author_id: Uuid,
+}
+
+impl RemovedContent<'_> {
+    fn kind(&self) -> &'static str {
+        if self.comment.is_some() {
+            "comment"
+        } else {
+            "post"
+        }
+    }
+}
+
+/// Check that the user can manage a board's removal reasons.
+pub async fn require_reason_manager(
+    user: &User,
+    pool: &DbPool,
+    board_id: Uuid,
+) -> Result<(), TinyBoardsError> {
+    require_mod_or_admin(user, pool, board_id, ModPerms::Config, Some(AdminPerms::Content)).await
+}
+
+/// Load the removal reason given with a removal, making sure it belongs to
+/// the board the content is being removed from. A removal message can only
+/// be sent along with a reason.
+pub async fn load_reason_for_board(
+    conn: &mut AsyncPgConnection,
+    reason_id: Option<&ID>,
+    delivery: Option<RemovalMessageDelivery>,
+    board_id: Uuid,
+) -> Result<Option<DbRemovalReason>, TinyBoardsError> {
+    let Some(reason_id) = reason_id else {
+        if delivery.is_some() {
+            return Err(TinyBoardsError::from_message(
+                400,
+                "Pick a removal reason to send the author a removal message",
+            ));
+        }
+        return Ok(None);
+    };
+
+    let reason_uuid: Uuid = reason_id
+        .parse()
+        .map_err(|_| TinyBoardsError::NotFound("Invalid removal reason ID".into()))?;
+
+    removal_reasons::table
+        .find(reason_uuid)
+        .filter(removal_reasons::board_id.eq(board_id))
+        .first(conn)
+        .await
+        .map(Some)
+        .map_err(|_| TinyBoardsError::NotFound("Removal reason not found for this board".into()))
+}
+
+/// A removal reason delivered within the removal's transaction.
+pub struct RemovalDelivery {
+    /// Moderation log metadata describing what was sent
+    pub metadata: serde_json::Value,
+    /// Live events for what was sent, to publish once the removal commits
+    pub events: Vec<LiveEvent>,
+}
+
+/// Add a notification on `conn`, returning its live event.
+async fn notify(
+    conn: &mut AsyncPgConnection,
+    form: NotificationInsertForm,
+) -> Result<LiveEvent, TinyBoardsError> {
+    let notification_id: Uuid = diesel::insert_into(notifications::table)
+        .values(&form)
+        .returning(notifications::id)
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(LiveEvent::NotificationCreated {
+        recipient_id: form.recipient_user_id,
+        notification_id,
+    })
+}
+
+/// Fill in the reason's message for this content and deliver it to the
+/// author. Everything is written through `conn`, so run it in the same
+/// transaction as the removal. Nothing is sent when moderators remove their
+/// own content.
+pub async fn deliver_removal_reason(
+    pool: &DbPool,
+    settings: &Settings,
+    conn: &mut AsyncPgConnection,
+    moderator: &User,
+    reason: &DbRemovalReason,
+    delivery: Option<RemovalMessageDelivery>,
+    content: RemovedContent<'_>,
+) -> Result<RemovalDelivery, TinyBoardsError> {
+    let mut metadata = serde_json::json!({
+        "removal_reason_id": reason.id,
+        "removal_reason_title": reason.title,
+    });
+
+    let Some(delivery) = delivery.filter(|_| content.author_id != moderator.id) else {
+        return Ok(RemovalDelivery { metadata, events: Vec::new() });
+    };
+
+    let author_name: String = users::table
+        .find(content.author_id)
+        .select(users::name)
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Author not found".into()))?;
+    let board_name: String = boards::table
+        .find(content.board_id)
+        .select(boards::name)
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Board not found".into()))?;
+
+    let message = render_removal_message(
+        &reason.message,
+        &RemovalMessageContext {
+            author: &author_name,
+            board: &board_name,
+            rule: reason.rule.as_deref(),
+        },
+    );
+    let body_html =
+        process_content_with_emojis(&message, pool, Some(content.board_id), settings, None).await?;
+
+    metadata["delivery"] = delivery.as_str().into();
+    let mut events = Vec::new();
+
+    match delivery {
+        RemovalMessageDelivery::Comment => {
+            let comment_id = Uuid::new_v4();
+
+            diesel::insert_into(comments::table)
+                .values(&CommentInsertForm {
+                    id: comment_id,
+                    body: message.clone(),
+                    body_html,
+                    slug: generate_slug(&message, Some(60)),
+                    creator_id: moderator.id,
+                    post_id: content.post_id,
+                    parent_id: content.comment.map(|c| c.id),
+                    board_id: content.board_id,
+                    language_id: None,
+                    level: content.comment.map(|c| c.level + 1).unwrap_or(0),
+                    approval_status: DbApprovalStatus::Approved,
+                    quoted_comment_id: None,
+                })
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+            diesel::update(comments::table.find(comment_id))
+                .set(&CommentUpdateForm {
+                    distinguished_as: Some(Some("mod".to_string())),
+                    ..Default::default()
+                })
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+            let kind = if content.comment.is_some() {
+                DbNotificationKind::CommentReply
+            } else {
+                DbNotificationKind::PostReply
+            };
+            let notification = notify(
+                conn,
+                NotificationInsertForm {
+                    kind,
+                    recipient_user_id: content.author_id,
+                    comment_id: Some(comment_id),
+                    post_id: Some(content.post_id),
+                    message_id: None,
+                    is_read: false,
+                    actor_user_id: Some(moderator.id),
+                    body: None,
+                },
+            )
+            .await?;
+
+            events.push(notification);
+            events.push(LiveEvent::CommentCreated {
+                post_id: content.post_id,
+                comment_id,
+            });
+
+            metadata["message_comment_id"] = comment_id.to_string().into();
+        }
+        RemovalMessageDelivery::PrivateMessage => {
+            let sent: DbPrivateMessage = diesel::insert_into(private_messages::table)
+                .values(&PrivateMessageInsertForm {
+                    creator_id: moderator.id,
+                    recipient_id: Some(content.author_id),
+                    recipient_board_id: None,
+                    subject: Some(format!("Your {} in b/{} was removed", content.kind(), board_name)),
+                    body: message,
+                    body_html,
+                    is_read: false,
+                    is_sender_hidden: false,
+                    thread_id: None,
+                    is_mod_note: false,
+                })
+                .get_result(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+            let notification = notify(
+                conn,
+                NotificationInsertForm {
+                    kind: DbNotificationKind::PrivateMessage,
+                    recipient_user_id: content.author_id,
+                    comment_id: None,
+                    post_id: None,
+                    message_id: Some(sent.id),
+                    is_read: false,
+                    actor_user_id: Some(moderator.id),
+                    body: None,
+                },
+            )
+            .await?;
+
+            events.push(notification);
+            events.push(LiveEvent::MessageSent {
+                recipient_id: content.author_id,
+                message_id: sent.id,
+            });
+
+            metadata["message_id"] = sent.id.to_string().into();
+        }
+        RemovalMessageDelivery::Notification => {
+            let notification = notify(
+                conn,
+                NotificationInsertForm {
+                    kind: DbNotificationKind::ModAction,
+                    recipient_user_id: content.author_id,
+                    comment_id: content.comment.map(|c| c.id),
+                    post_id: Some(content.post_id),
+                    message_id: None,
+                    is_read: false,
+                    actor_user_id: Some(moderator.id),
+                    body: Some(message),
+                },
+            )
+            .await?;
+
+            events.push(notification);
+        }
+    }
+
+    Ok(RemovalDelivery { metadata, events })
+}
@@ -1,6 +1,7 @@
 #![recursion_limit = "256"]
 
 pub mod context;
+pub mod events;
 pub(crate) mod helpers;
 pub(crate) mod loaders;
 pub mod mutations;
@@ -8,20 +9,23 @@ pub(crate) mod newtypes;
 pub mod queries;
 pub mod storage;
 pub(crate) mod structs;
+pub mod subscriptions;
+#[cfg(test)]
+mod test_utils;
 pub mod utils;
 
 use crate::mutations::{
-    admin::{board_moderation::AdminBoardModeration, registration_applications::RegistrationApplicationMutations, user_management::UserManagement},
+    admin::{board_moderation::AdminBoardModeration, purge::AdminPurge, registration_applications::RegistrationApplicationMutations, user_management::UserManagement},
     board::{actions::BoardActions, create::CreateBoard, settings::UpdateBoardSettings},
     board_moderation::BoardModerationMutations,
     emoji::EmojiMutations,
     flair::{assignment::FlairAssignmentMutations, filter::FlairFilterMutations, template::FlairTemplateMutations},
     flair_categories::MutationFlairCategories,
-    message::{actions::MessageActionMutations, send_message::SendMessageMutations, edit_message::EditMessageMutations},
+    message::{actions::MessageActionMutations, send_message::SendMessageMutations, edit_message::EditMessageMutations, modmail::ModmailMutations},
     moderation_unified::ModerationMutations,
     notifications::NotificationMutations,
     reactions::ReactionMutations,
-    user::{actions::UserActions, profile_management::ProfileManagement, settings::UpdateSettings},
+    user::{actions::UserActions, profile_management::ProfileManagement, sessions::SessionMutations, settings::UpdateSettings},
     comment::{
         actions::*, edit::EditComment, moderation::CommentModeration, submit_comment::SubmitComment,
     },
@@ -31,6 +35,7 @@ use crate::mutations::{
     wiki::{CreateWikiPage, WikiPageActions},
 };
 use async_graphql::*;
+pub use subscriptions::Subscription;
 use queries::{
     banned_users::QueryBannedUsers,
     board_management::QueryBoardManagement,
@@ -38,21 +43,27 @@ use queries::{
     board_moderators::QueryBoardModerators,
     boards::QueryBoards,
     comments::QueryComments,
+    data_exports::QueryDataExports,
     emojis::EmojiQueries,
     flairs::FlairQueries,
     invites::QueryInvites,
+    login_security::QueryLoginSecurity,
     site::QuerySite,
     me::MeQuery,
     messages::QueryMessages,
     moderation_unified::ModerationQueries,
+    modmail::QueryModmail,
     notifications::QueryNotifications,
     user::QueryUser,
     posts::QueryPosts,
     registration_applications::RegistrationApplicationQueries,
     reports::ReportQueries,
     search::QuerySearch,
+    sessions::QuerySessions,
+    uploads::QueryUploads,
     wiki::QueryWiki,
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_db::{models::user::user::User, utils::DbPool};
 //use queries::Query;
 use tinyboards_utils::{settings::structs::Settings as Settings_, TinyBoardsError};
@@ -65,6 +76,11 @@ pub struct LoggedInUser(Option<User>);
 pub struct MasterKey(String);
 /// Instance settings
 pub struct Settings(&'static Settings_);
+/// Hash of the request's refresh token cookie, which identifies the caller's session
+pub struct SessionTokenHash(Option<String>);
+/// Scope of the personal access token the request was made with; `None` for a
+/// login session, which isn't limited
+pub struct TokenScope(Option<ApiTokenScope>);
 
 /// Dataloader for batch loading
 pub struct PostgresLoader {
@@ -105,6 +121,7 @@ pub struct Query(
     QueryUser,
     QuerySite,
     QueryMessages,
+    QueryModmail,
     QueryNotifications,
     QueryInvites,
     QueryBoardModerators,
@@ -116,12 +133,17 @@ pub struct Query(
     ReportQueries,
     ModerationQueries,
     QueryWiki,
+    QueryUploads,
+    QuerySessions,
+    QueryLoginSecurity,
+    QueryDataExports,
 );
 
 #[derive(MergedObject, Default)]
 pub struct Mutation(
     UserManagement,
     AdminBoardModeration,
+    AdminPurge,
     BoardActions,
     CreateBoard,
     UpdateBoardSettings,
@@ -139,6 +161,7 @@ pub struct Mutation(
     SendMessageMutations,
     EditMessageMutations,
     MessageActionMutations,
+    ModmailMutations,
     SiteConfig,
     SiteInvite,
     NotificationMutations,
@@ -155,10 +178,13 @@ pub struct Mutation(
     mutations::file_upload::FileUploadMutation,
     CreateWikiPage,
     WikiPageActions,
+    SessionMutations,
 );
 
-pub fn gen_schema() -> Schema<Query, Mutation, EmptySubscription> {
-    Schema::new(Query::default(), Mutation::default(), EmptySubscription)
+pub fn gen_schema() -> Schema<Query, Mutation, Subscription> {
+    Schema::build(Query::default(), Mutation::default(), Subscription)
+        .extension(helpers::permissions::TokenScopeExtension)
+        .finish()
 }
 
 impl From<Option<User>> for LoggedInUser {
@@ -173,6 +199,18 @@ impl From<String> for MasterKey {
     }
 }
 
+impl From<Option<String>> for SessionTokenHash {
+    fn from(value: Option<String>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Option<ApiTokenScope>> for TokenScope {
+    fn from(value: Option<ApiTokenScope>) -> Self {
+        Self(value)
+    }
+}
+
 impl From<&'static Settings_> for Settings {
     fn from(value: &'static Settings_) -> Self {
         Self(value)
@@ -220,12 +258,23 @@ impl LoggedInUser {
 }
 
 impl MasterKey {
-    #[allow(dead_code)]
     pub(crate) fn as_ref(&self) -> &str {
         self.0.as_str()
     }
 }
 
+impl SessionTokenHash {
+    pub(crate) fn inner(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl TokenScope {
+    pub(crate) fn inner(&self) -> Option<ApiTokenScope> {
+        self.0
+    }
+}
+
 impl Settings {
     pub(crate) fn as_ref(&self) -> &'static Settings_ {
         self.0
@@ -1,7 +1,9 @@
 pub mod board_moderation;
+pub mod purge;
 pub mod registration_applications;
 pub mod user_management;
 
 pub use board_moderation::*;
+pub use purge::*;
 pub use registration_applications::*;
 pub use user_management::*;
\ No newline at end of file
@@ -0,0 +1,814 @@
+//! Purges permanently delete content instead of marking it deleted. The
+//! database removes dependent rows (votes, reactions, reports, flairs, saved
+//! items, notifications, replies) through `ON DELETE CASCADE`; here we also
+//! release the uploads behind the content and delete their files once the
+//! purge has committed. The moderation log entry only keeps counts, never
+//! titles, names or bodies.
+
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel::sql_types;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use tinyboards_db::{
+    enums::DbModerationAction,
+    models::{
+        board::boards::Board as DbBoard,
+        comment::comments::Comment as DbComment,
+        moderator::moderation_log::ModerationLogInsertForm,
+        post::posts::Post as DbPost,
+        user::user::{AdminPerms, User as DbUser},
+    },
+    schema::{boards, comment_votes, comments, emoji, moderation_log, post_votes, posts, reactions, uploads, users},
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::helpers::{files::cleanup, permissions};
+use crate::storage::StorageBackend;
+
+#[derive(Default)]
+pub struct AdminPurge;
+
+/// Which comments a purge starts from. Replies below them go too.
+#[derive(Clone, Copy)]
+enum CommentRoots {
+    Comment,
+    Post,
+    Board,
+    User,
+}
+
+impl CommentRoots {
+    fn filter(self) -> &'static str {
+        match self {
+            Self::Comment => "id = $1",
+            Self::Post => "post_id = $1",
+            Self::Board => "board_id = $1",
+            Self::User => "creator_id = $1 OR post_id IN (SELECT id FROM posts WHERE creator_id = $1)",
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct CommentIdRow {
+    #[diesel(sql_type = sql_types::Uuid)]
+    id: Uuid,
+}
+
+/// Comments removed along with a purge: the roots and every reply below them
+async fn comment_tree(
+    conn: &mut AsyncPgConnection,
+    roots: CommentRoots,
+    id: Uuid,
+) -> Result<Vec<Uuid>, TinyBoardsError> {
+    let query = format!(
+        "WITH RECURSIVE tree AS ( \
+            SELECT id FROM comments WHERE {} \
+            UNION SELECT c.id FROM comments c JOIN tree t ON c.parent_id = t.id \
+        ) SELECT id FROM tree",
+        roots.filter()
+    );
+
+    let rows: Vec<CommentIdRow> = diesel::sql_query(query)
+        .bind::<sql_types::Uuid, _>(id)
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(rows.into_iter().map(|row| row.id).collect())
+}
+
+/// Media a post stores by URL rather than through `content_uploads`
+fn post_media_urls(post: &DbPost) -> impl Iterator<Item = String> + '_ {
+    [&post.url, &post.image, &post.thumbnail_url]
+        .into_iter()
+        .flatten()
+        .cloned()
+}
+
+async fn log_purge(
+    conn: &mut AsyncPgConnection,
+    form: ModerationLogInsertForm,
+) -> Result<(), TinyBoardsError> {
+    diesel::insert_into(moderation_log::table)
+        .values(&form)
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    Ok(())
+}
+
+fn parse_id(id: &ID, what: &str) -> Result<Uuid, TinyBoardsError> {
+    id.parse()
+        .map_err(|_| TinyBoardsError::BadRequest(format!("Invalid {} ID", what)))
+}
+
+#[Object]
+impl AdminPurge {
+    /// Permanently delete a post, its comments and their media (admin only).
+    /// This can't be undone.
+    pub async fn purge_post(
+        &self,
+        ctx: &Context<'_>,
+        post_id: ID,
+        reason: Option<String>,
+    ) -> Result<bool> {
+        let pool = ctx.data::<DbPool>()?;
+        let admin = permissions::require_admin_permission(ctx, AdminPerms::Content)?;
+        let storage = ctx.data::<StorageBackend>()?;
+        let post_uuid = parse_id(&post_id, "post")?;
+        let conn = &mut get_conn(pool).await?;
+
+        let post: DbPost = posts::table
+            .find(post_uuid)
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("Post not found".to_string()))?;
+
+        let admin_id = admin.id;
+        let files = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    let comment_ids = comment_tree(conn, CommentRoots::Post, post.id).await?;
+                    let upload_ids = cleanup::linked_upload_ids(conn, &[post.id], &comment_ids).await?;
+                    let urls: Vec<String> = post_media_urls(&post).collect();
+
+                    diesel::delete(posts::table.find(post.id))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    let files = cleanup::delete_released_uploads(conn, &upload_ids, &urls).await?;
+
+                    log_purge(
+                        conn,
+                        ModerationLogInsertForm {
+                            moderator_id: admin_id,
+                            action_type: DbModerationAction::PurgePost,
+                            target_type: "post".to_string(),
+                            target_id: post.id,
+                            board_id: Some(post.board_id),
+                            reason,
+                            metadata: Some(serde_json::json!({ "comments": comment_ids.len(), "files": files.len() })),
+                            expires_at: None,
+                        },
+                    )
+                    .await?;
+
+                    Ok(files)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        cleanup::delete_purged_files(pool, &files, storage).await;
+        tracing::info!("Admin {} purged post {}", admin.name, post_uuid);
+
+        Ok(true)
+    }
+
+    /// Permanently delete a comment, the replies below it and their media
+    /// (admin only). This can't be undone.
+    pub async fn purge_comment(
+        &self,
+        ctx: &Context<'_>,
+        comment_id: ID,
+        reason: Option<String>,
+    ) -> Result<bool> {
+        let pool = ctx.data::<DbPool>()?;
+        let admin = permissions::require_admin_permission(ctx, AdminPerms::Content)?;
+        let storage = ctx.data::<StorageBackend>()?;
+        let comment_uuid = parse_id(&comment_id, "comment")?;
+        let conn = &mut get_conn(pool).await?;
+
+        let comment: DbComment = comments::table
+            .find(comment_uuid)
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("Comment not found".to_string()))?;
+
+        let admin_id = admin.id;
+        let files = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    let comment_ids = comment_tree(conn, CommentRoots::Comment, comment.id).await?;
+                    let upload_ids = cleanup::linked_upload_ids(conn, &[], &comment_ids).await?;
+
+                    diesel::delete(comments::table.find(comment.id))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    let files = cleanup::delete_released_uploads(conn, &upload_ids, &[]).await?;
+
+                    log_purge(
+                        conn,
+                        ModerationLogInsertForm {
+                            moderator_id: admin_id,
+                            action_type: DbModerationAction::PurgeComment,
+                            target_type: "comment".to_string(),
+                            target_id: comment.id,
+                            board_id: Some(comment.board_id),
+                            reason,
+                            metadata: Some(serde_json::json!({ "replies": comment_ids.len() - 1, "files": files.len() })),
+                            expires_at: None,
+                        },
+                    )
+                    .await?;
+
+                    Ok(files)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        cleanup::delete_purged_files(pool, &files, storage).await;
+        tracing::info!("Admin {} purged comment {}", admin.name, comment_uuid);
+
+        Ok(true)
+    }
+
+    /// Permanently delete a board with all of its posts, comments, emoji and
+    /// media (admin only). This can't be undone.
+    pub async fn purge_board(
+        &self,
+        ctx: &Context<'_>,
+        board_id: ID,
+        reason: Option<String>,
+    ) -> Result<bool> {
+        let pool = ctx.data::<DbPool>()?;
+        let admin = permissions::require_admin_permission(ctx, AdminPerms::Boards)?;
+        let storage = ctx.data::<StorageBackend>()?;
+        let board_uuid = parse_id(&board_id, "board")?;
+        let conn = &mut get_conn(pool).await?;
+
+        let board: DbBoard = boards::table
+            .find(board_uuid)
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("Board not found".to_string()))?;
+
+        let admin_id = admin.id;
+        let files = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    let board_posts: Vec<DbPost> = posts::table
+                        .filter(posts::board_id.eq(board.id))
+                        .load(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    let post_ids: Vec<Uuid> = board_posts.iter().map(|p| p.id).collect();
+                    let comment_ids = comment_tree(conn, CommentRoots::Board, board.id).await?;
+                    let upload_ids = cleanup::linked_upload_ids(conn, &post_ids, &comment_ids).await?;
+
+                    let emoji_urls: Vec<String> = emoji::table
+                        .filter(emoji::board_id.eq(board.id))
+                        .select(emoji::image_url)
+                        .load(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    let urls: Vec<String> = board_posts
+                        .iter()
+                        .flat_map(post_media_urls)
+                        .chain([board.icon.clone(), board.banner.clone()].into_iter().flatten())
+                        .chain(emoji_urls)
+                        .collect();
+
+                    diesel::delete(boards::table.find(board.id))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    let files = cleanup::delete_released_uploads(conn, &upload_ids, &urls).await?;
+
+                    log_purge(
+                        conn,
+                        ModerationLogInsertForm {
+                            moderator_id: admin_id,
+                            action_type: DbModerationAction::PurgeBoard,
+                            target_type: "board".to_string(),
+                            target_id: board.id,
+                            board_id: None,
+                            reason,
+                            metadata: Some(serde_json::json!({
+                                "posts": post_ids.len(),
+                                "comments": comment_ids.len(),
+                                "files": files.len(),
+                            })),
+                            expires_at: None,
+                        },
+                    )
+                    .await?;
+
+                    Ok(files)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        cleanup::delete_purged_files(pool, &files, storage).await;
+        tracing::info!("Admin {} purged board {}", admin.name, board_uuid);
+
+        Ok(true)
+    }
+
+    /// Permanently delete a user account with everything they posted and
+    /// uploaded (admin only). Replies to their posts and comments go too.
+    /// This can't be undone.
+    pub async fn purge_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: ID,
+        reason: Option<String>,
+    ) -> Result<bool> {
+        let pool = ctx.data::<DbPool>()?;
+        let admin = permissions::require_admin_permission(ctx, AdminPerms::Users)?;
+        let storage = ctx.data::<StorageBackend>()?;
+        let target_uuid = parse_id(&user_id, "user")?;
+
+        if admin.id == target_uuid {
+            return Err(TinyBoardsError::from_message(400, "You can't purge your own account").into());
+        }
+
+        let conn = &mut get_conn(pool).await?;
+
+        let target: DbUser = users::table
+            .find(target_uuid)
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("User not found".to_string()))?;
+
+        if target.is_admin && target.admin_level >= admin.admin_level && !admin.has_permission(AdminPerms::Owner) {
+            return Err(TinyBoardsError::from_message(
+                403,
+                "Cannot purge an admin with equal or higher permissions",
+            )
+            .into());
+        }
+
+        let admin_id = admin.id;
+        let files = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    let post_ids: Vec<Uuid> = posts::table
+                        .filter(posts::creator_id.eq(target.id))
+                        .select(posts::id)
+                        .load(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    let comment_ids = comment_tree(conn, CommentRoots::User, target.id).await?;
+                    let upload_ids = cleanup::linked_upload_ids(conn, &post_ids, &comment_ids).await?;
+
+                    // Their own uploads would go with the account anyway, so
+                    // delete the records here to keep hold of the file paths
+                    let mut files: Vec<cleanup::StoredFiles> = diesel::delete(uploads::table.filter(uploads::user_id.eq(target.id)))
+                        .returning((uploads::file_path, uploads::optimized_url))
+                        .get_results(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    // Delete votes before content and content before the
+                    // account. Left to cascades, a vote can be deleted after
+                    // its comment, and its aggregate trigger then fails.
+                    diesel::delete(comment_votes::table.filter(comment_votes::user_id.eq(target.id)))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    diesel::delete(post_votes::table.filter(post_votes::user_id.eq(target.id)))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    diesel::delete(reactions::table.filter(reactions::user_id.eq(target.id)))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    diesel::delete(posts::table.filter(posts::creator_id.eq(target.id)))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    diesel::delete(comments::table.filter(comments::creator_id.eq(target.id)))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    diesel::delete(users::table.find(target.id))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    // Media in other people's replies to the user's content
+                    files.extend(cleanup::delete_released_uploads(conn, &upload_ids, &[]).await?);
+
+                    log_purge(
+                        conn,
+                        ModerationLogInsertForm {
+                            moderator_id: admin_id,
+                            action_type: DbModerationAction::PurgeUser,
+                            target_type: "user".to_string(),
+                            target_id: target.id,
+                            board_id: None,
+                            reason,
+                            metadata: Some(serde_json::json!({
+                                "posts": post_ids.len(),
+                                "comments": comment_ids.len(),
+                                "files": files.len(),
+                            })),
+                            expires_at: None,
+                        },
+                    )
+                    .await?;
+
+                    Ok(files)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        cleanup::delete_purged_files(pool, &files, storage).await;
+        tracing::info!("Admin {} purged user {}", admin.name, target_uuid);
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{unique_name, TestDb};
+    use tinyboards_db::{
+        models::{moderator::moderation_log::ModerationLog, upload::Upload as DbUpload},
+        schema::{comment_reports, content_uploads, post_reports},
+    };
+
+    #[derive(QueryableByName)]
+    struct Count {
+        #[diesel(sql_type = sql_types::BigInt)]
+        count: i64,
+    }
+
+    /// Rows matching `sql`, a `SELECT count(*)` with `id` as its parameter
+    async fn count(db: &TestDb, sql: &str, id: Uuid) -> i64 {
+        diesel::sql_query(sql)
+            .bind::<sql_types::Uuid, _>(id)
+            .get_result::<Count>(&mut db.conn().await)
+            .await
+            .unwrap()
+            .count
+    }
+
+    /// An upload with its file written to storage
+    async fn stored_upload(db: &TestDb, owner: &DbUser) -> DbUpload {
+        let key = format!("{}.png", unique_name("purge"));
+        db.storage.write(&key, b"data".to_vec()).await.unwrap();
+        db.upload(owner, &key, None).await
+    }
+
+    async fn stored(db: &TestDb, upload: &DbUpload) -> bool {
+        db.storage.exists(&upload.file_path).await.unwrap()
+    }
+
+    async fn attach(db: &TestDb, upload: &DbUpload, post: Option<&DbPost>, comment: Option<&DbComment>) {
+        diesel::insert_into(content_uploads::table)
+            .values((
+                content_uploads::upload_id.eq(upload.id),
+                content_uploads::post_id.eq(post.map(|p| p.id)),
+                content_uploads::comment_id.eq(comment.map(|c| c.id)),
+            ))
+            .execute(&mut db.conn().await)
+            .await
+            .unwrap();
+    }
+
+    async fn vote_on_post(db: &TestDb, user: &DbUser, post: &DbPost) {
+        diesel::insert_into(post_votes::table)
+            .values((
+                post_votes::user_id.eq(user.id),
+                post_votes::post_id.eq(post.id),
+                post_votes::score.eq(1i16),
+            ))
+            .execute(&mut db.conn().await)
+            .await
+            .unwrap();
+    }
+
+    async fn vote_on_comment(db: &TestDb, user: &DbUser, comment: &DbComment) {
+        diesel::insert_into(comment_votes::table)
+            .values((
+                comment_votes::user_id.eq(user.id),
+                comment_votes::comment_id.eq(comment.id),
+                comment_votes::post_id.eq(comment.post_id),
+                comment_votes::score.eq(1i16),
+            ))
+            .execute(&mut db.conn().await)
+            .await
+            .unwrap();
+    }
+
+    async fn report_post(db: &TestDb, user: &DbUser, post: &DbPost) {
+        diesel::insert_into(post_reports::table)
+            .values((
+                post_reports::creator_id.eq(user.id),
+                post_reports::post_id.eq(post.id),
+                post_reports::original_post_title.eq(&post.title),
+                post_reports::reason.eq("Spam"),
+            ))
+            .execute(&mut db.conn().await)
+            .await
+            .unwrap();
+    }
+
+    async fn report_comment(db: &TestDb, user: &DbUser, comment: &DbComment) {
+        diesel::insert_into(comment_reports::table)
+            .values((
+                comment_reports::creator_id.eq(user.id),
+                comment_reports::comment_id.eq(comment.id),
+                comment_reports::original_comment_text.eq(&comment.body),
+                comment_reports::reason.eq("Spam"),
+            ))
+            .execute(&mut db.conn().await)
+            .await
+            .unwrap();
+    }
+
+    async fn log_entry(db: &TestDb, target_id: Uuid) -> ModerationLog {
+        moderation_log::table
+            .filter(moderation_log::target_id.eq(target_id))
+            .first(&mut db.conn().await)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_purge_post() {
+        let Some(db) = TestDb::new().await else { return };
+        let admin = db.user(3).await;
+        let author = db.user(0).await;
+        let other = db.user(0).await;
+        let board = db.board().await;
+
+        let post = db.post(&board, &author).await;
+        let comment = db.comment(&post, &other, None).await;
+        let reply = db.comment(&post, &author, Some(&comment)).await;
+        vote_on_post(&db, &other, &post).await;
+        vote_on_comment(&db, &author, &comment).await;
+        report_post(&db, &other, &post).await;
+        report_comment(&db, &author, &reply).await;
+
+        let kept_post = db.post(&board, &other).await;
+        let kept_comment = db.comment(&kept_post, &author, None).await;
+
+        let only_here = stored_upload(&db, &author).await;
+        attach(&db, &only_here, Some(&post), None).await;
+        let in_comment = stored_upload(&db, &other).await;
+        attach(&db, &in_comment, None, Some(&comment)).await;
+        let shared = stored_upload(&db, &author).await;
+        attach(&db, &shared, Some(&post), None).await;
+        attach(&db, &shared, Some(&kept_post), None).await;
+
+        let data = db
+            .execute_ok(
+                Some(&admin),
+                &format!(r#"mutation {{ purgePost(postId: "{}", reason: "Doxxing") }}"#, post.id),
+            )
+            .await;
+        assert_eq!(data["purgePost"], true);
+
+        assert_eq!(count(&db, "SELECT count(*) FROM posts WHERE id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE post_id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM post_votes WHERE post_id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comment_votes WHERE post_id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM post_reports WHERE post_id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comment_reports WHERE comment_id = $1", reply.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE id = $1", kept_comment.id).await, 1);
+
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", only_here.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", in_comment.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", shared.id).await, 1);
+        assert!(!stored(&db, &only_here).await);
+        assert!(!stored(&db, &in_comment).await);
+        assert!(stored(&db, &shared).await);
+
+        let entry = log_entry(&db, post.id).await;
+        assert_eq!(entry.action_type, DbModerationAction::PurgePost);
+        assert_eq!(entry.moderator_id, admin.id);
+        assert_eq!(entry.board_id, Some(board.id));
+        assert_eq!(entry.reason.as_deref(), Some("Doxxing"));
+        assert_eq!(entry.metadata, Some(serde_json::json!({ "comments": 2, "files": 2 })));
+    }
+
+    #[tokio::test]
+    async fn test_purge_comment() {
+        let Some(db) = TestDb::new().await else { return };
+        let admin = db.user(3).await;
+        let author = db.user(0).await;
+        let other = db.user(0).await;
+        let board = db.board().await;
+        let post = db.post(&board, &other).await;
+
+        let comment = db.comment(&post, &author, None).await;
+        let reply = db.comment(&post, &other, Some(&comment)).await;
+        let nested = db.comment(&post, &author, Some(&reply)).await;
+        let sibling = db.comment(&post, &author, None).await;
+        vote_on_comment(&db, &other, &comment).await;
+        vote_on_comment(&db, &author, &reply).await;
+        report_comment(&db, &other, &nested).await;
+
+        let in_reply = stored_upload(&db, &other).await;
+        attach(&db, &in_reply, None, Some(&reply)).await;
+        let shared = stored_upload(&db, &author).await;
+        attach(&db, &shared, None, Some(&comment)).await;
+        attach(&db, &shared, None, Some(&sibling)).await;
+
+        db.execute_ok(
+            Some(&admin),
+            &format!(r#"mutation {{ purgeComment(commentId: "{}") }}"#, comment.id),
+        )
+        .await;
+
+        for id in [comment.id, reply.id, nested.id] {
+            assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE id = $1", id).await, 0);
+            assert_eq!(count(&db, "SELECT count(*) FROM comment_votes WHERE comment_id = $1", id).await, 0);
+            assert_eq!(count(&db, "SELECT count(*) FROM comment_reports WHERE comment_id = $1", id).await, 0);
+        }
+        assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE id = $1", sibling.id).await, 1);
+        assert_eq!(count(&db, "SELECT count(*) FROM posts WHERE id = $1", post.id).await, 1);
+
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", in_reply.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", shared.id).await, 1);
+        assert!(!stored(&db, &in_reply).await);
+        assert!(stored(&db, &shared).await);
+
+        let entry = log_entry(&db, comment.id).await;
+        assert_eq!(entry.action_type, DbModerationAction::PurgeComment);
+        assert_eq!(entry.reason, None);
+        assert_eq!(entry.metadata, Some(serde_json::json!({ "replies": 2, "files": 1 })));
+    }
+
+    #[tokio::test]
+    async fn test_purge_board() {
+        let Some(db) = TestDb::new().await else { return };
+        let admin = db.user(5).await;
+        let author = db.user(0).await;
+        let other = db.user(0).await;
+        let board = db.board().await;
+
+        let post = db.post(&board, &author).await;
+        let other_post = db.post(&board, &other).await;
+        let comment = db.comment(&post, &other, None).await;
+        db.comment(&other_post, &author, Some(&db.comment(&other_post, &other, None).await)).await;
+        vote_on_post(&db, &other, &post).await;
+        vote_on_comment(&db, &author, &comment).await;
+        report_post(&db, &other, &other_post).await;
+
+        // Posted on this board and elsewhere
+        let elsewhere = db.board().await;
+        let elsewhere_post = db.post(&elsewhere, &author).await;
+        let shared = stored_upload(&db, &author).await;
+        attach(&db, &shared, Some(&post), None).await;
+        attach(&db, &shared, Some(&elsewhere_post), None).await;
+        let in_comment = stored_upload(&db, &other).await;
+        attach(&db, &in_comment, None, Some(&comment)).await;
+
+        // The board's icon is only referenced by URL
+        let icon = stored_upload(&db, &author).await;
+        diesel::update(boards::table.find(board.id))
+            .set(boards::icon.eq(&icon.upload_url))
+            .execute(&mut db.conn().await)
+            .await
+            .unwrap();
+
+        db.execute_ok(
+            Some(&admin),
+            &format!(r#"mutation {{ purgeBoard(boardId: "{}") }}"#, board.id),
+        )
+        .await;
+
+        assert_eq!(count(&db, "SELECT count(*) FROM boards WHERE id = $1", board.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM posts WHERE board_id = $1", board.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE board_id = $1", board.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM post_votes WHERE post_id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comment_votes WHERE post_id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM post_reports WHERE post_id = $1", other_post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM posts WHERE id = $1", elsewhere_post.id).await, 1);
+
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", in_comment.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", icon.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", shared.id).await, 1);
+        assert!(!stored(&db, &in_comment).await);
+        assert!(!stored(&db, &icon).await);
+        assert!(stored(&db, &shared).await);
+
+        let entry = log_entry(&db, board.id).await;
+        assert_eq!(entry.action_type, DbModerationAction::PurgeBoard);
+        assert_eq!(entry.board_id, None);
+        assert_eq!(entry.metadata, Some(serde_json::json!({ "posts": 2, "comments": 3, "files": 2 })));
+    }
+
+    #[tokio::test]
+    async fn test_purge_user() {
+        let Some(db) = TestDb::new().await else { return };
+        let admin = db.user(4).await;
+        let target = db.user(0).await;
+        let other = db.user(0).await;
+        let board = db.board().await;
+
+        let post = db.post(&board, &target).await;
+        let reply_to_post = db.comment(&post, &other, None).await;
+        let other_post = db.post(&board, &other).await;
+        let comment = db.comment(&other_post, &target, None).await;
+        let reply_to_comment = db.comment(&other_post, &other, Some(&comment)).await;
+        let kept_comment = db.comment(&other_post, &other, None).await;
+
+        // Their votes on other people's content, and other people's on theirs
+        vote_on_post(&db, &target, &other_post).await;
+        vote_on_comment(&db, &target, &kept_comment).await;
+        vote_on_post(&db, &other, &post).await;
+        vote_on_comment(&db, &other, &comment).await;
+        report_post(&db, &other, &post).await;
+        report_comment(&db, &target, &kept_comment).await;
+
+        let own = stored_upload(&db, &target).await;
+        attach(&db, &own, Some(&post), None).await;
+        let in_reply = stored_upload(&db, &other).await;
+        attach(&db, &in_reply, None, Some(&reply_to_comment)).await;
+        let shared = stored_upload(&db, &other).await;
+        attach(&db, &shared, None, Some(&reply_to_post)).await;
+        attach(&db, &shared, None, Some(&kept_comment)).await;
+
+        db.execute_ok(
+            Some(&admin),
+            &format!(r#"mutation {{ purgeUser(userId: "{}", reason: "Spam bot") }}"#, target.id),
+        )
+        .await;
+
+        assert_eq!(count(&db, "SELECT count(*) FROM users WHERE id = $1", target.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM posts WHERE id = $1", post.id).await, 0);
+        for id in [reply_to_post.id, comment.id, reply_to_comment.id] {
+            assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE id = $1", id).await, 0);
+        }
+        assert_eq!(count(&db, "SELECT count(*) FROM post_votes WHERE user_id = $1", target.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comment_votes WHERE user_id = $1", target.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comment_votes WHERE comment_id = $1", comment.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM post_reports WHERE post_id = $1", post.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM comment_reports WHERE creator_id = $1", target.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM posts WHERE id = $1", other_post.id).await, 1);
+        assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE id = $1", kept_comment.id).await, 1);
+
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", own.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", in_reply.id).await, 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads WHERE id = $1", shared.id).await, 1);
+        assert!(!stored(&db, &own).await);
+        assert!(!stored(&db, &in_reply).await);
+        assert!(stored(&db, &shared).await);
+
+        let entry = log_entry(&db, target.id).await;
+        assert_eq!(entry.action_type, DbModerationAction::PurgeUser);
+        assert_eq!(entry.reason.as_deref(), Some("Spam bot"));
+        assert_eq!(entry.metadata, Some(serde_json::json!({ "posts": 1, "comments": 3, "files": 2 })));
+    }
+
+    #[tokio::test]
+    async fn test_purge_permissions() {
+        let Some(db) = TestDb::new().await else { return };
+        let user = db.user(0).await;
+        let content_admin = db.user(3).await;
+        let users_admin = db.user(4).await;
+        let boards_admin = db.user(5).await;
+        let owner = db.user(7).await;
+        let other_owner = db.user(7).await;
+        let board = db.board().await;
+        let post = db.post(&board, &user).await;
+        let comment = db.comment(&post, &user, None).await;
+
+        let purge_post = format!(r#"mutation {{ purgePost(postId: "{}") }}"#, post.id);
+        let purge_comment = format!(r#"mutation {{ purgeComment(commentId: "{}") }}"#, comment.id);
+        let purge_board = format!(r#"mutation {{ purgeBoard(boardId: "{}") }}"#, board.id);
+        let purge_user = |user: &DbUser| format!(r#"mutation {{ purgeUser(userId: "{}") }}"#, user.id);
+
+        let refused = [
+            (&user, purge_post.clone()),
+            (&user, purge_comment.clone()),
+            (&user, purge_board.clone()),
+            (&user, purge_user(&content_admin)),
+            // Boards and users need more than content permissions
+            (&content_admin, purge_board.clone()),
+            (&content_admin, purge_user(&user)),
+            (&users_admin, purge_board.clone()),
+            // Admins can't purge themselves or an admin ranked at least as high
+            (&users_admin, purge_user(&users_admin)),
+            (&users_admin, purge_user(&boards_admin)),
+            (&owner, purge_user(&owner)),
+        ];
+        for (admin, query) in refused {
+            let response = TestDb::execute(&db, Some(admin), &query).await;
+            assert!(!response.errors.is_empty(), "{} was allowed for admin level {}", query, admin.admin_level);
+        }
+        assert_eq!(count(&db, "SELECT count(*) FROM comments WHERE id = $1", comment.id).await, 1);
+        assert_eq!(count(&db, "SELECT count(*) FROM boards WHERE id = $1", board.id).await, 1);
+        assert_eq!(count(&db, "SELECT count(*) FROM users WHERE id = $1", boards_admin.id).await, 1);
+
+        // Owners outrank every other admin
+        db.execute_ok(Some(&owner), &purge_user(&other_owner)).await;
+        db.execute_ok(Some(&content_admin), &purge_comment).await;
+    }
+}
@@ -8,7 +8,7 @@ use tinyboards_db::{
         moderator::moderation_log::ModerationLogInsertForm,
         user::user::{AdminPerms, User as DbUser, UserUpdateForm},
     },
-    schema::{moderation_log, user_aggregates, users},
+    schema::{account_lockouts, moderation_log, user_aggregates, users},
     utils::{get_conn, DbPool},
 };
 use tinyboards_utils::TinyBoardsError;
@@ -267,4 +267,27 @@ impl UserManagement {
 
         Ok(true)
     }
+
+    /// Lift a lockout from too many failed logins and reset the account's
+    /// failure count (admin with Users permission). Returns false if the
+    /// account had no failed logins on record.
+    pub async fn unlock_account(&self, ctx: &Context<'_>, user_id: ID) -> Result<bool> {
+        let pool = ctx.data::<DbPool>()?;
+        let admin = permissions::require_admin_permission(ctx, AdminPerms::Users)?;
+
+        let target_uuid: Uuid = user_id
+            .parse()
+            .map_err(|_| TinyBoardsError::BadRequest("Invalid user ID".to_string()))?;
+
+        let conn = &mut get_conn(pool).await?;
+        let deleted = diesel::delete(account_lockouts::table.find(target_uuid))
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(format!("Failed to unlock account: {}", e)))?;
+
+        if deleted > 0 {
+            tracing::info!("Admin {} unlocked account {}", admin.name, target_uuid);
+        }
+        Ok(deleted > 0)
+    }
 }
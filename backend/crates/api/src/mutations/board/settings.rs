@@ -1,5 +1,8 @@
 use crate::{
-    helpers::{files::upload::upload_file_opendal, permissions},
+    helpers::{
+        files::upload::upload_file_opendal,
+        permissions::{self, TokenScopeGuard},
+    },
     structs::boards::Board,
     Settings,
 };
@@ -17,6 +20,7 @@ use tinyboards_db::{
     schema::{board_aggregates, boards},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::{
     css_sanitizer::{sanitize_css, MAX_BOARD_CSS_BYTES},
     parser::parse_markdown_opt,
@@ -55,7 +59,7 @@ pub struct UpdateBoardSettingsResponse {
 #[derive(Default)]
 pub struct UpdateBoardSettings;
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl UpdateBoardSettings {
     /// Update board settings
     async fn update_board_settings(
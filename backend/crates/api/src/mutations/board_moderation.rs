@@ -14,9 +14,11 @@ use tinyboards_db::{
     schema::{board_aggregates, board_moderators, boards, users},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::permissions::TokenScopeGuard;
 use crate::structs::boards::Board as GqlBoard;
 
 #[derive(Default)]
@@ -40,7 +42,7 @@ pub struct TransferOwnershipResponse {
     pub message: String,
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl BoardModerationMutations {
     /// Add a moderator to a board
     pub async fn add_moderator(
@@ -1,3 +1,4 @@
+use crate::events::{self, LiveEvent};
 use crate::helpers::permissions;
 use crate::structs::comment::Comment;
 use crate::DbPool;
@@ -20,6 +21,7 @@ use tinyboards_db::{
     },
     utils::get_conn,
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -114,6 +116,14 @@ impl CommentActions {
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
         }
 
+        events::publish(
+            ctx,
+            LiveEvent::ScoreChanged {
+                post_id: comment.post_id,
+                comment_id: Some(comment_uuid),
+            },
+        );
+
         load_comment_with_counts(conn, comment_uuid)
             .await
             .map_err(|e| e.into())
@@ -186,23 +196,27 @@ impl CommentActions {
             .await
             .map_err(|_| TinyBoardsError::NotFound("Comment not found".into()))?;
 
-        let can_delete = comment.creator_id == user.id
-            || user.has_permission(AdminPerms::Content)
-            || board_moderators::table
-                .filter(board_moderators::board_id.eq(comment.board_id))
-                .filter(board_moderators::user_id.eq(user.id))
-                .first::<BoardModerator>(conn)
-                .await
-                .ok()
-                .map(|m| m.has_permission(ModPerms::Content))
-                .unwrap_or(false);
-
-        if !can_delete {
-            return Err(TinyBoardsError::from_message(
-                403,
-                "You don't have permission to delete this comment",
-            )
-            .into());
+        // Deleting someone else's comment is a moderator action
+        if comment.creator_id != user.id {
+            permissions::check_token_scope(ctx, ApiTokenScope::Moderate)?;
+
+            let can_delete = user.has_permission(AdminPerms::Content)
+                || board_moderators::table
+                    .filter(board_moderators::board_id.eq(comment.board_id))
+                    .filter(board_moderators::user_id.eq(user.id))
+                    .first::<BoardModerator>(conn)
+                    .await
+                    .ok()
+                    .map(|m| m.has_permission(ModPerms::Content))
+                    .unwrap_or(false);
+
+            if !can_delete {
+                return Err(TinyBoardsError::from_message(
+                    403,
+                    "You don't have permission to delete this comment",
+                )
+                .into());
+            }
         }
 
         let form = CommentUpdateForm {
@@ -1,9 +1,13 @@
-use crate::helpers::{permissions, validation::require_mod_or_admin};
-use crate::structs::comment::Comment;
-use crate::DbPool;
+use crate::helpers::{
+    permissions::{self, TokenScopeGuard},
+    removal_reasons::{deliver_removal_reason, load_reason_for_board, RemovedContent},
+    validation::require_mod_or_admin,
+};
+use crate::structs::{comment::Comment, removal_reason::RemovalMessageDelivery};
+use crate::{events, DbPool, Settings};
 use async_graphql::*;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use tinyboards_db::{
     enums::{DbApprovalStatus, DbModerationAction},
     models::{
@@ -16,6 +20,7 @@ use tinyboards_db::{
     schema::{comment_aggregates, comments, moderation_log},
     utils::get_conn,
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -40,14 +45,17 @@ async fn load_comment_with_counts(
     Ok(Comment::from((db_comment, agg)))
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl CommentModeration {
-    /// Remove a comment (mod/admin action)
+    /// Remove a comment (mod/admin action). A predefined removal reason can
+    /// be given, and its message optionally sent to the author.
     pub async fn remove_comment(
         &self,
         ctx: &Context<'_>,
         comment_id: ID,
         reason: Option<String>,
+        removal_reason_id: Option<ID>,
+        send_message: Option<RemovalMessageDelivery>,
     ) -> Result<Comment> {
         let user = permissions::require_auth_not_banned(ctx)?;
         let pool = ctx.data::<DbPool>()?;
@@ -72,30 +80,69 @@ impl CommentModeration {
         )
         .await?;
 
-        diesel::update(comments::table.find(comment_uuid))
-            .set(&CommentUpdateForm {
-                is_removed: Some(true),
-                ..Default::default()
+        let removal_reason =
+            load_reason_for_board(conn, removal_reason_id.as_ref(), send_message, comment.board_id).await?;
+        let settings = ctx.data::<Settings>()?.as_ref();
+
+        // The removal, the message to the author and the log entry are saved together
+        let live_events = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    diesel::update(comments::table.find(comment_uuid))
+                        .set(&CommentUpdateForm {
+                            is_removed: Some(true),
+                            ..Default::default()
+                        })
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    let (reason, metadata, live_events) = match removal_reason {
+                        Some(removal_reason) => {
+                            let delivery = deliver_removal_reason(
+                                pool,
+                                settings,
+                                conn,
+                                user,
+                                &removal_reason,
+                                send_message,
+                                RemovedContent {
+                                    board_id: comment.board_id,
+                                    post_id: comment.post_id,
+                                    comment: Some(&comment),
+                                    author_id: comment.creator_id,
+                                },
+                            )
+                            .await?;
+                            (reason.or(Some(removal_reason.title)), Some(delivery.metadata), delivery.events)
+                        }
+                        None => (reason, None, Vec::new()),
+                    };
+
+                    diesel::insert_into(moderation_log::table)
+                        .values(&ModerationLogInsertForm {
+                            moderator_id: user.id,
+                            action_type: DbModerationAction::RemoveComment,
+                            target_type: "comment".to_string(),
+                            target_id: comment_uuid,
+                            board_id: Some(comment.board_id),
+                            reason,
+                            metadata,
+                            expires_at: None,
+                        })
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    Ok(live_events)
+                }
+                .scope_boxed()
             })
-            .execute(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+            .await?;
 
-        // Log to moderation_log
-        diesel::insert_into(moderation_log::table)
-            .values(&ModerationLogInsertForm {
-                moderator_id: user.id,
-                action_type: DbModerationAction::RemoveComment,
-                target_type: "comment".to_string(),
-                target_id: comment_uuid,
-                board_id: Some(comment.board_id),
-                reason,
-                metadata: None,
-                expires_at: None,
-            })
-            .execute(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        for event in live_events {
+            events::publish(ctx, event);
+        }
 
         load_comment_with_counts(conn, comment_uuid)
             .await
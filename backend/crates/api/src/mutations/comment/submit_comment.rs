@@ -1,6 +1,8 @@
+use crate::events::{self, EventBus, LiveEvent};
 use crate::helpers::files::cleanup::link_content_uploads;
 use crate::helpers::permissions;
 use crate::structs::comment::Comment;
+use crate::utils::rate_limit::check_rate_limit;
 use crate::{DbPool, LoggedInUser, Settings};
 use async_graphql::*;
 use diesel::prelude::*;
@@ -17,7 +19,7 @@ use tinyboards_db::{
     schema::{board_user_bans, comment_aggregates, comment_votes, comments, posts, site},
     utils::get_conn,
 };
-use tinyboards_utils::{slug::generate_slug, TinyBoardsError};
+use tinyboards_utils::{rate_limit::RateLimitCell, slug::generate_slug, TinyBoardsError};
 use uuid::Uuid;
 
 #[derive(Default)]
@@ -37,6 +39,7 @@ impl SubmitComment {
             .data_unchecked::<LoggedInUser>()
             .require_user_approved(pool)
             .await?;
+        check_rate_limit(ctx, RateLimitCell::comment).await?;
         let settings = ctx.data::<Settings>()?.as_ref();
         let conn = &mut get_conn(pool).await?;
 
@@ -164,6 +167,7 @@ impl SubmitComment {
             extract_mentions, get_user_ids_for_mentions,
             create_comment_mention_notification,
         };
+        let event_bus = ctx.data_opt::<EventBus>();
 
         // Notify parent comment author if this is a reply to a comment
         if let Some(parent_uuid) = parent_uuid {
@@ -173,14 +177,14 @@ impl SubmitComment {
                 .await
                 .map_err(|_| TinyBoardsError::NotFound("Parent comment not found".into()))?;
             if parent.creator_id != v.id {
-                let _ = create_comment_reply_notification(pool, parent.creator_id, comment_id, v.id)
+                let _ = create_comment_reply_notification(pool, event_bus, parent.creator_id, comment_id, v.id)
                     .await;
             }
         }
 
         // Notify post author if this is a top-level comment on their post
         if parent_uuid.is_none() && post.creator_id != v.id {
-            let _ = create_post_reply_notification(pool, post.creator_id, post_uuid, comment_id, v.id)
+            let _ = create_post_reply_notification(pool, event_bus, post.creator_id, post_uuid, comment_id, v.id)
                 .await;
         }
 
@@ -191,7 +195,7 @@ impl SubmitComment {
                 for mentioned_user_id in mentioned_user_ids {
                     if mentioned_user_id != v.id {
                         let _ =
-                            create_comment_mention_notification(pool, mentioned_user_id, comment_id, v.id)
+                            create_comment_mention_notification(pool, event_bus, mentioned_user_id, comment_id, v.id)
                                 .await;
                     }
                 }
@@ -211,6 +215,14 @@ impl SubmitComment {
             .await
             .map_err(|_| TinyBoardsError::NotFound("Comment aggregates not found".into()))?;
 
+        events::publish(
+            ctx,
+            LiveEvent::CommentCreated {
+                post_id: post_uuid,
+                comment_id,
+            },
+        );
+
         Ok(Comment::from((db_comment, agg)))
     }
 }
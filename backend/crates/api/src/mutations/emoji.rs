@@ -1,6 +1,6 @@
 use crate::{
     LoggedInUser,
-    helpers::files::emoji::upload_emoji_file,
+    helpers::{files::emoji::upload_emoji_file, permissions::TokenScopeGuard},
     structs::emoji::{CreateEmojiInput, EmojiObject, EmojiScope, UpdateEmojiInput},
 };
 use async_graphql::*;
@@ -16,6 +16,7 @@ use tinyboards_db::{
     schema::{board_moderators, emoji, emoji_keywords},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -66,7 +67,7 @@ async fn check_emoji_permission(
     Ok(())
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl EmojiMutations {
     /// Create a new emoji (admin/mod only)
     async fn create_emoji(
@@ -1,12 +1,20 @@
-use crate::{LoggedInUser, Settings, helpers::files::upload::upload_file_opendal};
+use crate::{
+    helpers::files::upload::upload_file_queued, structs::upload::FileUpload,
+    utils::rate_limit::check_rate_limit, LoggedInUser, Settings,
+};
 use async_graphql::*;
+use tinyboards_utils::rate_limit::RateLimitCell;
 
 #[derive(Default)]
 pub struct FileUploadMutation;
 
 #[Object]
 impl FileUploadMutation {
-    /// Upload a file and return the URL
+    /// Upload a file and return it as soon as it's stored
+    ///
+    /// Images are optimized in the background: `processingStatus` starts as PENDING and
+    /// `optimizedUrl`/`thumbnailUrl` are filled in once it's DONE. Poll the `upload` query
+    /// or subscribe to `uploadProcessed` to pick them up; `url` works meanwhile.
     ///
     /// This creates an Upload record in the database but does NOT create a ContentUpload link.
     /// When the user actually submits their comment/post, the content should be scanned for
@@ -18,20 +26,20 @@ impl FileUploadMutation {
         &self,
         ctx: &Context<'_>,
         file: Upload,
-    ) -> Result<String> {
+    ) -> Result<FileUpload> {
         let user = ctx.data::<LoggedInUser>()?.require_user_not_banned()?;
+        check_rate_limit(ctx, RateLimitCell::image).await?;
         let settings = ctx.data::<Settings>()?.as_ref();
 
-        // Upload file and create Upload record
-        let url = upload_file_opendal(
+        // Store the file and create the Upload record; processing is queued
+        let upload = upload_file_queued(
             file,
-            None,
             user.id,
             Some(settings.media.max_file_size_mb),
             ctx,
         )
         .await?;
 
-        Ok(url.to_string())
+        Ok(FileUpload::from(upload))
     }
 }
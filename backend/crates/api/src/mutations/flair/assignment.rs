@@ -1,4 +1,5 @@
 use crate::{
+    helpers::permissions::{self, TokenScopeGuard},
     structs::flair::{AssignPostFlairInput, AssignUserFlairInput, PostFlair, UserFlair},
     LoggedInUser,
 };
@@ -18,6 +19,7 @@ use tinyboards_db::{
     schema::{board_moderators, flair_templates, post_flairs, posts, user_flairs},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -67,6 +69,7 @@ impl FlairAssignmentMutations {
 
         // Check permissions if not the author
         if !is_author && !user.is_admin {
+            permissions::check_token_scope(ctx, ApiTokenScope::Moderate)?;
             let _mod: BoardModerator = board_moderators::table
                 .filter(board_moderators::board_id.eq(post_board_id))
                 .filter(board_moderators::user_id.eq(user.id))
@@ -146,6 +149,7 @@ impl FlairAssignmentMutations {
 
         let is_author = post_creator_id == user.id;
         if !is_author && !user.is_admin {
+            permissions::check_token_scope(ctx, ApiTokenScope::Moderate)?;
             let _mod: BoardModerator = board_moderators::table
                 .filter(board_moderators::board_id.eq(post_board_id))
                 .filter(board_moderators::user_id.eq(user.id))
@@ -192,6 +196,7 @@ impl FlairAssignmentMutations {
 
         // If not self-assigning, check mod/admin
         if !is_self && !user.is_admin {
+            permissions::check_token_scope(ctx, ApiTokenScope::Moderate)?;
             let _mod: BoardModerator = board_moderators::table
                 .filter(board_moderators::board_id.eq(board_uuid))
                 .filter(board_moderators::user_id.eq(user.id))
@@ -288,6 +293,7 @@ impl FlairAssignmentMutations {
 
         let is_self = target_uuid == user.id;
         if !is_self && !user.is_admin {
+            permissions::check_token_scope(ctx, ApiTokenScope::Moderate)?;
             let _mod: BoardModerator = board_moderators::table
                 .filter(board_moderators::board_id.eq(board_uuid))
                 .filter(board_moderators::user_id.eq(user.id))
@@ -310,6 +316,7 @@ impl FlairAssignmentMutations {
     }
 
     /// Approve or reject a pending user flair (mod only)
+    #[graphql(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
     async fn approve_user_flair(
         &self,
         ctx: &Context<'_>,
@@ -1,4 +1,5 @@
 use crate::{
+    helpers::permissions::TokenScopeGuard,
     structs::flair::{CreateFlairTemplateInput, FlairTemplate, FlairType, UpdateFlairTemplateInput},
     LoggedInUser,
 };
@@ -14,6 +15,7 @@ use tinyboards_db::{
     schema::{board_moderators, flair_templates},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -44,7 +46,7 @@ async fn require_board_mod_or_admin(
 #[derive(Default)]
 pub struct FlairTemplateMutations;
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl FlairTemplateMutations {
     /// Create a new flair template (mod/admin only)
     async fn create_flair_template(
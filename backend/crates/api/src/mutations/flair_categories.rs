@@ -9,10 +9,12 @@ use tinyboards_db::{
     schema::{board_moderators, flair_categories},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
 use crate::{
+    helpers::permissions::TokenScopeGuard,
     structs::flair::{CreateFlairCategoryInput, FlairCategory, UpdateFlairCategoryInput},
     LoggedInUser,
 };
@@ -20,7 +22,7 @@ use crate::{
 #[derive(Default)]
 pub struct MutationFlairCategories;
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl MutationFlairCategories {
     /// Create a new flair category
     pub async fn create_flair_category(
@@ -9,7 +9,10 @@ use tinyboards_db::{
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
-use crate::LoggedInUser;
+use crate::{
+    events::{self, LiveEvent},
+    LoggedInUser,
+};
 
 #[derive(Default)]
 pub struct MessageActionMutations;
@@ -43,6 +46,13 @@ impl MessageActionMutations {
         .await
         .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
+        events::publish(
+            ctx,
+            LiveEvent::UnreadCountsChanged {
+                user_id: current_user.id,
+            },
+        );
+
         Ok(true)
     }
 
@@ -75,6 +85,10 @@ impl MessageActionMutations {
             return Err(TinyBoardsError::from_message(403, "You can only delete your own messages").into());
         }
 
+        if message.thread_id.is_some() {
+            return Err(TinyBoardsError::from_message(400, "Modmail messages can't be deleted").into());
+        }
+
         // Soft delete the message
         diesel::update(private_messages::table.find(msg_id))
             .set(private_messages::deleted_at.eq(chrono::Utc::now()))
@@ -82,6 +96,15 @@ impl MessageActionMutations {
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
+        if let Some(recipient_id) = message.recipient_id.filter(|_| !message.is_read) {
+            events::publish(
+                ctx,
+                LiveEvent::UnreadCountsChanged {
+                    user_id: recipient_id,
+                },
+            );
+        }
+
         Ok(true)
     }
 }
@@ -62,6 +62,11 @@ impl EditMessageMutations {
             return Err(TinyBoardsError::from_message(404, "Message has been deleted").into());
         }
 
+        // Modmail is kept as sent so both sides see the same history
+        if existing.thread_id.is_some() {
+            return Err(TinyBoardsError::from_message(400, "Modmail messages can't be edited").into());
+        }
+
         // Validate input if provided
         if let Some(ref subject) = input.subject {
             if subject.len() > 200 {
@@ -1,7 +1,9 @@
 pub mod actions;
 pub mod send_message;
 pub mod edit_message;
+pub mod modmail;
 
 pub use actions::*;
 pub use send_message::*;
-pub use edit_message::*;
\ No newline at end of file
+pub use edit_message::*;
+pub use modmail::*;
\ No newline at end of file
@@ -0,0 +1,155 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use tinyboards_db::{
+    models::message::modmail_thread::{
+        ModmailThread as DbModmailThread, ModmailThreadInsertForm, ModmailThreadUpdateForm,
+    },
+    schema::{boards, modmail_threads},
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::{rate_limit::RateLimitCell, TinyBoardsError};
+use uuid::Uuid;
+
+use crate::{
+    helpers::modmail::{self, MessageKind},
+    structs::modmail::{ModmailMessage, ModmailThread},
+    utils::rate_limit::check_rate_limit,
+    LoggedInUser, Settings,
+};
+
+#[derive(Default)]
+pub struct ModmailMutations;
+
+#[derive(InputObject)]
+pub struct SendModmailInput {
+    pub board_id: ID,
+    pub subject: String,
+    pub body: String,
+}
+
+#[Object]
+impl ModmailMutations {
+    /// Start a modmail thread with a board's moderators
+    pub async fn send_modmail(
+        &self,
+        ctx: &Context<'_>,
+        input: SendModmailInput,
+    ) -> Result<ModmailThread> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_approved(pool).await?;
+        check_rate_limit(ctx, RateLimitCell::message).await?;
+        let settings = ctx.data::<Settings>()?.as_ref();
+        let conn = &mut get_conn(pool).await?;
+
+        let board_id: Uuid = input.board_id
+            .parse()
+            .map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into()))?;
+
+        let board_exists: i64 = boards::table
+            .find(board_id)
+            .filter(boards::deleted_at.is_null())
+            .count()
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if board_exists == 0 {
+            return Err(TinyBoardsError::NotFound("Board not found".into()).into());
+        }
+
+        let subject = input.subject.trim();
+        if subject.is_empty() || subject.chars().count() > 200 {
+            return Err(TinyBoardsError::from_message(400, "Subjects must be between 1 and 200 characters").into());
+        }
+
+        let subject = subject.to_string();
+        let author_id = user.id;
+        let body = &input.body;
+        // A thread is only created along with its first message
+        let thread = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    let thread: DbModmailThread = diesel::insert_into(modmail_threads::table)
+                        .values(&ModmailThreadInsertForm {
+                            board_id,
+                            user_id: author_id,
+                            subject,
+                        })
+                        .get_result(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    modmail::insert_message(pool, conn, settings, &thread, author_id, body, MessageKind::FromUser)
+                        .await?;
+
+                    Ok(thread)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(ModmailThread::from_db(thread, false))
+    }
+
+    /// Reply to a modmail thread you started. Writing to an archived thread
+    /// reopens it.
+    pub async fn reply_to_modmail(
+        &self,
+        ctx: &Context<'_>,
+        thread_id: ID,
+        body: String,
+    ) -> Result<ModmailMessage> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_approved(pool).await?;
+        check_rate_limit(ctx, RateLimitCell::message).await?;
+        let settings = ctx.data::<Settings>()?.as_ref();
+        let conn = &mut get_conn(pool).await?;
+
+        let thread = modmail::find_thread(conn, &thread_id).await?;
+        if thread.user_id != user.id {
+            return Err(TinyBoardsError::NotFound("Thread not found".into()).into());
+        }
+
+        let message =
+            modmail::insert_message(pool, conn, settings, &thread, user.id, &body, MessageKind::FromUser).await?;
+
+        diesel::update(modmail_threads::table.find(thread.id))
+            .set(&ModmailThreadUpdateForm {
+                status: (thread.status == "archived").then(|| "new".to_string()),
+                is_read_by_mods: Some(false),
+                last_message_at: Some(message.created_at),
+                ..Default::default()
+            })
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(ModmailMessage::from_db(message, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::TestDb;
+
+    #[tokio::test]
+    async fn test_thread_is_not_kept_when_its_message_is_rejected() {
+        let Some(db) = TestDb::new().await else { return };
+        let board = db.board().await;
+        let user = db.user(0).await;
+
+        let response = db
+            .execute(
+                Some(&user),
+                &format!(
+                    r#"mutation {{ sendModmail(input: {{ boardId: "{}", subject: "Help", body: "   " }}) {{ id }} }}"#,
+                    board.id
+                ),
+            )
+            .await;
+        assert!(!response.errors.is_empty());
+
+        let data = db.execute_ok(Some(&user), "{ myModmailThreads { id } }").await;
+        assert!(data["myModmailThreads"].as_array().unwrap().is_empty());
+    }
+}
@@ -10,11 +10,13 @@ use tinyboards_db::{
     schema::{private_messages, notifications, user_blocks, site},
     utils::{get_conn, DbPool},
 };
-use tinyboards_utils::TinyBoardsError;
+use tinyboards_utils::{rate_limit::RateLimitCell, TinyBoardsError};
 use uuid::Uuid;
 
 use crate::{
+    events::{self, LiveEvent},
     structs::message::PrivateMessage,
+    utils::rate_limit::check_rate_limit,
     LoggedInUser,
     utils::emoji::process_content_with_emojis,
     Settings,
@@ -45,6 +47,7 @@ impl SendMessageMutations {
     ) -> Result<SendMessageResponse> {
         let pool = ctx.data::<DbPool>()?;
         let user = ctx.data_unchecked::<LoggedInUser>().require_user_approved(pool).await?;
+        check_rate_limit(ctx, RateLimitCell::message).await?;
         let conn = &mut get_conn(pool).await?;
 
         let recipient_id: Uuid = input.recipient_id
@@ -128,6 +131,8 @@ impl SendMessageMutations {
             body_html,
             is_read: false,
             is_sender_hidden: false,
+            thread_id: None,
+            is_mod_note: false,
         };
 
         let message: tinyboards_db::models::message::message::PrivateMessage =
@@ -146,14 +151,31 @@ impl SendMessageMutations {
             message_id: Some(message.id),
             is_read: false,
             actor_user_id: Some(user.id),
+            body: None,
         };
 
-        diesel::insert_into(notifications::table)
+        let notification_id: Uuid = diesel::insert_into(notifications::table)
             .values(&notif_form)
-            .execute(conn)
+            .returning(notifications::id)
+            .get_result(conn)
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
+        events::publish(
+            ctx,
+            LiveEvent::MessageSent {
+                recipient_id,
+                message_id: message.id,
+            },
+        );
+        events::publish(
+            ctx,
+            LiveEvent::NotificationCreated {
+                recipient_id,
+                notification_id,
+            },
+        );
+
         Ok(SendMessageResponse {
             message: PrivateMessage::from(message),
         })
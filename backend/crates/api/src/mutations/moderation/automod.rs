@@ -0,0 +1,215 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    enums::DbFlairType,
+    models::moderator::automod_rule::{
+        AutomodRule as DbAutomodRule, AutomodRuleInsertForm, AutomodRuleUpdateForm,
+    },
+    schema::{automod_rules, flair_templates},
+    utils::{get_conn, DbPool},
+};
+use tinyboards_auth::types::ApiTokenScope;
+use tinyboards_utils::{automod::AutomodActions, TinyBoardsError};
+use uuid::Uuid;
+
+use crate::helpers::{
+    automod::require_rule_manager,
+    permissions::{self, TokenScopeGuard},
+};
+use crate::structs::automod::{AutomodRule, CreateAutomodRuleInput, UpdateAutomodRuleInput};
+
+/// Rules per board, and site-wide
+const MAX_RULES: i64 = 100;
+
+#[derive(Default)]
+pub struct AutomodMutations;
+
+fn validate_name(name: &str) -> Result<String, TinyBoardsError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Err(TinyBoardsError::from_message(
+            400,
+            "Rule names must be between 1 and 100 characters",
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// A rule can only apply post flairs from its own board.
+async fn validate_flair(
+    conn: &mut diesel_async::AsyncPgConnection,
+    board_id: Option<Uuid>,
+    actions: &AutomodActions,
+) -> Result<(), TinyBoardsError> {
+    let Some(template_id) = actions.set_flair_id else {
+        return Ok(());
+    };
+    let Some(board_id) = board_id else {
+        return Err(TinyBoardsError::from_message(
+            400,
+            "Site-wide rules can't apply flair, since flairs belong to boards",
+        ));
+    };
+
+    let valid: i64 = flair_templates::table
+        .find(template_id)
+        .filter(flair_templates::board_id.eq(board_id))
+        .filter(flair_templates::flair_type.eq(DbFlairType::Post))
+        .filter(flair_templates::is_active.eq(true))
+        .count()
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    if valid == 0 {
+        return Err(TinyBoardsError::from_message(
+            400,
+            "The flair must be an active post flair from this board",
+        ));
+    }
+    Ok(())
+}
+
+async fn load_rule(
+    conn: &mut diesel_async::AsyncPgConnection,
+    rule_id: &ID,
+) -> Result<DbAutomodRule, TinyBoardsError> {
+    let rule_uuid: Uuid = rule_id
+        .parse()
+        .map_err(|_| TinyBoardsError::NotFound("Invalid rule ID".into()))?;
+    automod_rules::table
+        .find(rule_uuid)
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Rule not found".into()))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<serde_json::Value, TinyBoardsError> {
+    serde_json::to_value(value).map_err(|e| TinyBoardsError::Internal(e.to_string()))
+}
+
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
+impl AutomodMutations {
+    /// Add an AutoModerator rule to a board, or a site-wide rule without
+    /// `boardId` (admins only)
+    pub async fn create_automod_rule(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateAutomodRuleInput,
+    ) -> Result<AutomodRule> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+
+        let board_uuid: Option<Uuid> = input
+            .board_id
+            .map(|id| id.parse().map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into())))
+            .transpose()?;
+
+        require_rule_manager(user, pool, board_uuid).await?;
+
+        let name = validate_name(&input.name)?;
+        let conditions = input.conditions.into_conditions()?;
+        let actions = input.actions.into_actions()?;
+
+        let conn = &mut get_conn(pool).await?;
+        validate_flair(conn, board_uuid, &actions).await?;
+
+        let count: i64 = match board_uuid {
+            Some(id) => automod_rules::table
+                .filter(automod_rules::board_id.eq(id))
+                .count()
+                .get_result(conn)
+                .await,
+            None => automod_rules::table
+                .filter(automod_rules::board_id.is_null())
+                .count()
+                .get_result(conn)
+                .await,
+        }
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if count >= MAX_RULES {
+            return Err(TinyBoardsError::from_message(
+                400,
+                &format!("You can have at most {} rules", MAX_RULES),
+            )
+            .into());
+        }
+
+        let rule: DbAutomodRule = diesel::insert_into(automod_rules::table)
+            .values(&AutomodRuleInsertForm {
+                board_id: board_uuid,
+                name,
+                conditions: to_json(&conditions)?,
+                actions: to_json(&actions)?,
+                is_enabled: input.is_enabled.unwrap_or(true),
+                display_order: input.display_order.unwrap_or(0),
+                created_by: Some(user.id),
+            })
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(AutomodRule::from(rule))
+    }
+
+    /// Update an AutoModerator rule. Conditions and actions are replaced
+    /// as a whole when given.
+    pub async fn update_automod_rule(
+        &self,
+        ctx: &Context<'_>,
+        rule_id: ID,
+        input: UpdateAutomodRuleInput,
+    ) -> Result<AutomodRule> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let existing = load_rule(conn, &rule_id).await?;
+        require_rule_manager(user, pool, existing.board_id).await?;
+
+        let name = input.name.as_deref().map(validate_name).transpose()?;
+        let conditions = input
+            .conditions
+            .map(|c| c.into_conditions().and_then(|c| to_json(&c)))
+            .transpose()?;
+        let actions = match input.actions {
+            Some(actions) => {
+                let actions = actions.into_actions()?;
+                validate_flair(conn, existing.board_id, &actions).await?;
+                Some(to_json(&actions)?)
+            }
+            None => None,
+        };
+
+        let rule: DbAutomodRule = diesel::update(automod_rules::table.find(existing.id))
+            .set(&AutomodRuleUpdateForm {
+                name,
+                conditions,
+                actions,
+                is_enabled: input.is_enabled,
+                display_order: input.display_order,
+            })
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(AutomodRule::from(rule))
+    }
+
+    /// Delete an AutoModerator rule
+    pub async fn delete_automod_rule(&self, ctx: &Context<'_>, rule_id: ID) -> Result<bool> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let existing = load_rule(conn, &rule_id).await?;
+        require_rule_manager(user, pool, existing.board_id).await?;
+
+        diesel::delete(automod_rules::table.find(existing.id))
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(true)
+    }
+}
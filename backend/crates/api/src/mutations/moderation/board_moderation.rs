@@ -12,9 +12,11 @@ use tinyboards_db::{
     schema::{board_moderators, board_user_bans, moderation_log, users},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::permissions::TokenScopeGuard;
 use crate::LoggedInUser;
 
 #[derive(Default)]
@@ -41,7 +43,7 @@ pub struct BoardBanUserInput {
     pub expires_days: Option<i32>,
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl BoardBanMutations {
     /// Ban a user from a specific board (moderator/admin only)
     pub async fn ban_user_from_board(
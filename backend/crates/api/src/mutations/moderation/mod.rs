@@ -1,3 +1,6 @@
+pub mod automod;
+pub mod modmail;
+pub mod removal_reasons;
 pub mod site_moderation;
 pub mod board_moderation;
 pub mod report_moderation;
\ No newline at end of file
@@ -0,0 +1,267 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::{
+        message::modmail_thread::{ModmailThread as DbModmailThread, ModmailThreadUpdateForm},
+        user::user::User as DbUser,
+    },
+    schema::{modmail_threads, users},
+    utils::{get_conn, DbPool},
+};
+use tinyboards_auth::types::ApiTokenScope;
+use tinyboards_utils::{rate_limit::RateLimitCell, TinyBoardsError};
+use uuid::Uuid;
+
+use crate::events::EventBus;
+use crate::helpers::{
+    modmail::{self, MessageKind},
+    notifications::create_modmail_reply_notification,
+    permissions::{self, TokenScopeGuard},
+};
+use crate::structs::modmail::{ModmailMessage, ModmailStatus, ModmailThread};
+use crate::utils::rate_limit::check_rate_limit;
+use crate::Settings;
+
+#[derive(Default)]
+pub struct ModmailModerationMutations;
+
+#[derive(InputObject)]
+pub struct ModmailBoardReplyInput {
+    pub body: String,
+    /// Leave an internal note for the other moderators instead of replying
+    pub is_mod_note: Option<bool>,
+}
+
+async fn update_thread(
+    conn: &mut diesel_async::AsyncPgConnection,
+    thread_id: Uuid,
+    form: &ModmailThreadUpdateForm,
+) -> Result<DbModmailThread, TinyBoardsError> {
+    diesel::update(modmail_threads::table.find(thread_id))
+        .set(form)
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
+
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
+impl ModmailModerationMutations {
+    /// Reply to a modmail thread as the board, or leave a mod note. The user
+    /// sees replies as coming from the board, not from you.
+    pub async fn reply_to_modmail_as_board(
+        &self,
+        ctx: &Context<'_>,
+        thread_id: ID,
+        input: ModmailBoardReplyInput,
+    ) -> Result<ModmailMessage> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        check_rate_limit(ctx, RateLimitCell::message).await?;
+        let pool = ctx.data::<DbPool>()?;
+        let settings = ctx.data::<Settings>()?.as_ref();
+        let conn = &mut get_conn(pool).await?;
+
+        let thread = modmail::load_thread_as_moderator(conn, user, &thread_id).await?;
+        let is_mod_note = input.is_mod_note.unwrap_or(false);
+        let kind = if is_mod_note { MessageKind::ModNote } else { MessageKind::FromBoard };
+
+        let message = modmail::insert_message(pool, conn, settings, &thread, user.id, &input.body, kind).await?;
+
+        if !is_mod_note {
+            update_thread(
+                conn,
+                thread.id,
+                &ModmailThreadUpdateForm {
+                    status: (thread.status == "new").then(|| ModmailStatus::InProgress.as_str().to_string()),
+                    is_read_by_user: Some(false),
+                    is_read_by_mods: Some(true),
+                    last_message_at: Some(message.created_at),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+            if let Err(e) = create_modmail_reply_notification(
+                pool,
+                ctx.data_opt::<EventBus>(),
+                thread.user_id,
+                message.id,
+            )
+            .await
+            {
+                tracing::error!("Failed to notify user {} about a modmail reply: {:?}", thread.user_id, e);
+            }
+        }
+
+        Ok(ModmailMessage::from_db(message, true))
+    }
+
+    /// Move a modmail thread to another status. Archiving marks it read.
+    pub async fn set_modmail_status(
+        &self,
+        ctx: &Context<'_>,
+        thread_id: ID,
+        status: ModmailStatus,
+    ) -> Result<ModmailThread> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let thread = modmail::load_thread_as_moderator(conn, user, &thread_id).await?;
+        let thread = update_thread(
+            conn,
+            thread.id,
+            &ModmailThreadUpdateForm {
+                status: Some(status.as_str().to_string()),
+                is_read_by_mods: (status == ModmailStatus::Archived).then_some(true),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(ModmailThread::from_db(thread, true))
+    }
+
+    /// Assign a modmail thread to a moderator who can read the board's
+    /// modmail, or unassign it without `moderatorId`
+    pub async fn assign_modmail(
+        &self,
+        ctx: &Context<'_>,
+        thread_id: ID,
+        moderator_id: Option<ID>,
+    ) -> Result<ModmailThread> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let thread = modmail::load_thread_as_moderator(conn, user, &thread_id).await?;
+
+        let assignee = match moderator_id {
+            Some(id) => {
+                let moderator_uuid: Uuid = id
+                    .parse()
+                    .map_err(|_| TinyBoardsError::NotFound("Invalid user ID".into()))?;
+                let moderator: DbUser = users::table
+                    .find(moderator_uuid)
+                    .first(conn)
+                    .await
+                    .map_err(|_| TinyBoardsError::NotFound("User not found".into()))?;
+                if moderator.id == thread.user_id || !modmail::can_moderate(conn, &moderator, thread.board_id).await? {
+                    return Err(TinyBoardsError::from_message(
+                        400,
+                        "Threads can only be assigned to moderators who can read the board's modmail",
+                    )
+                    .into());
+                }
+                Some(moderator.id)
+            }
+            None => None,
+        };
+
+        let thread = update_thread(
+            conn,
+            thread.id,
+            &ModmailThreadUpdateForm {
+                assigned_to: Some(assignee),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(ModmailThread::from_db(thread, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::TestDb;
+    use tinyboards_db::models::{board::board_mods::ModPerms, board::boards::Board as DbBoard, user::user::User};
+
+    async fn start_thread(db: &TestDb, board: &DbBoard, user: &User) -> String {
+        let data = db
+            .execute_ok(
+                Some(user),
+                &format!(
+                    r#"mutation {{ sendModmail(input: {{ boardId: "{}", subject: "Help", body: "Why was my post removed?" }}) {{ id }} }}"#,
+                    board.id
+                ),
+            )
+            .await;
+        data["sendModmail"]["id"].as_str().unwrap().to_string()
+    }
+
+    fn reply(thread_id: &str, body: &str, is_mod_note: bool) -> String {
+        format!(
+            r#"mutation {{ replyToModmailAsBoard(threadId: "{}", input: {{ body: "{}", isModNote: {} }}) {{ id }} }}"#,
+            thread_id, body, is_mod_note
+        )
+    }
+
+    fn read_thread(thread_id: &str) -> String {
+        format!(
+            r#"{{ modmailThread(threadId: "{}") {{ messages {{ body isFromBoard isModNote author {{ id }} }} }} }}"#,
+            thread_id
+        )
+    }
+
+    #[tokio::test]
+    async fn test_user_sees_replies_from_the_board_without_mod_notes() {
+        let Some(db) = TestDb::new().await else { return };
+        let board = db.board().await;
+        let user = db.user(0).await;
+        let moderator = db.user(0).await;
+        db.moderator(&board, &moderator, ModPerms::Users.as_bitmask()).await;
+
+        let thread_id = start_thread(&db, &board, &user).await;
+        db.execute_ok(Some(&moderator), &reply(&thread_id, "Internal: spam filter", true)).await;
+        db.execute_ok(Some(&moderator), &reply(&thread_id, "It broke rule 2", false)).await;
+
+        let data = db.execute_ok(Some(&user), &read_thread(&thread_id)).await;
+        let messages = data["modmailThread"]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["author"]["id"], user.id.to_string());
+        assert_eq!(messages[1]["body"], "It broke rule 2");
+        assert_eq!(messages[1]["isFromBoard"], true);
+        assert!(messages[1]["author"].is_null());
+
+        // The moderators see the note, and who wrote what
+        let data = db.execute_ok(Some(&moderator), &read_thread(&thread_id)).await;
+        let messages = data["modmailThread"]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["isModNote"], true);
+        assert_eq!(messages[2]["author"]["id"], moderator.id.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_only_moderators_with_users_permission_can_reply_or_close() {
+        let Some(db) = TestDb::new().await else { return };
+        let board = db.board().await;
+        let user = db.user(0).await;
+        let content_moderator = db.user(0).await;
+        db.moderator(&board, &content_moderator, ModPerms::Content.as_bitmask()).await;
+        let stranger = db.user(0).await;
+
+        let thread_id = start_thread(&db, &board, &user).await;
+        let close = format!(
+            r#"mutation {{ setModmailStatus(threadId: "{}", status: ARCHIVED) {{ status }} }}"#,
+            thread_id
+        );
+
+        for someone in [&content_moderator, &stranger, &user] {
+            let response = db.execute(Some(someone), &reply(&thread_id, "Hello", false)).await;
+            assert!(!response.errors.is_empty());
+            let response = db.execute(Some(someone), &close).await;
+            assert!(!response.errors.is_empty());
+        }
+
+        // Nothing was written on the thread
+        let data = db.execute_ok(Some(&user), &read_thread(&thread_id)).await;
+        assert_eq!(data["modmailThread"]["messages"].as_array().unwrap().len(), 1);
+
+        // An admin who manages users can
+        let admin = db.user(4).await;
+        db.execute_ok(Some(&admin), &reply(&thread_id, "Hello", false)).await;
+        let data = db.execute_ok(Some(&admin), &close).await;
+        assert_eq!(data["setModmailStatus"]["status"], "ARCHIVED");
+    }
+}
@@ -0,0 +1,182 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::moderator::removal_reason::{
+        RemovalReason as DbRemovalReason, RemovalReasonInsertForm, RemovalReasonUpdateForm,
+    },
+    schema::removal_reasons,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_auth::types::ApiTokenScope;
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::helpers::{
+    permissions::{self, TokenScopeGuard},
+    removal_reasons::require_reason_manager,
+};
+use crate::structs::removal_reason::{
+    CreateRemovalReasonInput, RemovalReason, UpdateRemovalReasonInput,
+};
+
+/// Removal reasons per board
+const MAX_REASONS: i64 = 50;
+
+#[derive(Default)]
+pub struct RemovalReasonMutations;
+
+fn validate_title(title: &str) -> Result<String, TinyBoardsError> {
+    let title = title.trim();
+    if title.is_empty() || title.chars().count() > 100 {
+        return Err(TinyBoardsError::from_message(
+            400,
+            "Removal reason titles must be between 1 and 100 characters",
+        ));
+    }
+    Ok(title.to_string())
+}
+
+fn validate_message(message: &str) -> Result<String, TinyBoardsError> {
+    let message = message.trim();
+    if message.is_empty() || message.chars().count() > 10000 {
+        return Err(TinyBoardsError::from_message(
+            400,
+            "Removal messages must be between 1 and 10000 characters",
+        ));
+    }
+    Ok(message.to_string())
+}
+
+/// Blank rules are stored as no rule.
+fn validate_rule(rule: &str) -> Result<Option<String>, TinyBoardsError> {
+    let rule = rule.trim();
+    if rule.chars().count() > 200 {
+        return Err(TinyBoardsError::from_message(
+            400,
+            "Rules can be at most 200 characters",
+        ));
+    }
+    Ok((!rule.is_empty()).then(|| rule.to_string()))
+}
+
+async fn load_reason(
+    conn: &mut diesel_async::AsyncPgConnection,
+    reason_id: &ID,
+) -> Result<DbRemovalReason, TinyBoardsError> {
+    let reason_uuid: Uuid = reason_id
+        .parse()
+        .map_err(|_| TinyBoardsError::NotFound("Invalid removal reason ID".into()))?;
+    removal_reasons::table
+        .find(reason_uuid)
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Removal reason not found".into()))
+}
+
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
+impl RemovalReasonMutations {
+    /// Add a predefined removal reason to a board
+    pub async fn create_removal_reason(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateRemovalReasonInput,
+    ) -> Result<RemovalReason> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+
+        let board_uuid: Uuid = input
+            .board_id
+            .parse()
+            .map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into()))?;
+
+        require_reason_manager(user, pool, board_uuid).await?;
+
+        let title = validate_title(&input.title)?;
+        let message = validate_message(&input.message)?;
+        let rule = match input.rule {
+            Some(ref rule) => validate_rule(rule)?,
+            None => None,
+        };
+
+        let conn = &mut get_conn(pool).await?;
+        let count: i64 = removal_reasons::table
+            .filter(removal_reasons::board_id.eq(board_uuid))
+            .count()
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if count >= MAX_REASONS {
+            return Err(TinyBoardsError::from_message(
+                400,
+                &format!("A board can have at most {} removal reasons", MAX_REASONS),
+            )
+            .into());
+        }
+
+        let reason: DbRemovalReason = diesel::insert_into(removal_reasons::table)
+            .values(&RemovalReasonInsertForm {
+                board_id: board_uuid,
+                title,
+                message,
+                rule,
+                display_order: input.display_order.unwrap_or(0),
+                created_by: Some(user.id),
+            })
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(RemovalReason::from(reason))
+    }
+
+    /// Update a removal reason
+    pub async fn update_removal_reason(
+        &self,
+        ctx: &Context<'_>,
+        reason_id: ID,
+        input: UpdateRemovalReasonInput,
+    ) -> Result<RemovalReason> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let existing = load_reason(conn, &reason_id).await?;
+        require_reason_manager(user, pool, existing.board_id).await?;
+
+        let title = input.title.as_deref().map(validate_title).transpose()?;
+        let message = input.message.as_deref().map(validate_message).transpose()?;
+        let rule = input.rule.as_deref().map(validate_rule).transpose()?;
+
+        let reason: DbRemovalReason = diesel::update(removal_reasons::table.find(existing.id))
+            .set(&RemovalReasonUpdateForm {
+                title,
+                message,
+                rule,
+                display_order: input.display_order,
+            })
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(RemovalReason::from(reason))
+    }
+
+    /// Delete a removal reason. Moderation log entries that used it keep
+    /// its id and title.
+    pub async fn delete_removal_reason(&self, ctx: &Context<'_>, reason_id: ID) -> Result<bool> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let existing = load_reason(conn, &reason_id).await?;
+        require_reason_manager(user, pool, existing.board_id).await?;
+
+        diesel::delete(removal_reasons::table.find(existing.id))
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(true)
+    }
+}
@@ -13,9 +13,11 @@ use tinyboards_db::{
     schema::{board_moderators, comment_reports, comments, moderation_log, post_reports, posts},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::permissions::TokenScopeGuard;
 use crate::LoggedInUser;
 
 #[derive(Default)]
@@ -61,7 +63,7 @@ async fn check_mod_content_permission(
     Ok(true)
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl ReportModerationMutations {
     /// Resolve a post report (admin/moderator only)
     pub async fn resolve_post_report(
@@ -11,9 +11,11 @@ use tinyboards_db::{
     schema::{moderation_log, user_bans, users},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::permissions::TokenScopeGuard;
 use crate::LoggedInUser;
 
 #[derive(Default)]
@@ -39,7 +41,7 @@ pub struct BanUserInput {
     pub expires_days: Option<i32>,
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl SiteModerationMutations {
     /// Ban a user site-wide (admin only)
     pub async fn ban_user_from_site(
@@ -4,10 +4,16 @@ use async_graphql::*;
 pub use super::moderation::site_moderation::SiteModerationMutations;
 pub use super::moderation::board_moderation::BoardBanMutations;
 pub use super::moderation::report_moderation::ReportModerationMutations;
+pub use super::moderation::automod::AutomodMutations;
+pub use super::moderation::modmail::ModmailModerationMutations;
+pub use super::moderation::removal_reasons::RemovalReasonMutations;
 
 #[derive(MergedObject, Default)]
 pub struct ModerationMutations(
     SiteModerationMutations,
     BoardBanMutations,
     ReportModerationMutations,
+    AutomodMutations,
+    ModmailModerationMutations,
+    RemovalReasonMutations,
 );
\ No newline at end of file
@@ -15,7 +15,10 @@ use tinyboards_db::{
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
-use crate::LoggedInUser;
+use crate::{
+    events::{self, LiveEvent},
+    LoggedInUser,
+};
 
 #[derive(Default)]
 pub struct NotificationMutations;
@@ -90,6 +93,10 @@ impl NotificationMutations {
         .await
         .map_err(|e| TinyBoardsError::Database(e.to_string()))? as i32;
 
+        if marked_count > 0 {
+            events::publish(ctx, LiveEvent::UnreadCountsChanged { user_id: user.id });
+        }
+
         Ok(MarkNotificationsReadResponse {
             success: true,
             marked_count,
@@ -115,6 +122,10 @@ impl NotificationMutations {
         .await
         .map_err(|e| TinyBoardsError::Database(e.to_string()))? as i32;
 
+        if marked_count > 0 {
+            events::publish(ctx, LiveEvent::UnreadCountsChanged { user_id: user.id });
+        }
+
         Ok(MarkNotificationsReadResponse {
             success: true,
             marked_count,
@@ -145,6 +156,7 @@ impl NotificationMutations {
         .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
         if deleted_count > 0 {
+            events::publish(ctx, LiveEvent::UnreadCountsChanged { user_id: user.id });
             Ok(DeleteNotificationResponse { success: true })
         } else {
             Err(TinyBoardsError::NotFound("Notification not found".into()).into())
@@ -1,4 +1,5 @@
-use crate::helpers::permissions;
+use crate::events::{self, LiveEvent};
+use crate::helpers::permissions::{self, TokenScopeGuard};
 use crate::structs::post::Post;
 use crate::DbPool;
 use async_graphql::*;
@@ -20,6 +21,7 @@ use tinyboards_db::{
     },
     utils::get_conn,
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -128,6 +130,14 @@ impl PostActions {
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
         }
 
+        events::publish(
+            ctx,
+            LiveEvent::ScoreChanged {
+                post_id: post_uuid,
+                comment_id: None,
+            },
+        );
+
         load_post_with_counts(conn, post_uuid)
             .await
             .map_err(|e| e.into())
@@ -247,23 +257,27 @@ impl PostActions {
             .await
             .map_err(|_| TinyBoardsError::NotFound("Post not found".into()))?;
 
-        let can_delete = post.creator_id == user.id
-            || user.has_permission(AdminPerms::Content)
-            || board_moderators::table
-                .filter(board_moderators::board_id.eq(post.board_id))
-                .filter(board_moderators::user_id.eq(user.id))
-                .first::<BoardModerator>(conn)
-                .await
-                .ok()
-                .map(|m| m.has_permission(ModPerms::Content))
-                .unwrap_or(false);
-
-        if !can_delete {
-            return Err(TinyBoardsError::from_message(
-                403,
-                "You don't have permission to delete this post",
-            )
-            .into());
+        // Deleting someone else's post is a moderator action
+        if post.creator_id != user.id {
+            permissions::check_token_scope(ctx, ApiTokenScope::Moderate)?;
+
+            let can_delete = user.has_permission(AdminPerms::Content)
+                || board_moderators::table
+                    .filter(board_moderators::board_id.eq(post.board_id))
+                    .filter(board_moderators::user_id.eq(user.id))
+                    .first::<BoardModerator>(conn)
+                    .await
+                    .ok()
+                    .map(|m| m.has_permission(ModPerms::Content))
+                    .unwrap_or(false);
+
+            if !can_delete {
+                return Err(TinyBoardsError::from_message(
+                    403,
+                    "You don't have permission to delete this post",
+                )
+                .into());
+            }
         }
 
         // Clean up associated files
@@ -291,6 +305,7 @@ impl PostActions {
     }
 
     /// Feature or unfeature a post (moderator/admin action)
+    #[graphql(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
     pub async fn feature_post(
         &self,
         ctx: &Context<'_>,
@@ -366,3 +381,48 @@ impl PostActions {
             .map_err(|e| e.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+
+    async fn is_deleted(db: &TestDb, post_id: Uuid) -> bool {
+        posts::table
+            .find(post_id)
+            .select(posts::deleted_at.is_not_null())
+            .first(&mut db.conn().await)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_post_scope_tokens_cant_moderate_posts() {
+        let Some(db) = TestDb::new().await else { return };
+        let board = db.board().await;
+        let moderator = db.user(0).await;
+        db.moderator(&board, &moderator, ModPerms::Content.as_bitmask()).await;
+        let author = db.user(0).await;
+        let post = db.post(&board, &author).await;
+
+        let delete = format!(r#"mutation {{ deletePost(postId: "{}") {{ id }} }}"#, post.id);
+        let feature = format!(r#"mutation {{ featurePost(postId: "{}", featured: true) {{ id }} }}"#, post.id);
+        for query in [&delete, &feature] {
+            let response = db.execute_with_token(&moderator, ApiTokenScope::Post, query).await;
+            assert_eq!(response.errors.len(), 1, "{} was allowed", query);
+            assert!(response.errors[0].message.contains("moderate scope"));
+        }
+        assert!(!is_deleted(&db, post.id).await);
+
+        let response = db.execute_with_token(&moderator, ApiTokenScope::Moderate, &delete).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert!(is_deleted(&db, post.id).await);
+
+        // Authors can still delete their own posts
+        let own = db.post(&board, &author).await;
+        let delete_own = format!(r#"mutation {{ deletePost(postId: "{}") {{ id }} }}"#, own.id);
+        let response = db.execute_with_token(&author, ApiTokenScope::Post, &delete_own).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert!(is_deleted(&db, own.id).await);
+    }
+}
@@ -1,9 +1,13 @@
-use crate::helpers::{permissions, validation::require_mod_or_admin};
-use crate::structs::post::Post;
-use crate::DbPool;
+use crate::helpers::{
+    permissions::{self, TokenScopeGuard},
+    removal_reasons::{deliver_removal_reason, load_reason_for_board, RemovedContent},
+    validation::require_mod_or_admin,
+};
+use crate::structs::{post::Post, removal_reason::RemovalMessageDelivery};
+use crate::{events, DbPool, Settings};
 use async_graphql::*;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use tinyboards_db::{
     enums::{DbApprovalStatus, DbModerationAction},
     models::{
@@ -16,6 +20,7 @@ use tinyboards_db::{
     schema::{moderation_log, post_aggregates, posts},
     utils::get_conn,
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -40,14 +45,17 @@ async fn load_post_with_counts(
     Ok(Post::from((db_post, agg)))
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl PostModeration {
-    /// Remove a post (mod/admin action)
+    /// Remove a post (mod/admin action). A predefined removal reason can be
+    /// given, and its message optionally sent to the author.
     pub async fn remove_post(
         &self,
         ctx: &Context<'_>,
         post_id: ID,
         reason: Option<String>,
+        removal_reason_id: Option<ID>,
+        send_message: Option<RemovalMessageDelivery>,
     ) -> Result<Post> {
         let user = permissions::require_auth_not_banned(ctx)?;
         let pool = ctx.data::<DbPool>()?;
@@ -66,30 +74,69 @@ impl PostModeration {
         require_mod_or_admin(user, pool, post.board_id, ModPerms::Content, Some(AdminPerms::Content))
             .await?;
 
-        diesel::update(posts::table.find(post_uuid))
-            .set(&PostUpdateForm {
-                is_removed: Some(true),
-                ..Default::default()
+        let removal_reason =
+            load_reason_for_board(conn, removal_reason_id.as_ref(), send_message, post.board_id).await?;
+        let settings = ctx.data::<Settings>()?.as_ref();
+
+        // The removal, the message to the author and the log entry are saved together
+        let live_events = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    diesel::update(posts::table.find(post_uuid))
+                        .set(&PostUpdateForm {
+                            is_removed: Some(true),
+                            ..Default::default()
+                        })
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    let (reason, metadata, live_events) = match removal_reason {
+                        Some(removal_reason) => {
+                            let delivery = deliver_removal_reason(
+                                pool,
+                                settings,
+                                conn,
+                                user,
+                                &removal_reason,
+                                send_message,
+                                RemovedContent {
+                                    board_id: post.board_id,
+                                    post_id: post.id,
+                                    comment: None,
+                                    author_id: post.creator_id,
+                                },
+                            )
+                            .await?;
+                            (reason.or(Some(removal_reason.title)), Some(delivery.metadata), delivery.events)
+                        }
+                        None => (reason, None, Vec::new()),
+                    };
+
+                    diesel::insert_into(moderation_log::table)
+                        .values(&ModerationLogInsertForm {
+                            moderator_id: user.id,
+                            action_type: DbModerationAction::RemovePost,
+                            target_type: "post".to_string(),
+                            target_id: post_uuid,
+                            board_id: Some(post.board_id),
+                            reason,
+                            metadata,
+                            expires_at: None,
+                        })
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    Ok(live_events)
+                }
+                .scope_boxed()
             })
-            .execute(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+            .await?;
 
-        // Log to moderation_log
-        diesel::insert_into(moderation_log::table)
-            .values(&ModerationLogInsertForm {
-                moderator_id: user.id,
-                action_type: DbModerationAction::RemovePost,
-                target_type: "post".to_string(),
-                target_id: post_uuid,
-                board_id: Some(post.board_id),
-                reason,
-                metadata: None,
-                expires_at: None,
-            })
-            .execute(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        for event in live_events {
+            events::publish(ctx, event);
+        }
 
         load_post_with_counts(conn, post_uuid)
             .await
@@ -417,3 +464,95 @@ impl PostModeration {
             .map_err(|e| e.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use tinyboards_db::{
+        models::{message::message::PrivateMessage, moderator::moderation_log::ModerationLog},
+        schema::{notifications, private_messages, removal_reasons},
+    };
+
+    #[tokio::test]
+    async fn test_removal_message_needs_a_reason() {
+        let Some(db) = TestDb::new().await else { return };
+        let moderator = db.user(3).await;
+        let board = db.board().await;
+        let post = db.post(&board, &db.user(0).await).await;
+
+        let response = TestDb::execute(
+            &db,
+            Some(&moderator),
+            &format!(r#"mutation {{ removePost(postId: "{}", sendMessage: PRIVATE_MESSAGE) {{ id }} }}"#, post.id),
+        )
+        .await;
+        assert_eq!(response.errors.len(), 1);
+        assert!(response.errors[0].message.contains("removal reason"));
+
+        let is_removed: bool = posts::table
+            .find(post.id)
+            .select(posts::is_removed)
+            .first(&mut db.conn().await)
+            .await
+            .unwrap();
+        assert!(!is_removed);
+    }
+
+    #[tokio::test]
+    async fn test_removal_message_is_sent_and_logged() {
+        let Some(db) = TestDb::new().await else { return };
+        let moderator = db.user(3).await;
+        let author = db.user(0).await;
+        let board = db.board().await;
+        let post = db.post(&board, &author).await;
+        let reason_id: Uuid = diesel::insert_into(removal_reasons::table)
+            .values((
+                removal_reasons::board_id.eq(board.id),
+                removal_reasons::title.eq("Off topic"),
+                removal_reasons::message.eq("Hi {author}, this doesn't belong in b/{board}."),
+            ))
+            .returning(removal_reasons::id)
+            .get_result(&mut db.conn().await)
+            .await
+            .unwrap();
+
+        let data = db
+            .execute_ok(
+                Some(&moderator),
+                &format!(
+                    r#"mutation {{ removePost(postId: "{}", removalReasonId: "{}", sendMessage: PRIVATE_MESSAGE) {{ isRemoved }} }}"#,
+                    post.id, reason_id
+                ),
+            )
+            .await;
+        assert_eq!(data["removePost"]["isRemoved"], true);
+
+        let sent: PrivateMessage = private_messages::table
+            .filter(private_messages::recipient_id.eq(author.id))
+            .first(&mut db.conn().await)
+            .await
+            .unwrap();
+        assert_eq!(sent.creator_id, moderator.id);
+        assert_eq!(sent.body, format!("Hi {}, this doesn't belong in b/{}.", author.name, board.name));
+
+        let notified: i64 = notifications::table
+            .filter(notifications::message_id.eq(sent.id))
+            .count()
+            .get_result(&mut db.conn().await)
+            .await
+            .unwrap();
+        assert_eq!(notified, 1);
+
+        let entry: ModerationLog = moderation_log::table
+            .filter(moderation_log::target_id.eq(post.id))
+            .first(&mut db.conn().await)
+            .await
+            .unwrap();
+        assert_eq!(entry.action_type, DbModerationAction::RemovePost);
+        assert_eq!(entry.reason.as_deref(), Some("Off topic"));
+        let metadata = entry.metadata.unwrap();
+        assert_eq!(metadata["delivery"], "private_message");
+        assert_eq!(metadata["message_id"], sent.id.to_string());
+    }
+}
@@ -1,11 +1,13 @@
+use crate::events::EventBus;
 use crate::helpers::files::upload::upload_file_opendal;
 use crate::helpers::files::cleanup::link_content_uploads;
 use crate::helpers::permissions;
 use crate::structs::post::Post;
+use crate::utils::rate_limit::check_rate_limit;
 use crate::{DbPool, LoggedInUser, Settings};
 use async_graphql::*;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
 use tinyboards_db::{
     enums::{DbApprovalStatus, DbPostType},
     models::{
@@ -18,13 +20,15 @@ use tinyboards_db::{
         user::user::AdminPerms,
     },
     schema::{
-        board_moderators, board_user_bans, boards, post_aggregates, posts, site,
+        board_moderators, board_user_bans, boards, post_aggregates, posts, site, uploads,
     },
     utils::get_conn,
 };
 use tinyboards_utils::{
     content_filter::ContentFilter,
+    rate_limit::RateLimitCell,
     slug::generate_slug,
+    utils::is_video_type,
     TinyBoardsError,
 };
 use url::Url;
@@ -53,8 +57,11 @@ impl SubmitPost {
             .data_unchecked::<LoggedInUser>()
             .require_user_approved(pool)
             .await?;
+        check_rate_limit(ctx, RateLimitCell::post).await?;
         let settings = ctx.data::<Settings>()?.as_ref();
-        let conn = &mut get_conn(pool).await?;
+        // Transactions on the pooled wrapper can't borrow the post form below
+        let mut conn = get_conn(pool).await?;
+        let conn = &mut *conn;
 
         // Load site configuration
         let site_config: Site = site::table
@@ -152,7 +159,13 @@ impl SubmitPost {
         };
 
         // Determine post type enum
-        let db_post_type = if file.is_some() {
+        let file_is_video = match &file {
+            Some(file) => file.value(ctx)?.content_type.as_deref().is_some_and(is_video_type),
+            None => false,
+        };
+        let db_post_type = if file_is_video {
+            DbPostType::Video
+        } else if file.is_some() {
             DbPostType::Image
         } else if link.is_some() {
             DbPostType::Link
@@ -307,11 +320,46 @@ impl SubmitPost {
             is_thread: determined_post_type_str == "thread",
         };
 
-        diesel::insert_into(posts::table)
-            .values(&post_form)
-            .execute(conn)
-            .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        // Create the post with its author's upvote and let AutoModerator,
+        // which moderators and admins are exempt from, act on it in the same
+        // transaction: a post a rule removes is never visible, and a post the
+        // rules couldn't run on isn't created
+        let is_thread = determined_post_type_str == "thread";
+        let (db_post, automod_replies) = conn
+            .transaction::<_, TinyBoardsError, _>(|conn| {
+                async move {
+                    let mut db_post: DbPost = diesel::insert_into(posts::table)
+                        .values(&post_form)
+                        .get_result(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                    // Auto upvote own post (feed posts only)
+                    if !is_thread {
+                        let vote_form = PostVoteInsertForm {
+                            id: Uuid::new_v4(),
+                            user_id: db_post.creator_id,
+                            post_id: db_post.id,
+                            score: 1,
+                        };
+                        diesel::insert_into(tinyboards_db::schema::post_votes::table)
+                            .values(&vote_form)
+                            .execute(conn)
+                            .await
+                            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                    }
+
+                    let replies = if is_mod_or_admin {
+                        Vec::new()
+                    } else {
+                        crate::helpers::automod::run_for_post(pool, conn, settings, &mut db_post).await?
+                    };
+
+                    Ok((db_post, replies))
+                }
+                .scope_boxed()
+            })
+            .await?;
 
         // Handle file upload
         if let Some(file) = file {
@@ -321,21 +369,28 @@ impl SubmitPost {
                 .execute(conn)
                 .await
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-        }
 
-        // Auto upvote own post (feed posts only)
-        if determined_post_type_str != "thread" {
-            let vote_form = PostVoteInsertForm {
-                id: Uuid::new_v4(),
-                user_id: v.id,
-                post_id: post_id,
-                score: 1,
-            };
-            diesel::insert_into(tinyboards_db::schema::post_votes::table)
-                .values(&vote_form)
-                .execute(conn)
-                .await
-                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+            // The processing worker sets the poster frame on posts showing the
+            // video, unless it (or an earlier identical upload) already finished
+            if file_is_video {
+                let poster: Option<String> = uploads::table
+                    .filter(uploads::upload_url.eq(file_url.as_str()))
+                    .filter(uploads::thumbnail_url.is_not_null())
+                    .select(uploads::thumbnail_url)
+                    .first::<Option<String>>(conn)
+                    .await
+                    .optional()
+                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+                    .flatten();
+
+                if let Some(poster) = poster {
+                    diesel::update(posts::table.find(post_id))
+                        .set(posts::thumbnail_url.eq(Some(poster)))
+                        .execute(conn)
+                        .await
+                        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                }
+            }
         }
 
         // Link any uploaded images found in the HTML content
@@ -343,18 +398,23 @@ impl SubmitPost {
             link_content_uploads(pool, post_id, true, &body_html).await?;
         }
 
-        // Send notifications for mentions
+        let events = ctx.data_opt::<EventBus>();
+        crate::helpers::automod::notify_replies(pool, events, &db_post, &automod_replies).await;
+
+        // Send notifications for mentions, unless AutoModerator removed the
+        // post or held it for approval
         use crate::helpers::notifications::{
             extract_mentions, get_user_ids_for_mentions, create_post_mention_notification,
         };
 
         let mentions = extract_mentions(&all_text_for_mentions);
-        if !mentions.is_empty() {
+        if !mentions.is_empty() && !db_post.is_removed {
             if let Ok(mentioned_user_ids) = get_user_ids_for_mentions(pool, mentions).await {
                 for mentioned_user_id in mentioned_user_ids {
                     if mentioned_user_id != v.id {
                         let _ = create_post_mention_notification(
                             pool,
+                            events,
                             mentioned_user_id,
                             post_id,
                             v.id,
@@ -381,3 +441,64 @@ impl SubmitPost {
         Ok(Post::from((db_post, agg)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use tinyboards_db::{
+        models::moderator::automod_rule::AutomodRuleInsertForm,
+        schema::{automod_rules, notifications},
+    };
+
+    async fn mentions_of(db: &TestDb, user_id: Uuid) -> i64 {
+        notifications::table
+            .filter(notifications::recipient_user_id.eq(user_id))
+            .count()
+            .get_result(&mut db.conn().await)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_posts_automod_removes_dont_notify_mentions() {
+        let Some(db) = TestDb::new().await else { return };
+        let board = db.board().await;
+        let author = db.user(0).await;
+        let mentioned = db.user(0).await;
+        diesel::insert_into(automod_rules::table)
+            .values(&AutomodRuleInsertForm {
+                board_id: Some(board.id),
+                name: "No spam".to_string(),
+                conditions: serde_json::json!({ "title_regex": "spam" }),
+                actions: serde_json::json!({ "remove": true }),
+                is_enabled: true,
+                display_order: 0,
+                created_by: None,
+            })
+            .execute(&mut db.conn().await)
+            .await
+            .unwrap();
+
+        let create = |title: &str| {
+            format!(
+                r#"mutation {{ createPost(title: "{}", board: "{}", body: "hi @{}") {{ id }} }}"#,
+                title, board.name, mentioned.name
+            )
+        };
+
+        let data = db.execute_ok(Some(&author), &create("Cheap spam here")).await;
+        let post_id: Uuid = data["createPost"]["id"].as_str().unwrap().parse().unwrap();
+        let is_removed: bool = posts::table
+            .find(post_id)
+            .select(posts::is_removed)
+            .first(&mut db.conn().await)
+            .await
+            .unwrap();
+        assert!(is_removed);
+        assert_eq!(mentions_of(&db, mentioned.id).await, 0);
+
+        db.execute_ok(Some(&author), &create("A fine post")).await;
+        assert_eq!(mentions_of(&db, mentioned.id).await, 1);
+    }
+}
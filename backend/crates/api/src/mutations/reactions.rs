@@ -1,4 +1,6 @@
 use crate::{
+    events::{self, LiveEvent},
+    helpers::permissions::TokenScopeGuard,
     LoggedInUser,
     structs::reaction::{BoardReactionSettings as GqlBoardReactionSettings, Reaction as GqlReaction},
 };
@@ -20,6 +22,7 @@ use tinyboards_db::{
     schema::{board_moderators, board_reaction_settings, comments, posts, reactions},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
@@ -110,17 +113,17 @@ impl ReactionMutations {
         };
 
         // Get board_id to check reaction settings
-        let board_id: Uuid = if let Some(pid) = post_uuid {
+        let (board_id, parent_post_id): (Uuid, Uuid) = if let Some(pid) = post_uuid {
             posts::table
                 .find(pid)
-                .select(posts::board_id)
+                .select((posts::board_id, posts::id))
                 .first(conn)
                 .await
                 .map_err(|_| TinyBoardsError::NotFound("Post not found".into()))?
         } else if let Some(cid) = comment_uuid {
             comments::table
                 .find(cid)
-                .select(comments::board_id)
+                .select((comments::board_id, comments::post_id))
                 .first(conn)
                 .await
                 .map_err(|_| TinyBoardsError::NotFound("Comment not found".into()))?
@@ -198,6 +201,14 @@ impl ReactionMutations {
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
+        events::publish(
+            ctx,
+            LiveEvent::ScoreChanged {
+                post_id: parent_post_id,
+                comment_id: comment_uuid,
+            },
+        );
+
         Ok(AddReactionResponse {
             reaction: GqlReaction::from(reaction),
         })
@@ -227,7 +238,7 @@ impl ReactionMutations {
                 .parse()
                 .map_err(|_| TinyBoardsError::NotFound("Invalid post ID".into()))?;
 
-            diesel::delete(
+            let deleted = diesel::delete(
                 reactions::table
                     .filter(reactions::user_id.eq(user.id))
                     .filter(reactions::emoji.eq(&input.emoji))
@@ -235,13 +246,24 @@ impl ReactionMutations {
             )
             .execute(conn)
             .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+            if deleted > 0 {
+                events::publish(
+                    ctx,
+                    LiveEvent::ScoreChanged {
+                        post_id: post_uuid,
+                        comment_id: None,
+                    },
+                );
+            }
+            deleted
         } else if let Some(ref cid) = input.comment_id {
             let comment_uuid: Uuid = cid
                 .parse()
                 .map_err(|_| TinyBoardsError::NotFound("Invalid comment ID".into()))?;
 
-            diesel::delete(
+            let deleted = diesel::delete(
                 reactions::table
                     .filter(reactions::user_id.eq(user.id))
                     .filter(reactions::emoji.eq(&input.emoji))
@@ -249,7 +271,27 @@ impl ReactionMutations {
             )
             .execute(conn)
             .await
-            .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+            if deleted > 0 {
+                let post_id: Option<Uuid> = comments::table
+                    .find(comment_uuid)
+                    .select(comments::post_id)
+                    .first(conn)
+                    .await
+                    .optional()
+                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+                if let Some(post_id) = post_id {
+                    events::publish(
+                        ctx,
+                        LiveEvent::ScoreChanged {
+                            post_id,
+                            comment_id: Some(comment_uuid),
+                        },
+                    );
+                }
+            }
+            deleted
         } else {
             return Err(TinyBoardsError::from_message(400, "Must provide either post_id or comment_id").into());
         };
@@ -262,6 +304,7 @@ impl ReactionMutations {
     }
 
     /// Update board reaction settings (moderators/admin only, BUG-008 fix)
+    #[graphql(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
     async fn update_board_reaction_settings(
         &self,
         ctx: &Context<'_>,
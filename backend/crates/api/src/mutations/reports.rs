@@ -1,22 +1,32 @@
 use async_graphql::*;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use tinyboards_db::{
     enums::DbReportStatus,
     models::{
         board::board_mods::{BoardModerator, ModPerms},
         comment::comment_report::{CommentReportInsertForm, CommentReportUpdateForm},
         comment::comments::Comment as DbComment,
+        message::message::PrivateMessage,
+        message::message_report::{MessageReportInsertForm, MessageReportUpdateForm},
+        message::modmail_thread::ModmailThread,
         post::post_report::{PostReportInsertForm, PostReportUpdateForm},
         post::posts::Post as DbPost,
-        user::user::AdminPerms,
+        user::user::{AdminPerms, User},
+        user::user_report::{UserReportInsertForm, UserReportUpdateForm},
+        wiki::{WikiPage, WikiPageReportInsertForm, WikiPageReportUpdateForm},
+    },
+    schema::{
+        board_moderators, comment_reports, comments, message_reports, modmail_threads, post_reports,
+        posts, private_messages, user_reports, users, wiki_page_reports, wiki_pages,
     },
-    schema::{board_moderators, comment_reports, comments, post_reports, posts},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::{modmail, permissions::TokenScopeGuard};
 use crate::LoggedInUser;
 
 #[derive(Default)]
@@ -46,12 +56,7 @@ impl ReportMutations {
         let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
         let conn = &mut get_conn(pool).await?;
 
-        if reason.trim().len() < 3 {
-            return Err(TinyBoardsError::from_message(400, "Report reason must be at least 3 characters").into());
-        }
-        if reason.len() > 500 {
-            return Err(TinyBoardsError::from_message(400, "Report reason cannot exceed 500 characters").into());
-        }
+        validate_reason(&reason)?;
 
         let post_uuid: Uuid = post_id
             .parse()
@@ -68,6 +73,19 @@ impl ReportMutations {
             return Err(TinyBoardsError::from_message(400, "You cannot report your own post").into());
         }
 
+        let already_reported: bool = diesel::select(diesel::dsl::exists(
+            post_reports::table
+                .filter(post_reports::creator_id.eq(user.id))
+                .filter(post_reports::post_id.eq(post_uuid))
+                .filter(post_reports::status.eq(DbReportStatus::Pending)),
+        ))
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if already_reported {
+            return Err(TinyBoardsError::from_message(409, "You have already reported this post").into());
+        }
+
         let form = PostReportInsertForm {
             id: Uuid::new_v4(),
             creator_id: user.id,
@@ -103,12 +121,7 @@ impl ReportMutations {
         let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
         let conn = &mut get_conn(pool).await?;
 
-        if reason.trim().len() < 3 {
-            return Err(TinyBoardsError::from_message(400, "Report reason must be at least 3 characters").into());
-        }
-        if reason.len() > 500 {
-            return Err(TinyBoardsError::from_message(400, "Report reason cannot exceed 500 characters").into());
-        }
+        validate_reason(&reason)?;
 
         let comment_uuid: Uuid = comment_id
             .parse()
@@ -125,6 +138,19 @@ impl ReportMutations {
             return Err(TinyBoardsError::from_message(400, "You cannot report your own comment").into());
         }
 
+        let already_reported: bool = diesel::select(diesel::dsl::exists(
+            comment_reports::table
+                .filter(comment_reports::creator_id.eq(user.id))
+                .filter(comment_reports::comment_id.eq(comment_uuid))
+                .filter(comment_reports::status.eq(DbReportStatus::Pending)),
+        ))
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if already_reported {
+            return Err(TinyBoardsError::from_message(409, "You have already reported this comment").into());
+        }
+
         let form = CommentReportInsertForm {
             id: Uuid::new_v4(),
             creator_id: user.id,
@@ -147,7 +173,207 @@ impl ReportMutations {
         })
     }
 
-    /// Resolve a report (moderator/admin only)
+    /// Report a user's profile (handled by site admins)
+    pub async fn report_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: ID,
+        reason: String,
+    ) -> Result<ReportResponse> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        let conn = &mut get_conn(pool).await?;
+
+        validate_reason(&reason)?;
+
+        let user_uuid: Uuid = user_id
+            .parse()
+            .map_err(|_| TinyBoardsError::NotFound("Invalid user ID".into()))?;
+
+        if user_uuid == user.id {
+            return Err(TinyBoardsError::from_message(400, "You cannot report yourself").into());
+        }
+
+        let reported: User = users::table
+            .find(user_uuid)
+            .filter(users::deleted_at.is_null())
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("User not found".into()))?;
+
+        let already_reported: bool = diesel::select(diesel::dsl::exists(
+            user_reports::table
+                .filter(user_reports::creator_id.eq(user.id))
+                .filter(user_reports::user_id.eq(user_uuid))
+                .filter(user_reports::status.eq(DbReportStatus::Pending)),
+        ))
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if already_reported {
+            return Err(TinyBoardsError::from_message(409, "You have already reported this user").into());
+        }
+
+        let form = UserReportInsertForm {
+            id: Uuid::new_v4(),
+            creator_id: user.id,
+            user_id: user_uuid,
+            original_name: reported.name,
+            original_display_name: reported.display_name,
+            original_bio: reported.bio,
+            original_avatar: reported.avatar,
+            original_signature: reported.signature,
+            reason,
+            status: DbReportStatus::Pending,
+        };
+
+        let report: tinyboards_db::models::user::user_report::UserReport =
+            diesel::insert_into(user_reports::table)
+                .values(&form)
+                .get_result(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(ReportResponse {
+            success: true,
+            report_id: report.id.to_string().into(),
+        })
+    }
+
+    /// Report a private message or modmail message you received (handled by site admins)
+    pub async fn report_message(
+        &self,
+        ctx: &Context<'_>,
+        message_id: ID,
+        reason: String,
+    ) -> Result<ReportResponse> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        let conn = &mut get_conn(pool).await?;
+
+        validate_reason(&reason)?;
+
+        let message_uuid: Uuid = message_id
+            .parse()
+            .map_err(|_| TinyBoardsError::NotFound("Invalid message ID".into()))?;
+
+        let message: PrivateMessage = private_messages::table
+            .find(message_uuid)
+            .filter(private_messages::deleted_at.is_null())
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("Message not found".into()))?;
+
+        if message.creator_id == user.id {
+            return Err(TinyBoardsError::from_message(400, "You cannot report your own message").into());
+        }
+
+        if !can_see_message(conn, user, &message).await? {
+            return Err(TinyBoardsError::NotFound("Message not found".into()).into());
+        }
+
+        let already_reported: bool = diesel::select(diesel::dsl::exists(
+            message_reports::table
+                .filter(message_reports::creator_id.eq(user.id))
+                .filter(message_reports::message_id.eq(message_uuid))
+                .filter(message_reports::status.eq(DbReportStatus::Pending)),
+        ))
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if already_reported {
+            return Err(TinyBoardsError::from_message(409, "You have already reported this message").into());
+        }
+
+        let form = MessageReportInsertForm {
+            id: Uuid::new_v4(),
+            creator_id: user.id,
+            message_id: message_uuid,
+            original_message_text: message.body,
+            reason,
+            status: DbReportStatus::Pending,
+        };
+
+        let report: tinyboards_db::models::message::message_report::MessageReport =
+            diesel::insert_into(message_reports::table)
+                .values(&form)
+                .get_result(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(ReportResponse {
+            success: true,
+            report_id: report.id.to_string().into(),
+        })
+    }
+
+    /// Report a wiki page (handled by the board's moderators)
+    pub async fn report_wiki_page(
+        &self,
+        ctx: &Context<'_>,
+        page_id: ID,
+        reason: String,
+    ) -> Result<ReportResponse> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        let conn = &mut get_conn(pool).await?;
+
+        validate_reason(&reason)?;
+
+        let page_uuid: Uuid = page_id
+            .parse()
+            .map_err(|_| TinyBoardsError::NotFound("Invalid page ID".into()))?;
+
+        let page: WikiPage = wiki_pages::table
+            .find(page_uuid)
+            .filter(wiki_pages::deleted_at.is_null())
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("Wiki page not found".into()))?;
+
+        if page.creator_id == user.id {
+            return Err(TinyBoardsError::from_message(400, "You cannot report your own wiki page").into());
+        }
+
+        let already_reported: bool = diesel::select(diesel::dsl::exists(
+            wiki_page_reports::table
+                .filter(wiki_page_reports::creator_id.eq(user.id))
+                .filter(wiki_page_reports::page_id.eq(page_uuid))
+                .filter(wiki_page_reports::status.eq(DbReportStatus::Pending)),
+        ))
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        if already_reported {
+            return Err(TinyBoardsError::from_message(409, "You have already reported this wiki page").into());
+        }
+
+        let form = WikiPageReportInsertForm {
+            id: Uuid::new_v4(),
+            creator_id: user.id,
+            page_id: page_uuid,
+            original_page_title: page.title,
+            original_page_body: page.body,
+            reason,
+            status: DbReportStatus::Pending,
+        };
+
+        let report: tinyboards_db::models::wiki::WikiPageReport =
+            diesel::insert_into(wiki_page_reports::table)
+                .values(&form)
+                .get_result(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(ReportResponse {
+            success: true,
+            report_id: report.id.to_string().into(),
+        })
+    }
+
+    /// Resolve a report (moderator/admin only).
+    /// `report_type` is one of "post", "comment", "user", "message" or "wiki_page".
+    #[graphql(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
     pub async fn resolve_report(
         &self,
         ctx: &Context<'_>,
@@ -162,60 +388,14 @@ impl ReportMutations {
             .parse()
             .map_err(|_| TinyBoardsError::NotFound("Invalid report ID".into()))?;
 
-        match report_type.as_str() {
-            "post" => {
-                let report: tinyboards_db::models::post::post_report::PostReport =
-                    post_reports::table.find(report_uuid).first(conn).await
-                        .map_err(|_| TinyBoardsError::NotFound("Report not found".into()))?;
-
-                let post: DbPost = posts::table.find(report.post_id).first(conn).await
-                    .map_err(|_| TinyBoardsError::NotFound("Post not found".into()))?;
-
-                check_report_permission(conn, user, post.board_id).await?;
-
-                let form = PostReportUpdateForm {
-                    status: Some(DbReportStatus::Resolved),
-                    resolver_id: Some(Some(user.id)),
-                    updated_at: Some(chrono::Utc::now()),
-                };
-
-                diesel::update(post_reports::table.find(report_uuid))
-                    .set(&form)
-                    .execute(conn)
-                    .await
-                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-            }
-            "comment" => {
-                let report: tinyboards_db::models::comment::comment_report::CommentReport =
-                    comment_reports::table.find(report_uuid).first(conn).await
-                        .map_err(|_| TinyBoardsError::NotFound("Report not found".into()))?;
-
-                let comment: DbComment = comments::table.find(report.comment_id).first(conn).await
-                    .map_err(|_| TinyBoardsError::NotFound("Comment not found".into()))?;
-
-                check_report_permission(conn, user, comment.board_id).await?;
-
-                let form = CommentReportUpdateForm {
-                    status: Some(DbReportStatus::Resolved),
-                    resolver_id: Some(Some(user.id)),
-                    updated_at: Some(chrono::Utc::now()),
-                };
-
-                diesel::update(comment_reports::table.find(report_uuid))
-                    .set(&form)
-                    .execute(conn)
-                    .await
-                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-            }
-            _ => {
-                return Err(TinyBoardsError::from_message(400, "report_type must be 'post' or 'comment'").into());
-            }
-        }
+        set_report_status(conn, user, report_uuid, &report_type, DbReportStatus::Resolved).await?;
 
         Ok(ResolveReportResponse { success: true })
     }
 
-    /// Dismiss a report (moderator/admin only)
+    /// Dismiss a report (moderator/admin only).
+    /// `report_type` is one of "post", "comment", "user", "message" or "wiki_page".
+    #[graphql(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
     pub async fn dismiss_report(
         &self,
         ctx: &Context<'_>,
@@ -230,64 +410,185 @@ impl ReportMutations {
             .parse()
             .map_err(|_| TinyBoardsError::NotFound("Invalid report ID".into()))?;
 
-        match report_type.as_str() {
-            "post" => {
-                let report: tinyboards_db::models::post::post_report::PostReport =
-                    post_reports::table.find(report_uuid).first(conn).await
-                        .map_err(|_| TinyBoardsError::NotFound("Report not found".into()))?;
+        set_report_status(conn, user, report_uuid, &report_type, DbReportStatus::Dismissed).await?;
+
+        Ok(ResolveReportResponse { success: true })
+    }
+}
+
+fn validate_reason(reason: &str) -> Result<()> {
+    if reason.trim().len() < 3 {
+        return Err(TinyBoardsError::from_message(400, "Report reason must be at least 3 characters").into());
+    }
+    if reason.len() > 500 {
+        return Err(TinyBoardsError::from_message(400, "Report reason cannot exceed 500 characters").into());
+    }
+    Ok(())
+}
 
-                let post: DbPost = posts::table.find(report.post_id).first(conn).await
-                    .map_err(|_| TinyBoardsError::NotFound("Post not found".into()))?;
+/// Whether `user` received `message`: either as the direct recipient, or as
+/// someone who can read the modmail thread it belongs to. Internal mod notes
+/// are never reportable.
+async fn can_see_message(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    message: &PrivateMessage,
+) -> Result<bool> {
+    if message.recipient_id == Some(user.id) {
+        return Ok(true);
+    }
 
-                check_report_permission(conn, user, post.board_id).await?;
+    let Some(thread_id) = message.thread_id else {
+        return Ok(false);
+    };
+    if message.is_mod_note {
+        return Ok(false);
+    }
 
-                let form = PostReportUpdateForm {
-                    status: Some(DbReportStatus::Dismissed),
-                    resolver_id: Some(Some(user.id)),
-                    updated_at: Some(chrono::Utc::now()),
-                };
+    let thread: ModmailThread = modmail_threads::table
+        .find(thread_id)
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Message not found".into()))?;
 
-                diesel::update(post_reports::table.find(report_uuid))
-                    .set(&form)
-                    .execute(conn)
-                    .await
-                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
-            }
-            "comment" => {
-                let report: tinyboards_db::models::comment::comment_report::CommentReport =
-                    comment_reports::table.find(report_uuid).first(conn).await
-                        .map_err(|_| TinyBoardsError::NotFound("Report not found".into()))?;
-
-                let comment: DbComment = comments::table.find(report.comment_id).first(conn).await
-                    .map_err(|_| TinyBoardsError::NotFound("Comment not found".into()))?;
-
-                check_report_permission(conn, user, comment.board_id).await?;
-
-                let form = CommentReportUpdateForm {
-                    status: Some(DbReportStatus::Dismissed),
-                    resolver_id: Some(Some(user.id)),
-                    updated_at: Some(chrono::Utc::now()),
-                };
-
-                diesel::update(comment_reports::table.find(report_uuid))
-                    .set(&form)
-                    .execute(conn)
-                    .await
-                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    Ok(thread.user_id == user.id || modmail::can_moderate(conn, user, thread.board_id).await?)
+}
+
+/// Set the status of a report after checking that `user` may act on it.
+/// Post, comment and wiki page reports go to the board's moderators;
+/// user and message reports are site-wide and need the admin Users permission.
+async fn set_report_status(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    report_uuid: Uuid,
+    report_type: &str,
+    status: DbReportStatus,
+) -> Result<()> {
+    let resolver_id = Some(Some(user.id));
+    let updated_at = Some(chrono::Utc::now());
+
+    match report_type {
+        "post" => {
+            let report: tinyboards_db::models::post::post_report::PostReport =
+                post_reports::table.find(report_uuid).first(conn).await
+                    .map_err(|_| TinyBoardsError::NotFound("Report not found".into()))?;
+
+            let post: DbPost = posts::table.find(report.post_id).first(conn).await
+                .map_err(|_| TinyBoardsError::NotFound("Post not found".into()))?;
+
+            check_report_permission(conn, user, post.board_id).await?;
+
+            let form = PostReportUpdateForm {
+                status: Some(status),
+                resolver_id,
+                updated_at,
+            };
+
+            diesel::update(post_reports::table.find(report_uuid))
+                .set(&form)
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        }
+        "comment" => {
+            let report: tinyboards_db::models::comment::comment_report::CommentReport =
+                comment_reports::table.find(report_uuid).first(conn).await
+                    .map_err(|_| TinyBoardsError::NotFound("Report not found".into()))?;
+
+            let comment: DbComment = comments::table.find(report.comment_id).first(conn).await
+                .map_err(|_| TinyBoardsError::NotFound("Comment not found".into()))?;
+
+            check_report_permission(conn, user, comment.board_id).await?;
+
+            let form = CommentReportUpdateForm {
+                status: Some(status),
+                resolver_id,
+                updated_at,
+            };
+
+            diesel::update(comment_reports::table.find(report_uuid))
+                .set(&form)
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        }
+        "wiki_page" => {
+            let report: tinyboards_db::models::wiki::WikiPageReport =
+                wiki_page_reports::table.find(report_uuid).first(conn).await
+                    .map_err(|_| TinyBoardsError::NotFound("Report not found".into()))?;
+
+            let page: WikiPage = wiki_pages::table.find(report.page_id).first(conn).await
+                .map_err(|_| TinyBoardsError::NotFound("Wiki page not found".into()))?;
+
+            check_report_permission(conn, user, page.board_id).await?;
+
+            let form = WikiPageReportUpdateForm {
+                status: Some(status),
+                resolver_id,
+                updated_at,
+            };
+
+            diesel::update(wiki_page_reports::table.find(report_uuid))
+                .set(&form)
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        }
+        "user" => {
+            if !user.has_permission(AdminPerms::Users) {
+                return Err(TinyBoardsError::from_message(403, "Only admins can manage user reports").into());
             }
-            _ => {
-                return Err(TinyBoardsError::from_message(400, "report_type must be 'post' or 'comment'").into());
+
+            let form = UserReportUpdateForm {
+                status: Some(status),
+                resolver_id,
+                updated_at,
+            };
+
+            let updated = diesel::update(user_reports::table.find(report_uuid))
+                .set(&form)
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+            if updated == 0 {
+                return Err(TinyBoardsError::NotFound("Report not found".into()).into());
             }
         }
+        "message" => {
+            if !user.has_permission(AdminPerms::Users) {
+                return Err(TinyBoardsError::from_message(403, "Only admins can manage message reports").into());
+            }
 
-        Ok(ResolveReportResponse { success: true })
+            let form = MessageReportUpdateForm {
+                status: Some(status),
+                resolver_id,
+                updated_at,
+            };
+
+            let updated = diesel::update(message_reports::table.find(report_uuid))
+                .set(&form)
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+            if updated == 0 {
+                return Err(TinyBoardsError::NotFound("Report not found".into()).into());
+            }
+        }
+        _ => {
+            return Err(TinyBoardsError::from_message(
+                400,
+                "report_type must be 'post', 'comment', 'user', 'message' or 'wiki_page'",
+            ).into());
+        }
     }
+
+    Ok(())
 }
 
 /// Check if a user has permission to resolve/dismiss reports for a board
 async fn check_report_permission(
-    conn: &mut diesel_async::AsyncPgConnection,
-    user: &tinyboards_db::models::user::user::User,
+    conn: &mut AsyncPgConnection,
+    user: &User,
     board_id: Uuid,
 ) -> Result<()> {
     let is_admin = user.has_permission(AdminPerms::Content);
@@ -309,3 +610,196 @@ async fn check_report_permission(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use tinyboards_db::models::board::boards::Board as DbBoard;
+
+    async fn message(
+        db: &TestDb,
+        from: &User,
+        to: Option<&User>,
+        thread: Option<&ModmailThread>,
+        is_mod_note: bool,
+    ) -> PrivateMessage {
+        diesel::insert_into(private_messages::table)
+            .values((
+                private_messages::creator_id.eq(from.id),
+                private_messages::recipient_id.eq(to.map(|u| u.id)),
+                private_messages::recipient_board_id.eq(thread.map(|t| t.board_id)),
+                private_messages::body.eq("Hello"),
+                private_messages::body_html.eq("<p>Hello</p>"),
+                private_messages::thread_id.eq(thread.map(|t| t.id)),
+                private_messages::is_mod_note.eq(is_mod_note),
+            ))
+            .get_result(&mut db.conn().await)
+            .await
+            .unwrap()
+    }
+
+    async fn thread(db: &TestDb, board: &DbBoard, user: &User) -> ModmailThread {
+        diesel::insert_into(modmail_threads::table)
+            .values((
+                modmail_threads::board_id.eq(board.id),
+                modmail_threads::user_id.eq(user.id),
+                modmail_threads::subject.eq("Help"),
+            ))
+            .get_result(&mut db.conn().await)
+            .await
+            .unwrap()
+    }
+
+    async fn wiki_page(db: &TestDb, board: &DbBoard, creator: &User, last_edited_by: Option<&User>) -> WikiPage {
+        let slug = crate::test_utils::unique_name("page");
+        diesel::insert_into(wiki_pages::table)
+            .values((
+                wiki_pages::board_id.eq(board.id),
+                wiki_pages::slug.eq(&slug),
+                wiki_pages::title.eq("Rules"),
+                wiki_pages::body.eq("Be nice"),
+                wiki_pages::body_html.eq("<p>Be nice</p>"),
+                wiki_pages::creator_id.eq(creator.id),
+                wiki_pages::last_edited_by.eq(last_edited_by.map(|u| u.id)),
+            ))
+            .get_result(&mut db.conn().await)
+            .await
+            .unwrap()
+    }
+
+    async fn can_see(db: &TestDb, user: &User, message: &PrivateMessage) -> bool {
+        can_see_message(&mut *db.conn().await, user, message).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_can_see_message() {
+        let Some(db) = TestDb::new().await else { return };
+        let sender = db.user(0).await;
+        let recipient = db.user(0).await;
+        let stranger = db.user(0).await;
+
+        let direct = message(&db, &sender, Some(&recipient), None, false).await;
+        assert!(can_see(&db, &recipient, &direct).await);
+        assert!(!can_see(&db, &stranger, &direct).await);
+
+        let board = db.board().await;
+        let moderator = db.user(0).await;
+        db.moderator(&board, &moderator, ModPerms::Users.as_bitmask()).await;
+        let content_moderator = db.user(0).await;
+        db.moderator(&board, &content_moderator, ModPerms::Content.as_bitmask()).await;
+        let modmail = thread(&db, &board, &sender).await;
+
+        let from_board = message(&db, &moderator, None, Some(&modmail), false).await;
+        assert!(can_see(&db, &sender, &from_board).await);
+        let from_user = message(&db, &sender, None, Some(&modmail), false).await;
+        assert!(can_see(&db, &moderator, &from_user).await);
+        // Moderators who can't read the board's modmail didn't receive it
+        assert!(!can_see(&db, &content_moderator, &from_user).await);
+        assert!(!can_see(&db, &stranger, &from_user).await);
+
+        let note = message(&db, &moderator, None, Some(&modmail), true).await;
+        assert!(!can_see(&db, &sender, &note).await);
+        assert!(!can_see(&db, &moderator, &note).await);
+    }
+
+    #[tokio::test]
+    async fn test_self_reports_are_rejected() {
+        let Some(db) = TestDb::new().await else { return };
+        let user = db.user(0).await;
+        let other = db.user(0).await;
+        let board = db.board().await;
+
+        let report_user = format!(r#"mutation {{ reportUser(userId: "{}", reason: "Spam") {{ success }} }}"#, user.id);
+        let sent = message(&db, &user, Some(&other), None, false).await;
+        let report_message = format!(r#"mutation {{ reportMessage(messageId: "{}", reason: "Spam") {{ success }} }}"#, sent.id);
+        let page = wiki_page(&db, &board, &user, Some(&other)).await;
+        let report_page = format!(r#"mutation {{ reportWikiPage(pageId: "{}", reason: "Spam") {{ success }} }}"#, page.id);
+
+        for query in [&report_user, &report_message, &report_page] {
+            let response = TestDb::execute(&db, Some(&user), query).await;
+            assert!(!response.errors.is_empty(), "{} was allowed", query);
+        }
+
+        // Editing a page doesn't make it yours
+        db.execute_ok(Some(&other), &report_page).await;
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_reports_are_rejected_while_pending() {
+        let Some(db) = TestDb::new().await else { return };
+        let admin = db.user(4).await;
+        let reporter = db.user(0).await;
+        let reported = db.user(0).await;
+
+        let report = format!(r#"mutation {{ reportUser(userId: "{}", reason: "Spam") {{ reportId }} }}"#, reported.id);
+        let data = db.execute_ok(Some(&reporter), &report).await;
+        let report_id = data["reportUser"]["reportId"].as_str().unwrap().to_string();
+
+        let response = TestDb::execute(&db, Some(&reporter), &report).await;
+        assert_eq!(response.errors.len(), 1);
+        assert!(response.errors[0].message.contains("already reported"));
+
+        // Someone else can still report them
+        db.execute_ok(Some(&db.user(0).await), &report).await;
+
+        // Once the report is handled, a new one can be filed
+        db.execute_ok(
+            Some(&admin),
+            &format!(r#"mutation {{ dismissReport(reportId: "{}", reportType: "user") {{ success }} }}"#, report_id),
+        )
+        .await;
+        db.execute_ok(Some(&reporter), &report).await;
+    }
+
+    #[tokio::test]
+    async fn test_reports_show_up_in_the_moderation_queue() {
+        let Some(db) = TestDb::new().await else { return };
+        let reporter = db.user(0).await;
+        let reported = db.user(0).await;
+        let board = db.board().await;
+        let page = wiki_page(&db, &board, &reported, None).await;
+        let sent = message(&db, &reported, Some(&reporter), None, false).await;
+
+        for query in [
+            format!(r#"mutation {{ reportUser(userId: "{}", reason: "Abusive bio") {{ success }} }}"#, reported.id),
+            format!(r#"mutation {{ reportMessage(messageId: "{}", reason: "Harassment") {{ success }} }}"#, sent.id),
+            format!(r#"mutation {{ reportWikiPage(pageId: "{}", reason: "Vandalism") {{ success }} }}"#, page.id),
+        ] {
+            db.execute_ok(Some(&reporter), &query).await;
+        }
+
+        let queue = |board: Option<&DbBoard>| {
+            let board_id = board.map(|b| format!(r#"boardId: "{}", "#, b.id)).unwrap_or_default();
+            format!(r#"{{ getModerationQueue({}itemType: "reports", limit: 100) {{ items {{ itemType contentId reason }} }} }}"#, board_id)
+        };
+        let listed = |data: serde_json::Value| -> Vec<(String, String)> {
+            data["getModerationQueue"]["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| (item["itemType"].as_str().unwrap().to_string(), item["contentId"].as_str().unwrap().to_string()))
+                .collect()
+        };
+        let user_report = ("user_report".to_string(), reported.id.to_string());
+        let message_report = ("message_report".to_string(), sent.id.to_string());
+        let page_report = ("wiki_page_report".to_string(), page.id.to_string());
+
+        // Admins who manage users see the site-wide reports too
+        let items = listed(db.execute_ok(Some(&db.user(4).await), &queue(None)).await);
+        assert!(items.contains(&user_report));
+        assert!(items.contains(&message_report));
+        assert!(items.contains(&page_report));
+
+        let items = listed(db.execute_ok(Some(&db.user(3).await), &queue(None)).await);
+        assert!(!items.contains(&user_report));
+        assert!(!items.contains(&message_report));
+        assert!(items.contains(&page_report));
+
+        // The board's moderators only see the wiki page report
+        let moderator = db.user(0).await;
+        db.moderator(&board, &moderator, ModPerms::Content.as_bitmask()).await;
+        let items = listed(db.execute_ok(Some(&moderator), &queue(Some(&board))).await);
+        assert_eq!(items, vec![page_report]);
+    }
+}
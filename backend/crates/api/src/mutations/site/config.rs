@@ -3,14 +3,24 @@ use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
 use tinyboards_db::{
     enums::{DbBoardMode, DbRegistrationMode},
+    models::config::RateLimitUpdateForm,
     models::site::site::{Site as DbSite, SiteUpdateForm},
     models::user::user::AdminPerms,
-    schema::site,
+    schema::{rate_limits, site},
     utils::{get_conn, DbPool},
 };
-use tinyboards_utils::css_sanitizer::{sanitize_css, MAX_SITE_CSS_BYTES};
+use tinyboards_utils::{
+    css_sanitizer::{sanitize_css, MAX_SITE_CSS_BYTES},
+    rate_limit::RateLimitCell,
+};
 
-use crate::{helpers::permissions, structs::site::LocalSite};
+use crate::{
+    helpers::permissions,
+    storage::{maintenance, StorageBackend},
+    structs::site::{LocalSite, MediaGcRun},
+    utils::rate_limit::load_rate_limit_config,
+    Settings,
+};
 
 #[derive(Default)]
 pub struct SiteConfig;
@@ -52,10 +62,85 @@ pub struct UpdateSiteConfigInput {
     pub default_board_mode: Option<String>,
     pub custom_css: Option<String>,
     pub custom_css_enabled: Option<bool>,
+    /// Require admins to use two-factor authentication to log in.
+    pub require_two_factor_for_admins: Option<bool>,
+    /// Require board moderators to use two-factor authentication to log in.
+    pub require_two_factor_for_mods: Option<bool>,
+    pub magic_link_login_enabled: Option<bool>,
     pub trusted_user_min_reputation: Option<i32>,
     pub trusted_user_min_account_age_days: Option<i32>,
     pub trusted_user_manual_approval: Option<bool>,
     pub trusted_user_min_posts: Option<i32>,
+    pub rate_limits: Option<RateLimitsInput>,
+}
+
+/// Rate limit changes. Each action allows `<action>` requests per
+/// `<action>PerSecond` seconds; omitted fields are left unchanged.
+#[derive(InputObject)]
+pub struct RateLimitsInput {
+    pub message: Option<i32>,
+    pub message_per_second: Option<i32>,
+    pub post: Option<i32>,
+    pub post_per_second: Option<i32>,
+    pub register: Option<i32>,
+    pub register_per_second: Option<i32>,
+    pub login: Option<i32>,
+    pub login_per_second: Option<i32>,
+    pub image: Option<i32>,
+    pub image_per_second: Option<i32>,
+    pub comment: Option<i32>,
+    pub comment_per_second: Option<i32>,
+    pub search: Option<i32>,
+    pub search_per_second: Option<i32>,
+    pub magic_link: Option<i32>,
+    pub magic_link_per_second: Option<i32>,
+}
+
+impl RateLimitsInput {
+    fn into_form(self) -> Result<RateLimitUpdateForm, tinyboards_utils::TinyBoardsError> {
+        let values = [
+            self.message,
+            self.message_per_second,
+            self.post,
+            self.post_per_second,
+            self.register,
+            self.register_per_second,
+            self.login,
+            self.login_per_second,
+            self.image,
+            self.image_per_second,
+            self.comment,
+            self.comment_per_second,
+            self.search,
+            self.search_per_second,
+            self.magic_link,
+            self.magic_link_per_second,
+        ];
+        if values.iter().flatten().any(|v| *v < 1) {
+            return Err(tinyboards_utils::TinyBoardsError::BadRequest(
+                "Rate limit values must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(RateLimitUpdateForm {
+            message: self.message,
+            message_per_second: self.message_per_second,
+            post: self.post,
+            post_per_second: self.post_per_second,
+            register: self.register,
+            register_per_second: self.register_per_second,
+            login: self.login,
+            login_per_second: self.login_per_second,
+            image: self.image,
+            image_per_second: self.image_per_second,
+            comment: self.comment,
+            comment_per_second: self.comment_per_second,
+            search: self.search,
+            search_per_second: self.search_per_second,
+            magic_link: self.magic_link,
+            magic_link_per_second: self.magic_link_per_second,
+        })
+    }
 }
 
 #[Object]
@@ -85,6 +170,17 @@ impl SiteConfig {
             None => None,          // Not provided, leave unchanged
         };
 
+        if let Some(ref difficulty) = input.captcha_difficulty {
+            if !matches!(difficulty.as_str(), "easy" | "medium" | "hard") {
+                return Err(tinyboards_utils::TinyBoardsError::BadRequest(
+                    "Captcha difficulty must be easy, medium, or hard".to_string(),
+                )
+                .into());
+            }
+        }
+
+        let rate_limit_form = input.rate_limits.map(RateLimitsInput::into_form).transpose()?;
+
         let form = SiteUpdateForm {
             name: input.name,
             description: input.description.map(Some),
@@ -127,6 +223,9 @@ impl SiteConfig {
             banned_domains: input.banned_domains.map(Some),
             custom_css: sanitized_css,
             custom_css_enabled: input.custom_css_enabled,
+            require_two_factor_for_admins: input.require_two_factor_for_admins,
+            require_two_factor_for_mods: input.require_two_factor_for_mods,
+            magic_link_login_enabled: input.magic_link_login_enabled,
             // Fields not in the input are left None (unchanged)
             default_post_listing_type: None,
             default_avatar: None,
@@ -163,6 +262,31 @@ impl SiteConfig {
             .await
             .map_err(|e| tinyboards_utils::TinyBoardsError::Database(e.to_string()))?;
 
+        if let Some(rate_limit_form) = rate_limit_form {
+            diesel::update(rate_limits::table.filter(rate_limits::site_id.eq(existing.id)))
+                .set(&rate_limit_form)
+                .execute(conn)
+                .await
+                .map_err(|e| tinyboards_utils::TinyBoardsError::Database(e.to_string()))?;
+
+            // Apply the new limits to the running limiter without a restart
+            if let Some(cell) = ctx.data_opt::<RateLimitCell>() {
+                cell.send(load_rate_limit_config(pool).await?).await?;
+            }
+        }
+
         Ok(LocalSite::from(updated))
     }
+
+    /// Run media garbage collection now instead of waiting for the daily job
+    /// (admin with Config permission).
+    pub async fn run_media_gc(&self, ctx: &Context<'_>) -> Result<MediaGcRun> {
+        permissions::require_admin_permission(ctx, AdminPerms::Config)?;
+        let pool = ctx.data::<DbPool>()?;
+        let storage = ctx.data::<StorageBackend>()?;
+        let settings = ctx.data::<Settings>()?.as_ref();
+
+        let run = maintenance::run_media_gc(pool, storage, &settings.media).await?;
+        Ok(MediaGcRun::from(run))
+    }
 }
@@ -1,3 +1,4 @@
 pub mod actions;
 pub mod profile_management;
-pub mod settings;
\ No newline at end of file
+pub mod sessions;
+pub mod settings;
@@ -0,0 +1,108 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::{auth::AuthSession as DbAuthSession, user::user::AdminPerms},
+    schema::auth_sessions,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::{
+    helpers::permissions::{self, LoginSessionGuard},
+    structs::session::AuthSession,
+    SessionTokenHash,
+};
+
+/// Longest label a user can give a session
+const SESSION_NAME_MAX_CHARS: usize = 64;
+
+#[derive(Default)]
+pub struct SessionMutations;
+
+fn parse_session_id(session_id: &ID) -> Result<Uuid, TinyBoardsError> {
+    session_id
+        .parse::<Uuid>()
+        .map_err(|_| TinyBoardsError::BadRequest("Invalid session ID".to_string()))
+}
+
+#[Object(guard = "LoginSessionGuard")]
+impl SessionMutations {
+    /// Sign a session out. Its refresh token stops working at once; an access
+    /// token it already holds lasts until it expires (at most 15 minutes).
+    /// Admins with user management permission can revoke anyone's session.
+    pub async fn revoke_session(&self, ctx: &Context<'_>, session_id: ID) -> Result<bool> {
+        let me = permissions::require_auth(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let session_id = parse_session_id(&session_id)?;
+        let conn = &mut get_conn(pool).await?;
+
+        let session: DbAuthSession = auth_sessions::table
+            .find(session_id)
+            .first(conn)
+            .await
+            .optional()
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+            .ok_or_else(|| TinyBoardsError::NotFound("Session not found".to_string()))?;
+
+        if session.user_id != me.id {
+            // Don't reveal other users' session IDs to non-admins
+            if !me.has_permission(AdminPerms::Users) {
+                return Err(TinyBoardsError::NotFound("Session not found".to_string()).into());
+            }
+            tracing::info!(
+                "Admin {} revoked session {} of user {}",
+                me.id,
+                session.id,
+                session.user_id
+            );
+        }
+
+        diesel::delete(auth_sessions::table.find(session.id))
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Label one of your own sessions. An empty name clears the label.
+    pub async fn rename_session(
+        &self,
+        ctx: &Context<'_>,
+        session_id: ID,
+        name: Option<String>,
+    ) -> Result<AuthSession> {
+        let me = permissions::require_auth(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let session_id = parse_session_id(&session_id)?;
+
+        let name = name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        if name.as_ref().is_some_and(|name| name.chars().count() > SESSION_NAME_MAX_CHARS) {
+            return Err(TinyBoardsError::from_message(
+                400,
+                "Session name must be at most 64 characters",
+            )
+            .into());
+        }
+
+        let conn = &mut get_conn(pool).await?;
+        let session: DbAuthSession = diesel::update(
+            auth_sessions::table
+                .find(session_id)
+                .filter(auth_sessions::user_id.eq(me.id)),
+        )
+        .set(auth_sessions::name.eq(name))
+        .get_result(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+        .ok_or_else(|| TinyBoardsError::NotFound("Session not found".to_string()))?;
+
+        let current = ctx.data_opt::<SessionTokenHash>().and_then(SessionTokenHash::inner);
+        Ok(AuthSession::from_db(session, current))
+    }
+}
@@ -1,14 +1,24 @@
 use async_graphql::*;
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
+use chrono::Utc;
 use tinyboards_db::{
-    models::user::user::{User as DbUser, UserUpdateForm},
-    schema::users,
+    models::user::{
+        user::{User as DbUser, UserUpdateForm},
+        DataExport as DbDataExport, DataExportInsertForm,
+    },
+    schema::{data_exports, users},
     utils::{get_conn, DbPool},
 };
 use tinyboards_utils::TinyBoardsError;
 
-use crate::{helpers::permissions, structs::user::UserSettings};
+use crate::{
+    events::EventBus,
+    helpers::permissions::{self, LoginSessionGuard},
+    storage::{export, StorageBackend},
+    structs::{data_export::DataExport, user::UserSettings},
+    MasterKey,
+};
 
 #[derive(Default)]
 pub struct UpdateSettings;
@@ -18,11 +28,12 @@ pub struct UpdateSettingsInput {
     pub theme: Option<String>,
     pub show_nsfw: Option<bool>,
     pub show_bots: Option<bool>,
+    pub is_bot_account: Option<bool>,
     pub interface_language: Option<String>,
     pub is_email_notifications_enabled: Option<bool>,
 }
 
-#[Object]
+#[Object(guard = "LoginSessionGuard")]
 impl UpdateSettings {
     /// Update user preferences/settings.
     pub async fn update_settings(
@@ -38,6 +49,7 @@ impl UpdateSettings {
             theme: input.theme,
             show_nsfw: input.show_nsfw,
             show_bots: input.show_bots,
+            is_bot_account: input.is_bot_account,
             interface_language: input.interface_language,
             is_email_notifications_enabled: input.is_email_notifications_enabled,
             ..Default::default()
@@ -66,4 +78,42 @@ impl UpdateSettings {
 
         Ok(true)
     }
+
+    /// Start an export of your account data. It's built in the background
+    /// and you get a notification with a download link when it's ready.
+    /// Limited to one export a day.
+    pub async fn request_data_export(&self, ctx: &Context<'_>) -> Result<DataExport> {
+        let me = permissions::require_auth(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let storage = ctx.data::<StorageBackend>()?;
+        let secret = ctx.data::<MasterKey>()?.as_ref();
+        let conn = &mut get_conn(pool).await?;
+
+        if let Some(allowed_at) = export::next_export_allowed_at(conn, me.id).await? {
+            let wait = (allowed_at - Utc::now()).num_seconds().max(1) as u64;
+            return Err(TinyBoardsError::RateLimited(wait).into());
+        }
+
+        let export: DbDataExport = diesel::insert_into(data_exports::table)
+            .values(&DataExportInsertForm { user_id: me.id })
+            .get_result(conn)
+            .await
+            .map_err(|e| match e {
+                diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
+                    TinyBoardsError::Conflict("An export is already being prepared".to_string())
+                }
+                other => TinyBoardsError::Database(other.to_string()),
+            })?;
+
+        tokio::spawn(export::run_export(
+            pool.clone(),
+            storage.clone(),
+            ctx.data_opt::<EventBus>().cloned(),
+            secret.to_string(),
+            export.id,
+            me.id,
+        ));
+
+        Ok(DataExport::from_db(export, storage, secret))
+    }
 }
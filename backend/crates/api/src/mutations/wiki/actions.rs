@@ -13,10 +13,12 @@ use tinyboards_db::{
     schema::{board_moderators, wiki_page_revisions, wiki_pages},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
 use crate::{
+    helpers::permissions::TokenScopeGuard,
     structs::wiki::{EditWikiPageInput, WikiPage},
     LoggedInUser,
 };
@@ -48,7 +50,7 @@ async fn require_wiki_mod(
 #[derive(Default)]
 pub struct WikiPageActions;
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl WikiPageActions {
     /// Edit a wiki page (creates a new revision)
     async fn edit_wiki_page(
@@ -10,10 +10,12 @@ use tinyboards_db::{
     schema::{board_moderators, boards, wiki_pages},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
 use crate::{
+    helpers::permissions::TokenScopeGuard,
     structs::wiki::{CreateWikiPageInput, WikiPage},
     LoggedInUser,
 };
@@ -21,7 +23,7 @@ use crate::{
 #[derive(Default)]
 pub struct CreateWikiPage;
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl CreateWikiPage {
     /// Create a new wiki page for a board
     async fn create_wiki_page(
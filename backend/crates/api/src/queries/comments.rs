@@ -220,6 +220,15 @@ impl QueryComments {
 
         if let Some(uid) = user_uuid {
             query = query.filter(comments::creator_id.eq(uid));
+        } else if v_opt.is_some_and(|v| !v.show_bots) {
+            // Hide bot accounts' comments from users who turned bots off
+            query = query.filter(
+                comments::creator_id.ne_all(
+                    users::table
+                        .filter(users::is_bot_account.eq(true))
+                        .select(users::id),
+                ),
+            );
         }
 
         if let Some(bid) = board_uuid {
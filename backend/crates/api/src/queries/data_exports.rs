@@ -0,0 +1,48 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::user::DataExport as DbDataExport,
+    schema::data_exports,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+
+use crate::{
+    helpers::permissions,
+    storage::{export, StorageBackend},
+    structs::data_export::{DataExport, DataExportList},
+    MasterKey,
+};
+
+#[derive(Default)]
+pub struct QueryDataExports;
+
+#[Object]
+impl QueryDataExports {
+    /// Your data exports, newest first, with fresh download links for the
+    /// ones that are ready.
+    pub async fn data_exports(&self, ctx: &Context<'_>) -> Result<DataExportList> {
+        let me = permissions::require_auth(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let storage = ctx.data::<StorageBackend>()?;
+        let secret = ctx.data::<MasterKey>()?.as_ref();
+        let conn = &mut get_conn(pool).await?;
+
+        let exports: Vec<DbDataExport> = data_exports::table
+            .filter(data_exports::user_id.eq(me.id))
+            .order(data_exports::created_at.desc())
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        let next_export_allowed_at = export::next_export_allowed_at(conn, me.id).await?;
+
+        Ok(DataExportList {
+            exports: exports
+                .into_iter()
+                .map(|export| DataExport::from_db(export, storage, secret))
+                .collect(),
+            next_export_allowed_at: next_export_allowed_at.map(|t| t.to_rfc3339()),
+        })
+    }
+}
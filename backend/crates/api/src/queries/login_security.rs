@@ -0,0 +1,139 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use diesel::sql_types::{BigInt, Integer, Text, Timestamptz, Uuid as DieselUuid};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::user::user::AdminPerms,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::helpers::permissions;
+
+#[derive(Default)]
+pub struct QueryLoginSecurity;
+
+/// An account currently locked after too many failed logins.
+#[derive(SimpleObject)]
+pub struct LockedAccount {
+    pub user_id: ID,
+    pub name: String,
+    /// Consecutive failed logins so far
+    pub failed_attempts: i32,
+    pub locked_until: String,
+    pub last_failed_at: String,
+}
+
+/// An IP address with failed logins in the requested window.
+#[derive(SimpleObject)]
+pub struct LoginFailureIp {
+    pub ip_address: String,
+    pub failures: i64,
+    /// Distinct usernames or emails tried, a sign of credential stuffing
+    pub distinct_usernames: i64,
+    pub last_failed_at: String,
+}
+
+#[derive(QueryableByName)]
+struct LockedAccountRow {
+    #[diesel(sql_type = DieselUuid)]
+    user_id: Uuid,
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Integer)]
+    failed_attempts: i32,
+    #[diesel(sql_type = Timestamptz)]
+    locked_until: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    last_failed_at: DateTime<Utc>,
+}
+
+#[derive(QueryableByName)]
+struct LoginFailureIpRow {
+    #[diesel(sql_type = Text)]
+    ip_address: String,
+    #[diesel(sql_type = BigInt)]
+    failures: i64,
+    #[diesel(sql_type = BigInt)]
+    distinct_usernames: i64,
+    #[diesel(sql_type = Timestamptz)]
+    last_failed_at: DateTime<Utc>,
+}
+
+#[Object]
+impl QueryLoginSecurity {
+    /// Accounts that are locked right now, soonest unlock last. Admin-only
+    /// (requires Users permission).
+    pub async fn locked_accounts(&self, ctx: &Context<'_>) -> Result<Vec<LockedAccount>> {
+        permissions::require_admin_permission(ctx, AdminPerms::Users)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let rows: Vec<LockedAccountRow> = diesel::sql_query(
+            "SELECT l.user_id, u.name, l.failed_attempts, l.locked_until, l.last_failed_at \
+             FROM account_lockouts l \
+             INNER JOIN users u ON u.id = l.user_id \
+             WHERE l.locked_until > now() \
+             ORDER BY l.locked_until DESC",
+        )
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| LockedAccount {
+                user_id: ID(row.user_id.to_string()),
+                name: row.name,
+                failed_attempts: row.failed_attempts,
+                locked_until: row.locked_until.to_rfc3339(),
+                last_failed_at: row.last_failed_at.to_rfc3339(),
+            })
+            .collect())
+    }
+
+    /// IP addresses with the most failed logins in the last `hours` (default
+    /// 24, which is as far back as failures are kept). Admin-only (requires
+    /// Users permission).
+    pub async fn login_failure_ips(
+        &self,
+        ctx: &Context<'_>,
+        hours: Option<i32>,
+        limit: Option<i64>,
+    ) -> Result<Vec<LoginFailureIp>> {
+        permissions::require_admin_permission(ctx, AdminPerms::Users)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let hours = hours.unwrap_or(24).clamp(1, 24);
+        let limit = limit.unwrap_or(25).clamp(1, 100);
+
+        let rows: Vec<LoginFailureIpRow> = diesel::sql_query(
+            "SELECT ip_address, COUNT(*) AS failures, \
+                    COUNT(DISTINCT lower(username)) AS distinct_usernames, \
+                    MAX(created_at) AS last_failed_at \
+             FROM login_failures \
+             WHERE created_at > now() - make_interval(hours => $1) \
+             GROUP BY ip_address \
+             ORDER BY failures DESC, last_failed_at DESC \
+             LIMIT $2",
+        )
+        .bind::<Integer, _>(hours)
+        .bind::<BigInt, _>(limit)
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| LoginFailureIp {
+                ip_address: row.ip_address,
+                failures: row.failures,
+                distinct_usernames: row.distinct_usernames,
+                last_failed_at: row.last_failed_at.to_rfc3339(),
+            })
+            .collect())
+    }
+}
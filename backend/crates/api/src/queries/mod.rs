@@ -4,18 +4,23 @@ pub mod board_members;
 pub mod board_moderators;
 pub mod boards;
 pub mod comments;
+pub mod data_exports;
 pub mod emojis;
 pub mod flairs;
 pub mod invites;
+pub mod login_security;
+pub mod sessions;
 pub mod site;
 pub mod me;
 pub mod messages;
 pub mod moderation;
 pub mod moderation_unified;
+pub mod modmail;
 pub mod notifications;
 pub mod user;
 pub mod posts;
 pub mod registration_applications;
 pub mod reports;
 pub mod search;
+pub mod uploads;
 pub mod wiki;
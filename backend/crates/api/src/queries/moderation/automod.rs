@@ -0,0 +1,136 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::{moderator::automod_rule::AutomodRule as DbAutomodRule, post::posts::Post as DbPost},
+    schema::{automod_rules, posts},
+    utils::{get_conn, DbPool},
+};
+use tinyboards_auth::types::ApiTokenScope;
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::helpers::{
+    automod::{self, ParsedRule},
+    permissions::{self, TokenScopeGuard},
+};
+use crate::structs::automod::{AutomodDryRunMatch, AutomodRule};
+
+#[derive(Default)]
+pub struct AutomodQueries;
+
+fn parse_board_id(board_id: Option<ID>) -> Result<Option<Uuid>, TinyBoardsError> {
+    board_id
+        .map(|id| id.parse().map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into())))
+        .transpose()
+}
+
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
+impl AutomodQueries {
+    /// A board's AutoModerator rules, or the site-wide rules without `boardId`.
+    /// Includes disabled rules.
+    pub async fn automod_rules(&self, ctx: &Context<'_>, board_id: Option<ID>) -> Result<Vec<AutomodRule>> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let board_uuid = parse_board_id(board_id)?;
+
+        automod::require_rule_manager(user, pool, board_uuid).await?;
+
+        let conn = &mut get_conn(pool).await?;
+        let mut query = automod_rules::table
+            .order((automod_rules::display_order, automod_rules::created_at))
+            .into_boxed();
+        query = match board_uuid {
+            Some(id) => query.filter(automod_rules::board_id.eq(id)),
+            None => query.filter(automod_rules::board_id.is_null()),
+        };
+
+        let rules: Vec<DbAutomodRule> = query
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(rules.into_iter().map(AutomodRule::from).collect())
+    }
+
+    /// Check recent posts against AutoModerator rules without acting on them.
+    /// With `ruleId`, only that rule is checked, even if it's disabled;
+    /// otherwise every enabled rule that applies is. Without `boardId`, posts
+    /// from every board are checked against the site-wide rules. Posts by
+    /// moderators are included, though live runs skip them.
+    pub async fn automod_dry_run(
+        &self,
+        ctx: &Context<'_>,
+        board_id: Option<ID>,
+        rule_id: Option<ID>,
+        limit: Option<i64>,
+    ) -> Result<Vec<AutomodDryRunMatch>> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let board_uuid = parse_board_id(board_id)?;
+
+        automod::require_rule_manager(user, pool, board_uuid).await?;
+
+        let conn = &mut get_conn(pool).await?;
+        let limit = limit.unwrap_or(50).clamp(1, 200);
+
+        let rules: Vec<ParsedRule> = match rule_id {
+            Some(rule_id) => {
+                let rule_uuid: Uuid = rule_id
+                    .parse()
+                    .map_err(|_| TinyBoardsError::NotFound("Invalid rule ID".into()))?;
+                let rule: DbAutomodRule = automod_rules::table
+                    .find(rule_uuid)
+                    .first(conn)
+                    .await
+                    .map_err(|_| TinyBoardsError::NotFound("Rule not found".into()))?;
+                if rule.board_id.is_some() && rule.board_id != board_uuid {
+                    return Err(TinyBoardsError::NotFound("Rule not found".into()).into());
+                }
+                vec![rule.into()]
+            }
+            None => match board_uuid {
+                Some(id) => automod::enabled_rules(conn, id).await?,
+                None => automod_rules::table
+                    .filter(automod_rules::is_enabled.eq(true))
+                    .filter(automod_rules::board_id.is_null())
+                    .order((automod_rules::display_order, automod_rules::created_at))
+                    .load::<DbAutomodRule>(conn)
+                    .await
+                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+                    .into_iter()
+                    .map(ParsedRule::from)
+                    .collect(),
+            },
+        };
+
+        let mut query = posts::table
+            .filter(posts::deleted_at.is_null())
+            .order(posts::created_at.desc())
+            .limit(limit)
+            .into_boxed();
+        if let Some(id) = board_uuid {
+            query = query.filter(posts::board_id.eq(id));
+        }
+        let recent: Vec<DbPost> = query
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        let matches = automod::dry_run(conn, &recent, &rules).await?;
+
+        Ok(matches
+            .into_iter()
+            .map(|m| AutomodDryRunMatch {
+                post_id: m.post.id.to_string().into(),
+                post_title: m.post.title.clone(),
+                board_id: m.post.board_id.to_string().into(),
+                creator_name: m.author_name,
+                created_at: m.post.created_at.to_rfc3339(),
+                rule_id: m.rule.rule.id.to_string().into(),
+                rule_name: m.rule.rule.name.clone(),
+                actions: m.rule.actions.names().into_iter().map(str::to_string).collect(),
+            })
+            .collect())
+    }
+}
@@ -1,3 +1,5 @@
+pub mod automod;
 pub mod moderation_queue;
 pub mod moderation_log;
-pub mod moderation_stats;
\ No newline at end of file
+pub mod moderation_stats;
+pub mod removal_reasons;
\ No newline at end of file
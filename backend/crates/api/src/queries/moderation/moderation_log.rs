@@ -71,6 +71,10 @@ fn action_type_str(action: &DbModerationAction) -> &'static str {
         DbModerationAction::PurgeBoard => "purge_board",
         DbModerationAction::MarkNsfw => "mark_nsfw",
         DbModerationAction::UnmarkNsfw => "unmark_nsfw",
+        DbModerationAction::FilterPost => "filter_post",
+        DbModerationAction::FlairPost => "flair_post",
+        DbModerationAction::CommentPost => "comment_post",
+        DbModerationAction::ReportPost => "report_post",
     }
 }
 
@@ -268,6 +272,10 @@ fn parse_action_type(s: &str) -> Option<DbModerationAction> {
         "feature_post" => Some(DbModerationAction::FeaturePost),
         "unfeature_post" => Some(DbModerationAction::UnfeaturePost),
         "remove_board" => Some(DbModerationAction::RemoveBoard),
+        "filter_post" => Some(DbModerationAction::FilterPost),
+        "flair_post" => Some(DbModerationAction::FlairPost),
+        "comment_post" => Some(DbModerationAction::CommentPost),
+        "report_post" => Some(DbModerationAction::ReportPost),
         _ => None,
     }
 }
@@ -6,15 +6,23 @@ use tinyboards_db::{
     models::{
         board::board_mods::{BoardModerator, ModPerms},
         comment::comment_report::CommentReport as DbCommentReport,
+        message::message_report::MessageReport as DbMessageReport,
         post::post_report::PostReport as DbPostReport,
         user::user::AdminPerms,
+        user::user_report::UserReport as DbUserReport,
+        wiki::WikiPageReport as DbWikiPageReport,
+    },
+    schema::{
+        board_moderators, comment_reports, comments, message_reports, post_reports, posts,
+        user_reports, wiki_page_reports, wiki_pages,
     },
-    schema::{board_moderators, comment_reports, comments, post_reports, posts},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::permissions::TokenScopeGuard;
 use crate::LoggedInUser;
 
 #[derive(Default)]
@@ -42,7 +50,7 @@ pub struct ModerationQueue {
     pub pending_content: i32,
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl ModerationQueueQueries {
     /// Get moderation queue combining pending reports and unresolved content
     pub async fn get_moderation_queue(
@@ -145,11 +153,7 @@ impl ModerationQueueQueries {
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
             for (report, bid) in comment_reports_with_board {
-                let preview = if report.original_comment_text.len() > 100 {
-                    format!("{}...", &report.original_comment_text[..100])
-                } else {
-                    report.original_comment_text
-                };
+                let preview = truncate_preview(&report.original_comment_text);
 
                 items.push(ModerationQueueItem {
                     id: report.id.to_string().into(),
@@ -163,6 +167,88 @@ impl ModerationQueueQueries {
                     priority: 2,
                 });
             }
+
+            // Get pending wiki page reports
+            let mut query = wiki_page_reports::table
+                .inner_join(wiki_pages::table.on(wiki_page_reports::page_id.eq(wiki_pages::id)))
+                .filter(wiki_page_reports::status.eq(DbReportStatus::Pending))
+                .select((wiki_page_reports::all_columns, wiki_pages::board_id))
+                .order(wiki_page_reports::created_at.desc())
+                .into_boxed();
+
+            if let Some(bid) = board_uuid {
+                query = query.filter(wiki_pages::board_id.eq(bid));
+            }
+
+            let wiki_reports_with_board: Vec<(DbWikiPageReport, Uuid)> = query
+                .limit(limit)
+                .offset(offset)
+                .load(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+            for (report, bid) in wiki_reports_with_board {
+                items.push(ModerationQueueItem {
+                    id: report.id.to_string().into(),
+                    item_type: "wiki_page_report".to_string(),
+                    content_id: report.page_id.to_string().into(),
+                    reporter_id: Some(report.creator_id.to_string().into()),
+                    reason: Some(report.reason),
+                    content_preview: report.original_page_title,
+                    created_at: report.created_at.to_string(),
+                    board_id: Some(bid.to_string().into()),
+                    priority: 2,
+                });
+            }
+
+            // User and message reports are site-wide and only go to admins
+            if board_uuid.is_none() && user.has_permission(AdminPerms::Users) {
+                let user_reports_list: Vec<DbUserReport> = user_reports::table
+                    .filter(user_reports::status.eq(DbReportStatus::Pending))
+                    .order(user_reports::created_at.desc())
+                    .limit(limit)
+                    .offset(offset)
+                    .load(conn)
+                    .await
+                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                for report in user_reports_list {
+                    items.push(ModerationQueueItem {
+                        id: report.id.to_string().into(),
+                        item_type: "user_report".to_string(),
+                        content_id: report.user_id.to_string().into(),
+                        reporter_id: Some(report.creator_id.to_string().into()),
+                        reason: Some(report.reason),
+                        content_preview: report.original_name,
+                        created_at: report.created_at.to_string(),
+                        board_id: None,
+                        priority: 2,
+                    });
+                }
+
+                let message_reports_list: Vec<DbMessageReport> = message_reports::table
+                    .filter(message_reports::status.eq(DbReportStatus::Pending))
+                    .order(message_reports::created_at.desc())
+                    .limit(limit)
+                    .offset(offset)
+                    .load(conn)
+                    .await
+                    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+                for report in message_reports_list {
+                    items.push(ModerationQueueItem {
+                        id: report.id.to_string().into(),
+                        item_type: "message_report".to_string(),
+                        content_id: report.message_id.to_string().into(),
+                        reporter_id: Some(report.creator_id.to_string().into()),
+                        reason: Some(report.reason),
+                        content_preview: truncate_preview(&report.original_message_text),
+                        created_at: report.created_at.to_string(),
+                        board_id: None,
+                        priority: 2,
+                    });
+                }
+            }
         }
 
         // Get pending posts awaiting approval
@@ -184,11 +270,7 @@ impl ModerationQueueQueries {
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
             for post in pending_posts {
-                let preview = if post.title.len() > 100 {
-                    format!("{}...", &post.title[..100])
-                } else {
-                    post.title.clone()
-                };
+                let preview = truncate_preview(&post.title);
 
                 items.push(ModerationQueueItem {
                     id: post.id.to_string().into(),
@@ -223,11 +305,7 @@ impl ModerationQueueQueries {
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
             for comment in pending_comments {
-                let preview = if comment.body.len() > 100 {
-                    format!("{}...", &comment.body[..100])
-                } else {
-                    comment.body.clone()
-                };
+                let preview = truncate_preview(&comment.body);
 
                 items.push(ModerationQueueItem {
                     id: comment.id.to_string().into(),
@@ -265,3 +343,11 @@ impl ModerationQueueQueries {
         })
     }
 }
+
+/// Shorten content to a 100 character preview, cutting on a char boundary.
+fn truncate_preview(text: &str) -> String {
+    match text.char_indices().nth(100) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text.to_string(),
+    }
+}
@@ -1,20 +1,25 @@
 use async_graphql::*;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use std::collections::HashMap;
 use tinyboards_db::{
     enums::{DbModerationAction, DbReportStatus},
     models::{
         board::board_mods::{BoardModerator, ModPerms},
         moderator::moderation_log::ModerationLog as DbModerationLog,
-        user::user::AdminPerms,
+        user::user::{AdminPerms, User},
+    },
+    schema::{
+        board_moderators, comment_reports, message_reports, moderation_log, modmail_threads,
+        post_reports, user_bans, user_reports, users, wiki_page_reports, wiki_pages,
     },
-    schema::{board_moderators, comment_reports, moderation_log, post_reports, user_bans, users},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::{modmail, permissions::TokenScopeGuard};
 use crate::LoggedInUser;
 
 #[derive(Default)]
@@ -53,6 +58,9 @@ pub struct ModerationOverview {
     pub actions_this_week: i32,
     pub pending_reports: i32,
     pub banned_users: i32,
+    /// Modmail threads with messages no moderator has read yet. Zero unless
+    /// the viewer can read the board's modmail.
+    pub unread_modmail: i32,
 }
 
 fn action_to_str(action: &DbModerationAction) -> &'static str {
@@ -85,10 +93,14 @@ fn action_to_str(action: &DbModerationAction) -> &'static str {
         DbModerationAction::PurgeBoard => "purge_board",
         DbModerationAction::MarkNsfw => "mark_nsfw",
         DbModerationAction::UnmarkNsfw => "unmark_nsfw",
+        DbModerationAction::FilterPost => "filter_post",
+        DbModerationAction::FlairPost => "flair_post",
+        DbModerationAction::CommentPost => "comment_post",
+        DbModerationAction::ReportPost => "report_post",
     }
 }
 
-#[Object]
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl ModerationStatsQueries {
     /// Get moderation statistics (BUG-032 fix: proper date handling, no unwrap)
     pub async fn get_moderation_stats(
@@ -183,7 +195,9 @@ impl ModerationStatsQueries {
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
-        let pending_reports = (pending_post_reports + pending_comment_reports) as i32;
+        let pending_other_reports = count_other_pending_reports(conn, user, board_uuid).await?;
+
+        let pending_reports = (pending_post_reports + pending_comment_reports + pending_other_reports) as i32;
 
         // Get banned users count
         let banned_users: i64 = user_bans::table
@@ -356,6 +370,8 @@ impl ModerationStatsQueries {
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
+        let pending_other_reports = count_other_pending_reports(conn, user, board_uuid).await?;
+
         // Banned users
         let banned_users: i64 = user_bans::table
             .filter(
@@ -367,12 +383,70 @@ impl ModerationStatsQueries {
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
+        // Unread modmail
+        let can_read_modmail = match board_uuid {
+            Some(bid) => modmail::can_moderate(conn, user, bid).await?,
+            None => user.has_permission(AdminPerms::Users),
+        };
+        let unread_modmail: i64 = if can_read_modmail {
+            let mut query = modmail_threads::table
+                .filter(modmail_threads::is_read_by_mods.eq(false))
+                .filter(modmail_threads::status.ne("archived"))
+                .into_boxed();
+            if let Some(bid) = board_uuid {
+                query = query.filter(modmail_threads::board_id.eq(bid));
+            }
+            query.count().get_result(conn).await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+        } else {
+            0
+        };
+
         Ok(ModerationOverview {
             total_actions: total_actions as i32,
             actions_today: actions_today as i32,
             actions_this_week: actions_this_week as i32,
-            pending_reports: (pending_post_reports + pending_comment_reports) as i32,
+            pending_reports: (pending_post_reports + pending_comment_reports + pending_other_reports) as i32,
             banned_users: banned_users as i32,
+            unread_modmail: unread_modmail as i32,
         })
     }
 }
+
+/// Count pending wiki page, user and message reports. User and message reports are
+/// site-wide, so they are only included for admins looking at the whole site.
+async fn count_other_pending_reports(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    board_uuid: Option<Uuid>,
+) -> Result<i64> {
+    let mut query = wiki_page_reports::table
+        .inner_join(wiki_pages::table.on(wiki_page_reports::page_id.eq(wiki_pages::id)))
+        .filter(wiki_page_reports::status.eq(DbReportStatus::Pending))
+        .into_boxed();
+    if let Some(bid) = board_uuid {
+        query = query.filter(wiki_pages::board_id.eq(bid));
+    }
+    let mut count: i64 = query.count().get_result(conn).await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    if board_uuid.is_none() && user.has_permission(AdminPerms::Users) {
+        let pending_user_reports: i64 = user_reports::table
+            .filter(user_reports::status.eq(DbReportStatus::Pending))
+            .count()
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        let pending_message_reports: i64 = message_reports::table
+            .filter(message_reports::status.eq(DbReportStatus::Pending))
+            .count()
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        count += pending_user_reports + pending_message_reports;
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,54 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::{
+        board::board_mods::ModPerms, moderator::removal_reason::RemovalReason as DbRemovalReason,
+        user::user::AdminPerms,
+    },
+    schema::removal_reasons,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_auth::types::ApiTokenScope;
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::helpers::{
+    permissions::{self, TokenScopeGuard},
+    removal_reasons::require_reason_manager,
+    validation::require_mod_or_admin,
+};
+use crate::structs::removal_reason::RemovalReason;
+
+#[derive(Default)]
+pub struct RemovalReasonQueries;
+
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
+impl RemovalReasonQueries {
+    /// A board's predefined removal reasons. Visible to moderators who can
+    /// remove content or manage the board's config.
+    pub async fn board_removal_reasons(&self, ctx: &Context<'_>, board_id: ID) -> Result<Vec<RemovalReason>> {
+        let user = permissions::require_auth_not_banned(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+        let board_uuid: Uuid = board_id
+            .parse()
+            .map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into()))?;
+
+        if require_mod_or_admin(user, pool, board_uuid, ModPerms::Content, Some(AdminPerms::Content))
+            .await
+            .is_err()
+        {
+            require_reason_manager(user, pool, board_uuid).await?;
+        }
+
+        let conn = &mut get_conn(pool).await?;
+        let reasons: Vec<DbRemovalReason> = removal_reasons::table
+            .filter(removal_reasons::board_id.eq(board_uuid))
+            .order((removal_reasons::display_order, removal_reasons::created_at))
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(reasons.into_iter().map(RemovalReason::from).collect())
+    }
+}
@@ -4,10 +4,14 @@ use async_graphql::*;
 pub use super::moderation::moderation_queue::ModerationQueueQueries;
 pub use super::moderation::moderation_log::ModerationLogQueries;
 pub use super::moderation::moderation_stats::ModerationStatsQueries;
+pub use super::moderation::automod::AutomodQueries;
+pub use super::moderation::removal_reasons::RemovalReasonQueries;
 
 #[derive(MergedObject, Default)]
 pub struct ModerationQueries(
     ModerationQueueQueries,
     ModerationLogQueries,
     ModerationStatsQueries,
+    AutomodQueries,
+    RemovalReasonQueries,
 );
\ No newline at end of file
@@ -0,0 +1,141 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::message::{
+        message::PrivateMessage as DbPrivateMessage,
+        modmail_thread::ModmailThread as DbModmailThread,
+    },
+    schema::{modmail_threads, private_messages},
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::{
+    helpers::modmail,
+    structs::modmail::{ModmailConversation, ModmailMessage, ModmailStatus, ModmailThread},
+    LoggedInUser,
+};
+
+#[derive(Default)]
+pub struct QueryModmail;
+
+#[Object]
+impl QueryModmail {
+    /// Modmail threads you started, most recently active first
+    pub async fn my_modmail_threads(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<ModmailThread>> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let threads: Vec<DbModmailThread> = modmail_threads::table
+            .filter(modmail_threads::user_id.eq(user.id))
+            .order(modmail_threads::last_message_at.desc())
+            .limit(limit.unwrap_or(25).min(100))
+            .offset(offset.unwrap_or(0))
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(threads.into_iter().map(|t| ModmailThread::from_db(t, false)).collect())
+    }
+
+    /// A board's modmail threads, most recently active first. Without
+    /// `status`, threads that aren't archived.
+    pub async fn board_modmail_threads(
+        &self,
+        ctx: &Context<'_>,
+        board_id: ID,
+        status: Option<ModmailStatus>,
+        assigned_to_me: Option<bool>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<ModmailThread>> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let board_uuid: Uuid = board_id
+            .parse()
+            .map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into()))?;
+
+        if !modmail::can_moderate(conn, user, board_uuid).await? {
+            return Err(TinyBoardsError::from_message(403, "You can't read this board's modmail").into());
+        }
+
+        let mut query = modmail_threads::table
+            .filter(modmail_threads::board_id.eq(board_uuid))
+            .into_boxed();
+        query = match status {
+            Some(status) => query.filter(modmail_threads::status.eq(status.as_str())),
+            None => query.filter(modmail_threads::status.ne(ModmailStatus::Archived.as_str())),
+        };
+        if assigned_to_me.unwrap_or(false) {
+            query = query.filter(modmail_threads::assigned_to.eq(user.id));
+        }
+
+        let threads: Vec<DbModmailThread> = query
+            .order(modmail_threads::last_message_at.desc())
+            .limit(limit.unwrap_or(25).min(100))
+            .offset(offset.unwrap_or(0))
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(threads.into_iter().map(|t| ModmailThread::from_db(t, true)).collect())
+    }
+
+    /// A modmail thread with all of its messages. Mod notes are only
+    /// included for moderators. Marks the thread read for the viewer's side.
+    pub async fn modmail_thread(&self, ctx: &Context<'_>, thread_id: ID) -> Result<ModmailConversation> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let (mut thread, as_moderator) = modmail::load_thread(conn, user, &thread_id).await?;
+
+        let mut query = private_messages::table
+            .filter(private_messages::thread_id.eq(thread.id))
+            .filter(private_messages::deleted_at.is_null())
+            .into_boxed();
+        if !as_moderator {
+            query = query.filter(private_messages::is_mod_note.eq(false));
+        }
+        let messages: Vec<DbPrivateMessage> = query
+            .order(private_messages::created_at.asc())
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        if as_moderator && !thread.is_read_by_mods {
+            diesel::update(modmail_threads::table.find(thread.id))
+                .set(modmail_threads::is_read_by_mods.eq(true))
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+            thread.is_read_by_mods = true;
+        } else if !as_moderator && !thread.is_read_by_user {
+            diesel::update(modmail_threads::table.find(thread.id))
+                .set(modmail_threads::is_read_by_user.eq(true))
+                .execute(conn)
+                .await
+                .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+            thread.is_read_by_user = true;
+        }
+
+        Ok(ModmailConversation {
+            thread: ModmailThread::from_db(thread, as_moderator),
+            messages: messages
+                .into_iter()
+                .map(|m| ModmailMessage::from_db(m, as_moderator))
+                .collect(),
+            viewer_is_moderator: as_moderator,
+        })
+    }
+}
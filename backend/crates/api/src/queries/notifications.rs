@@ -59,6 +59,8 @@ pub struct NotificationCommentContext {
 pub struct NotificationMessageContext {
     pub id: ID,
     pub body: String,
+    /// Modmail thread the message belongs to, for replies from a board
+    pub thread_id: Option<ID>,
 }
 
 #[derive(SimpleObject)]
@@ -81,6 +83,8 @@ pub struct Notification {
     pub comment: Option<NotificationCommentContext>,
     /// Message context (body snippet) if notification is a private message
     pub message: Option<NotificationMessageContext>,
+    /// Text of a system notification
+    pub body: Option<String>,
 }
 
 #[derive(SimpleObject)]
@@ -139,6 +143,138 @@ fn truncate_snippet(text: &str, max_len: usize) -> String {
     }
 }
 
+/// Attach actor, post, comment and message context to notification rows,
+/// batch-loading everything they reference.
+pub(crate) async fn enrich_notifications(
+    conn: &mut diesel_async::AsyncPgConnection,
+    db_notifications: Vec<DbNotification>,
+) -> Vec<Notification> {
+    // Collect all referenced IDs for batch loading
+    let actor_ids: Vec<Uuid> = db_notifications.iter()
+        .filter_map(|n| n.actor_user_id)
+        .collect();
+    let comment_ids: Vec<Uuid> = db_notifications.iter()
+        .filter_map(|n| n.comment_id)
+        .collect();
+    let post_ids: Vec<Uuid> = db_notifications.iter()
+        .filter_map(|n| n.post_id)
+        .collect();
+    let message_ids: Vec<Uuid> = db_notifications.iter()
+        .filter_map(|n| n.message_id)
+        .collect();
+
+    // Batch load actors
+    let actors: Vec<(Uuid, String, Option<String>, Option<String>)> = if !actor_ids.is_empty() {
+        users::table
+            .filter(users::id.eq_any(&actor_ids))
+            .select((users::id, users::name, users::display_name, users::avatar))
+            .load::<(Uuid, String, Option<String>, Option<String>)>(conn)
+            .await
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+
+    // Batch load comments with their post titles and board names
+    let comment_data: Vec<(Uuid, String, Uuid, String, String)> = if !comment_ids.is_empty() {
+        comments::table
+            .inner_join(posts::table.on(posts::id.eq(comments::post_id)))
+            .inner_join(boards::table.on(boards::id.eq(comments::board_id)))
+            .filter(comments::id.eq_any(&comment_ids))
+            .select((
+                comments::id,
+                comments::body_html,
+                comments::post_id,
+                posts::title,
+                boards::name,
+            ))
+            .load::<(Uuid, String, Uuid, String, String)>(conn)
+            .await
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+
+    // Batch load posts with board names
+    let post_data: Vec<(Uuid, String, Uuid, String)> = if !post_ids.is_empty() {
+        posts::table
+            .inner_join(boards::table.on(boards::id.eq(posts::board_id)))
+            .filter(posts::id.eq_any(&post_ids))
+            .select((posts::id, posts::title, posts::board_id, boards::name))
+            .load::<(Uuid, String, Uuid, String)>(conn)
+            .await
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+
+    // Batch load messages
+    let message_data: Vec<(Uuid, String, Option<Uuid>)> = if !message_ids.is_empty() {
+        private_messages::table
+            .filter(private_messages::id.eq_any(&message_ids))
+            .select((private_messages::id, private_messages::body_html, private_messages::thread_id))
+            .load::<(Uuid, String, Option<Uuid>)>(conn)
+            .await
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+
+    // Build enriched notifications
+    db_notifications.into_iter().map(|n| {
+        let actor = n.actor_user_id.and_then(|aid| {
+            actors.iter().find(|a| a.0 == aid).map(|a| NotificationActor {
+                id: a.0.to_string().into(),
+                name: a.1.clone(),
+                display_name: a.2.clone(),
+                avatar: a.3.clone(),
+            })
+        });
+
+        let comment = n.comment_id.and_then(|cid| {
+            comment_data.iter().find(|c| c.0 == cid).map(|c| NotificationCommentContext {
+                id: c.0.to_string().into(),
+                body: truncate_snippet(&strip_html_tags(&c.1), 120),
+                post_id: c.2.to_string().into(),
+                post_title: c.3.clone(),
+                board_name: c.4.clone(),
+            })
+        });
+
+        let post = n.post_id.and_then(|pid| {
+            post_data.iter().find(|p| p.0 == pid).map(|p| NotificationPostContext {
+                id: p.0.to_string().into(),
+                title: p.1.clone(),
+                board_name: p.3.clone(),
+                board_id: p.2.to_string().into(),
+            })
+        });
+
+        let message = n.message_id.and_then(|mid| {
+            message_data.iter().find(|m| m.0 == mid).map(|m| NotificationMessageContext {
+                id: m.0.to_string().into(),
+                body: truncate_snippet(&strip_html_tags(&m.1), 120),
+                thread_id: m.2.map(|id| id.to_string().into()),
+            })
+        });
+
+        Notification {
+            id: n.id.to_string().into(),
+            kind: kind_to_str(&n.kind).to_string(),
+            is_read: n.is_read,
+            created_at: n.created_at.to_string(),
+            comment_id: n.comment_id.map(|id| id.to_string().into()),
+            post_id: n.post_id.map(|id| id.to_string().into()),
+            message_id: n.message_id.map(|id| id.to_string().into()),
+            actor,
+            post,
+            comment,
+            message,
+            body: n.body,
+        }
+    }).collect()
+}
+
 #[Object]
 impl QueryNotifications {
     /// Get user notifications with filtering, enriched with actor/context data
@@ -201,130 +337,7 @@ impl QueryNotifications {
             .await
             .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
-        // Collect all referenced IDs for batch loading
-        let actor_ids: Vec<Uuid> = db_notifications.iter()
-            .filter_map(|n| n.actor_user_id)
-            .collect();
-        let comment_ids: Vec<Uuid> = db_notifications.iter()
-            .filter_map(|n| n.comment_id)
-            .collect();
-        let post_ids: Vec<Uuid> = db_notifications.iter()
-            .filter_map(|n| n.post_id)
-            .collect();
-        let message_ids: Vec<Uuid> = db_notifications.iter()
-            .filter_map(|n| n.message_id)
-            .collect();
-
-        // Batch load actors
-        let actors: Vec<(Uuid, String, Option<String>, Option<String>)> = if !actor_ids.is_empty() {
-            users::table
-                .filter(users::id.eq_any(&actor_ids))
-                .select((users::id, users::name, users::display_name, users::avatar))
-                .load::<(Uuid, String, Option<String>, Option<String>)>(conn)
-                .await
-                .unwrap_or_default()
-        } else {
-            vec![]
-        };
-
-        // Batch load comments with their post titles and board names
-        let comment_data: Vec<(Uuid, String, Uuid, String, String)> = if !comment_ids.is_empty() {
-            comments::table
-                .inner_join(posts::table.on(posts::id.eq(comments::post_id)))
-                .inner_join(boards::table.on(boards::id.eq(comments::board_id)))
-                .filter(comments::id.eq_any(&comment_ids))
-                .select((
-                    comments::id,
-                    comments::body_html,
-                    comments::post_id,
-                    posts::title,
-                    boards::name,
-                ))
-                .load::<(Uuid, String, Uuid, String, String)>(conn)
-                .await
-                .unwrap_or_default()
-        } else {
-            vec![]
-        };
-
-        // Batch load posts with board names
-        let post_data: Vec<(Uuid, String, Uuid, String)> = if !post_ids.is_empty() {
-            posts::table
-                .inner_join(boards::table.on(boards::id.eq(posts::board_id)))
-                .filter(posts::id.eq_any(&post_ids))
-                .select((posts::id, posts::title, posts::board_id, boards::name))
-                .load::<(Uuid, String, Uuid, String)>(conn)
-                .await
-                .unwrap_or_default()
-        } else {
-            vec![]
-        };
-
-        // Batch load messages
-        let message_data: Vec<(Uuid, String)> = if !message_ids.is_empty() {
-            private_messages::table
-                .filter(private_messages::id.eq_any(&message_ids))
-                .select((private_messages::id, private_messages::body_html))
-                .load::<(Uuid, String)>(conn)
-                .await
-                .unwrap_or_default()
-        } else {
-            vec![]
-        };
-
-        // Build enriched notifications
-        let enriched: Vec<Notification> = db_notifications.into_iter().map(|n| {
-            let actor = n.actor_user_id.and_then(|aid| {
-                actors.iter().find(|a| a.0 == aid).map(|a| NotificationActor {
-                    id: a.0.to_string().into(),
-                    name: a.1.clone(),
-                    display_name: a.2.clone(),
-                    avatar: a.3.clone(),
-                })
-            });
-
-            let comment = n.comment_id.and_then(|cid| {
-                comment_data.iter().find(|c| c.0 == cid).map(|c| NotificationCommentContext {
-                    id: c.0.to_string().into(),
-                    body: truncate_snippet(&strip_html_tags(&c.1), 120),
-                    post_id: c.2.to_string().into(),
-                    post_title: c.3.clone(),
-                    board_name: c.4.clone(),
-                })
-            });
-
-            let post = n.post_id.and_then(|pid| {
-                post_data.iter().find(|p| p.0 == pid).map(|p| NotificationPostContext {
-                    id: p.0.to_string().into(),
-                    title: p.1.clone(),
-                    board_name: p.3.clone(),
-                    board_id: p.2.to_string().into(),
-                })
-            });
-
-            let message = n.message_id.and_then(|mid| {
-                message_data.iter().find(|m| m.0 == mid).map(|m| NotificationMessageContext {
-                    id: m.0.to_string().into(),
-                    body: truncate_snippet(&strip_html_tags(&m.1), 120),
-                })
-            });
-
-            Notification {
-                id: n.id.to_string().into(),
-                kind: kind_to_str(&n.kind).to_string(),
-                is_read: n.is_read,
-                created_at: n.created_at.to_string(),
-                comment_id: n.comment_id.map(|id| id.to_string().into()),
-                post_id: n.post_id.map(|id| id.to_string().into()),
-                message_id: n.message_id.map(|id| id.to_string().into()),
-                actor,
-                post,
-                comment,
-                message,
-            }
-        }).collect();
-
-        Ok(enriched)
+        Ok(enrich_notifications(conn, db_notifications).await)
     }
 
     /// Get user's notification settings
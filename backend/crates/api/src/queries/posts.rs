@@ -290,6 +290,18 @@ impl QueryPosts {
             }
         }
 
+        // Hide bot accounts' posts from users who turned bots off, except on
+        // the bot's own profile
+        if user_uuid.is_none() && v_opt.is_some_and(|v| !v.show_bots) {
+            query = query.filter(
+                posts::creator_id.ne_all(
+                    users::table
+                        .filter(users::is_bot_account.eq(true))
+                        .select(users::id),
+                ),
+            );
+        }
+
         // Exclude banned boards unless admin
         if !is_admin {
             query = query.filter(
@@ -1,20 +1,28 @@
 use async_graphql::*;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use tinyboards_db::{
     enums::DbReportStatus,
     models::{
         board::board_mods::{BoardModerator, ModPerms},
         comment::comment_report::CommentReport as DbCommentReport,
+        message::message_report::MessageReport as DbMessageReport,
         post::post_report::PostReport as DbPostReport,
-        user::user::AdminPerms,
+        user::user::{AdminPerms, User},
+        user::user_report::UserReport as DbUserReport,
+        wiki::WikiPageReport as DbWikiPageReport,
+    },
+    schema::{
+        board_moderators, comment_reports, comments, message_reports, post_reports, posts,
+        user_reports, wiki_page_reports, wiki_pages,
     },
-    schema::{board_moderators, comment_reports, comments, post_reports, posts},
     utils::{get_conn, DbPool},
 };
+use tinyboards_auth::types::ApiTokenScope;
 use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::helpers::permissions::TokenScopeGuard;
 use crate::LoggedInUser;
 
 #[derive(Default)]
@@ -52,6 +60,56 @@ pub struct CommentReportView {
     pub updated_at: String,
 }
 
+#[derive(SimpleObject)]
+pub struct UserReportView {
+    pub id: ID,
+    pub creator_id: ID,
+    pub user_id: ID,
+    pub original_name: String,
+    pub original_display_name: Option<String>,
+    pub original_bio: Option<String>,
+    pub original_avatar: Option<String>,
+    pub original_signature: Option<String>,
+    pub reason: String,
+    pub status: String,
+    pub resolver_id: Option<ID>,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+    #[graphql(name = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(SimpleObject)]
+pub struct MessageReportView {
+    pub id: ID,
+    pub creator_id: ID,
+    pub message_id: ID,
+    pub original_message_text: String,
+    pub reason: String,
+    pub status: String,
+    pub resolver_id: Option<ID>,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+    #[graphql(name = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(SimpleObject)]
+pub struct WikiPageReportView {
+    pub id: ID,
+    pub creator_id: ID,
+    pub page_id: ID,
+    pub original_page_title: String,
+    pub original_page_body: String,
+    pub reason: String,
+    pub status: String,
+    pub resolver_id: Option<ID>,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+    #[graphql(name = "updatedAt")]
+    pub updated_at: String,
+}
+
 fn report_status_str(status: &DbReportStatus) -> &'static str {
     match status {
         DbReportStatus::Pending => "pending",
@@ -94,7 +152,113 @@ impl From<DbCommentReport> for CommentReportView {
     }
 }
 
-#[Object]
+impl From<DbUserReport> for UserReportView {
+    fn from(r: DbUserReport) -> Self {
+        Self {
+            id: r.id.to_string().into(),
+            creator_id: r.creator_id.to_string().into(),
+            user_id: r.user_id.to_string().into(),
+            original_name: r.original_name,
+            original_display_name: r.original_display_name,
+            original_bio: r.original_bio,
+            original_avatar: r.original_avatar,
+            original_signature: r.original_signature,
+            reason: r.reason,
+            status: report_status_str(&r.status).to_string(),
+            resolver_id: r.resolver_id.map(|id| id.to_string().into()),
+            created_at: r.created_at.to_string(),
+            updated_at: r.updated_at.to_string(),
+        }
+    }
+}
+
+impl From<DbMessageReport> for MessageReportView {
+    fn from(r: DbMessageReport) -> Self {
+        Self {
+            id: r.id.to_string().into(),
+            creator_id: r.creator_id.to_string().into(),
+            message_id: r.message_id.to_string().into(),
+            original_message_text: r.original_message_text,
+            reason: r.reason,
+            status: report_status_str(&r.status).to_string(),
+            resolver_id: r.resolver_id.map(|id| id.to_string().into()),
+            created_at: r.created_at.to_string(),
+            updated_at: r.updated_at.to_string(),
+        }
+    }
+}
+
+impl From<DbWikiPageReport> for WikiPageReportView {
+    fn from(r: DbWikiPageReport) -> Self {
+        Self {
+            id: r.id.to_string().into(),
+            creator_id: r.creator_id.to_string().into(),
+            page_id: r.page_id.to_string().into(),
+            original_page_title: r.original_page_title,
+            original_page_body: r.original_page_body,
+            reason: r.reason,
+            status: report_status_str(&r.status).to_string(),
+            resolver_id: r.resolver_id.map(|id| id.to_string().into()),
+            created_at: r.created_at.to_string(),
+            updated_at: r.updated_at.to_string(),
+        }
+    }
+}
+
+fn parse_status_filter(status_filter: Option<&str>) -> Option<DbReportStatus> {
+    match status_filter {
+        Some("resolved") => Some(DbReportStatus::Resolved),
+        Some("dismissed") => Some(DbReportStatus::Dismissed),
+        Some("pending") => Some(DbReportStatus::Pending),
+        _ => None,
+    }
+}
+
+/// Resolve the board filter for board-scoped report queries. Admins may omit it;
+/// moderators must pass a board they moderate with the Content permission.
+async fn report_board_scope(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    board_id: Option<ID>,
+) -> Result<Option<Uuid>> {
+    let is_admin = user.has_permission(AdminPerms::Content);
+
+    let board_uuid: Option<Uuid> = if let Some(ref bid) = board_id {
+        Some(bid.parse().map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into()))?)
+    } else {
+        None
+    };
+
+    if !is_admin && board_uuid.is_none() {
+        return Err(TinyBoardsError::from_message(
+            403,
+            "You must specify a board_id if you're not an admin",
+        ).into());
+    }
+
+    if let Some(bid) = board_uuid {
+        if !is_admin {
+            let moderator: BoardModerator = board_moderators::table
+                .filter(board_moderators::board_id.eq(bid))
+                .filter(board_moderators::user_id.eq(user.id))
+                .filter(board_moderators::is_invite_accepted.eq(true))
+                .first(conn)
+                .await
+                .map_err(|_| TinyBoardsError::from_message(403, "You are not a moderator of this board"))?;
+
+            if !moderator.has_permission(ModPerms::Content) {
+                return Err(TinyBoardsError::from_message(
+                    403,
+                    "You don't have permission to view reports for this board",
+                ).into());
+            }
+        }
+    }
+
+    Ok(board_uuid)
+}
+
+#[Object(guard = "TokenScopeGuard(ApiTokenScope::Moderate)")]
 impl ReportQueries {
     /// Get post reports (moderator/admin only)
     pub async fn get_post_reports(
@@ -112,48 +276,7 @@ impl ReportQueries {
         let limit = limit.unwrap_or(20).min(100);
         let offset = offset.unwrap_or(0);
 
-        let is_admin = user.has_permission(AdminPerms::Content);
-
-        let board_uuid: Option<Uuid> = if let Some(ref bid) = board_id {
-            Some(bid.parse().map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into()))?)
-        } else {
-            None
-        };
-
-        if !is_admin && board_uuid.is_none() {
-            return Err(TinyBoardsError::from_message(
-                403,
-                "You must specify a board_id if you're not an admin",
-            ).into());
-        }
-
-        // If board_id is specified, check moderator permissions
-        if let Some(bid) = board_uuid {
-            if !is_admin {
-                let moderator: BoardModerator = board_moderators::table
-                    .filter(board_moderators::board_id.eq(bid))
-                    .filter(board_moderators::user_id.eq(user.id))
-                    .filter(board_moderators::is_invite_accepted.eq(true))
-                    .first(conn)
-                    .await
-                    .map_err(|_| TinyBoardsError::from_message(403, "You are not a moderator of this board"))?;
-
-                if !moderator.has_permission(ModPerms::Content) {
-                    return Err(TinyBoardsError::from_message(
-                        403,
-                        "You don't have permission to view reports for this board",
-                    ).into());
-                }
-            }
-        }
-
-        // Parse status filter
-        let status = match status_filter.as_deref() {
-            Some("resolved") => Some(DbReportStatus::Resolved),
-            Some("dismissed") => Some(DbReportStatus::Dismissed),
-            Some("pending") => Some(DbReportStatus::Pending),
-            _ => None,
-        };
+        let board_uuid = report_board_scope(conn, user, board_id).await?;
 
         // Build query - join with posts to filter by board_id
         let mut query = post_reports::table
@@ -166,7 +289,7 @@ impl ReportQueries {
             query = query.filter(posts::board_id.eq(bid));
         }
 
-        if let Some(s) = status {
+        if let Some(s) = parse_status_filter(status_filter.as_deref()) {
             query = query.filter(post_reports::status.eq(s));
         }
 
@@ -196,47 +319,7 @@ impl ReportQueries {
         let limit = limit.unwrap_or(20).min(100);
         let offset = offset.unwrap_or(0);
 
-        let is_admin = user.has_permission(AdminPerms::Content);
-
-        let board_uuid: Option<Uuid> = if let Some(ref bid) = board_id {
-            Some(bid.parse().map_err(|_| TinyBoardsError::NotFound("Invalid board ID".into()))?)
-        } else {
-            None
-        };
-
-        if !is_admin && board_uuid.is_none() {
-            return Err(TinyBoardsError::from_message(
-                403,
-                "You must specify a board_id if you're not an admin",
-            ).into());
-        }
-
-        // If board_id is specified, check moderator permissions
-        if let Some(bid) = board_uuid {
-            if !is_admin {
-                let moderator: BoardModerator = board_moderators::table
-                    .filter(board_moderators::board_id.eq(bid))
-                    .filter(board_moderators::user_id.eq(user.id))
-                    .filter(board_moderators::is_invite_accepted.eq(true))
-                    .first(conn)
-                    .await
-                    .map_err(|_| TinyBoardsError::from_message(403, "You are not a moderator of this board"))?;
-
-                if !moderator.has_permission(ModPerms::Content) {
-                    return Err(TinyBoardsError::from_message(
-                        403,
-                        "You don't have permission to view reports for this board",
-                    ).into());
-                }
-            }
-        }
-
-        let status = match status_filter.as_deref() {
-            Some("resolved") => Some(DbReportStatus::Resolved),
-            Some("dismissed") => Some(DbReportStatus::Dismissed),
-            Some("pending") => Some(DbReportStatus::Pending),
-            _ => None,
-        };
+        let board_uuid = report_board_scope(conn, user, board_id).await?;
 
         // Build query - join with comments to filter by board_id
         let mut query = comment_reports::table
@@ -249,7 +332,7 @@ impl ReportQueries {
             query = query.filter(comments::board_id.eq(bid));
         }
 
-        if let Some(s) = status {
+        if let Some(s) = parse_status_filter(status_filter.as_deref()) {
             query = query.filter(comment_reports::status.eq(s));
         }
 
@@ -262,4 +345,121 @@ impl ReportQueries {
 
         Ok(reports.into_iter().map(CommentReportView::from).collect())
     }
+
+    /// Get wiki page reports (moderator/admin only)
+    pub async fn get_wiki_page_reports(
+        &self,
+        ctx: &Context<'_>,
+        board_id: Option<ID>,
+        #[graphql(name = "statusFilter")] status_filter: Option<String>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<WikiPageReportView>> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let limit = limit.unwrap_or(20).min(100);
+        let offset = offset.unwrap_or(0);
+
+        let board_uuid = report_board_scope(conn, user, board_id).await?;
+
+        // Join with wiki_pages to filter by board_id
+        let mut query = wiki_page_reports::table
+            .inner_join(wiki_pages::table.on(wiki_page_reports::page_id.eq(wiki_pages::id)))
+            .select(wiki_page_reports::all_columns)
+            .order(wiki_page_reports::created_at.desc())
+            .into_boxed();
+
+        if let Some(bid) = board_uuid {
+            query = query.filter(wiki_pages::board_id.eq(bid));
+        }
+
+        if let Some(s) = parse_status_filter(status_filter.as_deref()) {
+            query = query.filter(wiki_page_reports::status.eq(s));
+        }
+
+        let reports: Vec<DbWikiPageReport> = query
+            .limit(limit)
+            .offset(offset)
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(reports.into_iter().map(WikiPageReportView::from).collect())
+    }
+
+    /// Get user profile reports (admin only)
+    pub async fn get_user_reports(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(name = "statusFilter")] status_filter: Option<String>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<UserReportView>> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        let conn = &mut get_conn(pool).await?;
+
+        if !user.has_permission(AdminPerms::Users) {
+            return Err(TinyBoardsError::from_message(403, "Only admins can view user reports").into());
+        }
+
+        let limit = limit.unwrap_or(20).min(100);
+        let offset = offset.unwrap_or(0);
+
+        let mut query = user_reports::table
+            .order(user_reports::created_at.desc())
+            .into_boxed();
+
+        if let Some(s) = parse_status_filter(status_filter.as_deref()) {
+            query = query.filter(user_reports::status.eq(s));
+        }
+
+        let reports: Vec<DbUserReport> = query
+            .limit(limit)
+            .offset(offset)
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(reports.into_iter().map(UserReportView::from).collect())
+    }
+
+    /// Get private message reports (admin only)
+    pub async fn get_message_reports(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(name = "statusFilter")] status_filter: Option<String>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<MessageReportView>> {
+        let pool = ctx.data::<DbPool>()?;
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        let conn = &mut get_conn(pool).await?;
+
+        if !user.has_permission(AdminPerms::Users) {
+            return Err(TinyBoardsError::from_message(403, "Only admins can view message reports").into());
+        }
+
+        let limit = limit.unwrap_or(20).min(100);
+        let offset = offset.unwrap_or(0);
+
+        let mut query = message_reports::table
+            .order(message_reports::created_at.desc())
+            .into_boxed();
+
+        if let Some(s) = parse_status_filter(status_filter.as_deref()) {
+            query = query.filter(message_reports::status.eq(s));
+        }
+
+        let reports: Vec<DbMessageReport> = query
+            .limit(limit)
+            .offset(offset)
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        Ok(reports.into_iter().map(MessageReportView::from).collect())
+    }
 }
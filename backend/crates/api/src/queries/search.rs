@@ -1,5 +1,9 @@
 use async_graphql::*;
-use diesel::prelude::*;
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    sql_types::{Bool, Float4, Text},
+};
 use diesel_async::RunQueryDsl;
 use tinyboards_db::{
     models::{
@@ -15,7 +19,11 @@ use tinyboards_db::{
     },
     utils::{get_conn, DbPool},
 };
-use tinyboards_utils::TinyBoardsError;
+use tinyboards_utils::{
+    rate_limit::RateLimitCell,
+    search_query::{self, HEADLINE_OPTIONS, HEADLINE_TITLE_OPTIONS},
+    TinyBoardsError,
+};
 use uuid::Uuid;
 
 use crate::{
@@ -25,6 +33,7 @@ use crate::{
         post::Post as GqlPost,
         user::User as GqlUser,
     },
+    utils::rate_limit::check_rate_limit,
     LoggedInUser, SortType,
 };
 
@@ -37,6 +46,20 @@ pub struct SearchResult {
     pub comments: Vec<GqlComment>,
     pub users: Vec<GqlUser>,
     pub boards: Vec<GqlBoard>,
+    /// Relevance and highlighted snippets for each matching post and comment,
+    /// in the same order as `posts` followed by `comments`.
+    pub highlights: Vec<SearchHighlight>,
+}
+
+/// Why a post or comment matched: its rank and `<mark>`-highlighted excerpts.
+#[derive(SimpleObject)]
+pub struct SearchHighlight {
+    pub id: ID,
+    pub search_type: SearchType,
+    pub rank: f64,
+    /// Post title with matches highlighted (posts only)
+    pub title: Option<String>,
+    pub snippet: String,
 }
 
 #[derive(Enum, Copy, Clone, Eq, PartialEq)]
@@ -56,8 +79,11 @@ pub enum SearchType {
 #[Object]
 impl QuerySearch {
     /// Search for content across posts, comments, users, and boards.
-    /// Uses ILIKE for fuzzy matching (pg_trgm indexes exist for username and board name).
-    // TODO: Add full-text search with tsvector/tsquery for better post/comment body search
+    ///
+    /// Posts and comments use Postgres full-text search and support `"phrases"`,
+    /// `-exclusions` and `OR`. Without an explicit `sort` they are ordered by
+    /// relevance. Users and boards use ILIKE (pg_trgm indexes exist for username
+    /// and board name).
     pub async fn search_content(
         &self,
         ctx: &Context<'_>,
@@ -81,8 +107,9 @@ impl QuerySearch {
             .into());
         }
 
+        check_rate_limit(ctx, RateLimitCell::search).await?;
+
         let search_type = search_type.unwrap_or(SearchType::All);
-        let _sort = sort.unwrap_or(SortType::New);
         let page = page.unwrap_or(1) as i64;
         let limit = limit.unwrap_or(20).min(50) as i64;
         let offset = (page - 1) * limit;
@@ -103,22 +130,40 @@ impl QuerySearch {
             None => None,
         };
 
+        let searches_text = matches!(
+            search_type,
+            SearchType::All | SearchType::Posts | SearchType::Comments
+        );
+        let tsquery = search_query::to_tsquery(&q);
+        if searches_text && tsquery.is_none() && search_type != SearchType::All {
+            return Err(TinyBoardsError::from_message(
+                400,
+                "Search query must contain at least one term that is not excluded",
+            )
+            .into());
+        }
+
+        let top_cutoff = sort.and_then(top_cutoff);
         let search_term = format!("%{}%", q.to_lowercase());
         let mut result_posts = Vec::new();
         let mut result_comments = Vec::new();
         let mut result_users = Vec::new();
         let mut result_boards = Vec::new();
+        let mut highlights = Vec::new();
 
         // Search posts
-        if matches!(search_type, SearchType::All | SearchType::Posts) {
+        if let (true, Some(tsq)) = (
+            matches!(search_type, SearchType::All | SearchType::Posts),
+            tsquery.as_ref(),
+        ) {
             let mut query = posts::table
                 .inner_join(post_aggregates::table.on(post_aggregates::post_id.eq(posts::id)))
                 .into_boxed();
 
             query = query.filter(
-                posts::title
-                    .ilike(&search_term)
-                    .or(posts::body.ilike(&search_term)),
+                sql::<Bool>("posts.search_vector @@ to_tsquery('english', ")
+                    .bind::<Text, _>(tsq.clone())
+                    .sql(")"),
             );
 
             if let Some(bid) = board_uuid {
@@ -127,25 +172,83 @@ impl QuerySearch {
             if let Some(cid) = creator_uuid {
                 query = query.filter(posts::creator_id.eq(cid));
             }
+            if let Some(cutoff) = top_cutoff {
+                query = query.filter(posts::created_at.gt(cutoff));
+            }
 
             query = query
                 .filter(posts::is_removed.eq(false))
                 .filter(posts::deleted_at.is_null());
 
-            let post_results: Vec<(DbPost, PostAggregates)> = query
-                .order(posts::created_at.desc())
-                .select((posts::all_columns, post_aggregates::all_columns))
+            query = match sort {
+                None => query.order(
+                    sql::<Float4>("ts_rank_cd(posts.search_vector, to_tsquery('english', ")
+                        .bind::<Text, _>(tsq.clone())
+                        .sql("))")
+                        .desc(),
+                ),
+                Some(SortType::New) => query.order(posts::created_at.desc()),
+                Some(SortType::Old) => query.order(posts::created_at.asc()),
+                Some(SortType::Hot) => query.order(post_aggregates::hot_rank.desc()),
+                Some(SortType::Active) => query.order(post_aggregates::hot_rank_active.desc()),
+                Some(
+                    SortType::TopDay
+                    | SortType::TopWeek
+                    | SortType::TopMonth
+                    | SortType::TopYear
+                    | SortType::TopAll,
+                ) => query.order(post_aggregates::score.desc()),
+                Some(SortType::MostComments) => query.order(post_aggregates::comments.desc()),
+                Some(SortType::NewComments) => {
+                    query.order(post_aggregates::newest_comment_time.desc())
+                }
+                Some(SortType::Controversial) => {
+                    query.order(post_aggregates::controversy_rank.desc())
+                }
+            };
+
+            let post_results: Vec<(DbPost, PostAggregates, f32, String, String)> = query
+                .then_order_by(posts::created_at.desc())
+                .select((
+                    posts::all_columns,
+                    post_aggregates::all_columns,
+                    sql::<Float4>("ts_rank_cd(posts.search_vector, to_tsquery('english', ")
+                        .bind::<Text, _>(tsq.clone())
+                        .sql("))"),
+                    sql::<Text>("ts_headline('english', posts.title, to_tsquery('english', ")
+                        .bind::<Text, _>(tsq.clone())
+                        .sql("), ")
+                        .bind::<Text, _>(HEADLINE_TITLE_OPTIONS)
+                        .sql(")"),
+                    sql::<Text>("ts_headline('english', posts.body, to_tsquery('english', ")
+                        .bind::<Text, _>(tsq.clone())
+                        .sql("), ")
+                        .bind::<Text, _>(HEADLINE_OPTIONS)
+                        .sql(")"),
+                ))
                 .limit(limit)
                 .offset(offset)
                 .load(conn)
                 .await
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
-            result_posts = post_results.into_iter().map(GqlPost::from).collect();
+            for (post, agg, rank, title, snippet) in post_results {
+                highlights.push(SearchHighlight {
+                    id: post.id.to_string().into(),
+                    search_type: SearchType::Posts,
+                    rank: rank as f64,
+                    title: Some(search_query::render_headline(&title)),
+                    snippet: search_query::render_headline(&snippet),
+                });
+                result_posts.push(GqlPost::from((post, agg)));
+            }
         }
 
         // Search comments
-        if matches!(search_type, SearchType::All | SearchType::Comments) {
+        if let (true, Some(tsq)) = (
+            matches!(search_type, SearchType::All | SearchType::Comments),
+            tsquery.as_ref(),
+        ) {
             let mut query = comments::table
                 .inner_join(
                     comment_aggregates::table
@@ -153,7 +256,11 @@ impl QuerySearch {
                 )
                 .into_boxed();
 
-            query = query.filter(comments::body.ilike(&search_term));
+            query = query.filter(
+                sql::<Bool>("comments.search_vector @@ to_tsquery('english', ")
+                    .bind::<Text, _>(tsq.clone())
+                    .sql(")"),
+            );
 
             if let Some(cid) = creator_uuid {
                 query = query.filter(comments::creator_id.eq(cid));
@@ -161,26 +268,78 @@ impl QuerySearch {
             if let Some(bid) = board_uuid {
                 query = query.filter(comments::board_id.eq(bid));
             }
+            if let Some(cutoff) = top_cutoff {
+                query = query.filter(comments::created_at.gt(cutoff));
+            }
 
             query = query
                 .filter(comments::is_removed.eq(false))
                 .filter(comments::deleted_at.is_null());
 
-            let comment_results: Vec<(DbComment, CommentAggregates)> = query
-                .order(comments::created_at.desc())
-                .select((comments::all_columns, comment_aggregates::all_columns))
+            query = match sort {
+                None => query.order(
+                    sql::<Float4>("ts_rank_cd(comments.search_vector, to_tsquery('english', ")
+                        .bind::<Text, _>(tsq.clone())
+                        .sql("))")
+                        .desc(),
+                ),
+                Some(SortType::New | SortType::NewComments) => {
+                    query.order(comments::created_at.desc())
+                }
+                Some(SortType::Old) => query.order(comments::created_at.asc()),
+                Some(SortType::Hot | SortType::Active) => {
+                    query.order(comment_aggregates::hot_rank.desc())
+                }
+                Some(
+                    SortType::TopDay
+                    | SortType::TopWeek
+                    | SortType::TopMonth
+                    | SortType::TopYear
+                    | SortType::TopAll,
+                ) => query.order(comment_aggregates::score.desc()),
+                Some(SortType::MostComments) => {
+                    query.order(comment_aggregates::child_count.desc())
+                }
+                Some(SortType::Controversial) => {
+                    query.order(comment_aggregates::controversy_rank.desc())
+                }
+            };
+
+            let comment_results: Vec<(DbComment, CommentAggregates, f32, String)> = query
+                .then_order_by(comments::created_at.desc())
+                .select((
+                    comments::all_columns,
+                    comment_aggregates::all_columns,
+                    sql::<Float4>("ts_rank_cd(comments.search_vector, to_tsquery('english', ")
+                        .bind::<Text, _>(tsq.clone())
+                        .sql("))"),
+                    sql::<Text>("ts_headline('english', comments.body, to_tsquery('english', ")
+                        .bind::<Text, _>(tsq.clone())
+                        .sql("), ")
+                        .bind::<Text, _>(HEADLINE_OPTIONS)
+                        .sql(")"),
+                ))
                 .limit(limit)
                 .offset(offset)
                 .load(conn)
                 .await
                 .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
 
-            result_comments = comment_results.into_iter().map(GqlComment::from).collect();
+            for (comment, agg, rank, snippet) in comment_results {
+                highlights.push(SearchHighlight {
+                    id: comment.id.to_string().into(),
+                    search_type: SearchType::Comments,
+                    rank: rank as f64,
+                    title: None,
+                    snippet: search_query::render_headline(&snippet),
+                });
+                result_comments.push(GqlComment::from((comment, agg)));
+            }
         }
 
         // Search users
         if matches!(search_type, SearchType::All | SearchType::Users) {
-            let user_results: Vec<(DbUser, Option<UserAggregates>)> = users::table
+            let mut query = users::table
                 .left_join(user_aggregates::table.on(user_aggregates::user_id.eq(users::id)))
                 .filter(
                     users::name
@@ -189,7 +348,32 @@ impl QuerySearch {
                 )
                 .filter(users::is_banned.eq(false))
                 .filter(users::deleted_at.is_null())
-                .order(users::created_at.desc())
+                .into_boxed();
+
+            query = match sort {
+                Some(SortType::Old) => query.order(users::created_at.asc()),
+                Some(SortType::MostComments) => query.order(
+                    user_aggregates::comment_count
+                        .nullable()
+                        .desc()
+                        .nulls_last(),
+                ),
+                Some(
+                    SortType::TopDay
+                    | SortType::TopWeek
+                    | SortType::TopMonth
+                    | SortType::TopYear
+                    | SortType::TopAll,
+                ) => query.order(
+                    (user_aggregates::post_score + user_aggregates::comment_score)
+                        .nullable()
+                        .desc()
+                        .nulls_last(),
+                ),
+                _ => query.order(users::created_at.desc()),
+            };
+
+            let user_results: Vec<(DbUser, Option<UserAggregates>)> = query
                 .select((users::all_columns, user_aggregates::all_columns.nullable()))
                 .limit(limit)
                 .offset(offset)
@@ -204,7 +388,7 @@ impl QuerySearch {
 
         // Search boards
         if matches!(search_type, SearchType::All | SearchType::Boards) {
-            let board_results: Vec<(DbBoard, Option<BoardAggregates>)> = boards::table
+            let mut query = boards::table
                 .left_join(
                     board_aggregates::table.on(board_aggregates::board_id.eq(boards::id)),
                 )
@@ -216,7 +400,32 @@ impl QuerySearch {
                 )
                 .filter(boards::is_banned.eq(false))
                 .filter(boards::deleted_at.is_null())
-                .order(boards::created_at.desc())
+                .into_boxed();
+
+            query = match sort {
+                None | Some(SortType::New) => query.order(boards::created_at.desc()),
+                Some(SortType::Old) => query.order(boards::created_at.asc()),
+                Some(SortType::Active) => query.order(
+                    board_aggregates::users_active_week
+                        .nullable()
+                        .desc()
+                        .nulls_last(),
+                ),
+                Some(SortType::MostComments) => query.order(
+                    board_aggregates::comments
+                        .nullable()
+                        .desc()
+                        .nulls_last(),
+                ),
+                Some(_) => query.order(
+                    board_aggregates::subscribers
+                        .nullable()
+                        .desc()
+                        .nulls_last(),
+                ),
+            };
+
+            let board_results: Vec<(DbBoard, Option<BoardAggregates>)> = query
                 .select((boards::all_columns, board_aggregates::all_columns.nullable()))
                 .limit(limit)
                 .offset(offset)
@@ -235,6 +444,19 @@ impl QuerySearch {
             comments: result_comments,
             users: result_users,
             boards: result_boards,
+            highlights,
         })
     }
 }
+
+/// Lower bound on `created_at` for the time-windowed "top" sorts.
+fn top_cutoff(sort: SortType) -> Option<chrono::DateTime<chrono::Utc>> {
+    let window = match sort {
+        SortType::TopDay => chrono::Duration::days(1),
+        SortType::TopWeek => chrono::Duration::weeks(1),
+        SortType::TopMonth => chrono::Duration::days(30),
+        SortType::TopYear => chrono::Duration::days(365),
+        _ => return None,
+    };
+    Some(chrono::Utc::now() - window)
+}
@@ -0,0 +1,52 @@
+use async_graphql::*;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::{auth::AuthSession as DbAuthSession, user::user::AdminPerms},
+    schema::auth_sessions,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::{helpers::permissions, structs::session::AuthSession, SessionTokenHash};
+
+#[derive(Default)]
+pub struct QuerySessions;
+
+#[Object]
+impl QuerySessions {
+    /// Active sessions, most recently used first. Admins with user
+    /// management permission can pass `userId` to see another user's.
+    pub async fn sessions(&self, ctx: &Context<'_>, user_id: Option<ID>) -> Result<Vec<AuthSession>> {
+        let me = permissions::require_auth(ctx)?;
+        let pool = ctx.data::<DbPool>()?;
+
+        let user_id = match user_id {
+            Some(id) => id
+                .parse::<Uuid>()
+                .map_err(|_| TinyBoardsError::BadRequest("Invalid user ID".to_string()))?,
+            None => me.id,
+        };
+        if user_id != me.id {
+            permissions::require_admin_permission(ctx, AdminPerms::Users)?;
+        }
+
+        let conn = &mut get_conn(pool).await?;
+        let sessions: Vec<DbAuthSession> = auth_sessions::table
+            .filter(auth_sessions::user_id.eq(user_id))
+            .filter(auth_sessions::expires_at.gt(Utc::now()))
+            .order(auth_sessions::last_used_at.desc().nulls_last())
+            .then_order_by(auth_sessions::created_at.desc())
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        let current = ctx.data_opt::<SessionTokenHash>().and_then(SessionTokenHash::inner);
+        Ok(sessions
+            .into_iter()
+            .map(|session| AuthSession::from_db(session, current))
+            .collect())
+    }
+}
@@ -4,12 +4,22 @@ use diesel_async::RunQueryDsl;
 use tinyboards_db::{
     models::site::site::Site as DbSite,
     models::aggregates::SiteAggregates as DbSiteAggregates,
-    schema::{site, site_aggregates},
+    models::config::RateLimit as DbRateLimit,
+    models::user::user::AdminPerms,
+    schema::{rate_limits, site, site_aggregates},
     utils::{get_conn, DbPool},
 };
 use tinyboards_utils::TinyBoardsError;
 
-use crate::structs::site::{LocalSite, SiteStats};
+use crate::{
+    helpers::permissions,
+    storage::{maintenance, signing, StorageBackend},
+    structs::site::{LocalSite, MediaStorage, SiteRateLimits, SiteStats},
+    LoggedInUser, MasterKey, Settings,
+};
+
+/// Most URLs `signedMediaUrls` signs per request
+const MAX_SIGNED_URLS: usize = 100;
 
 #[derive(Default)]
 pub struct QuerySite;
@@ -47,4 +57,55 @@ impl QuerySite {
 
         Ok(SiteStats::from(stats))
     }
+
+    /// Get the active rate limits (admin with Config permission).
+    pub async fn site_rate_limits(&self, ctx: &Context<'_>) -> Result<SiteRateLimits> {
+        permissions::require_admin_permission(ctx, AdminPerms::Config)?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let limits: DbRateLimit = rate_limits::table
+            .first(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(format!("Failed to load rate limits: {}", e)))?;
+
+        Ok(SiteRateLimits::from(limits))
+    }
+
+    /// Storage usage and the latest media garbage collection run (admin with Config permission).
+    /// Lists the whole storage backend, so this can be slow on large buckets.
+    pub async fn media_storage(&self, ctx: &Context<'_>) -> Result<MediaStorage> {
+        permissions::require_admin_permission(ctx, AdminPerms::Config)?;
+        let pool = ctx.data::<DbPool>()?;
+        let storage = ctx.data::<StorageBackend>()?;
+
+        let stats = maintenance::get_storage_stats(storage).await?;
+        let last_gc_run = maintenance::last_media_gc_run(pool).await?;
+
+        Ok(MediaStorage {
+            stats: stats.into(),
+            last_gc_run: last_gc_run.map(Into::into),
+        })
+    }
+
+    /// Sign media URLs so they can be fetched without the session cookie on a
+    /// private instance. URLs outside `/media/` are returned unchanged.
+    pub async fn signed_media_urls(&self, ctx: &Context<'_>, urls: Vec<String>) -> Result<Vec<String>> {
+        ctx.data_unchecked::<LoggedInUser>().require_user_not_banned()?;
+        if urls.len() > MAX_SIGNED_URLS {
+            return Err(TinyBoardsError::from_message(
+                400,
+                &format!("At most {} URLs can be signed at once", MAX_SIGNED_URLS),
+            )
+            .into());
+        }
+
+        let secret = ctx.data::<MasterKey>()?.as_ref();
+        let ttl = ctx.data::<Settings>()?.as_ref().media.signed_url_ttl_seconds as i64;
+
+        Ok(urls
+            .iter()
+            .map(|url| signing::sign_media_url(url, secret, ttl))
+            .collect())
+    }
 }
@@ -0,0 +1,40 @@
+use async_graphql::*;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::{upload::Upload as DbUpload, user::user::AdminPerms},
+    schema::uploads,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::{structs::upload::FileUpload, LoggedInUser};
+
+#[derive(Default)]
+pub struct QueryUploads;
+
+#[Object]
+impl QueryUploads {
+    /// One of your uploads, e.g. to poll its processing status (admins can see any).
+    pub async fn upload(&self, ctx: &Context<'_>, id: ID) -> Result<FileUpload> {
+        let user = ctx.data_unchecked::<LoggedInUser>().require_user()?;
+        let pool = ctx.data::<DbPool>()?;
+        let conn = &mut get_conn(pool).await?;
+
+        let upload_id: Uuid = id
+            .parse()
+            .map_err(|_| TinyBoardsError::from_message(400, "Invalid upload ID"))?;
+
+        let upload: DbUpload = uploads::table
+            .find(upload_id)
+            .first(conn)
+            .await
+            .optional()
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+            .filter(|u: &DbUpload| u.user_id == user.id || user.has_permission(AdminPerms::Content))
+            .ok_or_else(|| TinyBoardsError::NotFound("Upload not found".into()))?;
+
+        Ok(FileUpload::from(upload))
+    }
+}
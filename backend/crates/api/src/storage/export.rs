@@ -0,0 +1,493 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Text};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use std::io::{Cursor, Write};
+use tinyboards_db::{
+    enums::DbNotificationKind,
+    models::{notification::notifications::NotificationInsertForm, user::DataExport},
+    schema::data_exports,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{events::EventBus, helpers::notifications::insert_notification, storage::StorageBackend};
+
+/// Storage prefix for export archives. Media requests for these keys always
+/// need a signed URL, and storage GC leaves them to `cleanup_expired_exports`.
+pub const EXPORTS_PREFIX: &str = "exports/";
+/// How often an account can request an export
+pub const EXPORT_COOLDOWN_HOURS: i64 = 24;
+/// How long an archive (and its download link) is kept
+pub const EXPORT_RETENTION_DAYS: i64 = 7;
+/// A pending export older than this was abandoned by a restarted server
+const STALE_EXPORT_MINUTES: i64 = 60;
+
+/// One file in the archive: a JSON document built by Postgres. `$1` is the
+/// user's ID. `Array` queries are wrapped into a JSON array of their rows.
+enum Section {
+    Object(&'static str),
+    Array(&'static str),
+}
+
+/// Archive contents. Columns are picked by hand so nothing internal (password
+/// hashes, other users' private data, moderation notes) ends up in the archive.
+const SECTIONS: &[(&str, &[(&str, Section)])] = &[
+    (
+        "profile.json",
+        &[(
+            "profile",
+            Section::Object(
+                "SELECT id, name, display_name, email, is_email_verified, is_admin, admin_level,
+                        is_bot_account, bio, signature, avatar, banner, profile_background,
+                        avatar_frame, profile_music, profile_music_youtube, show_nsfw, show_bots,
+                        theme, default_sort_type, default_listing_type, interface_language,
+                        is_email_notifications_enabled, editor_mode, last_seen_at, created_at,
+                        updated_at
+                 FROM users WHERE id = $1",
+            ),
+        )],
+    ),
+    (
+        "posts.json",
+        &[(
+            "posts",
+            Section::Array(
+                "SELECT p.id, b.name AS board, p.title, p.post_type, p.url, p.body, p.image,
+                        p.alt_text, p.is_nsfw, p.is_removed, p.is_locked, p.created_at,
+                        p.updated_at, p.deleted_at
+                 FROM posts p JOIN boards b ON b.id = p.board_id
+                 WHERE p.creator_id = $1 ORDER BY p.created_at",
+            ),
+        )],
+    ),
+    (
+        "comments.json",
+        &[(
+            "comments",
+            Section::Array(
+                "SELECT c.id, c.post_id, c.parent_id, b.name AS board, c.body, c.is_removed,
+                        c.created_at, c.updated_at, c.deleted_at
+                 FROM comments c JOIN boards b ON b.id = c.board_id
+                 WHERE c.creator_id = $1 ORDER BY c.created_at",
+            ),
+        )],
+    ),
+    (
+        "messages.json",
+        &[(
+            "messages",
+            // Sent and received, including modmail threads the user started;
+            // senders who hid themselves, like moderators replying as the
+            // board, stay hidden
+            Section::Array(
+                "SELECT m.id,
+                        CASE WHEN m.creator_id <> $1 AND m.is_sender_hidden THEN NULL ELSE s.name END AS sender,
+                        r.name AS recipient, rb.name AS recipient_board, t.subject AS modmail_subject,
+                        m.subject, m.body, m.is_read, m.created_at, m.updated_at, m.deleted_at
+                 FROM private_messages m
+                 JOIN users s ON s.id = m.creator_id
+                 LEFT JOIN users r ON r.id = m.recipient_id
+                 LEFT JOIN boards rb ON rb.id = m.recipient_board_id
+                 LEFT JOIN modmail_threads t ON t.id = m.thread_id
+                 WHERE m.creator_id = $1 OR m.recipient_id = $1
+                    OR (t.user_id = $1 AND NOT m.is_mod_note)
+                 ORDER BY m.created_at",
+            ),
+        )],
+    ),
+    (
+        "votes.json",
+        &[
+            (
+                "posts",
+                Section::Array(
+                    "SELECT post_id, score, created_at FROM post_votes
+                     WHERE user_id = $1 ORDER BY created_at",
+                ),
+            ),
+            (
+                "comments",
+                Section::Array(
+                    "SELECT comment_id, post_id, score, created_at FROM comment_votes
+                     WHERE user_id = $1 ORDER BY created_at",
+                ),
+            ),
+            (
+                "reactions",
+                Section::Array(
+                    "SELECT post_id, comment_id, emoji, score, created_at FROM reactions
+                     WHERE user_id = $1 ORDER BY created_at",
+                ),
+            ),
+        ],
+    ),
+    (
+        "saved.json",
+        &[
+            (
+                "posts",
+                Section::Array(
+                    "SELECT post_id, created_at FROM post_saved WHERE user_id = $1 ORDER BY created_at",
+                ),
+            ),
+            (
+                "comments",
+                Section::Array(
+                    "SELECT comment_id, created_at FROM comment_saved WHERE user_id = $1 ORDER BY created_at",
+                ),
+            ),
+            (
+                "hidden_posts",
+                Section::Array(
+                    "SELECT post_id, created_at FROM post_hidden WHERE user_id = $1 ORDER BY created_at",
+                ),
+            ),
+        ],
+    ),
+    (
+        "follows.json",
+        &[
+            (
+                "following",
+                Section::Array(
+                    "SELECT u.name, f.is_pending, f.created_at
+                     FROM user_follows f JOIN users u ON u.id = f.user_id
+                     WHERE f.follower_id = $1 ORDER BY f.created_at",
+                ),
+            ),
+            (
+                "followers",
+                Section::Array(
+                    "SELECT u.name, f.is_pending, f.created_at
+                     FROM user_follows f JOIN users u ON u.id = f.follower_id
+                     WHERE f.user_id = $1 ORDER BY f.created_at",
+                ),
+            ),
+            (
+                "boards",
+                Section::Array(
+                    "SELECT b.name, s.is_pending, s.created_at
+                     FROM board_subscribers s JOIN boards b ON b.id = s.board_id
+                     WHERE s.user_id = $1 ORDER BY s.created_at",
+                ),
+            ),
+        ],
+    ),
+    (
+        "blocks.json",
+        &[
+            (
+                "users",
+                Section::Array(
+                    "SELECT u.name, x.created_at FROM user_blocks x JOIN users u ON u.id = x.target_id
+                     WHERE x.user_id = $1 ORDER BY x.created_at",
+                ),
+            ),
+            (
+                "boards",
+                Section::Array(
+                    "SELECT b.name, x.created_at FROM board_blocks x JOIN boards b ON b.id = x.board_id
+                     WHERE x.user_id = $1 ORDER BY x.created_at",
+                ),
+            ),
+        ],
+    ),
+    (
+        "flairs.json",
+        &[(
+            "flairs",
+            Section::Array(
+                "SELECT b.name AS board, t.text_display AS template, f.custom_text,
+                        f.custom_text_color, f.custom_background_color, f.is_approved, f.created_at
+                 FROM user_flairs f
+                 JOIN boards b ON b.id = f.board_id
+                 JOIN flair_templates t ON t.id = f.flair_template_id
+                 WHERE f.user_id = $1 ORDER BY f.created_at",
+            ),
+        )],
+    ),
+    (
+        "uploads.json",
+        &[(
+            "uploads",
+            Section::Array(
+                "SELECT id, original_name, upload_url, size_bytes, width, height,
+                        duration_seconds, created_at
+                 FROM uploads WHERE user_id = $1 ORDER BY created_at",
+            ),
+        )],
+    ),
+    (
+        "notification_settings.json",
+        &[(
+            "notification_settings",
+            Section::Object(
+                "SELECT is_email_enabled, is_comment_replies_enabled, is_post_replies_enabled,
+                        is_mentions_enabled, is_private_messages_enabled, is_board_invites_enabled,
+                        is_moderator_actions_enabled, is_system_notifications_enabled, updated_at
+                 FROM notification_settings WHERE user_id = $1",
+            ),
+        )],
+    ),
+];
+
+#[derive(QueryableByName)]
+struct JsonRow {
+    #[diesel(sql_type = Nullable<Text>)]
+    data: Option<String>,
+}
+
+/// Storage key for an export's archive
+pub fn export_storage_key(user_id: Uuid, export_id: Uuid) -> String {
+    format!("{}{}/{}.zip", EXPORTS_PREFIX, user_id, export_id)
+}
+
+/// When the account may request its next export, if it has to wait.
+/// An export still being built always has to finish first; failed ones
+/// don't count.
+pub async fn next_export_allowed_at(
+    conn: &mut AsyncPgConnection,
+    user_id: Uuid,
+) -> Result<Option<chrono::DateTime<Utc>>, TinyBoardsError> {
+    let latest: Option<DataExport> = data_exports::table
+        .filter(data_exports::user_id.eq(user_id))
+        .filter(data_exports::status.ne("failed"))
+        .order(data_exports::created_at.desc())
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(latest.and_then(|export| {
+        let allowed_at = export.created_at + Duration::hours(EXPORT_COOLDOWN_HOURS);
+        (export.status == "pending" || allowed_at > Utc::now()).then_some(allowed_at)
+    }))
+}
+
+async fn query_section(
+    conn: &mut AsyncPgConnection,
+    section: &Section,
+    user_id: Uuid,
+) -> Result<serde_json::Value, TinyBoardsError> {
+    let sql = match section {
+        Section::Object(sql) => format!("SELECT row_to_json(t)::text AS data FROM ({}) t", sql),
+        Section::Array(sql) => format!(
+            "SELECT COALESCE(json_agg(t), '[]'::json)::text AS data FROM ({}) t",
+            sql
+        ),
+    };
+    let row: Option<JsonRow> = diesel::sql_query(sql)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .get_result(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    match row.and_then(|row| row.data) {
+        Some(data) => serde_json::from_str(&data)
+            .map_err(|e| TinyBoardsError::Internal(format!("Invalid export JSON: {}", e))),
+        None => Ok(serde_json::Value::Null),
+    }
+}
+
+/// Collect everything in `SECTIONS` and zip it, one pretty-printed JSON file
+/// per entry.
+pub async fn build_archive(pool: &DbPool, user_id: Uuid) -> Result<Vec<u8>, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+
+    let mut files = Vec::with_capacity(SECTIONS.len());
+    for (file_name, parts) in SECTIONS {
+        let mut document = serde_json::Map::new();
+        for (key, section) in parts.iter() {
+            document.insert(key.to_string(), query_section(conn, section, user_id).await?);
+        }
+        let json = serde_json::to_vec_pretty(&document)
+            .map_err(|e| TinyBoardsError::Internal(e.to_string()))?;
+        files.push((*file_name, json));
+    }
+
+    // Compression is CPU-bound, so keep it off the async workers
+    tokio::task::spawn_blocking(move || zip_files(files))
+        .await
+        .map_err(|e| TinyBoardsError::Internal(e.to_string()))?
+}
+
+fn zip_files(files: Vec<(&str, Vec<u8>)>) -> Result<Vec<u8>, TinyBoardsError> {
+    let internal = |e: zip::result::ZipError| TinyBoardsError::Internal(format!("Failed to zip export: {}", e));
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, data) in files {
+        zip.start_file(name, options).map_err(internal)?;
+        zip.write_all(&data)
+            .map_err(|e| TinyBoardsError::Internal(format!("Failed to zip export: {}", e)))?;
+    }
+
+    Ok(zip.finish().map_err(internal)?.into_inner())
+}
+
+/// Build a requested export, store it and notify the user with a download
+/// link that lasts as long as the archive. Failures are recorded on the row
+/// and the user is told to try again.
+pub async fn run_export(
+    pool: DbPool,
+    storage: StorageBackend,
+    events: Option<EventBus>,
+    secret: String,
+    export_id: Uuid,
+    user_id: Uuid,
+) {
+    let key = export_storage_key(user_id, export_id);
+    let result = async {
+        let archive = build_archive(&pool, user_id).await?;
+        let size = archive.len() as i64;
+        storage.write(&key, archive).await?;
+        Ok::<i64, TinyBoardsError>(size)
+    }
+    .await;
+
+    let conn = &mut match get_conn(&pool).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!("Failed to record data export {}: {}", export_id, e);
+            return;
+        }
+    };
+
+    let body = match result {
+        Ok(size) => {
+            let expires_at = Utc::now() + Duration::days(EXPORT_RETENTION_DAYS);
+            let updated = diesel::update(data_exports::table.find(export_id))
+                .set((
+                    data_exports::status.eq("ready"),
+                    data_exports::storage_key.eq(&key),
+                    data_exports::size_bytes.eq(size),
+                    data_exports::completed_at.eq(Utc::now()),
+                    data_exports::expires_at.eq(expires_at),
+                ))
+                .execute(conn)
+                .await;
+            if let Err(e) = updated {
+                tracing::error!("Failed to record data export {}: {}", export_id, e);
+                let _ = storage.delete(&key).await;
+                return;
+            }
+
+            let url = storage.get_signed_url(&key, &secret, EXPORT_RETENTION_DAYS * 24 * 60 * 60);
+            format!(
+                "Your data export is ready. The link works for {} days: {}",
+                EXPORT_RETENTION_DAYS, url
+            )
+        }
+        Err(e) => {
+            tracing::error!("Data export {} failed: {}", export_id, e);
+            let _ = diesel::update(data_exports::table.find(export_id))
+                .set((
+                    data_exports::status.eq("failed"),
+                    data_exports::last_error.eq(e.to_string()),
+                    data_exports::completed_at.eq(Utc::now()),
+                ))
+                .execute(conn)
+                .await;
+            "Your data export couldn't be created. Please try again later.".to_string()
+        }
+    };
+
+    let form = NotificationInsertForm {
+        kind: DbNotificationKind::System,
+        recipient_user_id: user_id,
+        comment_id: None,
+        post_id: None,
+        message_id: None,
+        is_read: false,
+        actor_user_id: None,
+        body: Some(body),
+    };
+    if let Err(e) = insert_notification(&pool, events.as_ref(), form).await {
+        tracing::error!("Failed to notify user {} about data export: {}", user_id, e);
+    }
+}
+
+/// Delete archives past their expiry along with their rows, and fail exports
+/// a restarted server left pending so the user can ask again.
+pub async fn cleanup_expired_exports(pool: &DbPool, storage: &StorageBackend) -> Result<usize, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+
+    diesel::update(
+        data_exports::table
+            .filter(data_exports::status.eq("pending"))
+            .filter(data_exports::created_at.lt(Utc::now() - Duration::minutes(STALE_EXPORT_MINUTES))),
+    )
+    .set((
+        data_exports::status.eq("failed"),
+        data_exports::last_error.eq("Abandoned"),
+        data_exports::completed_at.eq(Utc::now()),
+    ))
+    .execute(conn)
+    .await
+    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    // Failed exports are kept for a while so the user can see what happened
+    let expired: Vec<DataExport> = data_exports::table
+        .filter(
+            data_exports::expires_at.lt(Utc::now()).or(data_exports::status.eq("failed").and(
+                data_exports::created_at.lt(Utc::now() - Duration::hours(EXPORT_COOLDOWN_HOURS)),
+            )),
+        )
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let mut removed = 0;
+    for export in expired {
+        if let Some(key) = &export.storage_key {
+            if let Err(e) = storage.delete(key).await {
+                tracing::error!("Failed to delete data export {}: {}", key, e);
+                continue;
+            }
+        }
+        diesel::delete(data_exports::table.find(export.id))
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_export_storage_key() {
+        let user_id = Uuid::nil();
+        let export_id = Uuid::from_u128(1);
+        let key = export_storage_key(user_id, export_id);
+        assert!(key.starts_with(EXPORTS_PREFIX));
+        assert_eq!(
+            key,
+            "exports/00000000-0000-0000-0000-000000000000/00000000-0000-0000-0000-000000000001.zip"
+        );
+    }
+
+    #[test]
+    fn test_zip_files() {
+        let archive = zip_files(vec![
+            ("profile.json", b"{\"profile\": null}".to_vec()),
+            ("posts.json", b"{\"posts\": []}".to_vec()),
+        ])
+        .unwrap();
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+        assert_eq!(zip.len(), 2);
+        let mut contents = String::new();
+        zip.by_name("posts.json").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "{\"posts\": []}");
+    }
+}
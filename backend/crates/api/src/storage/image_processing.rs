@@ -11,7 +11,8 @@ pub struct ImageProcessingSettings {
     /// Width of the generated thumbnail (height is calculated from aspect ratio).
     pub thumbnail_width: u32,
     /// Convert JPEG and PNG to WebP for smaller file sizes. GIF is never converted
-    /// because the `image` crate does not support animated WebP encoding.
+    /// because the `image` crate does not support animated WebP encoding; see
+    /// [`process_media`](crate::storage::transcoding::process_media) for that.
     pub convert_to_webp: bool,
     /// Strip EXIF metadata from JPEG images by re-encoding. PNG and WebP do not
     /// carry meaningful EXIF data and are re-encoded regardless.
@@ -142,9 +143,69 @@ pub fn process_upload(
     })
 }
 
+/// [`process_upload`] on the blocking thread pool, so decoding and resizing
+/// large images doesn't stall the async executor.
+pub async fn process_upload_blocking(
+    data: Vec<u8>,
+    mime_type: String,
+    settings: ImageProcessingSettings,
+) -> Result<ProcessedImage, TinyBoardsError> {
+    tokio::task::spawn_blocking(move || process_upload(&data, &mime_type, &settings))
+        .await
+        .map_err(|e| TinyBoardsError::from_message(500, &format!("Image processing task failed: {}", e)))?
+}
+
+/// Drop EXIF/XMP (APP1), IPTC (APP13) and comment segments from a JPEG
+/// without re-encoding it, so an original can be served before the
+/// background worker has optimized it. Returns `None` if the data doesn't
+/// parse as a JPEG.
+pub fn strip_jpeg_metadata(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut i = 2;
+
+    loop {
+        if i + 2 > data.len() || data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        match marker {
+            // Fill byte before a marker
+            0xFF => i += 1,
+            // Start of scan: the rest is image data
+            0xDA => {
+                out.extend_from_slice(&data[i..]);
+                return Some(out);
+            }
+            // Markers without a length
+            0x01 | 0xD0..=0xD7 => {
+                out.extend_from_slice(&data[i..i + 2]);
+                i += 2;
+            }
+            _ => {
+                if i + 4 > data.len() {
+                    return None;
+                }
+                let len = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+                if len < 2 || i + 2 + len > data.len() {
+                    return None;
+                }
+                if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+                    out.extend_from_slice(&data[i..i + 2 + len]);
+                }
+                i += 2 + len;
+            }
+        }
+    }
+}
+
 /// Resize the image to the given width (preserving aspect ratio) and encode
 /// as WebP for small file size.
-fn generate_thumbnail(
+pub(crate) fn generate_thumbnail(
     img: &DynamicImage,
     thumb_width: u32,
 ) -> Result<Vec<u8>, TinyBoardsError> {
@@ -187,3 +248,30 @@ fn encode_image(
 
     Ok((buf.into_inner(), mime.to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut seg = vec![0xFF, marker];
+        seg.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+        seg.extend_from_slice(payload);
+        seg
+    }
+
+    #[test]
+    fn test_strip_jpeg_metadata() {
+        let jfif = segment(0xE0, b"JFIF\0");
+        let exif = segment(0xE1, b"Exif\0\0GPS");
+        let quant = segment(0xDB, &[0; 4]);
+        let scan = [0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9];
+
+        let input = [&[0xFF, 0xD8][..], &jfif[..], &exif[..], &quant[..], &scan[..]].concat();
+        let expected = [&[0xFF, 0xD8][..], &jfif[..], &quant[..], &scan[..]].concat();
+        assert_eq!(strip_jpeg_metadata(&input), Some(expected));
+
+        assert_eq!(strip_jpeg_metadata(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(strip_jpeg_metadata(&[0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x40]), None);
+    }
+}
@@ -0,0 +1,562 @@
+use crate::{
+    helpers::files::{
+        cleanup::{delete_from_storage, StoredFiles},
+        upload::make_thumbnail_key,
+    },
+    storage::{export::EXPORTS_PREFIX, StorageBackend, StoredObject},
+    DbPool,
+};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use diesel_async::RunQueryDsl;
+use std::collections::{BTreeSet, HashSet};
+use tinyboards_db::{
+    models::upload::{MediaGcRun, MediaGcRunInsertForm},
+    schema::{media_gc_runs, uploads},
+    utils::get_conn,
+};
+use tinyboards_utils::{settings::structs::MediaConfig, TinyBoardsError};
+use tokio::fs;
+use uuid::Uuid;
+
+/// GC run history older than this is pruned
+const GC_RUN_RETENTION_DAYS: i64 = 90;
+
+/// Nothing points at upload `u`. Posts and comments are linked through
+/// `content_uploads`; everything else stores the URL directly. Identical
+/// files share a URL, so a row can be collected while the blob stays alive
+/// through another row (see `delete_from_storage`).
+pub(crate) const UPLOAD_IS_UNREFERENCED: &str = r#"
+      NOT EXISTS (SELECT 1 FROM content_uploads cu WHERE cu.upload_id = u.id)
+      AND NOT EXISTS (SELECT 1 FROM users x WHERE u.upload_url IN (x.avatar, x.banner, x.profile_background))
+      AND NOT EXISTS (SELECT 1 FROM boards x WHERE u.upload_url IN (x.icon, x.banner))
+      AND NOT EXISTS (SELECT 1 FROM site x WHERE u.upload_url IN (x.icon, x.homepage_banner, x.default_avatar))
+      AND NOT EXISTS (SELECT 1 FROM posts x WHERE u.upload_url IN (x.url, x.image, x.thumbnail_url))
+      AND NOT EXISTS (SELECT 1 FROM emoji x WHERE x.image_url = u.upload_url)
+      AND NOT EXISTS (SELECT 1 FROM user_reports x WHERE x.original_avatar = u.upload_url)
+      AND NOT EXISTS (SELECT 1 FROM private_messages x WHERE strpos(x.body_html, u.upload_url) > 0)
+      AND NOT EXISTS (SELECT 1 FROM wiki_pages x WHERE strpos(x.body_html, u.upload_url) > 0)
+      AND NOT EXISTS (SELECT 1 FROM wiki_page_revisions x WHERE strpos(x.body_html, u.upload_url) > 0)
+"#;
+
+/// Uploads older than the grace period that nothing points at
+fn unreferenced_uploads_query() -> String {
+    format!(
+        r#"
+    SELECT u.id, u.file_name, u.file_path, u.optimized_url, u.size_bytes
+    FROM uploads u
+    WHERE u.created_at < now() - make_interval(hours => $1)
+      AND {}"#,
+        UPLOAD_IS_UNREFERENCED
+    )
+}
+
+/// Media URLs stored outside the uploads table. Files behind them are never
+/// treated as orphaned, even without an upload record (e.g. seeded defaults).
+const REFERENCED_URLS_QUERY: &str = r#"
+    SELECT url FROM (
+        SELECT avatar AS url FROM users
+        UNION SELECT banner FROM users
+        UNION SELECT profile_background FROM users
+        UNION SELECT icon FROM boards
+        UNION SELECT banner FROM boards
+        UNION SELECT icon FROM site
+        UNION SELECT homepage_banner FROM site
+        UNION SELECT default_avatar FROM site
+        UNION SELECT image_url FROM emoji
+        UNION SELECT original_avatar FROM user_reports
+    ) refs
+    WHERE url IS NOT NULL
+"#;
+
+#[derive(QueryableByName)]
+struct UnreferencedUpload {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    id: Uuid,
+    #[diesel(sql_type = Text)]
+    file_name: String,
+    #[diesel(sql_type = Text)]
+    file_path: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    optimized_url: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    size_bytes: i64,
+}
+
+#[derive(QueryableByName)]
+struct ReferencedUrl {
+    #[diesel(sql_type = Nullable<Text>)]
+    url: Option<String>,
+}
+
+/// Run a full garbage collection pass against the configured storage backend
+/// and record the result in `media_gc_runs`.
+///
+/// 1. Deletes upload records nothing references (and their files, once no
+///    other record shares them)
+/// 2. Deletes stored files without any upload record or reference
+/// 3. Counts upload records whose file is missing from the backend. These are
+///    only reported: a misconfigured backend would otherwise wipe the table.
+/// 4. Deletes stale temp files from the local media directory
+pub async fn run_media_gc(
+    pool: &DbPool,
+    storage: &StorageBackend,
+    media: &MediaConfig,
+) -> Result<MediaGcRun, TinyBoardsError> {
+    let started_at = Utc::now();
+    let grace_hours = media.gc_grace_period_hours;
+    tracing::info!("Running media garbage collection (grace period {}h)...", grace_hours);
+
+    let mut form = MediaGcRunInsertForm {
+        started_at,
+        orphaned_uploads_deleted: 0,
+        orphaned_files_deleted: 0,
+        bytes_freed: 0,
+        missing_files: 0,
+        total_files: 0,
+        total_bytes: 0,
+        error: None,
+    };
+
+    let result: Result<(), TinyBoardsError> = async {
+        let (uploads_deleted, upload_bytes) =
+            delete_unreferenced_uploads(pool, storage, grace_hours).await?;
+        form.orphaned_uploads_deleted = uploads_deleted;
+        form.bytes_freed += upload_bytes;
+
+        let objects = storage.list_objects().await?;
+        let (files_deleted, file_bytes) =
+            cleanup_orphaned_files(pool, storage, &objects, &media.media_path, grace_hours).await?;
+        form.orphaned_files_deleted = files_deleted;
+        form.bytes_freed += file_bytes;
+
+        let missing = find_missing_files(pool, &objects, &media.media_path).await?;
+        for (upload_id, file_path) in &missing {
+            tracing::warn!("Upload {} points at missing file {}", upload_id, file_path);
+        }
+        form.missing_files = missing.len() as i32;
+
+        // Stats reflect the backend after this run's deletions
+        let stats = StorageStats::from_objects(&objects);
+        form.total_files = stats.total_files.saturating_sub(files_deleted as u64) as i64;
+        form.total_bytes = stats.total_size.saturating_sub(file_bytes as u64) as i64;
+
+        let temp_deleted = cleanup_temp_files(&media.media_path, u64::from(grace_hours)).await?;
+        if temp_deleted > 0 {
+            tracing::info!("Removed {} stale temp files", temp_deleted);
+        }
+
+        Ok(())
+    }
+    .await;
+
+    if let Err(ref e) = result {
+        tracing::error!("Media garbage collection failed: {}", e);
+        form.error = Some(e.to_string());
+    }
+
+    let conn = &mut get_conn(pool).await?;
+    let run: MediaGcRun = diesel::insert_into(media_gc_runs::table)
+        .values(&form)
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    diesel::delete(
+        media_gc_runs::table
+            .filter(media_gc_runs::started_at.lt(Utc::now() - Duration::days(GC_RUN_RETENTION_DAYS))),
+    )
+    .execute(conn)
+    .await
+    .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    tracing::info!(
+        "Media garbage collection done: {} uploads, {} files deleted, {} bytes freed, {} missing",
+        run.orphaned_uploads_deleted,
+        run.orphaned_files_deleted,
+        run.bytes_freed,
+        run.missing_files
+    );
+
+    Ok(run)
+}
+
+/// The most recent garbage collection run, if any
+pub async fn last_media_gc_run(pool: &DbPool) -> Result<Option<MediaGcRun>, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    media_gc_runs::table
+        .order(media_gc_runs::started_at.desc())
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
+
+/// Delete upload records that nothing has referenced for `grace_hours`.
+/// Returns the number of records deleted and the bytes freed in storage.
+pub async fn delete_unreferenced_uploads(
+    pool: &DbPool,
+    storage: &StorageBackend,
+    grace_hours: u32,
+) -> Result<(i32, i64), TinyBoardsError> {
+    let conn = &mut get_conn(pool)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let candidates: Vec<UnreferencedUpload> = diesel::sql_query(unreferenced_uploads_query())
+        .bind::<Integer, _>(grace_hours as i32)
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let mut deleted_count = 0;
+    let mut bytes_freed = 0;
+
+    for upload in candidates {
+        diesel::delete(uploads::table.find(upload.id))
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+        deleted_count += 1;
+
+        let files = StoredFiles {
+            file_path: upload.file_path,
+            optimized_url: upload.optimized_url,
+        };
+        match delete_from_storage(conn, &files, storage).await {
+            Ok(true) => {
+                tracing::info!("Deleted unreferenced upload: {}", upload.file_name);
+                bytes_freed += upload.size_bytes;
+            }
+            Ok(false) => {
+                tracing::debug!("Deleted unreferenced upload record: {}", upload.file_name);
+            }
+            Err(e) => tracing::error!("Failed to delete file from storage: {}", e),
+        }
+    }
+
+    Ok((deleted_count, bytes_freed))
+}
+
+/// Delete stored files that have no upload record and aren't referenced by
+/// any media URL. Files newer than the grace period are left alone so an
+/// upload that is still being written can't be collected.
+pub async fn cleanup_orphaned_files(
+    pool: &DbPool,
+    storage: &StorageBackend,
+    objects: &[StoredObject],
+    media_path: &str,
+    grace_hours: u32,
+) -> Result<(i32, i64), TinyBoardsError> {
+    let referenced = referenced_keys(pool, media_path).await?;
+    let cutoff = Utc::now() - Duration::hours(i64::from(grace_hours));
+
+    let mut deleted_count = 0;
+    let mut bytes_freed = 0;
+
+    for object in objects {
+        if referenced.contains(&object.key) || is_protected_key(&object.key) {
+            continue;
+        }
+        // Unknown age: keep it rather than risk deleting something fresh
+        match object.last_modified {
+            Some(modified) if modified < cutoff => {}
+            _ => continue,
+        }
+
+        match storage.delete(&object.key).await {
+            Ok(()) => {
+                tracing::info!("Deleted orphaned file: {}", object.key);
+                deleted_count += 1;
+                bytes_freed += object.size as i64;
+            }
+            Err(e) => tracing::error!("Failed to delete orphaned file {}: {}", object.key, e),
+        }
+    }
+
+    Ok((deleted_count, bytes_freed))
+}
+
+/// Find upload records whose file doesn't exist in the storage backend
+pub async fn find_missing_files(
+    pool: &DbPool,
+    objects: &[StoredObject],
+    media_path: &str,
+) -> Result<Vec<(Uuid, String)>, TinyBoardsError> {
+    let conn = &mut get_conn(pool)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let records: Vec<(Uuid, String)> = uploads::table
+        .select((uploads::id, uploads::file_path))
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let existing: HashSet<&str> = objects.iter().map(|o| o.key.as_str()).collect();
+
+    Ok(records
+        .into_iter()
+        .filter(|(_, file_path)| !existing.contains(storage_key(file_path, media_path).as_str()))
+        .collect())
+}
+
+/// Storage keys of every file the database points at: upload records, their
+/// thumbnails and optimized variants, and local media URLs stored elsewhere.
+pub(crate) async fn referenced_media_keys(
+    pool: &DbPool,
+    media_path: &str,
+) -> Result<BTreeSet<String>, TinyBoardsError> {
+    let conn = &mut get_conn(pool)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let records: Vec<(String, Option<String>, Option<String>)> = uploads::table
+        .select((uploads::file_path, uploads::thumbnail_url, uploads::optimized_url))
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let urls: Vec<ReferencedUrl> = diesel::sql_query(REFERENCED_URLS_QUERY)
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let mut keys = BTreeSet::new();
+    for (file_path, thumbnail_url, optimized_url) in records {
+        keys.insert(storage_key(&file_path, media_path));
+        keys.extend(
+            [thumbnail_url, optimized_url]
+                .iter()
+                .flatten()
+                .filter_map(|url| media_key_from_url(url)),
+        );
+    }
+    keys.extend(urls.iter().filter_map(|r| r.url.as_deref()).filter_map(media_key_from_url));
+
+    Ok(keys)
+}
+
+/// Keys that must survive garbage collection. Also covers thumbnails of
+/// uploads whose thumbnail URL wasn't recorded.
+async fn referenced_keys(pool: &DbPool, media_path: &str) -> Result<HashSet<String>, TinyBoardsError> {
+    let keys = referenced_media_keys(pool, media_path).await?;
+    let thumbnails: Vec<String> = keys.iter().map(|key| make_thumbnail_key(key)).collect();
+
+    Ok(keys.into_iter().chain(thumbnails).collect())
+}
+
+/// Storage key behind a media URL, or `None` for URLs that aren't served from
+/// `/media/` (e.g. external images).
+pub(crate) fn media_key_from_url(url: &str) -> Option<String> {
+    url.split_once("/media/")
+        .map(|(_, key)| key.trim_start_matches('/').to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// Storage key for an upload's `file_path` or a media URL. Older uploads
+/// stored the full path under `media_path` instead of the key.
+pub(crate) fn storage_key(path: &str, media_path: &str) -> String {
+    let media_path = media_path.trim_end_matches('/');
+    match path.strip_prefix(media_path) {
+        Some(rest) if !media_path.is_empty() && rest.starts_with('/') => {
+            rest.trim_start_matches('/').to_string()
+        }
+        _ => path
+            .split("/media/")
+            .last()
+            .unwrap_or(path)
+            .trim_start_matches('/')
+            .to_string(),
+    }
+}
+
+/// Files that ship with the instance or are managed elsewhere
+fn is_protected_key(key: &str) -> bool {
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    file_name.starts_with('.')
+        || file_name == "default_pfp.png"
+        || key.starts_with("temp/")
+        || key.starts_with(EXPORTS_PREFIX)
+}
+
+/// Get storage statistics for the configured storage backend
+pub async fn get_storage_stats(storage: &StorageBackend) -> Result<StorageStats, TinyBoardsError> {
+    let objects = storage.list_objects().await?;
+    Ok(StorageStats::from_objects(&objects))
+}
+
+/// Storage statistics structure
+#[derive(Default, Debug)]
+pub struct StorageStats {
+    pub total_size: u64,
+    pub total_files: u64,
+    pub emoji_size: u64,
+    pub emoji_count: u64,
+    pub avatar_size: u64,
+    pub avatar_count: u64,
+    pub video_size: u64,
+    pub video_count: u64,
+    pub audio_size: u64,
+    pub audio_count: u64,
+    pub document_size: u64,
+    pub document_count: u64,
+    pub other_size: u64,
+    pub other_count: u64,
+}
+
+impl StorageStats {
+    /// Tally objects by the subdirectory uploads are sorted into
+    pub fn from_objects(objects: &[StoredObject]) -> Self {
+        let mut stats = StorageStats::default();
+
+        for object in objects {
+            let size = object.size;
+            stats.total_size += size;
+            stats.total_files += 1;
+
+            let category = object.key.split_once('/').map(|(dir, _)| dir).unwrap_or("");
+            match category {
+                "emojis" => {
+                    stats.emoji_size += size;
+                    stats.emoji_count += 1;
+                }
+                "avatars" => {
+                    stats.avatar_size += size;
+                    stats.avatar_count += 1;
+                }
+                "videos" => {
+                    stats.video_size += size;
+                    stats.video_count += 1;
+                }
+                "audio" => {
+                    stats.audio_size += size;
+                    stats.audio_count += 1;
+                }
+                "documents" => {
+                    stats.document_size += size;
+                    stats.document_count += 1;
+                }
+                _ => {
+                    stats.other_size += size;
+                    stats.other_count += 1;
+                }
+            }
+        }
+
+        stats
+    }
+
+    #[allow(dead_code)]
+    pub fn format_summary(&self) -> String {
+        use tinyboards_utils::utils::format_file_size;
+
+        format!(
+            "Storage Summary:\n\
+            Total: {} files, {}\n\
+            Emojis: {} files, {}\n\
+            Avatars: {} files, {}\n\
+            Videos: {} files, {}\n\
+            Audio: {} files, {}\n\
+            Documents: {} files, {}\n\
+            Other: {} files, {}",
+            self.total_files, format_file_size(self.total_size as i64),
+            self.emoji_count, format_file_size(self.emoji_size as i64),
+            self.avatar_count, format_file_size(self.avatar_size as i64),
+            self.video_count, format_file_size(self.video_size as i64),
+            self.audio_count, format_file_size(self.audio_size as i64),
+            self.document_count, format_file_size(self.document_size as i64),
+            self.other_count, format_file_size(self.other_size as i64),
+        )
+    }
+}
+
+/// Delete old temporary files
+pub async fn cleanup_temp_files(media_path: &str, max_age_hours: u64) -> Result<usize, TinyBoardsError> {
+    let temp_dir = format!("{}/temp", media_path);
+    let mut deleted_count = 0;
+
+    if let Ok(mut entries) = fs::read_dir(&temp_dir).await {
+        let cutoff = std::time::SystemTime::now() - std::time::Duration::from_secs(max_age_hours * 3600);
+
+        while let Some(entry) = entries.next_entry().await.map_err(|e| TinyBoardsError::Database(e.to_string()))? {
+            let path = entry.path();
+            if path.is_file() {
+                if let Ok(metadata) = entry.metadata().await {
+                    if let Ok(created) = metadata.created().or_else(|_| metadata.modified()) {
+                        if created < cutoff {
+                            if let Err(e) = fs::remove_file(&path).await {
+                                tracing::error!("Failed to delete temp file {}: {}", path.display(), e);
+                            } else {
+                                deleted_count += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(deleted_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str, size: u64) -> StoredObject {
+        StoredObject {
+            key: key.to_string(),
+            size,
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn test_storage_key() {
+        assert_eq!(storage_key("avatars/abc.webp", "/app/media"), "avatars/abc.webp");
+        assert_eq!(storage_key("/app/media/emojis/emoji_x.png", "/app/media"), "emojis/emoji_x.png");
+        assert_eq!(storage_key("media/emojis/emoji_x.png", "media"), "emojis/emoji_x.png");
+        assert_eq!(
+            storage_key("https://example.com/media/videos/abc.mp4", "/app/media"),
+            "videos/abc.mp4"
+        );
+        assert_eq!(storage_key("mediafile.png", "media"), "mediafile.png");
+    }
+
+    #[test]
+    fn test_media_key_from_url() {
+        assert_eq!(
+            media_key_from_url("https://example.com/media/avatars/a.webp").as_deref(),
+            Some("avatars/a.webp")
+        );
+        assert_eq!(media_key_from_url("https://cdn.example.org/cat.png"), None);
+        assert_eq!(media_key_from_url("https://example.com/media/"), None);
+    }
+
+    #[test]
+    fn test_protected_keys() {
+        assert!(is_protected_key("default_pfp.png"));
+        assert!(is_protected_key("avatars/.keep"));
+        assert!(is_protected_key("temp/upload.part"));
+        assert!(is_protected_key("exports/user/export.zip"));
+        assert!(!is_protected_key("avatars/abc.webp"));
+    }
+
+    #[test]
+    fn test_stats_from_objects() {
+        let stats = StorageStats::from_objects(&[
+            object("avatars/a.webp", 10),
+            object("videos/b.mp4", 100),
+            object("c.webp", 5),
+            object("c_thumb.webp", 1),
+        ]);
+        assert_eq!(stats.total_files, 4);
+        assert_eq!(stats.total_size, 116);
+        assert_eq!(stats.avatar_count, 1);
+        assert_eq!(stats.video_size, 100);
+        assert_eq!(stats.other_count, 2);
+        assert_eq!(stats.other_size, 6);
+    }
+}
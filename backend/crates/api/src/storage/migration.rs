@@ -0,0 +1,376 @@
+use crate::{
+    storage::{maintenance::referenced_media_keys, StorageBackend},
+    DbPool,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use diesel_async::RunQueryDsl;
+use futures::stream::{self, StreamExt};
+use std::collections::HashSet;
+use tinyboards_db::{
+    models::upload::{
+        StorageMigration, StorageMigrationInsertForm, StorageMigrationObjectForm,
+        StorageMigrationUpdateForm,
+    },
+    schema::{storage_migration_objects, storage_migrations},
+    utils::get_conn,
+};
+use tinyboards_utils::{settings::structs::Settings, TinyBoardsError};
+use uuid::Uuid;
+
+/// Objects copied at the same time
+const COPY_CONCURRENCY: usize = 8;
+/// Progress counters are written back to the migration row this often
+const PROGRESS_INTERVAL: usize = 25;
+
+/// Outcome for a single object
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectStatus {
+    /// Copied and verified, or already present in the target with the same checksum
+    Copied,
+    /// Referenced in the database but absent from the source
+    Missing,
+    /// Copy or verification failed; retried when the migration is resumed
+    Failed,
+}
+
+impl ObjectStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ObjectStatus::Copied => "copied",
+            ObjectStatus::Missing => "missing",
+            ObjectStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Reported after each object
+pub struct MigrationProgress<'a> {
+    pub key: &'a str,
+    pub status: ObjectStatus,
+    pub done: usize,
+    pub total: usize,
+    pub error: Option<&'a str>,
+}
+
+const STATUS_COUNTS_QUERY: &str = r#"
+    SELECT status, COUNT(*) AS objects, COALESCE(SUM(size_bytes), 0)::bigint AS bytes
+    FROM storage_migration_objects
+    WHERE migration_id = $1
+    GROUP BY status
+"#;
+
+#[derive(QueryableByName)]
+struct StatusCount {
+    #[diesel(sql_type = Text)]
+    status: String,
+    #[diesel(sql_type = BigInt)]
+    objects: i64,
+    #[diesel(sql_type = BigInt)]
+    bytes: i64,
+}
+
+struct ObjectResult {
+    key: String,
+    status: ObjectStatus,
+    size: u64,
+    checksum: Option<String>,
+    error: Option<String>,
+}
+
+/// Copy every stored object the database references (uploads and their
+/// thumbnails, emoji, avatars, banners, board and site images) from one
+/// configured backend to another, verifying each copy by SHA-256.
+///
+/// Progress is recorded per object, so running the same source/target pair
+/// again resumes an unfinished migration and only retries what's left. The
+/// source is never modified.
+pub async fn migrate_storage<F>(
+    pool: &DbPool,
+    settings: &Settings,
+    source_backend: &str,
+    target_backend: &str,
+    mut on_progress: F,
+) -> Result<StorageMigration, TinyBoardsError>
+where
+    F: FnMut(&MigrationProgress),
+{
+    if source_backend == target_backend {
+        return Err(TinyBoardsError::from_message(400, "Source and target backends are the same"));
+    }
+
+    let source = StorageBackend::for_backend(settings, source_backend).await?;
+    let target = StorageBackend::for_backend(settings, target_backend).await?;
+
+    let migration = start_or_resume(pool, source_backend, target_backend).await?;
+    let keys = referenced_media_keys(pool, &settings.get_media_path()).await?;
+    let already_copied = copied_keys(pool, migration.id).await?;
+
+    let total = keys.len();
+    let mut done = already_copied.len().min(total);
+    update_migration(
+        pool,
+        migration.id,
+        StorageMigrationUpdateForm {
+            total_objects: Some(total as i32),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tracing::info!(
+        "Migrating {} objects from {} to {} ({} already copied)",
+        total,
+        source_backend,
+        target_backend,
+        done
+    );
+
+    let pending: Vec<String> = keys
+        .into_iter()
+        .filter(|key| !already_copied.contains(key))
+        .collect();
+
+    let mut results = stream::iter(pending)
+        .map(|key| {
+            let source = &source;
+            let target = &target;
+            async move { migrate_object(source, target, key).await }
+        })
+        .buffer_unordered(COPY_CONCURRENCY);
+
+    let conn = &mut get_conn(pool).await?;
+    let mut since_update = 0;
+
+    while let Some(result) = results.next().await {
+        let form = StorageMigrationObjectForm {
+            migration_id: migration.id,
+            key: result.key.clone(),
+            status: result.status.as_str().to_string(),
+            size_bytes: result.size as i64,
+            checksum: result.checksum.clone(),
+            error: result.error.clone(),
+            updated_at: Utc::now(),
+        };
+
+        diesel::insert_into(storage_migration_objects::table)
+            .values(&form)
+            .on_conflict((storage_migration_objects::migration_id, storage_migration_objects::key))
+            .do_update()
+            .set(&form)
+            .execute(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+        done += 1;
+        on_progress(&MigrationProgress {
+            key: &result.key,
+            status: result.status,
+            done,
+            total,
+            error: result.error.as_deref(),
+        });
+
+        since_update += 1;
+        if since_update >= PROGRESS_INTERVAL {
+            since_update = 0;
+            refresh_counters(pool, migration.id, None).await?;
+        }
+    }
+
+    let migration = refresh_counters(pool, migration.id, Some(Utc::now())).await?;
+
+    tracing::info!(
+        "Storage migration {} -> {} {}: {} copied, {} missing, {} failed",
+        source_backend,
+        target_backend,
+        migration.status,
+        migration.copied_objects,
+        migration.missing_objects,
+        migration.failed_objects
+    );
+
+    Ok(migration)
+}
+
+/// The most recent migration, if any
+pub async fn latest_migration(pool: &DbPool) -> Result<Option<StorageMigration>, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    storage_migrations::table
+        .order(storage_migrations::started_at.desc())
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
+
+/// Copy one object unless the target already holds an identical copy, then
+/// verify the target's checksum against what was read from the source.
+async fn migrate_object(source: &StorageBackend, target: &StorageBackend, key: String) -> ObjectResult {
+    let result = |status, size, checksum, error| ObjectResult {
+        key: key.clone(),
+        status,
+        size,
+        checksum,
+        error,
+    };
+
+    let size = match source.stat(&key).await {
+        Ok(metadata) => metadata.content_length(),
+        // Uploaded straight to the target after the switch, nothing to copy
+        Err(TinyBoardsError::NotFound(_)) if target.exists(&key).await.unwrap_or(false) => {
+            return result(ObjectStatus::Copied, 0, None, None);
+        }
+        Err(TinyBoardsError::NotFound(_)) => {
+            return result(ObjectStatus::Missing, 0, None, Some("Not found in source".to_string()));
+        }
+        Err(e) => return result(ObjectStatus::Failed, 0, None, Some(e.to_string())),
+    };
+
+    // An earlier, interrupted run may already have copied it
+    if target.exists(&key).await.unwrap_or(false) {
+        if let (Ok(source_sum), Ok(target_sum)) = (source.checksum(&key).await, target.checksum(&key).await) {
+            if source_sum == target_sum {
+                return result(ObjectStatus::Copied, size, Some(source_sum), None);
+            }
+        }
+    }
+
+    let (source_sum, copied) = match source.copy_to(target, &key).await {
+        Ok(copied) => copied,
+        Err(e) => return result(ObjectStatus::Failed, size, None, Some(e.to_string())),
+    };
+
+    match target.checksum(&key).await {
+        Ok(target_sum) if target_sum == source_sum => {
+            result(ObjectStatus::Copied, copied, Some(source_sum), None)
+        }
+        Ok(_) => {
+            // Don't leave a corrupt copy behind for the fallback-less server to serve
+            if let Err(e) = target.delete(&key).await {
+                tracing::error!("Failed to delete bad copy of {}: {}", key, e);
+            }
+            result(ObjectStatus::Failed, copied, Some(source_sum), Some("Checksum mismatch".to_string()))
+        }
+        Err(e) => result(ObjectStatus::Failed, copied, Some(source_sum), Some(e.to_string())),
+    }
+}
+
+/// Resume the latest unfinished migration between the same backends, or start a new one
+async fn start_or_resume(
+    pool: &DbPool,
+    source_backend: &str,
+    target_backend: &str,
+) -> Result<StorageMigration, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+
+    let unfinished: Option<StorageMigration> = storage_migrations::table
+        .filter(storage_migrations::source_backend.eq(source_backend))
+        .filter(storage_migrations::target_backend.eq(target_backend))
+        .filter(storage_migrations::status.ne("completed"))
+        .order(storage_migrations::started_at.desc())
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    match unfinished {
+        Some(migration) => {
+            tracing::info!("Resuming storage migration {}", migration.id);
+            update_migration(
+                pool,
+                migration.id,
+                StorageMigrationUpdateForm {
+                    status: Some("running".to_string()),
+                    error: Some(None),
+                    finished_at: Some(None),
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+        None => diesel::insert_into(storage_migrations::table)
+            .values(&StorageMigrationInsertForm {
+                source_backend: source_backend.to_string(),
+                target_backend: target_backend.to_string(),
+            })
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string())),
+    }
+}
+
+async fn copied_keys(pool: &DbPool, migration_id: Uuid) -> Result<HashSet<String>, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    let keys: Vec<String> = storage_migration_objects::table
+        .filter(storage_migration_objects::migration_id.eq(migration_id))
+        .filter(storage_migration_objects::status.eq(ObjectStatus::Copied.as_str()))
+        .select(storage_migration_objects::key)
+        .load(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(keys.into_iter().collect())
+}
+
+async fn update_migration(
+    pool: &DbPool,
+    migration_id: Uuid,
+    form: StorageMigrationUpdateForm,
+) -> Result<StorageMigration, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    diesel::update(storage_migrations::table.find(migration_id))
+        .set(&StorageMigrationUpdateForm {
+            updated_at: Some(Utc::now()),
+            ..form
+        })
+        .get_result(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
+
+/// Recount the per-object results. With `finished_at` set, also marks the
+/// migration completed, or failed if any object still failed.
+async fn refresh_counters(
+    pool: &DbPool,
+    migration_id: Uuid,
+    finished_at: Option<chrono::DateTime<Utc>>,
+) -> Result<StorageMigration, TinyBoardsError> {
+    let counts: Vec<StatusCount> = {
+        let conn = &mut get_conn(pool).await?;
+        diesel::sql_query(STATUS_COUNTS_QUERY)
+            .bind::<diesel::sql_types::Uuid, _>(migration_id)
+            .load(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+    };
+
+    let count = |status: ObjectStatus| {
+        counts
+            .iter()
+            .find(|c| c.status == status.as_str())
+            .map(|c| (c.objects as i32, c.bytes))
+            .unwrap_or((0, 0))
+    };
+    let (copied, bytes_copied) = count(ObjectStatus::Copied);
+    let (missing, _) = count(ObjectStatus::Missing);
+    let (failed, _) = count(ObjectStatus::Failed);
+
+    let status = finished_at.map(|_| if failed == 0 { "completed" } else { "failed" }.to_string());
+
+    update_migration(
+        pool,
+        migration_id,
+        StorageMigrationUpdateForm {
+            status,
+            copied_objects: Some(copied),
+            missing_objects: Some(missing),
+            failed_objects: Some(failed),
+            bytes_copied: Some(bytes_copied),
+            finished_at: finished_at.map(Some),
+            ..Default::default()
+        },
+    )
+    .await
+}
@@ -1,6 +1,16 @@
+pub mod export;
 pub mod image_processing;
+pub mod maintenance;
+pub mod migration;
+pub mod processing_queue;
+pub mod signing;
+pub mod transcoding;
 
-use opendal::{Operator, services, layers::LoggingLayer, ErrorKind};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use opendal::{Operator, services, layers::LoggingLayer, ErrorKind, FuturesBytesStream, Metadata};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
 use tinyboards_utils::{TinyBoardsError, settings::structs::Settings};
 use url::Url;
 
@@ -11,6 +21,14 @@ pub struct StorageBackend {
     base_url: String,
 }
 
+/// An object as reported by the backend's listing
+#[derive(Clone, Debug)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
 #[derive(Clone, Debug)]
 pub enum StorageType {
     Filesystem,
@@ -22,6 +40,13 @@ pub enum StorageType {
 impl StorageBackend {
     pub async fn from_settings(settings: &Settings) -> Result<Self, TinyBoardsError> {
         let backend_str = settings.storage.backend.as_deref().unwrap_or("fs");
+        Self::for_backend(settings, backend_str).await
+    }
+
+    /// Build a backend by name from its section of the storage config, which
+    /// need not be the active one. `"local"` is the `media.media_path`
+    /// directory that files were written to before storage backends existed.
+    pub async fn for_backend(settings: &Settings, backend_str: &str) -> Result<Self, TinyBoardsError> {
         let (operator, backend_type) = match backend_str {
             "s3" => {
                 let s3_config = settings.storage.s3.as_ref()
@@ -75,6 +100,17 @@ impl StorageBackend {
 
                 (op, StorageType::Gcs)
             },
+            "local" => {
+                let op = Operator::new(
+                    services::Fs::default()
+                        .root(&settings.get_media_path())
+                )
+                .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to initialize filesystem"))?
+                .layer(LoggingLayer::default())
+                .finish();
+
+                (op, StorageType::Filesystem)
+            },
             "fs" | "filesystem" => {
                 let fs_config = settings.storage.fs.as_ref()
                     .ok_or_else(|| TinyBoardsError::from_message(500, "FS config missing"))?;
@@ -102,6 +138,18 @@ impl StorageBackend {
         })
     }
 
+    /// Backend that keeps files in memory, for tests
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> Self {
+        Self {
+            operator: Operator::new(services::Memory::default())
+                .expect("memory backend needs no configuration")
+                .finish(),
+            backend_type: StorageType::Filesystem,
+            base_url: "https://example.com".to_string(),
+        }
+    }
+
     pub fn operator(&self) -> &Operator {
         &self.operator
     }
@@ -157,6 +205,74 @@ impl StorageBackend {
         Ok(buffer.to_vec())
     }
 
+    /// Object metadata (size, ETag, modification time) without reading the body.
+    pub async fn stat(&self, key: &str) -> Result<Metadata, TinyBoardsError> {
+        self.operator.stat(key).await
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => TinyBoardsError::from_message(404, "File not found"),
+                _ => TinyBoardsError::from_error_message(e, 500, "Failed to stat file"),
+            })
+    }
+
+    /// Stream a byte range of an object in chunks instead of buffering it.
+    pub async fn read_range_stream(
+        &self,
+        key: &str,
+        range: Range<u64>,
+    ) -> Result<FuturesBytesStream, TinyBoardsError> {
+        let reader = self.operator.reader_with(key)
+            .chunk(1024 * 1024)  // 1MB per backend request
+            .await
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => TinyBoardsError::from_message(404, "File not found"),
+                _ => TinyBoardsError::from_error_message(e, 500, "Failed to open file"),
+            })?;
+
+        reader.into_bytes_stream(range).await
+            .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to read file"))
+    }
+
+    /// Stream an object into the same key on another backend.
+    /// Returns the SHA-256 and size of the bytes read from this backend.
+    pub async fn copy_to(&self, target: &StorageBackend, key: &str) -> Result<(String, u64), TinyBoardsError> {
+        let mut stream = self.read_range_stream(key, 0..self.stat(key).await?.content_length()).await?;
+
+        let mut writer = target.operator.writer_with(key)
+            .chunk(8 * 1024 * 1024)  // 8MB chunks
+            .await
+            .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to create writer"))?;
+
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk
+                .map_err(|e| TinyBoardsError::from_message(500, &format!("Read error: {}", e)))?;
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            writer.write(chunk).await
+                .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to write chunk"))?;
+        }
+
+        writer.close().await
+            .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to close writer"))?;
+
+        Ok((hex::encode(hasher.finalize()), size))
+    }
+
+    /// SHA-256 of an object, streamed rather than buffered.
+    pub async fn checksum(&self, key: &str) -> Result<String, TinyBoardsError> {
+        let mut stream = self.read_range_stream(key, 0..self.stat(key).await?.content_length()).await?;
+
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk
+                .map_err(|e| TinyBoardsError::from_message(500, &format!("Read error: {}", e)))?;
+            hasher.update(&chunk);
+        }
+
+        Ok(hex::encode(hasher.finalize()))
+    }
+
     pub async fn delete(&self, key: &str) -> Result<(), TinyBoardsError> {
         self.operator.delete(key).await
             .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to delete file"))
@@ -167,7 +283,46 @@ impl StorageBackend {
             .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to check existence"))
     }
 
+    /// List every object in the backend. Services that don't return metadata
+    /// when listing (e.g. the local filesystem) are stat'ed per object.
+    pub async fn list_objects(&self) -> Result<Vec<StoredObject>, TinyBoardsError> {
+        let entries = self.operator.list_with("/").recursive(true).await
+            .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to list files"))?;
+
+        let mut objects = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (key, metadata) = entry.into_parts();
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let metadata = if metadata.last_modified().is_none() {
+                match self.operator.stat(&key).await {
+                    Ok(metadata) => metadata,
+                    // Deleted between listing and stat
+                    Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(TinyBoardsError::from_error_message(e, 500, "Failed to stat file")),
+                }
+            } else {
+                metadata
+            };
+
+            objects.push(StoredObject {
+                key,
+                size: metadata.content_length(),
+                last_modified: metadata.last_modified(),
+            });
+        }
+
+        Ok(objects)
+    }
+
     pub fn get_public_url(&self, key: &str) -> String {
         format!("{}/media/{}", self.base_url, key)
     }
+
+    /// Public URL with an expiring signature, for private instances
+    pub fn get_signed_url(&self, key: &str, secret: &str, ttl_seconds: i64) -> String {
+        signing::sign_media_url(&self.get_public_url(key), secret, ttl_seconds)
+    }
 }
@@ -0,0 +1,430 @@
+use crate::{
+    events::{EventBus, LiveEvent},
+    helpers::files::upload::{hash_content, make_thumbnail_key},
+    storage::{
+        transcoding::{process_media, TranscodeSettings},
+        StorageBackend,
+    },
+    DbPool,
+};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use tinyboards_db::{
+    models::upload::{MediaProcessingJob, MediaProcessingJobInsertForm, Upload as DbUpload},
+    schema::{media_processing_jobs, posts, uploads},
+    utils::get_conn,
+};
+use tinyboards_utils::{
+    settings::structs::MediaConfig,
+    utils::{detect_file_type_from_bytes, get_file_type_extended, get_storage_key_for_type, is_video_type},
+    TinyBoardsError,
+};
+use uuid::Uuid;
+
+/// Attempts before an upload is marked failed
+const MAX_ATTEMPTS: i32 = 5;
+/// First retry delay; doubles with each attempt
+const RETRY_BASE_SECONDS: i64 = 30;
+/// How long an idle worker waits before checking the queue again
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+/// A job still marked processing after this long is assumed abandoned by a
+/// worker that crashed or was restarted, and is picked up again
+const STALE_LOCK_MINUTES: i32 = 10;
+
+/// Claim the next due job. `SKIP LOCKED` lets several workers, in this or
+/// other server processes, share the queue without taking the same job.
+const CLAIM_JOB_QUERY: &str = r#"
+    UPDATE media_processing_jobs
+    SET status = 'processing', attempts = attempts + 1, locked_at = now(), updated_at = now()
+    WHERE id = (
+        SELECT id FROM media_processing_jobs
+        WHERE (status = 'pending' AND run_at <= now())
+           OR (status = 'processing' AND locked_at < now() - make_interval(mins => $1))
+        ORDER BY run_at
+        FOR UPDATE SKIP LOCKED
+        LIMIT 1
+    )
+    RETURNING *
+"#;
+
+/// Queue an upload for processing. Re-queuing a failed upload starts its
+/// attempts over.
+pub async fn enqueue(conn: &mut AsyncPgConnection, upload_id: Uuid) -> Result<(), TinyBoardsError> {
+    diesel::insert_into(media_processing_jobs::table)
+        .values(&MediaProcessingJobInsertForm { upload_id })
+        .on_conflict(media_processing_jobs::upload_id)
+        .do_update()
+        .set((
+            media_processing_jobs::status.eq("pending"),
+            media_processing_jobs::attempts.eq(0),
+            media_processing_jobs::run_at.eq(Utc::now()),
+            media_processing_jobs::locked_at.eq(None::<chrono::DateTime<Utc>>),
+            media_processing_jobs::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Start `media.processing_workers` tasks that process queued uploads for the
+/// life of the process. Image work itself runs on the blocking thread pool,
+/// video and GIF work in ffmpeg.
+pub fn spawn_workers(pool: DbPool, storage: StorageBackend, events: EventBus, media: &MediaConfig) {
+    let transcode = TranscodeSettings::from(media);
+    for _ in 0..media.processing_workers {
+        let pool = pool.clone();
+        let storage = storage.clone();
+        let events = events.clone();
+        let transcode = transcode.clone();
+        tokio::spawn(async move { worker_loop(pool, storage, events, transcode).await });
+    }
+}
+
+async fn worker_loop(pool: DbPool, storage: StorageBackend, events: EventBus, transcode: TranscodeSettings) {
+    loop {
+        match claim_job(&pool).await {
+            Ok(Some(job)) => run_job(&pool, &storage, &events, &transcode, job).await,
+            Ok(None) => tokio::time::sleep(POLL_INTERVAL).await,
+            Err(e) => {
+                tracing::error!("Failed to claim media processing job: {}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+async fn claim_job(pool: &DbPool) -> Result<Option<MediaProcessingJob>, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    diesel::sql_query(CLAIM_JOB_QUERY)
+        .bind::<Integer, _>(STALE_LOCK_MINUTES)
+        .get_result(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))
+}
+
+async fn run_job(
+    pool: &DbPool,
+    storage: &StorageBackend,
+    events: &EventBus,
+    transcode: &TranscodeSettings,
+    job: MediaProcessingJob,
+) {
+    let result = match process_upload_job(pool, storage, transcode, &job).await {
+        Ok(user_id) => {
+            events.publish(LiveEvent::UploadProcessed {
+                user_id,
+                upload_id: job.upload_id,
+            });
+            Ok(())
+        }
+        Err(error) => {
+            tracing::warn!(
+                "Processing upload {} failed (attempt {}): {}",
+                job.upload_id,
+                job.attempts,
+                error
+            );
+            fail_job(pool, events, &job, &error).await
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Failed to record result of media processing job {}: {}", job.id, e);
+    }
+}
+
+/// Write the optimized image (or transcoded GIF) and thumbnail next to the
+/// original and mark the upload done. Video posts waiting on the upload get
+/// its poster frame as their thumbnail. Safe to repeat: output keys are
+/// derived from content.
+async fn process_upload_job(
+    pool: &DbPool,
+    storage: &StorageBackend,
+    transcode: &TranscodeSettings,
+    job: &MediaProcessingJob,
+) -> Result<Uuid, TinyBoardsError> {
+    let upload: DbUpload = {
+        let conn = &mut get_conn(pool).await?;
+        diesel::update(uploads::table.find(job.upload_id))
+            .set(uploads::processing_status.eq("processing"))
+            .get_result(conn)
+            .await
+            .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+    };
+
+    let original = storage.read(&upload.file_path).await?;
+    let mime_type = detect_file_type_from_bytes(&original)
+        .ok_or_else(|| TinyBoardsError::from_message(400, "Unrecognized media data"))?;
+    let original_size = original.len();
+
+    let is_video = is_video_type(mime_type);
+    let processed = process_media(original, mime_type.to_string(), transcode).await?;
+    let processed_size = processed.data.len();
+
+    // Videos are served as uploaded
+    let optimized_key = if is_video {
+        upload.file_path.clone()
+    } else {
+        let optimized_name = format!(
+            "{}.{}",
+            hash_content(&processed.data),
+            get_file_type_extended(&processed.mime_type)
+        );
+        // The original's key keeps the subdirectory (e.g. avatars/)
+        get_storage_key_for_type(&optimized_name, &processed.mime_type, &upload.file_path)
+    };
+    if optimized_key != upload.file_path {
+        storage.write(&optimized_key, processed.data).await?;
+    }
+
+    let thumbnail_url = match processed.thumbnail_data {
+        Some(thumbnail) => {
+            let thumbnail_key = make_thumbnail_key(&optimized_key);
+            storage.write(&thumbnail_key, thumbnail).await?;
+            Some(storage.get_public_url(&thumbnail_key))
+        }
+        None => None,
+    };
+
+    tracing::info!(
+        "Processed upload {}: {}x{}, {} -> {} bytes ({})",
+        upload.id,
+        processed.width.unwrap_or(0),
+        processed.height.unwrap_or(0),
+        original_size,
+        processed_size,
+        processed.mime_type
+    );
+
+    let conn = &mut get_conn(pool).await?;
+    diesel::update(uploads::table.find(upload.id))
+        .set((
+            uploads::optimized_url.eq(Some(storage.get_public_url(&optimized_key))),
+            uploads::thumbnail_url.eq(&thumbnail_url),
+            uploads::width.eq(processed.width.map(|w| w as i32)),
+            uploads::height.eq(processed.height.map(|h| h as i32)),
+            uploads::duration_seconds.eq(processed.duration_seconds),
+            uploads::processing_status.eq("done"),
+        ))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    // Video posts are created before the upload is processed
+    if let (true, Some(thumbnail_url)) = (is_video, &thumbnail_url) {
+        diesel::update(
+            posts::table
+                .filter(posts::image.eq(&upload.upload_url))
+                .filter(posts::thumbnail_url.is_null()),
+        )
+        .set(posts::thumbnail_url.eq(thumbnail_url))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+    }
+
+    diesel::delete(media_processing_jobs::table.find(job.id))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    Ok(upload.user_id)
+}
+
+/// Wait before the next try of a job that has failed `attempts` times: 30s,
+/// 1m, 2m, 4m, ... capped at about 8.5 hours.
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::seconds(RETRY_BASE_SECONDS << (attempts - 1).clamp(0, 10))
+}
+
+/// Schedule a retry with exponential backoff, or give up once attempts are
+/// exhausted. Images that can't be decoded or whose original is gone are not
+/// retried.
+async fn fail_job(
+    pool: &DbPool,
+    events: &EventBus,
+    job: &MediaProcessingJob,
+    error: &TinyBoardsError,
+) -> Result<(), TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    let give_up = matches!(error, TinyBoardsError::BadRequest(_) | TinyBoardsError::NotFound(_))
+        || job.attempts >= MAX_ATTEMPTS;
+
+    let (job_status, upload_status, run_at) = if give_up {
+        ("failed", "failed", job.run_at)
+    } else {
+        ("pending", "pending", Utc::now() + retry_delay(job.attempts))
+    };
+
+    diesel::update(media_processing_jobs::table.find(job.id))
+        .set((
+            media_processing_jobs::status.eq(job_status),
+            media_processing_jobs::last_error.eq(Some(error.to_string())),
+            media_processing_jobs::run_at.eq(run_at),
+            media_processing_jobs::locked_at.eq(None::<chrono::DateTime<Utc>>),
+            media_processing_jobs::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)
+        .await
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    let user_id: Option<Uuid> = diesel::update(uploads::table.find(job.upload_id))
+        .set(uploads::processing_status.eq(upload_status))
+        .returning(uploads::user_id)
+        .get_result(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?;
+
+    if let (true, Some(user_id)) = (give_up, user_id) {
+        events.publish(LiveEvent::UploadProcessed {
+            user_id,
+            upload_id: job.upload_id,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use diesel_async::SimpleAsyncConnection;
+    use futures::{FutureExt, StreamExt};
+
+    #[test]
+    fn test_retry_delay_doubles_from_the_base() {
+        let seconds: Vec<i64> = (1..=5).map(|a| retry_delay(a).num_seconds()).collect();
+        assert_eq!(seconds, [30, 60, 120, 240, 480]);
+        assert_eq!(retry_delay(0).num_seconds(), RETRY_BASE_SECONDS);
+        assert_eq!(retry_delay(11), retry_delay(50));
+    }
+
+    /// A queued job for a fresh upload, as a worker would have claimed it
+    async fn claimed_job(db: &TestDb, attempts: i32) -> MediaProcessingJob {
+        let owner = db.user(0).await;
+        let upload = db.upload(&owner, &format!("images/{}.png", Uuid::new_v4()), None).await;
+        let conn = &mut *db.conn().await;
+        enqueue(conn, upload.id).await.unwrap();
+        diesel::update(media_processing_jobs::table.filter(media_processing_jobs::upload_id.eq(upload.id)))
+            .set((
+                media_processing_jobs::status.eq("processing"),
+                media_processing_jobs::attempts.eq(attempts),
+                media_processing_jobs::locked_at.eq(Some(Utc::now())),
+            ))
+            .get_result(conn)
+            .await
+            .unwrap()
+    }
+
+    async fn reload(db: &TestDb, job: &MediaProcessingJob) -> (MediaProcessingJob, String) {
+        let conn = &mut *db.conn().await;
+        let job: MediaProcessingJob = media_processing_jobs::table.find(job.id).first(conn).await.unwrap();
+        let status = uploads::table
+            .find(job.upload_id)
+            .select(uploads::processing_status)
+            .first(conn)
+            .await
+            .unwrap();
+        (job, status)
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_is_retried_with_backoff() {
+        let Some(db) = TestDb::new().await else { return };
+        let job = claimed_job(&db, 2).await;
+
+        let before = Utc::now();
+        fail_job(&db.pool, &db.events, &job, &TinyBoardsError::Internal("ffmpeg crashed".into()))
+            .await
+            .unwrap();
+
+        let (job, upload_status) = reload(&db, &job).await;
+        assert_eq!(job.status, "pending");
+        assert_eq!(upload_status, "pending");
+        assert_eq!(job.locked_at, None);
+        assert_eq!(job.last_error.as_deref(), Some("Internal error: ffmpeg crashed"));
+        assert!(job.run_at >= before + retry_delay(2));
+        assert!(job.run_at <= Utc::now() + retry_delay(2));
+    }
+
+    #[tokio::test]
+    async fn test_job_is_marked_failed_after_max_attempts() {
+        let Some(db) = TestDb::new().await else { return };
+        let mut processed = Box::pin(db.events.subscribe());
+        let job = claimed_job(&db, MAX_ATTEMPTS).await;
+
+        fail_job(&db.pool, &db.events, &job, &TinyBoardsError::Internal("ffmpeg crashed".into()))
+            .await
+            .unwrap();
+
+        let (failed, upload_status) = reload(&db, &job).await;
+        assert_eq!(failed.status, "failed");
+        assert_eq!(upload_status, "failed");
+        assert_eq!(failed.attempts, MAX_ATTEMPTS);
+        // The uploader hears that processing is over
+        assert!(matches!(
+            processed.next().now_or_never(),
+            Some(Some(LiveEvent::UploadProcessed { upload_id, .. })) if upload_id == job.upload_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_upload_is_not_retried() {
+        let Some(db) = TestDb::new().await else { return };
+        let job = claimed_job(&db, 1).await;
+
+        fail_job(&db.pool, &db.events, &job, &TinyBoardsError::BadRequest("Unrecognized media data".into()))
+            .await
+            .unwrap();
+
+        assert_eq!(reload(&db, &job).await.0.status, "failed");
+    }
+
+    #[tokio::test]
+    async fn test_claim_skips_jobs_locked_by_another_worker() {
+        let Some(db) = TestDb::new().await else { return };
+        db.conn().await.batch_execute("DELETE FROM media_processing_jobs").await.unwrap();
+        let owner = db.user(0).await;
+        let first = db.upload(&owner, &format!("images/{}.png", Uuid::new_v4()), None).await;
+        let second = db.upload(&owner, &format!("images/{}.png", Uuid::new_v4()), None).await;
+        {
+            let conn = &mut *db.conn().await;
+            enqueue(conn, first.id).await.unwrap();
+            enqueue(conn, second.id).await.unwrap();
+            // Make the first job the one every worker reaches for
+            diesel::update(media_processing_jobs::table.filter(media_processing_jobs::upload_id.eq(first.id)))
+                .set(media_processing_jobs::run_at.eq(Utc::now() - Duration::minutes(1)))
+                .execute(conn)
+                .await
+                .unwrap();
+        }
+
+        // Another worker is in the middle of claiming the first job
+        let mut other_worker = db.conn().await;
+        other_worker
+            .batch_execute(&format!(
+                "BEGIN; SELECT id FROM media_processing_jobs WHERE upload_id = '{}' FOR UPDATE",
+                first.id
+            ))
+            .await
+            .unwrap();
+
+        let claimed = claim_job(&db.pool).await.unwrap().expect("the unlocked job");
+        assert_eq!(claimed.upload_id, second.id);
+        assert_eq!(claimed.status, "processing");
+        assert_eq!(claimed.attempts, 1);
+        assert!(claim_job(&db.pool).await.unwrap().is_none());
+
+        other_worker.batch_execute("ROLLBACK").await.unwrap();
+        drop(other_worker);
+        let claimed = claim_job(&db.pool).await.unwrap().expect("the released job");
+        assert_eq!(claimed.upload_id, first.id);
+    }
+}
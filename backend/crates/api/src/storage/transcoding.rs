@@ -0,0 +1,464 @@
+use crate::storage::image_processing::{
+    generate_thumbnail, process_upload_blocking, ImageProcessingSettings, ProcessedImage,
+};
+use serde::Deserialize;
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use tinyboards_utils::{settings::structs::MediaConfig, utils::is_video_type, TinyBoardsError};
+use tokio::{process::Command, sync::OnceCell};
+use uuid::Uuid;
+
+/// Whether ffmpeg and ffprobe could be run, checked once per process
+static FFMPEG_AVAILABLE: OnceCell<bool> = OnceCell::const_new();
+
+/// What animated GIFs are converted to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GifFormat {
+    /// Animated WebP, still displayed as an image
+    WebP,
+    /// H.264 MP4, smaller but needs a video element
+    Mp4,
+}
+
+impl GifFormat {
+    /// Unknown values fall back to WebP
+    pub fn from_config(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "mp4" => GifFormat::Mp4,
+            _ => GifFormat::WebP,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            GifFormat::WebP => "webp",
+            GifFormat::Mp4 => "mp4",
+        }
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match self {
+            GifFormat::WebP => "image/webp",
+            GifFormat::Mp4 => "video/mp4",
+        }
+    }
+
+    fn encoder_args(&self) -> &'static [&'static str] {
+        match self {
+            GifFormat::WebP => &["-c:v", "libwebp", "-lossless", "0", "-quality", "75", "-loop", "0"],
+            // yuv420p needs even dimensions
+            GifFormat::Mp4 => &[
+                "-c:v", "libx264", "-pix_fmt", "yuv420p",
+                "-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2",
+                "-movflags", "+faststart",
+            ],
+        }
+    }
+}
+
+/// Settings for running ffmpeg, taken from the `media` config section.
+#[derive(Clone, Debug)]
+pub struct TranscodeSettings {
+    pub ffmpeg_path: String,
+    pub ffprobe_path: String,
+    pub gif_format: GifFormat,
+    /// Limit for a single ffmpeg or ffprobe run
+    pub timeout: Duration,
+}
+
+impl From<&MediaConfig> for TranscodeSettings {
+    fn from(config: &MediaConfig) -> Self {
+        Self {
+            ffmpeg_path: config.ffmpeg_path.clone(),
+            ffprobe_path: config.ffprobe_path.clone(),
+            gif_format: GifFormat::from_config(&config.gif_transcode_format),
+            timeout: Duration::from_secs(config.transcode_timeout_seconds.max(1) as u64),
+        }
+    }
+}
+
+/// The result of processing an uploaded image or video.
+pub struct ProcessedMedia {
+    /// Bytes to serve: the optimized image, the transcoded GIF, or the
+    /// original for videos and anything that couldn't be improved on.
+    pub data: Vec<u8>,
+    /// MIME type of `data`
+    pub mime_type: String,
+    /// WebP thumbnail, or the poster frame for videos
+    pub thumbnail_data: Option<Vec<u8>>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Running time of videos and animations
+    pub duration_seconds: Option<f64>,
+}
+
+impl From<ProcessedImage> for ProcessedMedia {
+    fn from(image: ProcessedImage) -> Self {
+        Self {
+            data: image.data,
+            mime_type: image.mime_type,
+            thumbnail_data: image.thumbnail_data,
+            width: Some(image.width),
+            height: Some(image.height),
+            duration_seconds: None,
+        }
+    }
+}
+
+/// Dimensions and duration as reported by ffprobe
+#[derive(Debug, Default, PartialEq)]
+struct MediaInfo {
+    width: Option<u32>,
+    height: Option<u32>,
+    duration_seconds: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+/// Process an uploaded image or video.
+///
+/// Images go through [`process_upload`](crate::storage::image_processing::process_upload).
+/// Animated GIFs are additionally converted to the configured format, and
+/// videos are probed for their dimensions and duration and get a poster
+/// frame as their thumbnail. Both need ffmpeg; without it GIFs are passed
+/// through and videos are returned unchanged with no metadata.
+pub async fn process_media(
+    data: Vec<u8>,
+    mime_type: String,
+    settings: &TranscodeSettings,
+) -> Result<ProcessedMedia, TinyBoardsError> {
+    if is_video_type(&mime_type) {
+        return process_video(data, mime_type, settings).await;
+    }
+
+    let is_gif = mime_type == "image/gif";
+    let image: ProcessedMedia =
+        process_upload_blocking(data, mime_type, ImageProcessingSettings::default())
+            .await?
+            .into();
+
+    if !is_gif || !ffmpeg_available(settings).await {
+        return Ok(image);
+    }
+
+    match transcode_gif(&image.data, settings).await {
+        Ok((data, info)) if data.len() < image.data.len() => Ok(ProcessedMedia {
+            data,
+            mime_type: settings.gif_format.mime_type().to_string(),
+            duration_seconds: info.duration_seconds,
+            ..image
+        }),
+        Ok((_, info)) => Ok(ProcessedMedia {
+            duration_seconds: info.duration_seconds,
+            ..image
+        }),
+        Err(e) => {
+            // The GIF itself decoded fine above, so serve it as uploaded
+            tracing::warn!("Failed to transcode GIF, keeping the original: {}", e);
+            Ok(image)
+        }
+    }
+}
+
+/// Whether ffmpeg and ffprobe can be run. Checked on first use; a missing
+/// binary is logged once.
+pub async fn ffmpeg_available(settings: &TranscodeSettings) -> bool {
+    *FFMPEG_AVAILABLE
+        .get_or_init(|| async {
+            let available = runs(&settings.ffmpeg_path).await && runs(&settings.ffprobe_path).await;
+            if !available {
+                tracing::warn!(
+                    "`{}`/`{}` not found: animated GIFs will be served as uploaded and videos won't get poster frames",
+                    settings.ffmpeg_path,
+                    settings.ffprobe_path
+                );
+            }
+            available
+        })
+        .await
+}
+
+async fn runs(program: &str) -> bool {
+    Command::new(program)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Probe a video and grab its poster frame. The video itself is kept as is.
+async fn process_video(
+    data: Vec<u8>,
+    mime_type: String,
+    settings: &TranscodeSettings,
+) -> Result<ProcessedMedia, TinyBoardsError> {
+    if !ffmpeg_available(settings).await {
+        return Ok(ProcessedMedia {
+            data,
+            mime_type,
+            thumbnail_data: None,
+            width: None,
+            height: None,
+            duration_seconds: None,
+        });
+    }
+
+    let dir = TempDir::new().await?;
+    let input = dir.file("input");
+    write_file(&input, &data).await?;
+
+    let info = probe(&input, settings).await?;
+
+    let poster = dir.file("poster.png");
+    run(
+        &settings.ffmpeg_path,
+        vec![
+            "-v".into(), "error".into(), "-y".into(),
+            // Seeking before the input is fast and only decodes from the nearest keyframe
+            "-ss".into(), poster_offset(info.duration_seconds).to_string().into(),
+            "-i".into(), input.into_os_string(),
+            "-frames:v".into(), "1".into(),
+            poster.clone().into_os_string(),
+        ],
+        settings.timeout,
+    )
+    .await?;
+
+    // Videos without a video stream (audio in a video container) produce no frame
+    let thumbnail_data = match tokio::fs::read(&poster).await {
+        Ok(png) => Some(poster_thumbnail(png).await?),
+        Err(_) => None,
+    };
+
+    Ok(ProcessedMedia {
+        data,
+        mime_type,
+        thumbnail_data,
+        width: info.width,
+        height: info.height,
+        duration_seconds: info.duration_seconds,
+    })
+}
+
+/// Convert an animated GIF to the configured format, returning the new bytes
+/// and the GIF's duration.
+async fn transcode_gif(
+    data: &[u8],
+    settings: &TranscodeSettings,
+) -> Result<(Vec<u8>, MediaInfo), TinyBoardsError> {
+    let dir = TempDir::new().await?;
+    let input = dir.file("input.gif");
+    write_file(&input, data).await?;
+
+    let info = probe(&input, settings).await?;
+
+    let output = dir.file(&format!("output.{}", settings.gif_format.extension()));
+    let mut args: Vec<OsString> = vec![
+        "-v".into(), "error".into(), "-y".into(),
+        "-i".into(), input.into_os_string(),
+        "-an".into(),
+    ];
+    args.extend(settings.gif_format.encoder_args().iter().map(OsString::from));
+    args.push(output.clone().into_os_string());
+    run(&settings.ffmpeg_path, args, settings.timeout).await?;
+
+    let transcoded = tokio::fs::read(&output).await.map_err(|e| {
+        TinyBoardsError::from_message(500, &format!("Failed to read transcoded GIF: {}", e))
+    })?;
+
+    Ok((transcoded, info))
+}
+
+async fn probe(input: &Path, settings: &TranscodeSettings) -> Result<MediaInfo, TinyBoardsError> {
+    let output = run(
+        &settings.ffprobe_path,
+        vec![
+            "-v".into(), "error".into(),
+            "-select_streams".into(), "v:0".into(),
+            "-show_entries".into(), "stream=width,height:format=duration".into(),
+            "-of".into(), "json".into(),
+            input.as_os_str().to_owned(),
+        ],
+        settings.timeout,
+    )
+    .await?;
+
+    parse_probe_output(&output)
+}
+
+fn parse_probe_output(json: &[u8]) -> Result<MediaInfo, TinyBoardsError> {
+    let output: ProbeOutput = serde_json::from_slice(json).map_err(|e| {
+        TinyBoardsError::from_message(500, &format!("Unexpected ffprobe output: {}", e))
+    })?;
+
+    let stream = output.streams.into_iter().next();
+    Ok(MediaInfo {
+        width: stream.as_ref().and_then(|s| s.width),
+        height: stream.as_ref().and_then(|s| s.height),
+        duration_seconds: output
+            .format
+            .and_then(|f| f.duration)
+            .and_then(|d| d.parse::<f64>().ok())
+            .filter(|d| d.is_finite() && *d > 0.0),
+    })
+}
+
+/// Where to take the poster frame from. The first second is skipped since
+/// videos often fade in from black; short clips use their first frame.
+fn poster_offset(duration_seconds: Option<f64>) -> f64 {
+    match duration_seconds {
+        Some(duration) if duration > 2.0 => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// Scale an extracted frame down to a WebP thumbnail
+async fn poster_thumbnail(png: Vec<u8>) -> Result<Vec<u8>, TinyBoardsError> {
+    tokio::task::spawn_blocking(move || {
+        let frame = image::load_from_memory(&png).map_err(|e| {
+            TinyBoardsError::from_message(500, &format!("Failed to decode poster frame: {}", e))
+        })?;
+        generate_thumbnail(&frame, ImageProcessingSettings::default().thumbnail_width)
+    })
+    .await
+    .map_err(|e| TinyBoardsError::from_message(500, &format!("Poster thumbnail task failed: {}", e)))?
+}
+
+/// Run a program to completion, killing it once `timeout` passes. A non-zero
+/// exit means ffmpeg couldn't read the input, which retrying won't fix, so
+/// it's reported as a bad request.
+async fn run(program: &str, args: Vec<OsString>, timeout: Duration) -> Result<Vec<u8>, TinyBoardsError> {
+    let child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+
+    let output = match tokio::time::timeout(timeout, child).await {
+        Ok(output) => output.map_err(|e| {
+            TinyBoardsError::from_message(500, &format!("Failed to run {}: {}", program, e))
+        })?,
+        Err(_) => {
+            return Err(TinyBoardsError::from_message(
+                500,
+                &format!("{} timed out after {}s", program, timeout.as_secs()),
+            ));
+        }
+    };
+
+    if !output.status.success() {
+        return Err(TinyBoardsError::from_message(
+            400,
+            &format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()),
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+async fn write_file(path: &Path, data: &[u8]) -> Result<(), TinyBoardsError> {
+    tokio::fs::write(path, data).await.map_err(|e| {
+        TinyBoardsError::from_message(500, &format!("Failed to write temporary file: {}", e))
+    })
+}
+
+/// Scratch directory for one ffmpeg job, removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    async fn new() -> Result<Self, TinyBoardsError> {
+        let path = std::env::temp_dir().join(format!("tinyboards-transcode-{}", Uuid::new_v4()));
+        tokio::fs::create_dir(&path).await.map_err(|e| {
+            TinyBoardsError::from_message(500, &format!("Failed to create temporary directory: {}", e))
+        })?;
+        Ok(Self(path))
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            tracing::warn!("Failed to remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_output() {
+        let json = br#"{
+            "programs": [],
+            "streams": [{ "width": 1920, "height": 1080 }],
+            "format": { "duration": "12.480000" }
+        }"#;
+        assert_eq!(
+            parse_probe_output(json).unwrap(),
+            MediaInfo {
+                width: Some(1920),
+                height: Some(1080),
+                duration_seconds: Some(12.48),
+            }
+        );
+
+        // Audio-only input: no video stream, duration still reported
+        let json = br#"{ "streams": [], "format": { "duration": "3.0" } }"#;
+        assert_eq!(
+            parse_probe_output(json).unwrap(),
+            MediaInfo {
+                duration_seconds: Some(3.0),
+                ..Default::default()
+            }
+        );
+
+        let json = br#"{ "streams": [], "format": { "duration": "N/A" } }"#;
+        assert_eq!(parse_probe_output(json).unwrap(), MediaInfo::default());
+        assert!(parse_probe_output(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_poster_offset() {
+        assert_eq!(poster_offset(Some(30.0)), 1.0);
+        assert_eq!(poster_offset(Some(1.5)), 0.0);
+        assert_eq!(poster_offset(None), 0.0);
+    }
+
+    #[test]
+    fn test_gif_format_from_config() {
+        assert_eq!(GifFormat::from_config("webp"), GifFormat::WebP);
+        assert_eq!(GifFormat::from_config(" MP4 "), GifFormat::Mp4);
+        assert_eq!(GifFormat::from_config("avif"), GifFormat::WebP);
+    }
+}
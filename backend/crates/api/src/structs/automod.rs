@@ -0,0 +1,219 @@
+use async_graphql::*;
+use tinyboards_db::models::moderator::automod_rule::AutomodRule as DbAutomodRule;
+use tinyboards_utils::{automod, TinyBoardsError};
+use uuid::Uuid;
+
+/// What a new post has to look like for a rule to act on it. Every condition
+/// that is set must hold; a list matches when any entry does.
+#[derive(SimpleObject, Clone)]
+pub struct AutomodConditions {
+    /// Case-insensitive regex searched for in the title
+    pub title_regex: Option<String>,
+    /// Case-insensitive regex searched for in the body
+    pub body_regex: Option<String>,
+    /// Link domains, subdomains included
+    pub domains: Vec<String>,
+    /// Author's account is younger than this many days
+    pub account_age_under_days: Option<i64>,
+    /// Author's combined post and comment score is below this
+    pub reputation_under: Option<i64>,
+    /// Author has one of these user flairs in the board
+    pub author_flair_ids: Vec<ID>,
+    /// text, link, image or video
+    pub post_types: Vec<String>,
+}
+
+/// What a rule does to a post it matches.
+#[derive(SimpleObject, Clone)]
+pub struct AutomodActions {
+    pub remove: bool,
+    /// Hide the post until a moderator approves it from the queue
+    pub filter: bool,
+    pub lock: bool,
+    #[graphql(name = "markNSFW")]
+    pub mark_nsfw: bool,
+    /// Post flair template to apply
+    pub set_flair_id: Option<ID>,
+    /// Reply to the post with this comment
+    pub comment: Option<String>,
+    /// Report the post with this reason
+    pub report: Option<String>,
+}
+
+#[derive(SimpleObject, Clone)]
+pub struct AutomodRule {
+    pub id: ID,
+    /// Null for site-wide rules
+    pub board_id: Option<ID>,
+    pub name: String,
+    pub conditions: AutomodConditions,
+    pub actions: AutomodActions,
+    pub is_enabled: bool,
+    pub display_order: i32,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+    #[graphql(name = "updatedAt")]
+    pub updated_at: String,
+}
+
+/// A post a rule would have acted on, from a dry run.
+#[derive(SimpleObject, Clone)]
+pub struct AutomodDryRunMatch {
+    pub post_id: ID,
+    pub post_title: String,
+    pub board_id: ID,
+    pub creator_name: String,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+    pub rule_id: ID,
+    pub rule_name: String,
+    /// remove, filter, lock, mark_nsfw, set_flair, comment or report
+    pub actions: Vec<String>,
+}
+
+#[derive(InputObject, Default)]
+pub struct AutomodConditionsInput {
+    pub title_regex: Option<String>,
+    pub body_regex: Option<String>,
+    pub domains: Option<Vec<String>>,
+    pub account_age_under_days: Option<i64>,
+    pub reputation_under: Option<i64>,
+    pub author_flair_ids: Option<Vec<ID>>,
+    pub post_types: Option<Vec<String>>,
+}
+
+#[derive(InputObject, Default)]
+pub struct AutomodActionsInput {
+    pub remove: Option<bool>,
+    pub filter: Option<bool>,
+    pub lock: Option<bool>,
+    #[graphql(name = "markNSFW")]
+    pub mark_nsfw: Option<bool>,
+    pub set_flair_id: Option<ID>,
+    pub comment: Option<String>,
+    pub report: Option<String>,
+}
+
+#[derive(InputObject)]
+pub struct CreateAutomodRuleInput {
+    /// Leave out for a site-wide rule (admins only)
+    pub board_id: Option<ID>,
+    pub name: String,
+    pub conditions: AutomodConditionsInput,
+    pub actions: AutomodActionsInput,
+    pub is_enabled: Option<bool>,
+    pub display_order: Option<i32>,
+}
+
+#[derive(InputObject)]
+pub struct UpdateAutomodRuleInput {
+    pub name: Option<String>,
+    pub conditions: Option<AutomodConditionsInput>,
+    pub actions: Option<AutomodActionsInput>,
+    pub is_enabled: Option<bool>,
+    pub display_order: Option<i32>,
+}
+
+fn parse_id(id: &ID, what: &str) -> Result<Uuid, TinyBoardsError> {
+    id.parse()
+        .map_err(|_| TinyBoardsError::from_message(400, &format!("Invalid {} ID", what)))
+}
+
+/// Blank text fields count as unset.
+fn non_blank(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+impl AutomodConditionsInput {
+    /// Convert and validate.
+    pub fn into_conditions(self) -> Result<automod::AutomodConditions, TinyBoardsError> {
+        let author_flair_ids = self
+            .author_flair_ids
+            .unwrap_or_default()
+            .iter()
+            .map(|id| parse_id(id, "flair"))
+            .collect::<Result<_, _>>()?;
+
+        let conditions = automod::AutomodConditions {
+            title_regex: non_blank(self.title_regex),
+            body_regex: non_blank(self.body_regex),
+            domains: self.domains.unwrap_or_default(),
+            account_age_under_days: self.account_age_under_days,
+            reputation_under: self.reputation_under,
+            author_flair_ids,
+            post_types: self.post_types.unwrap_or_default(),
+        }
+        .normalize();
+        conditions.validate()?;
+        Ok(conditions)
+    }
+}
+
+impl AutomodActionsInput {
+    /// Convert and validate.
+    pub fn into_actions(self) -> Result<automod::AutomodActions, TinyBoardsError> {
+        let actions = automod::AutomodActions {
+            remove: self.remove.unwrap_or(false),
+            filter: self.filter.unwrap_or(false),
+            lock: self.lock.unwrap_or(false),
+            mark_nsfw: self.mark_nsfw.unwrap_or(false),
+            set_flair_id: self
+                .set_flair_id
+                .as_ref()
+                .map(|id| parse_id(id, "flair"))
+                .transpose()?,
+            comment: non_blank(self.comment),
+            report: non_blank(self.report),
+        };
+        actions.validate()?;
+        Ok(actions)
+    }
+}
+
+impl From<automod::AutomodConditions> for AutomodConditions {
+    fn from(c: automod::AutomodConditions) -> Self {
+        Self {
+            title_regex: c.title_regex,
+            body_regex: c.body_regex,
+            domains: c.domains,
+            account_age_under_days: c.account_age_under_days,
+            reputation_under: c.reputation_under,
+            author_flair_ids: c.author_flair_ids.iter().map(|id| id.to_string().into()).collect(),
+            post_types: c.post_types,
+        }
+    }
+}
+
+impl From<automod::AutomodActions> for AutomodActions {
+    fn from(a: automod::AutomodActions) -> Self {
+        Self {
+            remove: a.remove,
+            filter: a.filter,
+            lock: a.lock,
+            mark_nsfw: a.mark_nsfw,
+            set_flair_id: a.set_flair_id.map(|id| id.to_string().into()),
+            comment: a.comment,
+            report: a.report,
+        }
+    }
+}
+
+impl From<DbAutomodRule> for AutomodRule {
+    fn from(db: DbAutomodRule) -> Self {
+        let conditions: automod::AutomodConditions =
+            serde_json::from_value(db.conditions).unwrap_or_default();
+        let actions: automod::AutomodActions = serde_json::from_value(db.actions).unwrap_or_default();
+
+        Self {
+            id: db.id.to_string().into(),
+            board_id: db.board_id.map(|id| id.to_string().into()),
+            name: db.name,
+            conditions: conditions.into(),
+            actions: actions.into(),
+            is_enabled: db.is_enabled,
+            display_order: db.display_order,
+            created_at: db.created_at.to_rfc3339(),
+            updated_at: db.updated_at.to_rfc3339(),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use async_graphql::*;
+use chrono::Utc;
+use tinyboards_db::models::user::DataExport as DbDataExport;
+
+use crate::storage::{export::EXPORT_RETENTION_DAYS, StorageBackend};
+
+/// An account data export: a ZIP of JSON files with the user's content and
+/// settings.
+#[derive(SimpleObject, Clone)]
+pub struct DataExport {
+    pub id: ID,
+    /// pending, ready or failed
+    pub status: String,
+    /// Archive size once ready
+    pub size_bytes: Option<i64>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    /// When the archive is deleted
+    pub expires_at: Option<String>,
+    /// Signed link to the archive, valid until it expires
+    pub download_url: Option<String>,
+}
+
+impl DataExport {
+    pub fn from_db(export: DbDataExport, storage: &StorageBackend, secret: &str) -> Self {
+        let download_url = match (&export.storage_key, export.expires_at) {
+            (Some(key), Some(expires_at)) if export.status == "ready" && expires_at > Utc::now() => {
+                let ttl = (expires_at - Utc::now()).num_seconds().min(EXPORT_RETENTION_DAYS * 24 * 60 * 60);
+                Some(storage.get_signed_url(key, secret, ttl))
+            }
+            _ => None,
+        };
+
+        Self {
+            id: ID(export.id.to_string()),
+            status: export.status,
+            size_bytes: export.size_bytes,
+            created_at: export.created_at.to_rfc3339(),
+            completed_at: export.completed_at.map(|t| t.to_rfc3339()),
+            expires_at: export.expires_at.map(|t| t.to_rfc3339()),
+            download_url,
+        }
+    }
+}
+
+/// The user's exports and when they can ask for another.
+#[derive(SimpleObject)]
+pub struct DataExportList {
+    pub exports: Vec<DataExport>,
+    /// Set while a new export can't be requested yet
+    pub next_export_allowed_at: Option<String>,
+}
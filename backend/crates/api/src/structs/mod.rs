@@ -1,11 +1,17 @@
+pub mod automod;
 pub mod board_mods;
 pub mod boards;
 pub mod comment;
+pub mod data_export;
 pub mod emoji;
 pub mod flair;
 pub mod message;
+pub mod modmail;
 pub mod post;
 pub mod reaction;
+pub mod removal_reason;
+pub mod session;
 pub mod site;
+pub mod upload;
 pub mod user;
 pub mod wiki;
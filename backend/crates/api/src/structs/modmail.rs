@@ -0,0 +1,171 @@
+use async_graphql::*;
+use async_graphql::dataloader::DataLoader;
+use tinyboards_db::models::message::{
+    message::PrivateMessage as DbPrivateMessage,
+    modmail_thread::ModmailThread as DbModmailThread,
+};
+use uuid::Uuid;
+
+use crate::{
+    newtypes::{BoardId, UserId},
+    structs::{boards::Board, user::User},
+    PostgresLoader,
+};
+
+/// Where a modmail thread is in the moderators' workflow
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ModmailStatus {
+    /// Waiting for the moderators. Archived threads come back here when
+    /// the user writes again.
+    New,
+    InProgress,
+    Archived,
+}
+
+impl ModmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::InProgress => "in_progress",
+            Self::Archived => "archived",
+        }
+    }
+}
+
+impl From<&str> for ModmailStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "in_progress" => Self::InProgress,
+            "archived" => Self::Archived,
+            _ => Self::New,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct ModmailThread {
+    pub id: ID,
+    pub board_id: ID,
+    pub subject: String,
+    pub status: ModmailStatus,
+    /// For the user who started the thread, a reply from the board they
+    /// haven't read; for moderators, a message from the user nobody on the
+    /// team has read yet
+    pub is_unread: bool,
+    #[graphql(name = "lastMessageAt")]
+    pub last_message_at: String,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+
+    #[graphql(skip)]
+    pub uuid_board_id: Uuid,
+    #[graphql(skip)]
+    pub uuid_user_id: Uuid,
+    #[graphql(skip)]
+    pub uuid_assigned_to: Option<Uuid>,
+}
+
+#[ComplexObject]
+impl ModmailThread {
+    pub async fn board(&self, ctx: &Context<'_>) -> Result<Option<Board>> {
+        let loader = ctx.data_unchecked::<DataLoader<PostgresLoader>>();
+        Ok(loader.load_one(BoardId(self.uuid_board_id)).await?)
+    }
+
+    /// The user who started the thread
+    pub async fn user(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        let loader = ctx.data_unchecked::<DataLoader<PostgresLoader>>();
+        Ok(loader.load_one(UserId(self.uuid_user_id)).await?)
+    }
+
+    /// Moderator handling the thread. Only shown to moderators.
+    pub async fn assigned_to(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        match self.uuid_assigned_to {
+            Some(id) => {
+                let loader = ctx.data_unchecked::<DataLoader<PostgresLoader>>();
+                Ok(loader.load_one(UserId(id)).await?)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl ModmailThread {
+    /// `as_moderator` picks whose unread flag applies and whether the
+    /// assignee is shown.
+    pub fn from_db(thread: DbModmailThread, as_moderator: bool) -> Self {
+        Self {
+            id: thread.id.to_string().into(),
+            board_id: thread.board_id.to_string().into(),
+            subject: thread.subject,
+            status: ModmailStatus::from(thread.status.as_str()),
+            is_unread: if as_moderator {
+                !thread.is_read_by_mods
+            } else {
+                !thread.is_read_by_user
+            },
+            last_message_at: thread.last_message_at.to_rfc3339(),
+            created_at: thread.created_at.to_rfc3339(),
+            uuid_board_id: thread.board_id,
+            uuid_user_id: thread.user_id,
+            uuid_assigned_to: thread.assigned_to.filter(|_| as_moderator),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct ModmailMessage {
+    pub id: ID,
+    pub body: String,
+    #[graphql(name = "bodyHTML")]
+    pub body_html: String,
+    /// Sent by a moderator on behalf of the board
+    pub is_from_board: bool,
+    /// Internal note, only shown to moderators
+    pub is_mod_note: bool,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+
+    #[graphql(skip)]
+    pub uuid_author_id: Option<Uuid>,
+}
+
+#[ComplexObject]
+impl ModmailMessage {
+    /// Who wrote the message. Null for replies from the board when shown to
+    /// the user who started the thread.
+    pub async fn author(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        match self.uuid_author_id {
+            Some(id) => {
+                let loader = ctx.data_unchecked::<DataLoader<PostgresLoader>>();
+                Ok(loader.load_one(UserId(id)).await?)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl ModmailMessage {
+    pub fn from_db(msg: DbPrivateMessage, as_moderator: bool) -> Self {
+        Self {
+            id: msg.id.to_string().into(),
+            body: msg.body,
+            body_html: msg.body_html,
+            is_from_board: msg.is_sender_hidden,
+            is_mod_note: msg.is_mod_note,
+            created_at: msg.created_at.to_rfc3339(),
+            uuid_author_id: Some(msg.creator_id).filter(|_| as_moderator || !msg.is_sender_hidden),
+        }
+    }
+}
+
+/// A modmail thread with its messages, oldest first.
+#[derive(SimpleObject)]
+pub struct ModmailConversation {
+    pub thread: ModmailThread,
+    pub messages: Vec<ModmailMessage>,
+    /// Whether the viewer is answering as one of the board's moderators
+    pub viewer_is_moderator: bool,
+}
@@ -0,0 +1,74 @@
+use async_graphql::*;
+use tinyboards_db::models::moderator::removal_reason::RemovalReason as DbRemovalReason;
+
+/// A predefined reason a board's moderators can give when removing a post
+/// or comment.
+#[derive(SimpleObject, Clone)]
+pub struct RemovalReason {
+    pub id: ID,
+    pub board_id: ID,
+    pub title: String,
+    /// Markdown sent to the author, with {author}, {board} and {rule} placeholders
+    pub message: String,
+    /// Board rule the reason enforces, used for {rule}
+    pub rule: Option<String>,
+    pub display_order: i32,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+    #[graphql(name = "updatedAt")]
+    pub updated_at: String,
+}
+
+/// How the author is told why their content was removed.
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum RemovalMessageDelivery {
+    /// Reply to the removed content with a distinguished moderator comment
+    Comment,
+    /// Send the author a private message from the moderator
+    PrivateMessage,
+    /// Send the author a notification
+    Notification,
+}
+
+impl RemovalMessageDelivery {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Comment => "comment",
+            Self::PrivateMessage => "private_message",
+            Self::Notification => "notification",
+        }
+    }
+}
+
+#[derive(InputObject)]
+pub struct CreateRemovalReasonInput {
+    pub board_id: ID,
+    pub title: String,
+    pub message: String,
+    pub rule: Option<String>,
+    pub display_order: Option<i32>,
+}
+
+#[derive(InputObject)]
+pub struct UpdateRemovalReasonInput {
+    pub title: Option<String>,
+    pub message: Option<String>,
+    /// An empty string clears the rule
+    pub rule: Option<String>,
+    pub display_order: Option<i32>,
+}
+
+impl From<DbRemovalReason> for RemovalReason {
+    fn from(db: DbRemovalReason) -> Self {
+        Self {
+            id: db.id.to_string().into(),
+            board_id: db.board_id.to_string().into(),
+            title: db.title,
+            message: db.message,
+            rule: db.rule,
+            display_order: db.display_order,
+            created_at: db.created_at.to_rfc3339(),
+            updated_at: db.updated_at.to_rfc3339(),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use async_graphql::*;
+use tinyboards_auth::user_agent;
+use tinyboards_db::models::auth::AuthSession as DbAuthSession;
+
+/// A signed-in device or browser. Revoking it signs that device out.
+#[derive(SimpleObject, Clone)]
+pub struct AuthSession {
+    pub id: ID,
+    /// Label the user gave this session
+    pub name: Option<String>,
+    /// Summary of the user agent, e.g. "Firefox 128 on Windows"
+    pub device: String,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    /// desktop, mobile, tablet, bot or unknown
+    pub device_type: String,
+    pub ip_address: Option<String>,
+    pub created_at: String,
+    /// Approximate: updated each time the session refreshes its access token
+    pub last_seen_at: String,
+    pub expires_at: String,
+    /// The session making this request
+    pub is_current: bool,
+}
+
+impl AuthSession {
+    /// `current_token_hash` is the hash of the request's refresh cookie, if any.
+    pub fn from_db(session: DbAuthSession, current_token_hash: Option<&str>) -> Self {
+        let device = user_agent::parse(session.user_agent.as_deref().unwrap_or_default());
+        Self {
+            id: ID(session.id.to_string()),
+            name: session.name,
+            device: device.describe(),
+            browser: device.browser,
+            os: device.os,
+            device_type: device.device_type.as_str().to_string(),
+            ip_address: session.ip_address,
+            created_at: session.created_at.to_rfc3339(),
+            last_seen_at: session.last_used_at.unwrap_or(session.created_at).to_rfc3339(),
+            expires_at: session.expires_at.to_rfc3339(),
+            is_current: current_token_hash == Some(session.refresh_token_hash.as_str()),
+        }
+    }
+}
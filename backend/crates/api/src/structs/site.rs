@@ -1,7 +1,10 @@
 use async_graphql::*;
 use crate::helpers::permissions;
+use crate::storage::maintenance::StorageStats;
 use tinyboards_db::models::site::site::Site as DbSite;
 use tinyboards_db::models::aggregates::SiteAggregates as DbSiteAggregates;
+use tinyboards_db::models::config::RateLimit as DbRateLimit;
+use tinyboards_db::models::upload::MediaGcRun as DbMediaGcRun;
 use uuid::Uuid;
 
 #[derive(SimpleObject)]
@@ -90,6 +93,9 @@ pub struct LocalSite {
     pub board_emojis_enabled: bool,
     pub default_board_mode: String,
     pub custom_css_enabled: bool,
+    pub require_two_factor_for_admins: bool,
+    pub require_two_factor_for_mods: bool,
+    pub magic_link_login_enabled: bool,
     pub created_at: String,
     pub updated_at: String,
     // Hidden fields — only admins can see
@@ -193,6 +199,9 @@ impl From<DbSite> for LocalSite {
                 tinyboards_db::enums::DbBoardMode::Forum => "forum".to_string(),
             },
             custom_css_enabled: v.custom_css_enabled,
+            require_two_factor_for_admins: v.require_two_factor_for_admins,
+            require_two_factor_for_mods: v.require_two_factor_for_mods,
+            magic_link_login_enabled: v.magic_link_login_enabled,
             created_at: v.created_at.to_rfc3339(),
             updated_at: v.updated_at.to_rfc3339(),
             welcome_message_: v.welcome_message,
@@ -200,3 +209,131 @@ impl From<DbSite> for LocalSite {
         }
     }
 }
+
+/// Active rate limits. Each action allows `<action>` requests per
+/// `<action>PerSecond` seconds, per IP and per user.
+#[derive(SimpleObject)]
+pub struct SiteRateLimits {
+    pub message: i32,
+    pub message_per_second: i32,
+    pub post: i32,
+    pub post_per_second: i32,
+    pub register: i32,
+    pub register_per_second: i32,
+    pub login: i32,
+    pub login_per_second: i32,
+    pub image: i32,
+    pub image_per_second: i32,
+    pub comment: i32,
+    pub comment_per_second: i32,
+    pub search: i32,
+    pub search_per_second: i32,
+    pub magic_link: i32,
+    pub magic_link_per_second: i32,
+}
+
+impl From<DbRateLimit> for SiteRateLimits {
+    fn from(r: DbRateLimit) -> Self {
+        Self {
+            message: r.message,
+            message_per_second: r.message_per_second,
+            post: r.post,
+            post_per_second: r.post_per_second,
+            register: r.register,
+            register_per_second: r.register_per_second,
+            login: r.login,
+            login_per_second: r.login_per_second,
+            image: r.image,
+            image_per_second: r.image_per_second,
+            comment: r.comment,
+            comment_per_second: r.comment_per_second,
+            search: r.search,
+            search_per_second: r.search_per_second,
+            magic_link: r.magic_link,
+            magic_link_per_second: r.magic_link_per_second,
+        }
+    }
+}
+
+/// Media storage usage and the latest garbage collection result
+#[derive(SimpleObject)]
+pub struct MediaStorage {
+    pub stats: MediaStorageStats,
+    pub last_gc_run: Option<MediaGcRun>,
+}
+
+/// Files in the storage backend, grouped by upload subdirectory. Sizes are in bytes.
+#[derive(SimpleObject)]
+pub struct MediaStorageStats {
+    pub total_files: i64,
+    pub total_bytes: i64,
+    pub emoji_files: i64,
+    pub emoji_bytes: i64,
+    pub avatar_files: i64,
+    pub avatar_bytes: i64,
+    pub video_files: i64,
+    pub video_bytes: i64,
+    pub audio_files: i64,
+    pub audio_bytes: i64,
+    pub document_files: i64,
+    pub document_bytes: i64,
+    pub other_files: i64,
+    pub other_bytes: i64,
+}
+
+impl From<StorageStats> for MediaStorageStats {
+    fn from(s: StorageStats) -> Self {
+        Self {
+            total_files: s.total_files as i64,
+            total_bytes: s.total_size as i64,
+            emoji_files: s.emoji_count as i64,
+            emoji_bytes: s.emoji_size as i64,
+            avatar_files: s.avatar_count as i64,
+            avatar_bytes: s.avatar_size as i64,
+            video_files: s.video_count as i64,
+            video_bytes: s.video_size as i64,
+            audio_files: s.audio_count as i64,
+            audio_bytes: s.audio_size as i64,
+            document_files: s.document_count as i64,
+            document_bytes: s.document_size as i64,
+            other_files: s.other_count as i64,
+            other_bytes: s.other_size as i64,
+        }
+    }
+}
+
+/// Result of a media garbage collection run
+#[derive(SimpleObject)]
+pub struct MediaGcRun {
+    pub id: ID,
+    pub started_at: String,
+    pub finished_at: String,
+    /// Upload records deleted because nothing referenced them
+    pub orphaned_uploads_deleted: i32,
+    /// Stored files deleted because no upload record or URL pointed at them
+    pub orphaned_files_deleted: i32,
+    pub bytes_freed: i64,
+    /// Upload records whose file is missing from the storage backend
+    pub missing_files: i32,
+    pub total_files: i64,
+    pub total_bytes: i64,
+    /// Set if the run stopped early
+    pub error: Option<String>,
+}
+
+impl From<DbMediaGcRun> for MediaGcRun {
+    fn from(r: DbMediaGcRun) -> Self {
+        Self {
+            id: r.id.to_string().into(),
+            started_at: r.started_at.to_rfc3339(),
+            finished_at: r.finished_at.to_rfc3339(),
+            orphaned_uploads_deleted: r.orphaned_uploads_deleted,
+            orphaned_files_deleted: r.orphaned_files_deleted,
+            bytes_freed: r.bytes_freed,
+            missing_files: r.missing_files,
+            total_files: r.total_files,
+            total_bytes: r.total_bytes,
+            error: r.error,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use async_graphql::*;
+use tinyboards_db::models::upload::Upload as DbUpload;
+
+/// Where an upload is in the background processing pipeline
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum UploadProcessingStatus {
+    /// Queued for optimization; `url` already serves the original
+    Pending,
+    Processing,
+    /// `optimizedUrl` and `thumbnailUrl` are set (for images), and the
+    /// dimensions and poster frame for videos when the server has ffmpeg
+    Done,
+    /// Gave up after retrying; `url` keeps serving the original
+    Failed,
+}
+
+impl From<&str> for UploadProcessingStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "pending" => Self::Pending,
+            "processing" => Self::Processing,
+            "failed" => Self::Failed,
+            _ => Self::Done,
+        }
+    }
+}
+
+#[derive(SimpleObject, Clone)]
+pub struct FileUpload {
+    pub id: ID,
+    /// URL of the file as uploaded
+    pub url: String,
+    /// Resized/re-encoded version, once processing is done. Animated GIFs
+    /// may be converted to animated WebP or MP4.
+    pub optimized_url: Option<String>,
+    /// Thumbnail, or the poster frame of a video
+    pub thumbnail_url: Option<String>,
+    pub processing_status: UploadProcessingStatus,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// Running time of a video or animation
+    pub duration_seconds: Option<f64>,
+    pub size_bytes: i64,
+    #[graphql(name = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<DbUpload> for FileUpload {
+    fn from(upload: DbUpload) -> Self {
+        Self {
+            id: upload.id.to_string().into(),
+            url: upload.upload_url,
+            optimized_url: upload.optimized_url,
+            thumbnail_url: upload.thumbnail_url,
+            processing_status: upload.processing_status.as_str().into(),
+            width: upload.width,
+            height: upload.height,
+            duration_seconds: upload.duration_seconds,
+            size_bytes: upload.size_bytes,
+            created_at: upload.created_at.to_rfc3339(),
+        }
+    }
+}
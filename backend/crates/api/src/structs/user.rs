@@ -125,6 +125,9 @@ pub struct UserSettings {
     #[graphql(name = "showNSFW")]
     pub show_nsfw: bool,
     pub show_bots: bool,
+    /// Marks the account as automated. Users who turn off `showBots` don't see
+    /// its posts and comments in listings
+    pub is_bot_account: bool,
     pub theme: String,
     pub default_sort_type: String,
     pub default_listing_type: String,
@@ -143,6 +146,7 @@ impl From<DbUser> for UserSettings {
             email: u.email,
             show_nsfw: u.show_nsfw,
             show_bots: u.show_bots,
+            is_bot_account: u.is_bot_account,
             theme: u.theme,
             default_sort_type: format!("{:?}", u.default_sort_type),
             default_listing_type: format!("{:?}", u.default_listing_type),
@@ -0,0 +1,434 @@
+use async_graphql::*;
+// Not diesel::prelude::*, and diesel_async::RunQueryDsl only inside the
+// loader functions: in module scope its blanket `execute` shadows the one
+// #[Subscription] calls.
+use diesel::{ExpressionMethods, JoinOnDsl, OptionalExtension, QueryDsl};
+use futures::{
+    future,
+    stream::{self, Stream, StreamExt},
+};
+use tinyboards_db::{
+    models::{
+        aggregates::{CommentAggregates, PostAggregates, ReactionAggregates},
+        board::boards::Board as DbBoard,
+        comment::comments::Comment as DbComment,
+        message::message::PrivateMessage as DbPrivateMessage,
+        notification::notifications::Notification as DbNotification,
+        post::posts::Post as DbPost,
+        upload::Upload as DbUpload,
+        user::user::{AdminPerms, User},
+    },
+    schema::{
+        boards, comment_aggregates, comments, notifications, post_aggregates, posts,
+        private_messages, reaction_aggregates, uploads,
+    },
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::TinyBoardsError;
+use uuid::Uuid;
+
+use crate::{
+    events::{EventBus, LiveEvent},
+    helpers::{permissions, validation::check_private_instance},
+    queries::notifications::{enrich_notifications, Notification},
+    structs::{
+        comment::Comment, message::PrivateMessage, reaction::ReactionAggregate, upload::FileUpload,
+    },
+    LoggedInUser,
+};
+
+#[derive(Default)]
+pub struct Subscription;
+
+#[derive(SimpleObject)]
+pub struct UnreadCounts {
+    pub notifications: i32,
+    pub messages: i32,
+}
+
+/// Current vote and reaction totals for a post or one of its comments
+#[derive(SimpleObject)]
+pub struct ScoreUpdate {
+    pub post_id: ID,
+    /// Set when the change was on a comment rather than the post itself
+    pub comment_id: Option<ID>,
+    pub score: i64,
+    pub upvotes: i64,
+    pub downvotes: i64,
+    pub reaction_counts: Vec<ReactionAggregate>,
+}
+
+#[Subscription]
+impl Subscription {
+    /// New notifications for the logged-in user
+    async fn notification_created(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<impl Stream<Item = Notification>> {
+        let user_id = ctx.data::<LoggedInUser>()?.require_user_not_banned()?.id;
+        let pool = ctx.data::<DbPool>()?.clone();
+        let events = ctx.data::<EventBus>()?;
+
+        Ok(events.subscribe().filter_map(move |event| {
+            let pool = pool.clone();
+            async move {
+                match event {
+                    LiveEvent::NotificationCreated {
+                        recipient_id,
+                        notification_id,
+                    } if recipient_id == user_id => load_notification(&pool, notification_id)
+                        .await
+                        .ok()
+                        .flatten(),
+                    _ => None,
+                }
+            }
+        }))
+    }
+
+    /// Private messages sent to the logged-in user
+    async fn message_received(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<impl Stream<Item = PrivateMessage>> {
+        let user_id = ctx.data::<LoggedInUser>()?.require_user_not_banned()?.id;
+        let pool = ctx.data::<DbPool>()?.clone();
+        let events = ctx.data::<EventBus>()?;
+
+        Ok(events.subscribe().filter_map(move |event| {
+            let pool = pool.clone();
+            async move {
+                match event {
+                    LiveEvent::MessageSent {
+                        recipient_id,
+                        message_id,
+                    } if recipient_id == user_id => {
+                        load_message(&pool, message_id).await.ok().flatten()
+                    }
+                    _ => None,
+                }
+            }
+        }))
+    }
+
+    /// Unread notification and message counts. Emits the current counts
+    /// immediately, then again whenever they change.
+    async fn unread_counts(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = UnreadCounts>> {
+        let user_id = ctx.data::<LoggedInUser>()?.require_user_not_banned()?.id;
+        let pool = ctx.data::<DbPool>()?.clone();
+        let events = ctx.data::<EventBus>()?;
+
+        let changes = events
+            .subscribe()
+            .filter(move |event| future::ready(changes_unread_counts(event, user_id)));
+
+        Ok(stream::once(future::ready(()))
+            .chain(changes.map(|_| ()))
+            .filter_map(move |_| {
+                let pool = pool.clone();
+                async move { load_unread_counts(&pool, user_id).await.ok() }
+            }))
+    }
+
+    /// Comments added to a post, for live-updating an open thread
+    async fn comment_created(
+        &self,
+        ctx: &Context<'_>,
+        post_id: ID,
+    ) -> Result<impl Stream<Item = Comment>> {
+        let post_uuid = parse_post_id(&post_id)?;
+        let pool = ctx.data::<DbPool>()?.clone();
+        let events = ctx.data::<EventBus>()?;
+        require_visible_post(&pool, permissions::optional_auth(ctx), post_uuid).await?;
+
+        Ok(events.subscribe().filter_map(move |event| {
+            let pool = pool.clone();
+            async move {
+                match event {
+                    LiveEvent::CommentCreated {
+                        post_id,
+                        comment_id,
+                    } if post_id == post_uuid => {
+                        load_comment(&pool, comment_id).await.ok().flatten()
+                    }
+                    _ => None,
+                }
+            }
+        }))
+    }
+
+    /// The logged-in user's uploads as their background processing finishes
+    /// (DONE or FAILED)
+    async fn upload_processed(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = FileUpload>> {
+        let user_id = ctx.data::<LoggedInUser>()?.require_user()?.id;
+        let pool = ctx.data::<DbPool>()?.clone();
+        let events = ctx.data::<EventBus>()?;
+
+        Ok(events.subscribe().filter_map(move |event| {
+            let pool = pool.clone();
+            async move {
+                match event {
+                    LiveEvent::UploadProcessed {
+                        user_id: owner_id,
+                        upload_id,
+                    } if owner_id == user_id => load_upload(&pool, upload_id).await.ok().flatten(),
+                    _ => None,
+                }
+            }
+        }))
+    }
+
+    /// Vote and reaction count changes on a post and its comments
+    async fn score_changed(
+        &self,
+        ctx: &Context<'_>,
+        post_id: ID,
+    ) -> Result<impl Stream<Item = ScoreUpdate>> {
+        let post_uuid = parse_post_id(&post_id)?;
+        let pool = ctx.data::<DbPool>()?.clone();
+        let events = ctx.data::<EventBus>()?;
+        require_visible_post(&pool, permissions::optional_auth(ctx), post_uuid).await?;
+
+        Ok(events.subscribe().filter_map(move |event| {
+            let pool = pool.clone();
+            async move {
+                match event {
+                    LiveEvent::ScoreChanged {
+                        post_id,
+                        comment_id,
+                    } if post_id == post_uuid => load_score(&pool, post_id, comment_id).await.ok(),
+                    _ => None,
+                }
+            }
+        }))
+    }
+}
+
+/// Whether `event` may have changed `user_id`'s unread counts.
+fn changes_unread_counts(event: &LiveEvent, user_id: Uuid) -> bool {
+    match event {
+        LiveEvent::NotificationCreated { recipient_id, .. }
+        | LiveEvent::MessageSent { recipient_id, .. } => *recipient_id == user_id,
+        LiveEvent::UnreadCountsChanged { user_id: changed } => *changed == user_id,
+        _ => false,
+    }
+}
+
+fn parse_post_id(post_id: &ID) -> Result<Uuid> {
+    post_id
+        .parse::<Uuid>()
+        .map_err(|_| TinyBoardsError::from_message(400, "Invalid post ID").into())
+}
+
+/// The same checks the `post` query makes, so a subscription never shows
+/// activity on a post the caller couldn't open.
+async fn require_visible_post(pool: &DbPool, user: Option<&User>, post_id: Uuid) -> Result<()> {
+    use diesel_async::RunQueryDsl;
+
+    check_private_instance(user, pool).await?;
+
+    let conn = &mut get_conn(pool).await?;
+    let post: DbPost = posts::table
+        .find(post_id)
+        .first(conn)
+        .await
+        .map_err(|_| TinyBoardsError::NotFound("Post not found".into()))?;
+
+    if post.deleted_at.is_some() || post.is_removed {
+        return Err(TinyBoardsError::from_message(404, "Post has been deleted or removed").into());
+    }
+
+    if !user.is_some_and(|u| u.has_permission(AdminPerms::Boards)) {
+        let board: DbBoard = boards::table
+            .find(post.board_id)
+            .first(conn)
+            .await
+            .map_err(|_| TinyBoardsError::NotFound("Board not found".into()))?;
+        if board.is_banned {
+            return Err(TinyBoardsError::from_message(
+                403,
+                board
+                    .public_ban_reason
+                    .as_deref()
+                    .unwrap_or("This board has been banned"),
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+async fn load_notification(
+    pool: &DbPool,
+    notification_id: Uuid,
+) -> Result<Option<Notification>, TinyBoardsError> {
+    use diesel_async::RunQueryDsl;
+
+    let conn = &mut get_conn(pool).await?;
+    let notification: Option<DbNotification> = notifications::table
+        .find(notification_id)
+        .first(conn)
+        .await
+        .optional()?;
+
+    match notification {
+        Some(n) => Ok(enrich_notifications(conn, vec![n]).await.pop()),
+        None => Ok(None),
+    }
+}
+
+async fn load_message(
+    pool: &DbPool,
+    message_id: Uuid,
+) -> Result<Option<PrivateMessage>, TinyBoardsError> {
+    use diesel_async::RunQueryDsl;
+
+    let conn = &mut get_conn(pool).await?;
+    let message: Option<DbPrivateMessage> = private_messages::table
+        .find(message_id)
+        .filter(private_messages::deleted_at.is_null())
+        .first(conn)
+        .await
+        .optional()?;
+
+    Ok(message.map(PrivateMessage::from))
+}
+
+async fn load_comment(pool: &DbPool, comment_id: Uuid) -> Result<Option<Comment>, TinyBoardsError> {
+    use diesel_async::RunQueryDsl;
+
+    let conn = &mut get_conn(pool).await?;
+    let comment: Option<(DbComment, CommentAggregates)> = comments::table
+        .inner_join(comment_aggregates::table.on(comment_aggregates::comment_id.eq(comments::id)))
+        .filter(comments::id.eq(comment_id))
+        .filter(comments::is_removed.eq(false))
+        .filter(comments::deleted_at.is_null())
+        .select((comments::all_columns, comment_aggregates::all_columns))
+        .first(conn)
+        .await
+        .optional()?;
+
+    Ok(comment.map(Comment::from))
+}
+
+async fn load_upload(pool: &DbPool, upload_id: Uuid) -> Result<Option<FileUpload>, TinyBoardsError> {
+    use diesel_async::RunQueryDsl;
+
+    let conn = &mut get_conn(pool).await?;
+    let upload: Option<DbUpload> = uploads::table.find(upload_id).first(conn).await.optional()?;
+
+    Ok(upload.map(FileUpload::from))
+}
+
+async fn load_unread_counts(pool: &DbPool, user_id: Uuid) -> Result<UnreadCounts, TinyBoardsError> {
+    use diesel_async::RunQueryDsl;
+
+    let conn = &mut get_conn(pool).await?;
+
+    let notification_count: i64 = notifications::table
+        .filter(notifications::recipient_user_id.eq(user_id))
+        .filter(notifications::is_read.eq(false))
+        .count()
+        .get_result(conn)
+        .await?;
+
+    let message_count: i64 = private_messages::table
+        .filter(private_messages::recipient_id.eq(user_id))
+        .filter(private_messages::is_read.eq(false))
+        .filter(private_messages::deleted_at.is_null())
+        .count()
+        .get_result(conn)
+        .await?;
+
+    Ok(UnreadCounts {
+        notifications: notification_count as i32,
+        messages: message_count as i32,
+    })
+}
+
+async fn load_score(
+    pool: &DbPool,
+    post_id: Uuid,
+    comment_id: Option<Uuid>,
+) -> Result<ScoreUpdate, TinyBoardsError> {
+    use diesel_async::RunQueryDsl;
+
+    let conn = &mut get_conn(pool).await?;
+
+    let (score, upvotes, downvotes, reactions) = match comment_id {
+        Some(cid) => {
+            let agg: CommentAggregates = comment_aggregates::table
+                .filter(comment_aggregates::comment_id.eq(cid))
+                .first(conn)
+                .await?;
+            let reactions: Vec<ReactionAggregates> = reaction_aggregates::table
+                .filter(reaction_aggregates::comment_id.eq(cid))
+                .load(conn)
+                .await?;
+            (agg.score, agg.upvotes, agg.downvotes, reactions)
+        }
+        None => {
+            let agg: PostAggregates = post_aggregates::table
+                .filter(post_aggregates::post_id.eq(post_id))
+                .first(conn)
+                .await?;
+            let reactions: Vec<ReactionAggregates> = reaction_aggregates::table
+                .filter(reaction_aggregates::post_id.eq(post_id))
+                .load(conn)
+                .await?;
+            (agg.score, agg.upvotes, agg.downvotes, reactions)
+        }
+    };
+
+    Ok(ScoreUpdate {
+        post_id: post_id.to_string().into(),
+        comment_id: comment_id.map(|id| id.to_string().into()),
+        score,
+        upvotes,
+        downvotes,
+        reaction_counts: reactions.into_iter().map(ReactionAggregate::from).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_unread_counts_only_follow_the_users_own_events() {
+        let me = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let events = EventBus::new();
+        let mut changes = Box::pin(
+            events
+                .subscribe()
+                .filter(move |event| future::ready(changes_unread_counts(event, me))),
+        );
+
+        events.publish(LiveEvent::MessageSent {
+            recipient_id: other,
+            message_id: Uuid::new_v4(),
+        });
+        events.publish(LiveEvent::CommentCreated {
+            post_id: Uuid::new_v4(),
+            comment_id: Uuid::new_v4(),
+        });
+        events.publish(LiveEvent::UnreadCountsChanged { user_id: other });
+        events.publish(LiveEvent::NotificationCreated {
+            recipient_id: me,
+            notification_id: Uuid::new_v4(),
+        });
+        events.publish(LiveEvent::UnreadCountsChanged { user_id: me });
+
+        assert!(matches!(
+            block_on(changes.next()),
+            Some(LiveEvent::NotificationCreated { recipient_id, .. }) if recipient_id == me
+        ));
+        assert!(matches!(
+            block_on(changes.next()),
+            Some(LiveEvent::UnreadCountsChanged { user_id }) if user_id == me
+        ));
+    }
+}
@@ -1,10 +1,13 @@
 use chrono::Utc;
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
-use tinyboards_auth::tokens::validate_access_token;
+use tinyboards_auth::{
+    tokens::{hash_personal_token, validate_access_token, PERSONAL_TOKEN_PREFIX},
+    types::ApiTokenScope,
+};
 use tinyboards_db::{
-    models::auth::Secret,
-    schema::users,
+    models::{auth::{ApiToken, Secret}, user::User},
+    schema::{api_tokens, users},
     utils::{get_conn, DbPool},
 };
 use tinyboards_utils::TinyBoardsError;
@@ -17,7 +20,20 @@ pub async fn get_user_from_header_opt(
     pool: &DbPool,
     master_key: &Secret,
     auth: Option<&str>,
-) -> Result<Option<tinyboards_db::models::user::User>, TinyBoardsError> {
+) -> Result<Option<User>, TinyBoardsError> {
+    Ok(get_auth_from_header_opt(pool, master_key, auth)
+        .await?
+        .map(|(user, _)| user))
+}
+
+/// Like [`get_user_from_header_opt`], but also returns the scope when the
+/// header carries a personal access token (`Bearer tbp_...`). Access tokens
+/// from a login have no scope and aren't limited.
+pub async fn get_auth_from_header_opt(
+    pool: &DbPool,
+    master_key: &Secret,
+    auth: Option<&str>,
+) -> Result<Option<(User, Option<ApiTokenScope>)>, TinyBoardsError> {
     let auth = match auth {
         Some(a) if !a.is_empty() => a,
         _ => return Ok(None),
@@ -32,6 +48,11 @@ pub async fn get_user_from_header_opt(
 
     let token = &auth[7..];
 
+    if token.starts_with(PERSONAL_TOKEN_PREFIX) {
+        let (user, scope) = get_user_from_personal_token(pool, token).await?;
+        return Ok(Some((user, Some(scope))));
+    }
+
     let claims = validate_access_token(token, &master_key.jwt_secret)
         .map_err(|_| TinyBoardsError::from_message(401, "Invalid or expired token"))?;
 
@@ -39,7 +60,7 @@ pub async fn get_user_from_header_opt(
 
     // Load user from database
     let conn = &mut get_conn(pool).await?;
-    let user: tinyboards_db::models::user::User = users::table
+    let user: User = users::table
         .find(user_uuid)
         .first(conn)
         .await
@@ -57,7 +78,53 @@ pub async fn get_user_from_header_opt(
         }
     });
 
-    Ok(Some(user))
+    Ok(Some((user, None)))
+}
+
+/// Look up the owner of a personal access token and record that the token
+/// was used. Below the admin scope the user is loaded without admin powers,
+/// so every admin check in the API treats the request as a regular user's.
+async fn get_user_from_personal_token(
+    pool: &DbPool,
+    token: &str,
+) -> Result<(User, ApiTokenScope), TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    let (api_token, mut user): (ApiToken, User) = api_tokens::table
+        .inner_join(users::table)
+        .filter(api_tokens::token_hash.eq(hash_personal_token(token)))
+        .filter(
+            api_tokens::expires_at
+                .is_null()
+                .or(api_tokens::expires_at.gt(Utc::now())),
+        )
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+        .ok_or_else(|| TinyBoardsError::from_message(401, "Invalid or expired token"))?;
+
+    let scope = ApiTokenScope::parse(&api_token.scope).unwrap_or(ApiTokenScope::Read);
+    if scope < ApiTokenScope::Admin {
+        user.is_admin = false;
+        user.admin_level = 0;
+    }
+
+    // Update last-used timestamps (fire and forget)
+    let pool_clone = pool.clone();
+    tokio::spawn(async move {
+        if let Ok(conn) = &mut get_conn(&pool_clone).await {
+            let _ = diesel::update(api_tokens::table.find(api_token.id))
+                .set(api_tokens::last_used_at.eq(Utc::now()))
+                .execute(conn)
+                .await;
+            let _ = diesel::update(users::table.find(api_token.user_id))
+                .set(users::updated_at.eq(Utc::now()))
+                .execute(conn)
+                .await;
+        }
+    });
+
+    Ok((user, scope))
 }
 
 /// Checks the password length
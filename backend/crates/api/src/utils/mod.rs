@@ -6,4 +6,5 @@ pub mod site_metadata;
 pub mod request;
 pub mod files;
 pub mod emoji;
-pub mod url_builder;
\ No newline at end of file
+pub mod url_builder;
+pub mod rate_limit;
\ No newline at end of file
@@ -0,0 +1,79 @@
+use async_graphql::{Context, ErrorExtensions};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tinyboards_db::{
+    models::config::RateLimit,
+    schema::rate_limits,
+    utils::{get_conn, DbPool},
+};
+use tinyboards_utils::{
+    rate_limit::{RateLimitCell, RateLimitConfig, RateLimitedGuard},
+    settings::SETTINGS,
+    IpAddr, TinyBoardsError,
+};
+
+use crate::LoggedInUser;
+
+/// Load the active limits from the `rate_limits` row, falling back to the
+/// config file when the site hasn't been set up yet.
+pub async fn load_rate_limit_config(pool: &DbPool) -> Result<RateLimitConfig, TinyBoardsError> {
+    let conn = &mut get_conn(pool).await?;
+    let row: Option<RateLimit> = rate_limits::table.first(conn).await.optional()?;
+
+    Ok(match row {
+        Some(r) => RateLimitConfig {
+            message: r.message,
+            message_per_second: r.message_per_second,
+            post: r.post,
+            post_per_second: r.post_per_second,
+            register: r.register,
+            register_per_second: r.register_per_second,
+            login: r.login,
+            login_per_second: r.login_per_second,
+            image: r.image,
+            image_per_second: r.image_per_second,
+            comment: r.comment,
+            comment_per_second: r.comment_per_second,
+            search: r.search,
+            search_per_second: r.search_per_second,
+            magic_link: r.magic_link,
+            magic_link_per_second: r.magic_link_per_second,
+        },
+        None => SETTINGS
+            .rate_limit
+            .as_ref()
+            .map(RateLimitConfig::from)
+            .unwrap_or_else(|| RateLimitConfig::builder().build()),
+    })
+}
+
+/// Apply a rate limit bucket to the current GraphQL request, keyed by the
+/// client IP and the logged-in user. Schemas built without a limiter (e.g. in
+/// tests) are not limited.
+pub(crate) async fn check_rate_limit(
+    ctx: &Context<'_>,
+    bucket: fn(&RateLimitCell) -> RateLimitedGuard,
+) -> async_graphql::Result<()> {
+    let cell = match ctx.data_opt::<RateLimitCell>() {
+        Some(cell) => cell,
+        None => return Ok(()),
+    };
+
+    let ip = ctx
+        .data_opt::<IpAddr>()
+        .cloned()
+        .unwrap_or_else(|| IpAddr("unknown".to_string()));
+    let user_id = ctx
+        .data_opt::<LoggedInUser>()
+        .and_then(|u| u.inner())
+        .map(|u| u.id);
+
+    match bucket(cell).check_request(&ip, user_id).await {
+        Ok(()) => Ok(()),
+        Err(e @ TinyBoardsError::RateLimited(retry_after)) => Err(e.extend_with(|_, ext| {
+            ext.set("code", "RATE_LIMITED");
+            ext.set("retryAfter", retry_after);
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -33,3 +33,38 @@ impl Claims {
         }
     }
 }
+
+/// `purpose` of an [`MfaPendingClaims`] token
+pub const MFA_PENDING_PURPOSE: &str = "mfa_pending";
+
+/// JWT claims for the token issued after a correct password on an account
+/// with two-factor authentication. It only lets the holder submit a code
+/// (or enroll, where 2FA is required); it's never accepted as an access token
+/// since it has no `role`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaPendingClaims {
+    /// User ID (UUID)
+    pub sub: Uuid,
+    /// Token ID, which wrong codes are counted against
+    pub jti: Uuid,
+    /// Always [`MFA_PENDING_PURPOSE`]
+    pub purpose: String,
+    /// Issued at (UNIX timestamp)
+    pub iat: i64,
+    /// Expiration (UNIX timestamp)
+    pub exp: i64,
+}
+
+impl MfaPendingClaims {
+    /// MFA pending tokens are valid for 5 minutes.
+    pub fn new(user_id: Uuid) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        MfaPendingClaims {
+            sub: user_id,
+            jti: Uuid::new_v4(),
+            purpose: MFA_PENDING_PURPOSE.to_string(),
+            iat: now,
+            exp: now + 300, // 5 minutes
+        }
+    }
+}
@@ -13,6 +13,13 @@ pub const REFRESH_COOKIE_NAME: &str = "tb_refresh";
 /// browser from sending the cookie to the BFF proxy.
 pub const REFRESH_COOKIE_PATH: &str = "/";
 
+/// Cookie tying an in-flight single sign-on login to the browser that
+/// started it, so a callback URL can't be replayed in someone else's browser.
+pub const OIDC_STATE_COOKIE_NAME: &str = "tb_oidc_state";
+
+/// Path for the single sign-on state cookie; only the callback needs it.
+pub const OIDC_STATE_COOKIE_PATH: &str = "/api/v2/auth/oidc";
+
 /// Access token cookie lifetime in seconds (7 days).
 ///
 /// The JWT inside the cookie has a 15-minute `exp` claim. This longer cookie
@@ -85,6 +92,30 @@ pub fn clear_refresh_cookie() -> Cookie<'static> {
         .finish()
 }
 
+/// Build the single sign-on state cookie.
+///
+/// httpOnly, SameSite=Lax so it's sent on the provider's redirect back.
+pub fn build_oidc_state_cookie(state: &str, max_age_seconds: i64) -> Cookie<'static> {
+    Cookie::build(OIDC_STATE_COOKIE_NAME, state.to_string())
+        .http_only(true)
+        .secure(use_secure_cookies())
+        .same_site(SameSite::Lax)
+        .path(OIDC_STATE_COOKIE_PATH)
+        .max_age(actix_web::cookie::time::Duration::seconds(max_age_seconds))
+        .finish()
+}
+
+/// Build a cookie that clears the single sign-on state (maxAge=0).
+pub fn clear_oidc_state_cookie() -> Cookie<'static> {
+    Cookie::build(OIDC_STATE_COOKIE_NAME, "")
+        .http_only(true)
+        .secure(use_secure_cookies())
+        .same_site(SameSite::Lax)
+        .path(OIDC_STATE_COOKIE_PATH)
+        .max_age(actix_web::cookie::time::Duration::ZERO)
+        .finish()
+}
+
 /// Set both auth cookies on an HTTP response.
 pub fn set_auth_cookies(response: &mut HttpResponse, access_token: &str, refresh_token: &str) {
     response.add_cookie(&build_access_cookie(access_token)).ok();
@@ -67,6 +67,24 @@ pub enum AuthError {
     #[error("Already verified")]
     AlreadyVerified,
 
+    #[error("Invalid email address")]
+    InvalidEmail,
+
+    #[error("That is already your email address")]
+    EmailUnchanged,
+
+    #[error("Invalid or expired email change link")]
+    InvalidEmailChangeToken,
+
+    #[error("No email change is pending")]
+    NoPendingEmailChange,
+
+    #[error("Login links are disabled on this site")]
+    MagicLinkDisabled,
+
+    #[error("Invalid or expired login link")]
+    InvalidMagicLink,
+
     #[error("Already logged in")]
     AlreadyLoggedIn,
 
@@ -76,6 +94,75 @@ pub enum AuthError {
     #[error("Incorrect current password")]
     IncorrectPassword,
 
+    #[error("Invalid two-factor code")]
+    InvalidTwoFactorCode,
+
+    #[error("Two-factor login expired, please log in again")]
+    InvalidMfaToken,
+
+    #[error("Two-factor authentication is already enabled")]
+    TwoFactorAlreadyEnabled,
+
+    #[error("Two-factor authentication is not enabled")]
+    TwoFactorNotEnabled,
+
+    #[error("Two-factor authentication is required for this account")]
+    TwoFactorRequired,
+
+    #[error("Session not found")]
+    SessionNotFound,
+
+    #[error("Session name must be at most 64 characters")]
+    InvalidSessionName,
+
+    #[error("API token not found")]
+    ApiTokenNotFound,
+
+    #[error("Token name must be 1-64 characters")]
+    InvalidApiTokenName,
+
+    #[error("Token expiry must be between 1 and 365 days")]
+    InvalidApiTokenExpiry,
+
+    #[error("You can have at most {0} API tokens")]
+    TooManyApiTokens(i64),
+
+    #[error("Passkey not found")]
+    PasskeyNotFound,
+
+    #[error("Passkey name must be 1-64 characters")]
+    InvalidPasskeyName,
+
+    #[error("You can have at most {0} passkeys")]
+    TooManyPasskeys(i64),
+
+    #[error("This passkey is already registered")]
+    PasskeyAlreadyRegistered,
+
+    #[error("Passkey verification failed: {0}")]
+    PasskeyFailed(String),
+
+    #[error("Insufficient admin permissions")]
+    InsufficientPermissions,
+
+    #[error("Please complete the captcha")]
+    CaptchaRequired,
+
+    #[error("Captcha expired or incorrect, please try again")]
+    InvalidCaptcha,
+
+    #[error("Unknown single sign-on provider")]
+    SsoProviderNotFound,
+
+    #[error("Single sign-on request expired, please try again")]
+    InvalidSsoState,
+
+    #[error("No account is linked to this login")]
+    SsoAccountNotLinked,
+
+    #[error("Single sign-on failed: {0}")]
+    SsoFailed(String),
+
     #[error("Password hashing failed: {0}")]
     HashingFailed(String),
 
@@ -90,6 +177,15 @@ pub enum AuthError {
 
     #[error("Missing or invalid X-Requested-With header")]
     CsrfViolation,
+
+    #[error("Rate limit exceeded. Try again in {0} seconds.")]
+    RateLimited(u64),
+
+    #[error("Too many failed login attempts. Try again in {0} seconds.")]
+    TooManyLoginAttempts(u64),
+
+    #[error("This account is temporarily locked after too many failed logins. Try again in {} minutes.", .0.div_ceil(60))]
+    AccountLocked(u64),
 }
 
 impl AuthError {
@@ -116,14 +212,46 @@ impl AuthError {
             Self::ResetTokenUsed => StatusCode::BAD_REQUEST,
             Self::InvalidVerificationToken => StatusCode::BAD_REQUEST,
             Self::AlreadyVerified => StatusCode::BAD_REQUEST,
+            Self::InvalidEmail => StatusCode::BAD_REQUEST,
+            Self::EmailUnchanged => StatusCode::BAD_REQUEST,
+            Self::InvalidEmailChangeToken => StatusCode::BAD_REQUEST,
+            Self::NoPendingEmailChange => StatusCode::NOT_FOUND,
+            Self::MagicLinkDisabled => StatusCode::FORBIDDEN,
+            Self::InvalidMagicLink => StatusCode::BAD_REQUEST,
             Self::AlreadyLoggedIn => StatusCode::BAD_REQUEST,
             Self::LoginRequired => StatusCode::UNAUTHORIZED,
             Self::IncorrectPassword => StatusCode::UNAUTHORIZED,
+            Self::InvalidTwoFactorCode => StatusCode::UNAUTHORIZED,
+            Self::InvalidMfaToken => StatusCode::UNAUTHORIZED,
+            Self::TwoFactorAlreadyEnabled => StatusCode::BAD_REQUEST,
+            Self::TwoFactorNotEnabled => StatusCode::BAD_REQUEST,
+            Self::TwoFactorRequired => StatusCode::FORBIDDEN,
+            Self::SessionNotFound => StatusCode::NOT_FOUND,
+            Self::InvalidSessionName => StatusCode::BAD_REQUEST,
+            Self::ApiTokenNotFound => StatusCode::NOT_FOUND,
+            Self::InvalidApiTokenName => StatusCode::BAD_REQUEST,
+            Self::InvalidApiTokenExpiry => StatusCode::BAD_REQUEST,
+            Self::TooManyApiTokens(_) => StatusCode::BAD_REQUEST,
+            Self::PasskeyNotFound => StatusCode::NOT_FOUND,
+            Self::InvalidPasskeyName => StatusCode::BAD_REQUEST,
+            Self::TooManyPasskeys(_) => StatusCode::BAD_REQUEST,
+            Self::PasskeyAlreadyRegistered => StatusCode::CONFLICT,
+            Self::PasskeyFailed(_) => StatusCode::UNAUTHORIZED,
+            Self::InsufficientPermissions => StatusCode::FORBIDDEN,
+            Self::CaptchaRequired => StatusCode::BAD_REQUEST,
+            Self::InvalidCaptcha => StatusCode::BAD_REQUEST,
+            Self::SsoProviderNotFound => StatusCode::NOT_FOUND,
+            Self::InvalidSsoState => StatusCode::BAD_REQUEST,
+            Self::SsoAccountNotLinked => StatusCode::FORBIDDEN,
+            Self::SsoFailed(_) => StatusCode::BAD_GATEWAY,
             Self::HashingFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::TokenGenerationFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::CsrfViolation => StatusCode::FORBIDDEN,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::TooManyLoginAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::AccountLocked(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 }
@@ -141,7 +269,14 @@ impl actix_web::error::ResponseError for AuthError {
 
     fn error_response(&self) -> HttpResponse {
         let status = self.status_code();
-        HttpResponse::build(status).json(ErrorResponse {
+        let mut response = HttpResponse::build(status);
+        if let Self::RateLimited(retry_after)
+        | Self::TooManyLoginAttempts(retry_after)
+        | Self::AccountLocked(retry_after) = self
+        {
+            response.insert_header(("Retry-After", retry_after.to_string()));
+        }
+        response.json(ErrorResponse {
             error: self.to_string(),
             error_code: status.as_u16(),
         })
@@ -5,15 +5,24 @@ use futures::future::{ok, LocalBoxFuture, Ready};
 use regex::Regex;
 use std::rc::Rc;
 use std::task::{Context, Poll};
+use tinyboards_utils::rate_limit::{RateLimitCell, RateLimitedGuard};
+use tinyboards_utils::settings::structs::OidcProviderConfig;
+use tinyboards_utils::utils::get_ip;
+use tinyboards_utils::TinyBoardsError;
 use uuid::Uuid;
 
+use crate::captcha;
 use crate::cookies;
 use crate::errors::AuthError;
+use crate::lockout;
 use crate::middleware::AuthExt;
+use crate::oidc::{self, OidcProviders};
 use crate::password;
 use crate::session::{self, DbPool};
 use crate::tokens;
+use crate::totp;
 use crate::types::*;
+use crate::webauthn::{self, CredentialDescriptor};
 
 // ============================================================
 // CSRF Guard — validates X-Requested-With: XMLHttpRequest
@@ -81,6 +90,88 @@ where
     }
 }
 
+// ============================================================
+// Rate limiting
+// ============================================================
+
+/// Apply a rate limit bucket to the caller's IP. Apps that don't register a
+/// `RateLimitCell` (such as the integration tests) aren't limited.
+async fn check_rate_limit(
+    req: &HttpRequest,
+    bucket: fn(&RateLimitCell) -> RateLimitedGuard,
+) -> Result<(), AuthError> {
+    let cell = match req.app_data::<web::Data<RateLimitCell>>() {
+        Some(cell) => cell,
+        None => return Ok(()),
+    };
+
+    let ip = get_ip(&req.connection_info());
+    bucket(cell).check_request(&ip, None).await.map_err(|e| match e {
+        TinyBoardsError::RateLimited(retry_after) => AuthError::RateLimited(retry_after),
+        other => AuthError::InternalError(other.to_string()),
+    })
+}
+
+// ============================================================
+// Captcha
+// ============================================================
+
+/// Issue a proof-of-work challenge if the site has captcha enabled.
+pub async fn captcha_challenge(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let settings = session::get_captcha_settings(&pool).await?;
+    if !settings.captcha_enabled {
+        return Ok(HttpResponse::Ok().json(CaptchaResponse {
+            enabled: false,
+            challenge: None,
+        }));
+    }
+
+    // Each challenge is a database row, so don't hand them out unmetered.
+    // The login bucket is used because the register one is only a few per hour.
+    check_rate_limit(&req, RateLimitCell::login).await?;
+
+    let salt = captcha::generate_salt();
+    let difficulty = captcha::difficulty_bits(&settings.captcha_difficulty);
+    let row = session::create_captcha_challenge(
+        &pool,
+        &salt,
+        difficulty,
+        captcha::CHALLENGE_TTL_MINUTES,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CaptchaResponse {
+        enabled: true,
+        challenge: Some(CaptchaChallenge {
+            id: row.id,
+            algorithm: "sha256",
+            salt: row.salt,
+            difficulty: row.difficulty,
+            expires_at: row.expires_at.and_utc().to_rfc3339(),
+        }),
+    }))
+}
+
+/// Verify and consume a captcha solution when the site requires one.
+async fn check_captcha(pool: &DbPool, solution: Option<&CaptchaSolution>) -> Result<(), AuthError> {
+    if !session::get_captcha_settings(pool).await?.captcha_enabled {
+        return Ok(());
+    }
+
+    let solution = solution.ok_or(AuthError::CaptchaRequired)?;
+    let challenge = session::take_captcha_challenge(pool, solution.id)
+        .await?
+        .ok_or(AuthError::InvalidCaptcha)?;
+
+    if !captcha::verify_solution(&challenge.salt, &solution.nonce, challenge.difficulty) {
+        return Err(AuthError::InvalidCaptcha);
+    }
+    Ok(())
+}
+
 // ============================================================
 // Login
 // ============================================================
@@ -90,22 +181,37 @@ pub async fn login(
     req: HttpRequest,
     body: web::Json<LoginRequest>,
 ) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+
+    // Back off clients that keep failing to log in, and ask them for a
+    // captcha once they've failed a few times
+    let ip = get_ip(&req.connection_info()).0;
+    if check_ip_backoff(&pool, &ip).await? >= lockout::CAPTCHA_AFTER_FAILURES {
+        check_captcha(&pool, body.captcha.as_ref()).await?;
+    }
+
     // Look up user by email or username
-    let user = if body.username_or_email.contains('@') {
-        session::get_user_by_email(&pool, &body.username_or_email).await?
+    let lookup = if body.username_or_email.contains('@') {
+        session::get_user_by_email(&pool, &body.username_or_email).await
     } else {
-        session::get_user_by_name(&pool, &body.username_or_email).await?
+        session::get_user_by_name(&pool, &body.username_or_email).await
+    };
+    let user = match lookup {
+        // Deleted accounts look the same as unknown ones
+        Ok(user) if user.deleted_at.is_none() => user,
+        Ok(_) | Err(AuthError::InvalidCredentials) => {
+            return Err(failed_login(&pool, &ip, &body.username_or_email, None).await?);
+        }
+        Err(e) => return Err(e),
     };
 
-    // Check if account is deleted
-    if user.deleted_at.is_some() {
-        return Err(AuthError::InvalidCredentials);
-    }
+    // Locked accounts don't get their password checked at all
+    check_account_lock(&pool, &ip, &body.username_or_email, &user).await?;
 
     // Verify password
     let password_valid = password::verify_password(&body.password, &user.passhash)?;
     if !password_valid {
-        return Err(AuthError::InvalidCredentials);
+        return Err(failed_login(&pool, &ip, &body.username_or_email, Some(&user)).await?);
     }
 
     // Check if banned
@@ -121,8 +227,152 @@ pub async fn login(
         }
     }
 
-    // Create session and tokens
-    let jwt_secret = session::get_jwt_secret(&pool).await?;
+    // Accounts with two-factor authentication (or required to enroll) get a
+    // short-lived token for the second step instead of a session. Their run
+    // of failures is only cleared once they pass it.
+    if let Some(step) = second_factor_step(&pool, &user).await? {
+        return Ok(HttpResponse::Ok().json(step));
+    }
+    session::clear_failed_logins(&pool, user.id).await?;
+
+    let (access_token, refresh_token) = start_session(&pool, &req, &user).await?;
+
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: None,
+        user: Some(UserInfo {
+            id: user.id,
+            name: user.name,
+            is_admin: user.is_admin,
+            admin_level: user.admin_level,
+        }),
+    });
+
+    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
+
+    Ok(response)
+}
+
+/// Refuse clients that have to back off after failed logins, returning how
+/// many times they've failed recently.
+async fn check_ip_backoff(pool: &DbPool, ip: &str) -> Result<i64, AuthError> {
+    let now = chrono::Utc::now().naive_utc();
+    let ip_failures = session::get_ip_login_failures(pool, ip, lockout::IP_WINDOW_MINUTES).await?;
+    if let Some(retry_after) = lockout::ip_retry_after(ip_failures.failures, ip_failures.last_failed_at, now) {
+        return Err(AuthError::TooManyLoginAttempts(retry_after));
+    }
+    Ok(ip_failures.failures)
+}
+
+/// Refuse a locked account, counting the attempt against the IP.
+async fn check_account_lock(
+    pool: &DbPool,
+    ip: &str,
+    username_or_email: &str,
+    user: &AuthUser,
+) -> Result<(), AuthError> {
+    let locked_until = session::get_account_lockout(pool, user.id)
+        .await?
+        .and_then(|lockout| lockout.locked_until);
+    if let Some(retry_after) = lockout::locked_for(locked_until, chrono::Utc::now().naive_utc()) {
+        session::record_login_failure(pool, ip, &attempted_name(username_or_email), Some(user.id)).await?;
+        return Err(AuthError::AccountLocked(retry_after));
+    }
+    Ok(())
+}
+
+/// Longest attempted username kept in `login_failures`
+const MAX_ATTEMPTED_NAME_CHARS: usize = 100;
+
+fn attempted_name(username_or_email: &str) -> String {
+    username_or_email.trim().chars().take(MAX_ATTEMPTED_NAME_CHARS).collect()
+}
+
+/// Record a wrong password or code (or unknown account) and return the error
+/// to show: `InvalidCredentials`, or `AccountLocked` if this failure locked
+/// the account.
+async fn failed_login(
+    pool: &DbPool,
+    ip: &str,
+    username_or_email: &str,
+    user: Option<&AuthUser>,
+) -> Result<AuthError, AuthError> {
+    session::record_login_failure(pool, ip, &attempted_name(username_or_email), user.map(|u| u.id)).await?;
+
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(AuthError::InvalidCredentials),
+    };
+    let failed_attempts = session::increment_failed_logins(pool, user.id).await?;
+    let minutes = match lockout::lockout_minutes(failed_attempts) {
+        Some(minutes) => minutes,
+        None => return Ok(AuthError::InvalidCredentials),
+    };
+
+    session::lock_account(pool, user.id, minutes).await?;
+    tracing::warn!(
+        "Locked account {} for {} minutes after {} failed logins (latest from {})",
+        user.name, minutes, failed_attempts, ip
+    );
+    // Tell the owner about the first lock in a run, not every extension of it
+    if failed_attempts == lockout::ACCOUNT_LOCKOUT_THRESHOLD {
+        notify_account_locked(pool, user, minutes).await;
+    }
+
+    Ok(AuthError::AccountLocked((minutes * 60) as u64))
+}
+
+/// Let an account's owner know it was locked, by notification and by email
+/// if SMTP is configured. Failures are logged rather than returned, since
+/// the lock itself has already happened.
+async fn notify_account_locked(pool: &DbPool, user: &AuthUser, minutes: i64) {
+    let message = format!(
+        "Your account was locked for {} minutes after {} failed login attempts. \
+         If this wasn't you, consider changing your password and enabling two-factor authentication.",
+        minutes,
+        lockout::ACCOUNT_LOCKOUT_THRESHOLD
+    );
+    if let Err(e) = session::create_system_notification(pool, user.id, &message).await {
+        tracing::error!("Failed to create lockout notification: {}", e);
+    }
+
+    let settings = &tinyboards_utils::settings::SETTINGS;
+    let email = match (&settings.email, &user.email) {
+        (Some(_), Some(email)) => email,
+        _ => return,
+    };
+    let site_name = settings.setup.as_ref()
+        .map(|s| s.site_name.as_str())
+        .unwrap_or(&settings.hostname);
+    let reset_url = format!("{}/forgot-password", settings.get_protocol_and_hostname());
+    let html = format!(
+        "<div style=\"font-family:sans-serif;max-width:480px;margin:0 auto\">\
+         <h2>Account Locked</h2>\
+         <p>Hi {},</p>\
+         <p>Someone failed to log in to your account on {} {} times, so we've locked it for {} minutes.</p>\
+         <p>If this was you, you can try again once the lock expires. If it wasn't, we recommend <a href=\"{}\">resetting your password</a> and enabling two-factor authentication.</p>\
+         </div>",
+        user.name, site_name, lockout::ACCOUNT_LOCKOUT_THRESHOLD, minutes, reset_url
+    );
+    if let Err(e) = tinyboards_utils::email::send_email(
+        &format!("Account locked — {}", site_name),
+        email,
+        &user.name,
+        &html,
+        settings,
+    ) {
+        tracing::error!("Failed to send account lockout email: {:?}", e);
+    }
+}
+
+/// Create a session for a user who has passed every login check and return
+/// its access and refresh tokens.
+async fn start_session(
+    pool: &DbPool,
+    req: &HttpRequest,
+    user: &AuthUser,
+) -> Result<(String, String), AuthError> {
+    let jwt_secret = session::get_jwt_secret(pool).await?;
     let role = UserRole::from_admin_fields(user.is_admin, user.admin_level);
 
     let access_token = tokens::create_access_token(user.id, role, &jwt_secret)?;
@@ -140,7 +390,7 @@ pub async fn login(
         .map(|s| s.to_string());
 
     session::create_session(
-        &pool,
+        pool,
         user.id,
         &refresh_hash,
         user_agent.as_deref(),
@@ -148,20 +398,37 @@ pub async fn login(
     )
     .await?;
 
-    let mut response = HttpResponse::Ok().json(AuthResponse {
-        success: true,
-        message: None,
-        user: Some(UserInfo {
-            id: user.id,
-            name: user.name,
-            is_admin: user.is_admin,
-            admin_level: user.admin_level,
-        }),
-    });
+    Ok((access_token, refresh_token))
+}
 
-    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
+/// The second login step for accounts with two-factor authentication (an
+/// authenticator app or a passkey), or required to set it up. `None` if the
+/// user can be signed in straight away.
+async fn second_factor_step(pool: &DbPool, user: &AuthUser) -> Result<Option<MfaRequiredResponse>, AuthError> {
+    let totp_enabled = session::get_totp(pool, user.id)
+        .await?
+        .is_some_and(|totp| totp.is_enabled());
+    let passkey = session::count_passkeys(pool, user.id).await? > 0;
+    if !totp_enabled && !passkey && !session::is_two_factor_required(pool, user).await? {
+        return Ok(None);
+    }
 
-    Ok(response)
+    let jwt_secret = session::get_jwt_secret(pool).await?;
+    let message = match (totp_enabled, passkey) {
+        (true, true) => "Enter the code from your authenticator app, or use your passkey.",
+        (true, false) => "Enter the code from your authenticator app.",
+        (false, true) => "Use your passkey to finish logging in.",
+        (false, false) => "Two-factor authentication is required for this account. Set it up to continue.",
+    };
+
+    Ok(Some(MfaRequiredResponse {
+        success: false,
+        mfa_required: true,
+        enrollment_required: !totp_enabled && !passkey,
+        passkey,
+        mfa_token: tokens::create_mfa_token(user.id, &jwt_secret)?,
+        message: message.to_string(),
+    }))
 }
 
 // ============================================================
@@ -173,6 +440,8 @@ pub async fn register(
     req: HttpRequest,
     body: web::Json<RegisterRequest>,
 ) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::register).await?;
+
     // Get site registration mode
     let site_info = session::get_registration_mode(&pool).await?;
     let reg_mode = site_info.registration_mode.as_str();
@@ -204,6 +473,9 @@ pub async fn register(
     // Validate password length
     password::validate_password_length(&body.password)?;
 
+    // Checked after the cheap validation so a typo doesn't burn the challenge
+    check_captcha(&pool, body.captcha.as_ref()).await?;
+
     // If invite mode, validate and consume invite
     if reg_mode == "invite_only" {
         if let Some(ref code) = body.invite_code {
@@ -293,235 +565,322 @@ pub async fn register(
 }
 
 // ============================================================
-// Logout
+// Single sign-on (OpenID Connect)
 // ============================================================
 
-pub async fn logout(
-    pool: web::Data<DbPool>,
-    req: HttpRequest,
-) -> Result<HttpResponse, AuthError> {
-    let auth_user = req.require_auth()?;
-
-    // Find the session to delete by checking the refresh token
-    if let Some(cookie) = req.cookie(cookies::REFRESH_COOKIE_NAME) {
-        let raw_token = cookie.value();
-        let sessions = session::get_active_sessions(&pool, auth_user.id).await?;
-
-        for sess in &sessions {
-            if tokens::verify_refresh_token(raw_token, &sess.refresh_token_hash) {
-                session::delete_session(&pool, sess.id, auth_user.id).await?;
-                break;
-            }
-        }
-    }
-
-    let mut response = HttpResponse::Ok().json(AuthResponse {
-        success: true,
-        message: Some("Logged out successfully".to_string()),
-        user: None,
-    });
+/// List the configured single sign-on providers for the login page.
+pub async fn oidc_providers(req: HttpRequest) -> HttpResponse {
+    let providers = req
+        .app_data::<web::Data<OidcProviders>>()
+        .map(|providers| {
+            providers
+                .list()
+                .iter()
+                .map(|p| OidcProviderInfo {
+                    name: p.name.clone(),
+                    display_name: if p.display_name.is_empty() { p.name.clone() } else { p.display_name.clone() },
+                    authorize_url: format!("/api/v2/auth/oidc/{}/authorize", p.name),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
 
-    cookies::clear_auth_cookies(&mut response);
-    Ok(response)
+    HttpResponse::Ok().json(OidcProvidersResponse { providers })
 }
 
-// ============================================================
-// Logout all devices
-// ============================================================
-
-pub async fn logout_all(
+/// Send the browser to the provider to log in. Errors send it back to the
+/// login page instead, since this is a navigation rather than an API call.
+pub async fn oidc_authorize(
     pool: web::Data<DbPool>,
     req: HttpRequest,
-) -> Result<HttpResponse, AuthError> {
-    let auth_user = req.require_auth()?;
-
-    let count = session::delete_all_sessions(&pool, auth_user.id).await?;
+    path: web::Path<String>,
+    query: web::Query<OidcAuthorizeQuery>,
+) -> HttpResponse {
+    match start_oidc_login(&pool, &req, &path, query.redirect.as_deref()).await {
+        Ok(response) => response,
+        Err(e) => sso_error_redirect(&e),
+    }
+}
 
-    let mut response = HttpResponse::Ok().json(AuthResponse {
-        success: true,
-        message: Some(format!("Logged out from {} device(s)", count)),
-        user: None,
-    });
+async fn start_oidc_login(
+    pool: &DbPool,
+    req: &HttpRequest,
+    provider_name: &str,
+    redirect: Option<&str>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(req, RateLimitCell::login).await?;
+    let (providers, provider) = oidc_provider(req, provider_name)?;
+    let metadata = oidc::fetch_metadata(providers.client(), provider).await?;
+
+    let state = oidc::random_token();
+    let nonce = oidc::random_token();
+    let code_verifier = oidc::random_token();
+    session::create_oidc_auth_request(
+        pool,
+        &state,
+        &provider.name,
+        &code_verifier,
+        &nonce,
+        &oidc::safe_redirect(redirect),
+        oidc::AUTH_REQUEST_TTL_MINUTES,
+    )
+    .await?;
 
-    cookies::clear_auth_cookies(&mut response);
+    let url = oidc::authorization_url(
+        &metadata,
+        provider,
+        &providers.redirect_uri(provider),
+        &state,
+        &nonce,
+        &oidc::code_challenge(&code_verifier),
+    )?;
+
+    let mut response = found(&url);
+    response
+        .add_cookie(&cookies::build_oidc_state_cookie(&state, oidc::AUTH_REQUEST_TTL_MINUTES * 60))
+        .ok();
     Ok(response)
 }
 
-// ============================================================
-// Refresh token
-// ============================================================
-
-pub async fn refresh_token(
+/// Where the provider sends the browser back to. Logs the user in (creating
+/// or linking an account if needed) and redirects to the page they started
+/// from, or back to the login page with an `sso_error` code.
+pub async fn oidc_callback(
     pool: web::Data<DbPool>,
     req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<OidcCallbackQuery>,
+) -> HttpResponse {
+    let mut response = match finish_oidc_login(&pool, &req, &path, &query).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!("Single sign-on with {} failed: {}", path.as_str(), e);
+            sso_error_redirect(&e)
+        }
+    };
+    response.add_cookie(&cookies::clear_oidc_state_cookie()).ok();
+    response
+}
+
+async fn finish_oidc_login(
+    pool: &DbPool,
+    req: &HttpRequest,
+    provider_name: &str,
+    query: &OidcCallbackQuery,
 ) -> Result<HttpResponse, AuthError> {
-    // Get the refresh token from the cookie
-    let refresh_cookie = req
-        .cookie(cookies::REFRESH_COOKIE_NAME)
-        .ok_or(AuthError::InvalidRefreshToken)?;
+    let (providers, provider) = oidc_provider(req, provider_name)?;
+    if let Some(ref error) = query.error {
+        return Err(AuthError::SsoFailed(format!("Provider returned {}", error)));
+    }
 
-    let raw_token = refresh_cookie.value();
+    // The state has to match the cookie set when this browser started the
+    // login, and can only be used once
+    let state = query.state.as_deref().ok_or(AuthError::InvalidSsoState)?;
+    let cookie_state = req.cookie(cookies::OIDC_STATE_COOKIE_NAME);
+    if cookie_state.as_ref().map(|c| c.value()) != Some(state) {
+        return Err(AuthError::InvalidSsoState);
+    }
+    let auth_request = session::take_oidc_auth_request(pool, state)
+        .await?
+        .filter(|r| r.provider == provider.name)
+        .ok_or(AuthError::InvalidSsoState)?;
+    let code = query
+        .code
+        .as_deref()
+        .ok_or_else(|| AuthError::SsoFailed("No authorization code".to_string()))?;
+
+    let metadata = oidc::fetch_metadata(providers.client(), provider).await?;
+    let id_token = oidc::exchange_code(
+        providers.client(),
+        &metadata,
+        provider,
+        &providers.redirect_uri(provider),
+        code,
+        &auth_request.code_verifier,
+    )
+    .await?;
+    let claims = oidc::validate_id_token(providers.client(), &metadata, provider, &id_token, &auth_request.nonce).await?;
 
-    // We need to find which session this token belongs to.
-    // Since we don't know the user yet, we try to extract it from the (possibly expired) access token.
-    // If no access token, the refresh still works if we can match the token.
+    let user = resolve_oidc_user(pool, provider, &claims).await?;
+    if user.deleted_at.is_some() {
+        return Err(AuthError::AccountDeleted);
+    }
+    if user.is_banned {
+        return Err(AuthError::AccountBanned);
+    }
+    if !user.is_application_accepted && session::has_pending_application(pool, user.id).await? {
+        return Err(AuthError::ApplicationPending);
+    }
 
-    // First try to get user from access token (even if expired, we just need the sub)
-    let jwt_secret = session::get_jwt_secret(&pool).await?;
+    // The provider stands in for the password, not for the site's own second
+    // factor: hand off to the login page's 2FA step, keeping the token out of
+    // server logs by putting it in the fragment
+    if let Some(step) = second_factor_step(pool, &user).await? {
+        let redirect: String = url::form_urlencoded::byte_serialize(auth_request.redirect_to.as_bytes()).collect();
+        let enroll = if step.enrollment_required { "&enroll=1" } else { "" };
+        let passkey = if step.passkey { "&passkey=1" } else { "" };
+        return Ok(found(&format!("/login?redirect={}#mfa={}{}{}", redirect, step.mfa_token, enroll, passkey)));
+    }
 
-    let user_id: Uuid = if let Some(access_cookie) = req.cookie(cookies::ACCESS_COOKIE_NAME) {
-        // Try to decode without expiry validation to get the user ID
-        let key = jsonwebtoken::DecodingKey::from_secret(jwt_secret.as_bytes());
-        let mut validation = jsonwebtoken::Validation::default();
-        validation.validate_exp = false; // Allow expired tokens for refresh
-        validation.required_spec_claims.clear();
+    let (access_token, refresh_token) = start_session(pool, req, &user).await?;
+    let mut response = found(&auth_request.redirect_to);
+    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
+    Ok(response)
+}
 
-        match jsonwebtoken::decode::<crate::claims::Claims>(access_cookie.value(), &key, &validation) {
-            Ok(token_data) => token_data.claims.sub,
-            Err(_) => return Err(AuthError::InvalidRefreshToken),
-        }
-    } else {
-        return Err(AuthError::InvalidRefreshToken);
-    };
+fn oidc_provider<'a>(
+    req: &'a HttpRequest,
+    name: &str,
+) -> Result<(&'a OidcProviders, &'a OidcProviderConfig), AuthError> {
+    let providers = req
+        .app_data::<web::Data<OidcProviders>>()
+        .ok_or(AuthError::SsoProviderNotFound)?;
+    let provider = providers.get(name).ok_or(AuthError::SsoProviderNotFound)?;
+    Ok((providers.get_ref(), provider))
+}
 
-    // Find matching session
-    let sessions = session::get_active_sessions(&pool, user_id).await?;
-    let matching_session = sessions
-        .iter()
-        .find(|s| tokens::verify_refresh_token(raw_token, &s.refresh_token_hash))
-        .ok_or(AuthError::InvalidRefreshToken)?;
+/// Find the account for a provider identity: the one already linked to it,
+/// else one with the same verified email if the provider is trusted for
+/// that, else a new one if the provider and the registration mode allow it.
+async fn resolve_oidc_user(
+    pool: &DbPool,
+    provider: &OidcProviderConfig,
+    claims: &oidc::IdTokenClaims,
+) -> Result<AuthUser, AuthError> {
+    let email = claims.verified_email();
+
+    let existing = match session::get_user_by_identity(pool, &provider.name, &claims.sub).await? {
+        Some(user) => Some(user),
+        None if provider.link_by_email => match email {
+            Some(email) => session::find_user_by_email(pool, email).await?,
+            None => None,
+        },
+        None => None,
+    };
+    let user = match existing {
+        Some(user) => user,
+        None if provider.auto_provision => provision_oidc_user(pool, provider, claims).await?,
+        None => return Err(AuthError::SsoAccountNotLinked),
+    };
 
-    let session_id = matching_session.id;
+    session::upsert_user_identity(pool, user.id, &provider.name, &claims.sub, email).await?;
+    Ok(user)
+}
 
-    // Generate new token pair
-    let user = session::get_user_by_id(&pool, user_id).await?;
-    let role = UserRole::from_admin_fields(user.is_admin, user.admin_level);
+/// Create an account for a first-time single sign-on user. The site's
+/// registration mode applies as it does to the register form, except that
+/// there's no way to enter an invite code here.
+async fn provision_oidc_user(
+    pool: &DbPool,
+    provider: &OidcProviderConfig,
+    claims: &oidc::IdTokenClaims,
+) -> Result<AuthUser, AuthError> {
+    let site_info = session::get_registration_mode(pool).await?;
+    let requires_application = match site_info.registration_mode.as_str() {
+        "closed" => return Err(AuthError::RegistrationClosed),
+        "invite_only" => return Err(AuthError::InviteRequired),
+        "application_required" => true,
+        _ => false,
+    };
 
-    let new_access_token = tokens::create_access_token(user.id, role, &jwt_secret)?;
-    let new_refresh_token = tokens::generate_refresh_token();
-    let new_refresh_hash = tokens::hash_refresh_token(&new_refresh_token);
+    // The account can't be logged into with a password until the user sets
+    // one through a password reset
+    let passhash = password::hash_password(&tokens::generate_random_token())?;
+    let email = claims.verified_email();
+    let display_name = claims.name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+
+    let mut created = None;
+    for candidate in oidc::username_candidates(&oidc::base_username(claims)) {
+        match session::create_user(pool, &candidate, display_name, email, &passhash, !requires_application).await {
+            Ok(user) => {
+                created = Some(user);
+                break;
+            }
+            Err(AuthError::DuplicateUser(field)) if field == "username" => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let created = created.ok_or_else(|| AuthError::DuplicateUser("username".to_string()))?;
 
-    // Rotate the session
-    session::rotate_session(&pool, session_id, &new_refresh_hash).await?;
+    if email.is_some() {
+        session::set_email_verified(pool, created.id).await?;
+    }
+    if requires_application {
+        let label = if provider.display_name.is_empty() { &provider.name } else { &provider.display_name };
+        session::create_application(pool, created.id, &format!("Signed up with {}.", label)).await?;
+    }
+    tracing::info!("Created account {} for {} login {}", created.name, provider.name, claims.sub);
 
-    let mut response = HttpResponse::Ok().json(AuthResponse {
-        success: true,
-        message: None,
-        user: Some(UserInfo {
-            id: user.id,
-            name: user.name,
-            is_admin: user.is_admin,
-            admin_level: user.admin_level,
-        }),
-    });
+    session::get_user_by_id(pool, created.id).await
+}
 
-    cookies::set_auth_cookies(&mut response, &new_access_token, &new_refresh_token);
+fn found(location: &str) -> HttpResponse {
+    HttpResponse::Found()
+        .insert_header((actix_web::http::header::LOCATION, location))
+        .finish()
+}
 
-    Ok(response)
+/// Send a failed single sign-on back to the login page with a short code
+/// the page turns into a message.
+fn sso_error_redirect(error: &AuthError) -> HttpResponse {
+    let code = match error {
+        AuthError::SsoProviderNotFound => "unknown_provider",
+        AuthError::InvalidSsoState => "expired",
+        AuthError::SsoAccountNotLinked => "not_linked",
+        AuthError::RegistrationClosed | AuthError::InviteRequired => "registration_closed",
+        AuthError::ApplicationPending => "application_pending",
+        AuthError::AccountBanned => "banned",
+        AuthError::AccountDeleted => "deleted",
+        AuthError::DuplicateUser(_) => "email_taken",
+        AuthError::RateLimited(_) => "rate_limited",
+        _ => "failed",
+    };
+    found(&format!("/login?sso_error={}", code))
 }
 
 // ============================================================
-// Change password
+// Magic-link login
 // ============================================================
 
-pub async fn change_password(
+/// An account gets at most one login link per this many seconds, however
+/// many addresses ask for it.
+const MAGIC_LINK_COOLDOWN_SECONDS: i32 = 60;
+
+/// Email a one-time login link, if the site allows passwordless login.
+pub async fn request_magic_link(
     pool: web::Data<DbPool>,
     req: HttpRequest,
-    body: web::Json<ChangePasswordRequest>,
+    body: web::Json<MagicLinkRequest>,
 ) -> Result<HttpResponse, AuthError> {
-    let auth_user = req.require_auth()?;
+    if !session::is_magic_link_enabled(&pool).await? {
+        return Err(AuthError::MagicLinkDisabled);
+    }
+    check_rate_limit(&req, RateLimitCell::magic_link).await?;
 
-    // Validate new password length
-    password::validate_password_length(&body.new_password)?;
+    // Always return success to prevent email enumeration
+    let success_response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("If an account with that email exists, a login link has been sent.".to_string()),
+        user: None,
+    });
 
-    // Get current user data to verify old password
-    let user = session::get_user_by_id(&pool, auth_user.id).await?;
-
-    // Verify current password
-    let current_valid = password::verify_password(&body.current_password, &user.passhash)?;
-    if !current_valid {
-        return Err(AuthError::IncorrectPassword);
-    }
-
-    // Hash new password
-    let new_hash = password::hash_password(&body.new_password)?;
-
-    // Update password in database
-    session::update_user_passhash(&pool, auth_user.id, &new_hash).await?;
-
-    // Invalidate all other sessions (keep current one active via new tokens)
-    session::delete_all_sessions(&pool, auth_user.id).await?;
-
-    // Create a new session for the current device
-    let jwt_secret = session::get_jwt_secret(&pool).await?;
-    let role = UserRole::from_admin_fields(user.is_admin, user.admin_level);
-
-    let access_token = tokens::create_access_token(auth_user.id, role, &jwt_secret)?;
-    let refresh_token = tokens::generate_refresh_token();
-    let refresh_hash = tokens::hash_refresh_token(&refresh_token);
-
-    let user_agent = req
-        .headers()
-        .get("User-Agent")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
-    let ip_address = req
-        .connection_info()
-        .realip_remote_addr()
-        .map(|s| s.to_string());
-
-    session::create_session(
-        &pool,
-        auth_user.id,
-        &refresh_hash,
-        user_agent.as_deref(),
-        ip_address.as_deref(),
-    )
-    .await?;
-
-    let mut response = HttpResponse::Ok().json(AuthResponse {
-        success: true,
-        message: Some("Password changed successfully. All other sessions have been invalidated.".to_string()),
-        user: None,
-    });
-
-    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
-
-    Ok(response)
-}
-
-// ============================================================
-// Request password reset
-// ============================================================
-
-pub async fn request_password_reset(
-    pool: web::Data<DbPool>,
-    body: web::Json<PasswordResetRequest>,
-) -> Result<HttpResponse, AuthError> {
-    // Always return success to prevent email enumeration
-    let success_response = HttpResponse::Ok().json(AuthResponse {
-        success: true,
-        message: Some("If an account with that email exists, a password reset link has been sent.".to_string()),
-        user: None,
-    });
-
-    // Look up user by email
-    let user = match session::find_user_by_email(&pool, &body.email).await? {
-        Some(u) => u,
-        None => return Ok(success_response), // Don't reveal if email exists
+    let user = match session::find_user_by_email(&pool, body.email.trim()).await? {
+        Some(u) if u.deleted_at.is_none() => u,
+        _ => return Ok(success_response),
     };
+    if session::has_recent_magic_link(&pool, user.id, MAGIC_LINK_COOLDOWN_SECONDS).await? {
+        return Ok(success_response);
+    }
 
-    // Generate token and store hash
     let raw_token = tokens::generate_random_token();
     let token_hash = tokens::hash_refresh_token(&raw_token);
+    session::create_magic_link(&pool, user.id, &token_hash).await?;
 
-    session::create_password_reset(&pool, user.id, &token_hash).await?;
-
-    // Send the reset email if SMTP is configured, otherwise log the token
+    // Send the link if SMTP is configured, otherwise log the token
     let settings = &tinyboards_utils::settings::SETTINGS;
     if settings.email.is_some() {
-        let reset_url = format!(
-            "{}/reset-password?token={}",
+        let login_url = format!(
+            "{}/magic-link?token={}",
             settings.get_protocol_and_hostname(),
             raw_token
         );
@@ -530,28 +889,28 @@ pub async fn request_password_reset(
             .unwrap_or(&settings.hostname);
         let html = format!(
             "<div style=\"font-family:sans-serif;max-width:480px;margin:0 auto\">\
-             <h2>Password Reset</h2>\
+             <h2>Your Login Link</h2>\
              <p>Hi {},</p>\
-             <p>We received a request to reset your password on {}. Click the link below to set a new password:</p>\
-             <p><a href=\"{}\" style=\"display:inline-block;padding:10px 20px;background:#6366f1;color:#fff;text-decoration:none;border-radius:6px\">Reset Password</a></p>\
+             <p>Click the link below to log in to {}. It can be used once.</p>\
+             <p><a href=\"{}\" style=\"display:inline-block;padding:10px 20px;background:#6366f1;color:#fff;text-decoration:none;border-radius:6px\">Log In</a></p>\
              <p>Or copy this link into your browser:</p>\
              <p style=\"word-break:break-all;color:#666\">{}</p>\
-             <p style=\"color:#999;font-size:13px\">If you didn't request this, you can ignore this email. The link expires in 24 hours.</p>\
+             <p style=\"color:#999;font-size:13px\">If you didn't request this, you can ignore this email. The link expires in 15 minutes.</p>\
              </div>",
-            user.name, site_name, reset_url, reset_url
+            user.name, site_name, login_url, login_url
         );
         if let Err(e) = tinyboards_utils::email::send_email(
-            &format!("Password Reset — {}", site_name),
-            &body.email,
+            &format!("Log in to {}", site_name),
+            body.email.trim(),
             &user.name,
             &html,
             settings,
         ) {
-            tracing::error!("Failed to send password reset email: {:?}", e);
+            tracing::error!("Failed to send login link email: {:?}", e);
         }
     } else {
         tracing::info!(
-            "Password reset token for user {} (id: {}): {} (no SMTP configured)",
+            "Login link token for user {} (id: {}): {} (no SMTP configured)",
             user.name, user.id, raw_token
         );
     }
@@ -559,187 +918,1543 @@ pub async fn request_password_reset(
     Ok(success_response)
 }
 
-// ============================================================
-// Complete password reset
-// ============================================================
-
-pub async fn complete_password_reset(
+/// Exchange a login link token for a session. The link stands in for the
+/// password only: banned accounts and pending applications are turned away
+/// and accounts with two-factor authentication still need their code.
+pub async fn complete_magic_link(
     pool: web::Data<DbPool>,
-    body: web::Json<PasswordResetComplete>,
+    req: HttpRequest,
+    body: web::Json<MagicLinkComplete>,
 ) -> Result<HttpResponse, AuthError> {
-    // Validate new password
-    password::validate_password_length(&body.new_password)?;
-
-    // Hash the submitted token for comparison
-    let submitted_hash = tokens::hash_refresh_token(&body.token);
-
-    // We need to find which user this token belongs to.
-    // Search all valid (non-expired, non-used) resets.
-    // This is a brute-force approach; in production with many users,
-    // you'd want to include user_id or email in the reset request.
-    let conn = &mut pool.get().await.map_err(|e| {
-        AuthError::DatabaseError(format!("Pool error: {}", e))
-    })?;
-
-    #[derive(diesel::QueryableByName)]
-    struct ResetMatch {
-        #[diesel(sql_type = diesel::sql_types::Uuid)]
-        id: Uuid,
-        #[diesel(sql_type = diesel::sql_types::Uuid)]
-        user_id: Uuid,
+    if !session::is_magic_link_enabled(&pool).await? {
+        return Err(AuthError::MagicLinkDisabled);
     }
+    check_rate_limit(&req, RateLimitCell::login).await?;
 
-    use diesel::sql_query;
-    use diesel::sql_types::Text;
-    use diesel_async::RunQueryDsl;
-
-    let reset_match: ResetMatch = sql_query(
-        "SELECT id, user_id FROM password_resets
-         WHERE reset_token = $1 AND used_at IS NULL AND expires_at > NOW()
-         LIMIT 1"
-    )
-    .bind::<Text, _>(&submitted_hash)
-    .get_result(conn)
-    .await
-    .map_err(|_| AuthError::InvalidResetToken)?;
+    let user_id = session::use_magic_link(&pool, &tokens::hash_refresh_token(&body.token))
+        .await?
+        .ok_or(AuthError::InvalidMagicLink)?;
+    let user = session::get_user_by_id(&pool, user_id).await?;
+    if user.deleted_at.is_some() {
+        return Err(AuthError::AccountDeleted);
+    }
+    if user.is_banned {
+        return Err(AuthError::AccountBanned);
+    }
+    if !user.is_application_accepted && session::has_pending_application(&pool, user.id).await? {
+        return Err(AuthError::ApplicationPending);
+    }
 
-    // Hash new password
-    let new_hash = password::hash_password(&body.new_password)?;
+    if let Some(step) = second_factor_step(&pool, &user).await? {
+        return Ok(HttpResponse::Ok().json(step));
+    }
 
-    // Update user password
-    session::update_user_passhash(&pool, reset_match.user_id, &new_hash).await?;
+    let (access_token, refresh_token) = start_session(&pool, &req, &user).await?;
 
-    // Mark reset token as used
-    session::mark_reset_used(&pool, reset_match.id).await?;
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: None,
+        user: Some(UserInfo {
+            id: user.id,
+            name: user.name,
+            is_admin: user.is_admin,
+            admin_level: user.admin_level,
+        }),
+    });
 
-    // Invalidate all sessions for this user
-    session::delete_all_sessions(&pool, reset_match.user_id).await?;
+    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
 
-    Ok(HttpResponse::Ok().json(AuthResponse {
-        success: true,
-        message: Some("Password has been reset. Please log in with your new password.".to_string()),
-        user: None,
-    }))
+    Ok(response)
 }
 
 // ============================================================
-// Verify email
+// Logout
 // ============================================================
 
-pub async fn verify_email(
+pub async fn logout(
     pool: web::Data<DbPool>,
-    body: web::Json<EmailVerifyRequest>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, AuthError> {
-    let submitted_hash = tokens::hash_refresh_token(&body.token);
+    let auth_user = req.require_auth()?;
 
-    let conn = &mut pool.get().await.map_err(|e| {
-        AuthError::DatabaseError(format!("Pool error: {}", e))
-    })?;
+    // Find the session to delete by checking the refresh token
+    if let Some(cookie) = req.cookie(cookies::REFRESH_COOKIE_NAME) {
+        let raw_token = cookie.value();
+        let sessions = session::get_active_sessions(&pool, auth_user.id).await?;
 
-    #[derive(diesel::QueryableByName)]
-    struct VerifyMatch {
-        #[diesel(sql_type = diesel::sql_types::Uuid)]
-        id: Uuid,
-        #[diesel(sql_type = diesel::sql_types::Uuid)]
-        user_id: Uuid,
+        for sess in &sessions {
+            if tokens::verify_refresh_token(raw_token, &sess.refresh_token_hash) {
+                session::delete_session(&pool, sess.id, auth_user.id).await?;
+                break;
+            }
+        }
     }
 
-    use diesel::sql_query;
-    use diesel::sql_types::Text;
-    use diesel_async::RunQueryDsl;
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Logged out successfully".to_string()),
+        user: None,
+    });
 
-    let verify_match: VerifyMatch = sql_query(
-        "SELECT id, user_id FROM email_verification
-         WHERE verification_code = $1 AND verified_at IS NULL
-         LIMIT 1"
-    )
-    .bind::<Text, _>(&submitted_hash)
-    .get_result(conn)
-    .await
-    .map_err(|_| AuthError::InvalidVerificationToken)?;
+    cookies::clear_auth_cookies(&mut response);
+    Ok(response)
+}
 
-    // Mark verification as complete
-    session::mark_email_verified(&pool, verify_match.id).await?;
+// ============================================================
+// Logout all devices
+// ============================================================
 
-    // Update user's email_verified flag
-    session::set_email_verified(&pool, verify_match.user_id).await?;
+pub async fn logout_all(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
 
-    Ok(HttpResponse::Ok().json(AuthResponse {
+    let count = session::delete_all_sessions(&pool, auth_user.id).await?;
+
+    let mut response = HttpResponse::Ok().json(AuthResponse {
         success: true,
-        message: Some("Email verified successfully.".to_string()),
+        message: Some(format!("Logged out from {} device(s)", count)),
         user: None,
-    }))
+    });
+
+    cookies::clear_auth_cookies(&mut response);
+    Ok(response)
 }
 
 // ============================================================
-// Request email verification
+// Refresh token
 // ============================================================
 
-pub async fn request_email_verification(
+pub async fn refresh_token(
     pool: web::Data<DbPool>,
     req: HttpRequest,
-    body: web::Json<RequestEmailVerification>,
 ) -> Result<HttpResponse, AuthError> {
-    let auth_user = req.require_auth()?;
+    // Get the refresh token from the cookie
+    let refresh_cookie = req
+        .cookie(cookies::REFRESH_COOKIE_NAME)
+        .ok_or(AuthError::InvalidRefreshToken)?;
 
-    // Check if already verified
-    let user = session::get_user_by_id(&pool, auth_user.id).await?;
-    if user.is_email_verified {
-        return Err(AuthError::AlreadyVerified);
-    }
+    let raw_token = refresh_cookie.value();
 
-    // Generate verification token
-    let raw_token = tokens::generate_random_token();
-    let token_hash = tokens::hash_refresh_token(&raw_token);
+    // We need to find which session this token belongs to.
+    // Since we don't know the user yet, we try to extract it from the (possibly expired) access token.
+    // If no access token, the refresh still works if we can match the token.
 
-    session::create_email_verification(&pool, auth_user.id, &body.email, &token_hash).await?;
+    // First try to get user from access token (even if expired, we just need the sub)
+    let jwt_secret = session::get_jwt_secret(&pool).await?;
 
-    // Send the verification email if SMTP is configured, otherwise log the token
-    let settings = &tinyboards_utils::settings::SETTINGS;
-    if settings.email.is_some() {
-        let verify_url = format!(
-            "{}/verify-email?token={}",
-            settings.get_protocol_and_hostname(),
-            raw_token
-        );
-        let site_name = settings.setup.as_ref()
-            .map(|s| s.site_name.as_str())
-            .unwrap_or(&settings.hostname);
-        let html = format!(
-            "<div style=\"font-family:sans-serif;max-width:480px;margin:0 auto\">\
-             <h2>Verify Your Email</h2>\
-             <p>Hi {},</p>\
-             <p>Please verify your email address on {} by clicking the link below:</p>\
-             <p><a href=\"{}\" style=\"display:inline-block;padding:10px 20px;background:#6366f1;color:#fff;text-decoration:none;border-radius:6px\">Verify Email</a></p>\
-             <p>Or copy this link into your browser:</p>\
-             <p style=\"word-break:break-all;color:#666\">{}</p>\
-             <p style=\"color:#999;font-size:13px\">If you didn't create an account, you can ignore this email.</p>\
-             </div>",
-            user.name, site_name, verify_url, verify_url
-        );
-        if let Err(e) = tinyboards_utils::email::send_email(
-            &format!("Verify Your Email — {}", site_name),
-            &body.email,
-            &user.name,
-            &html,
-            settings,
-        ) {
-            tracing::error!("Failed to send verification email: {:?}", e);
+    let user_id: Uuid = if let Some(access_cookie) = req.cookie(cookies::ACCESS_COOKIE_NAME) {
+        // Try to decode without expiry validation to get the user ID
+        let key = jsonwebtoken::DecodingKey::from_secret(jwt_secret.as_bytes());
+        let mut validation = jsonwebtoken::Validation::default();
+        validation.validate_exp = false; // Allow expired tokens for refresh
+        validation.required_spec_claims.clear();
+
+        match jsonwebtoken::decode::<crate::claims::Claims>(access_cookie.value(), &key, &validation) {
+            Ok(token_data) => token_data.claims.sub,
+            Err(_) => return Err(AuthError::InvalidRefreshToken),
         }
     } else {
+        return Err(AuthError::InvalidRefreshToken);
+    };
+
+    // Find matching session
+    let sessions = session::get_active_sessions(&pool, user_id).await?;
+    let matching_session = sessions
+        .iter()
+        .find(|s| tokens::verify_refresh_token(raw_token, &s.refresh_token_hash))
+        .ok_or(AuthError::InvalidRefreshToken)?;
+
+    let session_id = matching_session.id;
+
+    // Generate new token pair
+    let user = session::get_user_by_id(&pool, user_id).await?;
+    let role = UserRole::from_admin_fields(user.is_admin, user.admin_level);
+
+    let new_access_token = tokens::create_access_token(user.id, role, &jwt_secret)?;
+    let new_refresh_token = tokens::generate_refresh_token();
+    let new_refresh_hash = tokens::hash_refresh_token(&new_refresh_token);
+
+    // Rotate the session
+    session::rotate_session(&pool, session_id, &new_refresh_hash).await?;
+
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: None,
+        user: Some(UserInfo {
+            id: user.id,
+            name: user.name,
+            is_admin: user.is_admin,
+            admin_level: user.admin_level,
+        }),
+    });
+
+    cookies::set_auth_cookies(&mut response, &new_access_token, &new_refresh_token);
+
+    Ok(response)
+}
+
+// ============================================================
+// Sessions
+// ============================================================
+
+/// Longest label a user can give a session
+const SESSION_NAME_MAX_CHARS: usize = 64;
+
+/// Admin level that may view and revoke other users' sessions. Matches
+/// `AdminPerms::Users` in the db crate.
+const MANAGE_USERS_ADMIN_LEVEL: i32 = 4;
+
+fn can_manage_users(user: &AuthenticatedUser) -> bool {
+    matches!(user.role, UserRole::Admin(level) if level >= MANAGE_USERS_ADMIN_LEVEL)
+}
+
+/// Hash of the caller's refresh cookie, which identifies their own session.
+fn current_session_hash(req: &HttpRequest) -> Option<String> {
+    req.cookie(cookies::REFRESH_COOKIE_NAME)
+        .map(|cookie| tokens::hash_refresh_token(cookie.value()))
+}
+
+/// List active sessions, newest activity first. Admins can pass `user_id`
+/// to see another user's sessions.
+pub async fn list_sessions(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    query: web::Query<SessionListQuery>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+
+    let user_id = match query.user_id {
+        Some(user_id) if user_id != auth_user.id => {
+            if !can_manage_users(&auth_user) {
+                return Err(AuthError::InsufficientPermissions);
+            }
+            user_id
+        }
+        _ => auth_user.id,
+    };
+
+    let current = current_session_hash(&req);
+    let sessions = session::get_active_sessions(&pool, user_id)
+        .await?
+        .into_iter()
+        .map(|row| SessionInfo::from_row(row, current.as_deref()))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(SessionListResponse { sessions }))
+}
+
+/// Label one of your own sessions.
+pub async fn rename_session(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: web::Json<RenameSessionRequest>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+
+    let name = body.name.as_deref().map(str::trim).filter(|name| !name.is_empty());
+    if name.is_some_and(|name| name.chars().count() > SESSION_NAME_MAX_CHARS) {
+        return Err(AuthError::InvalidSessionName);
+    }
+
+    if !session::rename_session(&pool, path.into_inner(), auth_user.id, name).await? {
+        return Err(AuthError::SessionNotFound);
+    }
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: None,
+        user: None,
+    }))
+}
+
+/// Revoke a single session. Its refresh token stops working immediately; an
+/// access token already issued to it stays valid until it expires (at most
+/// 15 minutes). Admins can revoke other users' sessions.
+pub async fn revoke_session(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+    let session_id = path.into_inner();
+
+    let target = session::get_session(&pool, session_id)
+        .await?
+        .ok_or(AuthError::SessionNotFound)?;
+
+    if target.user_id != auth_user.id {
+        // Don't reveal other users' session IDs to non-admins
+        if !can_manage_users(&auth_user) {
+            return Err(AuthError::SessionNotFound);
+        }
         tracing::info!(
-            "Email verification token for user {} (id: {}): {} (no SMTP configured)",
-            user.name, auth_user.id, raw_token
+            "Admin {} revoked session {} of user {}",
+            auth_user.id,
+            session_id,
+            target.user_id
+        );
+    }
+
+    session::delete_session(&pool, session_id, target.user_id).await?;
+
+    let is_current = current_session_hash(&req).as_deref() == Some(target.refresh_token_hash.as_str());
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Session revoked.".to_string()),
+        user: None,
+    });
+
+    if is_current {
+        cookies::clear_auth_cookies(&mut response);
+    }
+
+    Ok(response)
+}
+
+// ============================================================
+// Personal access tokens
+// ============================================================
+
+/// Most unexpired tokens one user can hold
+const MAX_API_TOKENS: i64 = 25;
+
+/// Longest name a token can be given
+const API_TOKEN_NAME_MAX_CHARS: usize = 64;
+
+/// Longest lifetime a token can be given, in days
+const MAX_API_TOKEN_DAYS: i64 = 365;
+
+/// Characters of a new token kept in the clear so users can tell tokens apart
+/// (`tbp_` plus 8 hex characters)
+const API_TOKEN_PREFIX_CHARS: usize = 12;
+
+/// List personal access tokens. Admins can pass `user_id` to see another
+/// user's tokens.
+///
+/// Token management only accepts a browser session, so a leaked token can't
+/// be used to mint more.
+pub async fn list_api_tokens(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    query: web::Query<ApiTokenListQuery>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+
+    let user_id = match query.user_id {
+        Some(user_id) if user_id != auth_user.id => {
+            if !can_manage_users(&auth_user) {
+                return Err(AuthError::InsufficientPermissions);
+            }
+            user_id
+        }
+        _ => auth_user.id,
+    };
+
+    let tokens = session::get_api_tokens(&pool, user_id)
+        .await?
+        .into_iter()
+        .map(ApiTokenInfo::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(ApiTokenListResponse { tokens }))
+}
+
+/// Create a personal access token. The raw token is only ever returned here.
+pub async fn create_api_token(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<CreateApiTokenRequest>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > API_TOKEN_NAME_MAX_CHARS {
+        return Err(AuthError::InvalidApiTokenName);
+    }
+
+    let expires_in_days = match body.expires_in_days {
+        Some(days) if !(1..=MAX_API_TOKEN_DAYS).contains(&days) => {
+            return Err(AuthError::InvalidApiTokenExpiry);
+        }
+        Some(days) => Some(days as i32),
+        None => None,
+    };
+
+    // A token never grants more than the account has
+    if body.scope == ApiTokenScope::Admin && !auth_user.role.is_admin() {
+        return Err(AuthError::InsufficientPermissions);
+    }
+
+    if session::count_api_tokens(&pool, auth_user.id).await? >= MAX_API_TOKENS {
+        return Err(AuthError::TooManyApiTokens(MAX_API_TOKENS));
+    }
+
+    let token = tokens::generate_personal_token();
+    let row = session::create_api_token(
+        &pool,
+        auth_user.id,
+        name,
+        &tokens::hash_personal_token(&token),
+        &token[..API_TOKEN_PREFIX_CHARS],
+        body.scope,
+        expires_in_days,
+    )
+    .await?;
+
+    tracing::info!(
+        "User {} created {} API token {} with scope {}",
+        auth_user.id,
+        if row.is_bot { "bot" } else { "personal" },
+        row.id,
+        row.scope
+    );
+
+    Ok(HttpResponse::Ok().json(CreatedApiTokenResponse {
+        token,
+        api_token: ApiTokenInfo::from(row),
+    }))
+}
+
+/// Revoke a personal access token; it stops working immediately. Admins can
+/// revoke other users' tokens.
+pub async fn revoke_api_token(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+    let token_id = path.into_inner();
+
+    let target = session::get_api_token(&pool, token_id)
+        .await?
+        .ok_or(AuthError::ApiTokenNotFound)?;
+
+    if target.user_id != auth_user.id {
+        // Don't reveal other users' token IDs to non-admins
+        if !can_manage_users(&auth_user) {
+            return Err(AuthError::ApiTokenNotFound);
+        }
+        tracing::info!(
+            "Admin {} revoked API token {} of user {}",
+            auth_user.id,
+            token_id,
+            target.user_id
         );
     }
 
+    session::delete_api_token(&pool, token_id, target.user_id).await?;
+
     Ok(HttpResponse::Ok().json(AuthResponse {
         success: true,
-        message: Some("Verification email has been sent.".to_string()),
+        message: Some("Token revoked.".to_string()),
         user: None,
     }))
 }
 
+// ============================================================
+// Change password
+// ============================================================
+
+pub async fn change_password(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<ChangePasswordRequest>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+
+    // Validate new password length
+    password::validate_password_length(&body.new_password)?;
+
+    // Get current user data to verify old password
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+
+    // Verify current password
+    let current_valid = password::verify_password(&body.current_password, &user.passhash)?;
+    if !current_valid {
+        return Err(AuthError::IncorrectPassword);
+    }
+
+    // Hash new password
+    let new_hash = password::hash_password(&body.new_password)?;
+
+    // Update password in database
+    session::update_user_passhash(&pool, auth_user.id, &new_hash).await?;
+
+    // Invalidate all other sessions (keep current one active via new tokens)
+    session::delete_all_sessions(&pool, auth_user.id).await?;
+
+    // Create a new session for the current device
+    let jwt_secret = session::get_jwt_secret(&pool).await?;
+    let role = UserRole::from_admin_fields(user.is_admin, user.admin_level);
+
+    let access_token = tokens::create_access_token(auth_user.id, role, &jwt_secret)?;
+    let refresh_token = tokens::generate_refresh_token();
+    let refresh_hash = tokens::hash_refresh_token(&refresh_token);
+
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let ip_address = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|s| s.to_string());
+
+    session::create_session(
+        &pool,
+        auth_user.id,
+        &refresh_hash,
+        user_agent.as_deref(),
+        ip_address.as_deref(),
+    )
+    .await?;
+
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Password changed successfully. All other sessions have been invalidated.".to_string()),
+        user: None,
+    });
+
+    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
+
+    Ok(response)
+}
+
+// ============================================================
+// Request password reset
+// ============================================================
+
+pub async fn request_password_reset(
+    pool: web::Data<DbPool>,
+    body: web::Json<PasswordResetRequest>,
+) -> Result<HttpResponse, AuthError> {
+    // Always return success to prevent email enumeration
+    let success_response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("If an account with that email exists, a password reset link has been sent.".to_string()),
+        user: None,
+    });
+
+    // Look up user by email
+    let user = match session::find_user_by_email(&pool, &body.email).await? {
+        Some(u) => u,
+        None => return Ok(success_response), // Don't reveal if email exists
+    };
+
+    // Generate token and store hash
+    let raw_token = tokens::generate_random_token();
+    let token_hash = tokens::hash_refresh_token(&raw_token);
+
+    session::create_password_reset(&pool, user.id, &token_hash).await?;
+
+    // Send the reset email if SMTP is configured, otherwise log the token
+    let settings = &tinyboards_utils::settings::SETTINGS;
+    if settings.email.is_some() {
+        let reset_url = format!(
+            "{}/reset-password?token={}",
+            settings.get_protocol_and_hostname(),
+            raw_token
+        );
+        let site_name = settings.setup.as_ref()
+            .map(|s| s.site_name.as_str())
+            .unwrap_or(&settings.hostname);
+        let html = format!(
+            "<div style=\"font-family:sans-serif;max-width:480px;margin:0 auto\">\
+             <h2>Password Reset</h2>\
+             <p>Hi {},</p>\
+             <p>We received a request to reset your password on {}. Click the link below to set a new password:</p>\
+             <p><a href=\"{}\" style=\"display:inline-block;padding:10px 20px;background:#6366f1;color:#fff;text-decoration:none;border-radius:6px\">Reset Password</a></p>\
+             <p>Or copy this link into your browser:</p>\
+             <p style=\"word-break:break-all;color:#666\">{}</p>\
+             <p style=\"color:#999;font-size:13px\">If you didn't request this, you can ignore this email. The link expires in 24 hours.</p>\
+             </div>",
+            user.name, site_name, reset_url, reset_url
+        );
+        if let Err(e) = tinyboards_utils::email::send_email(
+            &format!("Password Reset — {}", site_name),
+            &body.email,
+            &user.name,
+            &html,
+            settings,
+        ) {
+            tracing::error!("Failed to send password reset email: {:?}", e);
+        }
+    } else {
+        tracing::info!(
+            "Password reset token for user {} (id: {}): {} (no SMTP configured)",
+            user.name, user.id, raw_token
+        );
+    }
+
+    Ok(success_response)
+}
+
+// ============================================================
+// Complete password reset
+// ============================================================
+
+pub async fn complete_password_reset(
+    pool: web::Data<DbPool>,
+    body: web::Json<PasswordResetComplete>,
+) -> Result<HttpResponse, AuthError> {
+    // Validate new password
+    password::validate_password_length(&body.new_password)?;
+
+    // Hash the submitted token for comparison
+    let submitted_hash = tokens::hash_refresh_token(&body.token);
+
+    // We need to find which user this token belongs to.
+    // Search all valid (non-expired, non-used) resets.
+    // This is a brute-force approach; in production with many users,
+    // you'd want to include user_id or email in the reset request.
+    let conn = &mut pool.get().await.map_err(|e| {
+        AuthError::DatabaseError(format!("Pool error: {}", e))
+    })?;
+
+    #[derive(diesel::QueryableByName)]
+    struct ResetMatch {
+        #[diesel(sql_type = diesel::sql_types::Uuid)]
+        id: Uuid,
+        #[diesel(sql_type = diesel::sql_types::Uuid)]
+        user_id: Uuid,
+    }
+
+    use diesel::sql_query;
+    use diesel::sql_types::Text;
+    use diesel_async::RunQueryDsl;
+
+    let reset_match: ResetMatch = sql_query(
+        "SELECT id, user_id FROM password_resets
+         WHERE reset_token = $1 AND used_at IS NULL AND expires_at > NOW()
+         LIMIT 1"
+    )
+    .bind::<Text, _>(&submitted_hash)
+    .get_result(conn)
+    .await
+    .map_err(|_| AuthError::InvalidResetToken)?;
+
+    // Hash new password
+    let new_hash = password::hash_password(&body.new_password)?;
+
+    // Update user password
+    session::update_user_passhash(&pool, reset_match.user_id, &new_hash).await?;
+
+    // Mark reset token as used
+    session::mark_reset_used(&pool, reset_match.id).await?;
+
+    // Invalidate all sessions for this user
+    session::delete_all_sessions(&pool, reset_match.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Password has been reset. Please log in with your new password.".to_string()),
+        user: None,
+    }))
+}
+
+// ============================================================
+// Verify email
+// ============================================================
+
+pub async fn verify_email(
+    pool: web::Data<DbPool>,
+    body: web::Json<EmailVerifyRequest>,
+) -> Result<HttpResponse, AuthError> {
+    let submitted_hash = tokens::hash_refresh_token(&body.token);
+
+    let conn = &mut pool.get().await.map_err(|e| {
+        AuthError::DatabaseError(format!("Pool error: {}", e))
+    })?;
+
+    #[derive(diesel::QueryableByName)]
+    struct VerifyMatch {
+        #[diesel(sql_type = diesel::sql_types::Uuid)]
+        id: Uuid,
+        #[diesel(sql_type = diesel::sql_types::Uuid)]
+        user_id: Uuid,
+    }
+
+    use diesel::sql_query;
+    use diesel::sql_types::Text;
+    use diesel_async::RunQueryDsl;
+
+    // Only a link for the account's current address counts, so one sent
+    // before an email change can't mark the new address verified
+    let verify_match: VerifyMatch = sql_query(
+        "SELECT ev.id, ev.user_id FROM email_verification ev
+         JOIN users u ON u.id = ev.user_id AND u.email = ev.email
+         WHERE ev.verification_code = $1 AND ev.verified_at IS NULL
+         LIMIT 1"
+    )
+    .bind::<Text, _>(&submitted_hash)
+    .get_result(conn)
+    .await
+    .map_err(|_| AuthError::InvalidVerificationToken)?;
+
+    // Mark verification as complete
+    session::mark_email_verified(&pool, verify_match.id).await?;
+
+    // Update user's email_verified flag
+    session::set_email_verified(&pool, verify_match.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Email verified successfully.".to_string()),
+        user: None,
+    }))
+}
+
+// ============================================================
+// Request email verification
+// ============================================================
+
+pub async fn request_email_verification(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<RequestEmailVerification>,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+
+    // Check if already verified
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+    if user.is_email_verified {
+        return Err(AuthError::AlreadyVerified);
+    }
+
+    // Generate verification token
+    let raw_token = tokens::generate_random_token();
+    let token_hash = tokens::hash_refresh_token(&raw_token);
+
+    session::create_email_verification(&pool, auth_user.id, &body.email, &token_hash).await?;
+
+    // Send the verification email if SMTP is configured, otherwise log the token
+    let settings = &tinyboards_utils::settings::SETTINGS;
+    if settings.email.is_some() {
+        let verify_url = format!(
+            "{}/verify-email?token={}",
+            settings.get_protocol_and_hostname(),
+            raw_token
+        );
+        let site_name = settings.setup.as_ref()
+            .map(|s| s.site_name.as_str())
+            .unwrap_or(&settings.hostname);
+        let html = format!(
+            "<div style=\"font-family:sans-serif;max-width:480px;margin:0 auto\">\
+             <h2>Verify Your Email</h2>\
+             <p>Hi {},</p>\
+             <p>Please verify your email address on {} by clicking the link below:</p>\
+             <p><a href=\"{}\" style=\"display:inline-block;padding:10px 20px;background:#6366f1;color:#fff;text-decoration:none;border-radius:6px\">Verify Email</a></p>\
+             <p>Or copy this link into your browser:</p>\
+             <p style=\"word-break:break-all;color:#666\">{}</p>\
+             <p style=\"color:#999;font-size:13px\">If you didn't create an account, you can ignore this email.</p>\
+             </div>",
+            user.name, site_name, verify_url, verify_url
+        );
+        if let Err(e) = tinyboards_utils::email::send_email(
+            &format!("Verify Your Email — {}", site_name),
+            &body.email,
+            &user.name,
+            &html,
+            settings,
+        ) {
+            tracing::error!("Failed to send verification email: {:?}", e);
+        }
+    } else {
+        tracing::info!(
+            "Email verification token for user {} (id: {}): {} (no SMTP configured)",
+            user.name, auth_user.id, raw_token
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Verification email has been sent.".to_string()),
+        user: None,
+    }))
+}
+
+// ============================================================
+// Change email
+// ============================================================
+
+/// Longest address RFC 5321 allows through SMTP.
+const EMAIL_MAX_CHARS: usize = 254;
+
+/// Loose sanity check; the confirmation link is the real test of an address.
+fn is_plausible_email(email: &str) -> bool {
+    if email.chars().count() > EMAIL_MAX_CHARS || email.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+pub async fn email_change_status(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+    let pending = session::get_email_change(&pool, auth_user.id).await?;
+    Ok(HttpResponse::Ok().json(EmailChangeStatusResponse {
+        pending: pending.map(PendingEmailChange::from),
+    }))
+}
+
+/// Start changing the account's email address. Nothing changes until the
+/// link sent to the new address is opened; the old address, if any, is told
+/// about the request and gets a link to cancel it.
+pub async fn request_email_change(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<RequestEmailChange>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+    let auth_user = req.require_auth()?;
+
+    let new_email = body.new_email.trim();
+    if !is_plausible_email(new_email) {
+        return Err(AuthError::InvalidEmail);
+    }
+
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+    if !password::verify_password(&body.password, &user.passhash)? {
+        return Err(AuthError::IncorrectPassword);
+    }
+    if user.email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(new_email)) {
+        return Err(AuthError::EmailUnchanged);
+    }
+    if session::find_user_by_email(&pool, new_email).await?.is_some() {
+        return Err(AuthError::DuplicateUser("email address".to_string()));
+    }
+
+    let confirm_token = tokens::generate_random_token();
+    let cancel_token = tokens::generate_random_token();
+    session::create_email_change(
+        &pool,
+        user.id,
+        user.email.as_deref(),
+        new_email,
+        &tokens::hash_refresh_token(&confirm_token),
+        &tokens::hash_refresh_token(&cancel_token),
+    )
+    .await?;
+
+    send_email_change_emails(&user, new_email, &confirm_token, &cancel_token);
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some(format!(
+            "Check {} for a link to confirm the change. Your email address stays the same until then.",
+            new_email
+        )),
+        user: None,
+    }))
+}
+
+/// Send the confirmation link to the new address and the cancel link to the
+/// old one if SMTP is configured, otherwise log the tokens.
+fn send_email_change_emails(user: &AuthUser, new_email: &str, confirm_token: &str, cancel_token: &str) {
+    let settings = &tinyboards_utils::settings::SETTINGS;
+    if settings.email.is_none() {
+        tracing::info!(
+            "Email change tokens for user {} (id: {}): confirm {}, cancel {} (no SMTP configured)",
+            user.name, user.id, confirm_token, cancel_token
+        );
+        return;
+    }
+
+    let site_name = settings.setup.as_ref()
+        .map(|s| s.site_name.as_str())
+        .unwrap_or(&settings.hostname);
+    let confirm_url = format!(
+        "{}/email-change?token={}",
+        settings.get_protocol_and_hostname(),
+        confirm_token
+    );
+    let html = format!(
+        "<div style=\"font-family:sans-serif;max-width:480px;margin:0 auto\">\
+         <h2>Confirm Your New Email</h2>\
+         <p>Hi {},</p>\
+         <p>Please confirm that you want to use this address for your account on {} by clicking the link below:</p>\
+         <p><a href=\"{}\" style=\"display:inline-block;padding:10px 20px;background:#6366f1;color:#fff;text-decoration:none;border-radius:6px\">Confirm Email</a></p>\
+         <p>Or copy this link into your browser:</p>\
+         <p style=\"word-break:break-all;color:#666\">{}</p>\
+         <p style=\"color:#999;font-size:13px\">If you didn't request this, you can ignore this email. The link expires in 24 hours.</p>\
+         </div>",
+        user.name, site_name, confirm_url, confirm_url
+    );
+    if let Err(e) = tinyboards_utils::email::send_email(
+        &format!("Confirm Your New Email — {}", site_name),
+        new_email,
+        &user.name,
+        &html,
+        settings,
+    ) {
+        tracing::error!("Failed to send email change confirmation: {:?}", e);
+    }
+
+    let old_email = match &user.email {
+        Some(email) => email,
+        None => return,
+    };
+    let cancel_url = format!(
+        "{}/email-change?cancel={}",
+        settings.get_protocol_and_hostname(),
+        cancel_token
+    );
+    let html = format!(
+        "<div style=\"font-family:sans-serif;max-width:480px;margin:0 auto\">\
+         <h2>Email Change Requested</h2>\
+         <p>Hi {},</p>\
+         <p>Someone asked to change the email address of your account on {} to {}. It will change once the new address is confirmed.</p>\
+         <p>If this wasn't you, cancel the change and then change your password:</p>\
+         <p><a href=\"{}\" style=\"display:inline-block;padding:10px 20px;background:#dc2626;color:#fff;text-decoration:none;border-radius:6px\">Cancel Change</a></p>\
+         <p>Or copy this link into your browser:</p>\
+         <p style=\"word-break:break-all;color:#666\">{}</p>\
+         </div>",
+        user.name, site_name, new_email, cancel_url, cancel_url
+    );
+    if let Err(e) = tinyboards_utils::email::send_email(
+        &format!("Email Change Requested — {}", site_name),
+        old_email,
+        &user.name,
+        &html,
+        settings,
+    ) {
+        tracing::error!("Failed to send email change notice: {:?}", e);
+    }
+}
+
+/// Apply a pending email change from the link sent to the new address.
+pub async fn confirm_email_change(
+    pool: web::Data<DbPool>,
+    body: web::Json<ConfirmEmailChangeRequest>,
+) -> Result<HttpResponse, AuthError> {
+    let change = session::confirm_email_change(&pool, &tokens::hash_refresh_token(&body.token)).await?;
+    tracing::info!("User {} changed their email address", change.user_id);
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some(format!("Your email address is now {}.", change.new_email)),
+        user: None,
+    }))
+}
+
+/// Cancel a pending email change, either from the link sent to the old
+/// address or, without a token, the logged-in user's own.
+pub async fn cancel_email_change(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<CancelEmailChangeRequest>,
+) -> Result<HttpResponse, AuthError> {
+    match &body.token {
+        Some(token) => {
+            let change = session::cancel_email_change(&pool, &tokens::hash_refresh_token(token)).await?;
+            tracing::info!("Email change for user {} cancelled from the old address", change.user_id);
+        }
+        None => {
+            let auth_user = req.require_auth()?;
+            if !session::delete_email_change(&pool, auth_user.id).await? {
+                return Err(AuthError::NoPendingEmailChange);
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("The email change was cancelled.".to_string()),
+        user: None,
+    }))
+}
+
+// ============================================================
+// Two-factor authentication
+// ============================================================
+
+/// The user a two-factor request acts for: the holder of an MFA token
+/// (enrolling during login) or the signed-in user.
+async fn two_factor_user(
+    pool: &DbPool,
+    req: &HttpRequest,
+    mfa_token: Option<&str>,
+) -> Result<AuthUser, AuthError> {
+    let user_id = match mfa_token {
+        Some(token) => {
+            let jwt_secret = session::get_jwt_secret(pool).await?;
+            tokens::validate_mfa_token(token, &jwt_secret)?
+        }
+        None => req.require_auth()?.id,
+    };
+    session::get_user_by_id(pool, user_id).await
+}
+
+/// Enrolling with an MFA token is only for accounts that have no second
+/// factor yet. An account with a passkey has to use it, or the password
+/// alone would be enough to set up an authenticator app and log in.
+async fn check_login_enrollment(pool: &DbPool, user: &AuthUser, mfa_token: Option<&str>) -> Result<(), AuthError> {
+    if mfa_token.is_some() && session::count_passkeys(pool, user.id).await? > 0 {
+        return Err(AuthError::TwoFactorAlreadyEnabled);
+    }
+    Ok(())
+}
+
+/// Check an authenticator code, recording its time step so it can't be used twice.
+async fn check_totp_code(pool: &DbPool, totp: &TotpRow, code: &str) -> Result<(), AuthError> {
+    let now = chrono::Utc::now().timestamp();
+    let step = totp::verify_code(&totp.secret, code, now, totp.last_used_step)
+        .ok_or(AuthError::InvalidTwoFactorCode)?;
+
+    if !session::record_totp_step(pool, totp.user_id, step).await? {
+        return Err(AuthError::InvalidTwoFactorCode);
+    }
+    Ok(())
+}
+
+/// Check an authenticator code, or failing that, spend a recovery code.
+async fn check_second_factor(pool: &DbPool, totp: &TotpRow, code: &str) -> Result<(), AuthError> {
+    if check_totp_code(pool, totp, code).await.is_ok() {
+        return Ok(());
+    }
+    let code_hash = tokens::hash_refresh_token(&totp::normalize_recovery_code(code));
+    if session::use_recovery_code(pool, totp.user_id, &code_hash).await? {
+        return Ok(());
+    }
+    Err(AuthError::InvalidTwoFactorCode)
+}
+
+/// Generate and store a fresh set of recovery codes, returning them in plain text.
+async fn issue_recovery_codes(pool: &DbPool, user_id: Uuid) -> Result<Vec<String>, AuthError> {
+    let codes = totp::generate_recovery_codes();
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| tokens::hash_refresh_token(&totp::normalize_recovery_code(code)))
+        .collect();
+    session::replace_recovery_codes(pool, user_id, &hashes).await?;
+    Ok(codes)
+}
+
+/// Second login step: exchange the MFA token and a code for a session.
+pub async fn login_two_factor(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<LoginTwoFactorRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+    let ip = get_ip(&req.connection_info()).0;
+    check_ip_backoff(&pool, &ip).await?;
+
+    // A token is only good for a few wrong codes
+    let jwt_secret = session::get_jwt_secret(&pool).await?;
+    let claims = tokens::decode_mfa_token(&body.mfa_token, &jwt_secret)?;
+    if session::get_mfa_token_failures(&pool, claims.jti).await? >= lockout::MFA_TOKEN_MAX_FAILURES {
+        return Err(AuthError::InvalidMfaToken);
+    }
+
+    let user = session::get_user_by_id(&pool, claims.sub).await?;
+    if user.deleted_at.is_some() {
+        return Err(AuthError::InvalidMfaToken);
+    }
+    if user.is_banned {
+        return Err(AuthError::AccountBanned);
+    }
+    check_account_lock(&pool, &ip, &user.name, &user).await?;
+
+    let totp = session::get_totp(&pool, user.id)
+        .await?
+        .filter(|totp| totp.is_enabled())
+        .ok_or(AuthError::TwoFactorNotEnabled)?;
+
+    let checked = match (&body.code, &body.recovery_code) {
+        (Some(code), _) => check_totp_code(&pool, &totp, code).await,
+        (None, Some(recovery_code)) => check_second_factor(&pool, &totp, recovery_code).await,
+        (None, None) => return Err(AuthError::InvalidTwoFactorCode),
+    };
+    match checked {
+        Ok(()) => session::clear_failed_logins(&pool, user.id).await?,
+        // Wrong codes count against the token and towards locking the account
+        Err(AuthError::InvalidTwoFactorCode) => {
+            session::record_mfa_token_failure(&pool, claims.jti, user.id, claims.exp).await?;
+            return Err(match failed_login(&pool, &ip, &user.name, Some(&user)).await? {
+                AuthError::InvalidCredentials => AuthError::InvalidTwoFactorCode,
+                locked => locked,
+            });
+        }
+        Err(e) => return Err(e),
+    }
+
+    let (access_token, refresh_token) = start_session(&pool, &req, &user).await?;
+
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: None,
+        user: Some(UserInfo {
+            id: user.id,
+            name: user.name,
+            is_admin: user.is_admin,
+            admin_level: user.admin_level,
+        }),
+    });
+
+    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
+
+    Ok(response)
+}
+
+/// Whether the signed-in user has 2FA enabled, or is required to.
+pub async fn two_factor_status(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+
+    let enabled = session::get_totp(&pool, user.id)
+        .await?
+        .is_some_and(|totp| totp.is_enabled());
+    let recovery_codes_remaining = if enabled {
+        session::count_recovery_codes(&pool, user.id).await?
+    } else {
+        0
+    };
+
+    Ok(HttpResponse::Ok().json(TwoFactorStatusResponse {
+        enabled,
+        required: session::is_two_factor_required(&pool, &user).await?,
+        recovery_codes_remaining,
+        passkeys: session::count_passkeys(&pool, user.id).await?,
+    }))
+}
+
+/// Start enrollment: generate a secret for the authenticator app. Nothing
+/// changes for the account until `/2fa/enable` confirms a code.
+pub async fn two_factor_setup(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<TwoFactorSetupRequest>,
+) -> Result<HttpResponse, AuthError> {
+    let user = two_factor_user(&pool, &req, body.mfa_token.as_deref()).await?;
+    check_login_enrollment(&pool, &user, body.mfa_token.as_deref()).await?;
+
+    let secret = totp::generate_secret();
+    if !session::save_pending_totp(&pool, user.id, &secret).await? {
+        return Err(AuthError::TwoFactorAlreadyEnabled);
+    }
+
+    let settings = &tinyboards_utils::settings::SETTINGS;
+    let site_name = settings.setup.as_ref()
+        .map(|s| s.site_name.as_str())
+        .unwrap_or(&settings.hostname);
+
+    Ok(HttpResponse::Ok().json(TwoFactorSetupResponse {
+        success: true,
+        otpauth_uri: totp::otpauth_uri(&secret, &user.name, site_name),
+        secret,
+    }))
+}
+
+/// Finish enrollment with a first code and return the recovery codes. When
+/// enrolling during login, this also signs the user in.
+pub async fn two_factor_enable(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<TwoFactorEnableRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+
+    let user = two_factor_user(&pool, &req, body.mfa_token.as_deref()).await?;
+    check_login_enrollment(&pool, &user, body.mfa_token.as_deref()).await?;
+
+    let totp = match session::get_totp(&pool, user.id).await? {
+        Some(totp) if totp.is_enabled() => return Err(AuthError::TwoFactorAlreadyEnabled),
+        Some(totp) => totp,
+        None => return Err(AuthError::TwoFactorNotEnabled),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let step = totp::verify_code(&totp.secret, &body.code, now, None)
+        .ok_or(AuthError::InvalidTwoFactorCode)?;
+    if !session::enable_totp(&pool, user.id, step).await? {
+        return Err(AuthError::TwoFactorAlreadyEnabled);
+    }
+
+    let recovery_codes = issue_recovery_codes(&pool, user.id).await?;
+
+    if body.mfa_token.is_none() {
+        return Ok(HttpResponse::Ok().json(RecoveryCodesResponse {
+            success: true,
+            recovery_codes,
+            user: None,
+        }));
+    }
+
+    if user.deleted_at.is_some() {
+        return Err(AuthError::InvalidMfaToken);
+    }
+    if user.is_banned {
+        return Err(AuthError::AccountBanned);
+    }
+    let (access_token, refresh_token) = start_session(&pool, &req, &user).await?;
+
+    let mut response = HttpResponse::Ok().json(RecoveryCodesResponse {
+        success: true,
+        recovery_codes,
+        user: Some(UserInfo {
+            id: user.id,
+            name: user.name,
+            is_admin: user.is_admin,
+            admin_level: user.admin_level,
+        }),
+    });
+
+    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
+
+    Ok(response)
+}
+
+/// Turn off 2FA. Needs the password and a current code (or recovery code),
+/// and isn't allowed where site policy requires 2FA and there are no passkeys.
+pub async fn two_factor_disable(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<TwoFactorDisableRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+
+    let auth_user = req.require_auth()?;
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+
+    if !password::verify_password(&body.password, &user.passhash)? {
+        return Err(AuthError::IncorrectPassword);
+    }
+
+    let totp = session::get_totp(&pool, user.id)
+        .await?
+        .filter(|totp| totp.is_enabled())
+        .ok_or(AuthError::TwoFactorNotEnabled)?;
+
+    // Passkeys still satisfy the policy on their own
+    if session::is_two_factor_required(&pool, &user).await? && session::count_passkeys(&pool, user.id).await? == 0 {
+        return Err(AuthError::TwoFactorRequired);
+    }
+
+    check_second_factor(&pool, &totp, &body.code).await?;
+    session::delete_totp(&pool, user.id).await?;
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Two-factor authentication has been disabled.".to_string()),
+        user: None,
+    }))
+}
+
+/// Replace the recovery codes, invalidating the old ones.
+pub async fn regenerate_recovery_codes(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<TwoFactorCodeRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+
+    let auth_user = req.require_auth()?;
+    let totp = session::get_totp(&pool, auth_user.id)
+        .await?
+        .filter(|totp| totp.is_enabled())
+        .ok_or(AuthError::TwoFactorNotEnabled)?;
+
+    check_totp_code(&pool, &totp, &body.code).await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse {
+        success: true,
+        recovery_codes: issue_recovery_codes(&pool, auth_user.id).await?,
+        user: None,
+    }))
+}
+
+// ============================================================
+// Passkeys (WebAuthn)
+// ============================================================
+
+/// Most passkeys one account can register
+const MAX_PASSKEYS: i64 = 10;
+
+/// Longest passkey label
+const PASSKEY_NAME_MAX_CHARS: usize = 64;
+
+fn relying_party() -> webauthn::RelyingParty {
+    webauthn::RelyingParty::from_settings(&tinyboards_utils::settings::SETTINGS)
+}
+
+fn passkey_expired() -> AuthError {
+    AuthError::PasskeyFailed("the request expired, please try again".to_string())
+}
+
+/// The signed-in user's passkeys.
+pub async fn list_passkeys(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+    let passkeys = session::get_passkeys(&pool, auth_user.id).await?;
+
+    Ok(HttpResponse::Ok().json(PasskeyListResponse {
+        passkeys: passkeys.into_iter().map(PasskeyInfo::from).collect(),
+    }))
+}
+
+/// Start registering a passkey: options for `navigator.credentials.create()`.
+pub async fn passkey_registration_options(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let auth_user = req.require_auth()?;
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+
+    let existing = session::get_passkeys(&pool, user.id).await?;
+    if existing.len() as i64 >= MAX_PASSKEYS {
+        return Err(AuthError::TooManyPasskeys(MAX_PASSKEYS));
+    }
+
+    let challenge = webauthn::generate_challenge();
+    session::create_webauthn_challenge(&pool, &challenge, Some(user.id), "register", webauthn::CHALLENGE_TTL_SECONDS)
+        .await?;
+
+    // Stop the browser from registering the same authenticator twice
+    let exclude = existing
+        .into_iter()
+        .map(|passkey| CredentialDescriptor::new(passkey.credential_id, passkey.transports))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(PasskeyCreationOptionsResponse {
+        public_key: webauthn::creation_options(&relying_party(), challenge, user.id, &user.name, exclude),
+    }))
+}
+
+/// Finish registering a passkey with the authenticator's response. Needs
+/// the password, so a stolen session can't add its own way in.
+pub async fn register_passkey(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<RegisterPasskeyRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+    let auth_user = req.require_auth()?;
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+    if !password::verify_password(&body.password, &user.passhash)? {
+        return Err(AuthError::IncorrectPassword);
+    }
+
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > PASSKEY_NAME_MAX_CHARS {
+        return Err(AuthError::InvalidPasskeyName);
+    }
+
+    let challenge = webauthn::response_challenge(&body.credential.response.client_data_json)?;
+    let ceremony = session::take_webauthn_challenge(&pool, &challenge).await?;
+    if !ceremony.is_some_and(|c| c.purpose == "register" && c.user_id == Some(user.id)) {
+        return Err(passkey_expired());
+    }
+    if session::count_passkeys(&pool, user.id).await? >= MAX_PASSKEYS {
+        return Err(AuthError::TooManyPasskeys(MAX_PASSKEYS));
+    }
+
+    let credential = webauthn::verify_registration(&relying_party(), &body.credential, &challenge)?;
+    let transports: Vec<String> = body
+        .credential
+        .response
+        .transports
+        .iter()
+        .filter(|t| webauthn::TRANSPORTS.contains(&t.as_str()))
+        .cloned()
+        .collect();
+    let passkey = session::create_passkey(
+        &pool,
+        user.id,
+        &credential.credential_id,
+        &credential.public_key,
+        i64::from(credential.sign_count),
+        name,
+        &transports,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(PasskeyInfo::from(passkey)))
+}
+
+/// Remove a passkey. Needs the password, and the last second factor can't
+/// be removed where site policy requires one.
+pub async fn delete_passkey(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: web::Json<DeletePasskeyRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+    let auth_user = req.require_auth()?;
+    let passkey_id = path.into_inner();
+    let user = session::get_user_by_id(&pool, auth_user.id).await?;
+    if !password::verify_password(&body.password, &user.passhash)? {
+        return Err(AuthError::IncorrectPassword);
+    }
+
+    let passkeys = session::get_passkeys(&pool, user.id).await?;
+    if !passkeys.iter().any(|passkey| passkey.id == passkey_id) {
+        return Err(AuthError::PasskeyNotFound);
+    }
+    if passkeys.len() == 1 && session::is_two_factor_required(&pool, &user).await? {
+        let totp_enabled = session::get_totp(&pool, user.id)
+            .await?
+            .is_some_and(|totp| totp.is_enabled());
+        if !totp_enabled {
+            return Err(AuthError::TwoFactorRequired);
+        }
+    }
+
+    if !session::delete_passkey(&pool, passkey_id, user.id).await? {
+        return Err(AuthError::PasskeyNotFound);
+    }
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: Some("Passkey removed.".to_string()),
+        user: None,
+    }))
+}
+
+/// Options for `navigator.credentials.get()`: for logging in with a passkey
+/// on its own, or with an MFA token as the second step after the password.
+pub async fn passkey_login_options(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<PasskeyLoginOptionsRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+
+    let rp = relying_party();
+    let challenge = webauthn::generate_challenge();
+    let options = match body.mfa_token.as_deref() {
+        Some(mfa_token) => {
+            let user = two_factor_user(&pool, &req, Some(mfa_token)).await?;
+            let passkeys = session::get_passkeys(&pool, user.id).await?;
+            if passkeys.is_empty() {
+                return Err(AuthError::PasskeyNotFound);
+            }
+            session::create_webauthn_challenge(
+                &pool,
+                &challenge,
+                Some(user.id),
+                "second_factor",
+                webauthn::CHALLENGE_TTL_SECONDS,
+            )
+            .await?;
+            let allow = passkeys
+                .into_iter()
+                .map(|passkey| CredentialDescriptor::new(passkey.credential_id, passkey.transports))
+                .collect();
+            webauthn::request_options(&rp, challenge, allow, false)
+        }
+        None => {
+            session::create_webauthn_challenge(&pool, &challenge, None, "login", webauthn::CHALLENGE_TTL_SECONDS)
+                .await?;
+            webauthn::request_options(&rp, challenge, Vec::new(), true)
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(PasskeyRequestOptionsResponse { public_key: options }))
+}
+
+/// Log in with a passkey. On its own, the authenticator has to have verified
+/// the user with a PIN or biometrics, which makes the passkey two factors in
+/// one; as the second step after a password, presence is enough.
+pub async fn passkey_login(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    body: web::Json<PasskeyLoginRequest>,
+) -> Result<HttpResponse, AuthError> {
+    check_rate_limit(&req, RateLimitCell::login).await?;
+
+    let challenge = webauthn::response_challenge(&body.credential.response.client_data_json)?;
+    let ceremony = session::take_webauthn_challenge(&pool, &challenge)
+        .await?
+        .ok_or_else(passkey_expired)?;
+    let second_factor = match (ceremony.purpose.as_str(), body.mfa_token.as_deref()) {
+        ("login", None) => false,
+        ("second_factor", Some(mfa_token)) => {
+            let jwt_secret = session::get_jwt_secret(&pool).await?;
+            if ceremony.user_id != Some(tokens::validate_mfa_token(mfa_token, &jwt_secret)?) {
+                return Err(passkey_expired());
+            }
+            true
+        }
+        _ => return Err(passkey_expired()),
+    };
+
+    let unknown = || AuthError::PasskeyFailed("this passkey isn't registered for the account".to_string());
+    let passkey = session::get_passkey_by_credential_id(&pool, body.credential.raw_id.trim_end_matches('='))
+        .await?
+        .ok_or_else(unknown)?;
+    if ceremony.user_id.is_some_and(|user_id| user_id != passkey.user_id) {
+        return Err(unknown());
+    }
+    // Discoverable credentials also name their account; it has to agree
+    let user_handle = body.credential.response.user_handle.as_deref().unwrap_or_default();
+    if !user_handle.is_empty() && user_handle.trim_end_matches('=') != webauthn::user_handle(passkey.user_id) {
+        return Err(unknown());
+    }
+
+    let assertion = webauthn::verify_authentication(
+        &relying_party(),
+        &body.credential,
+        &challenge,
+        &passkey.public_key,
+        passkey.sign_count,
+        !second_factor,
+    )?;
+    if !session::record_passkey_use(&pool, passkey.id, passkey.sign_count, i64::from(assertion.sign_count)).await? {
+        return Err(AuthError::PasskeyFailed("the passkey was used by another request".to_string()));
+    }
+
+    let user = session::get_user_by_id(&pool, passkey.user_id).await?;
+    if user.deleted_at.is_some() {
+        return Err(AuthError::AccountDeleted);
+    }
+    if user.is_banned {
+        return Err(AuthError::AccountBanned);
+    }
+    if !user.is_application_accepted && session::has_pending_application(&pool, user.id).await? {
+        return Err(AuthError::ApplicationPending);
+    }
+    session::clear_failed_logins(&pool, user.id).await?;
+
+    let (access_token, refresh_token) = start_session(&pool, &req, &user).await?;
+
+    let mut response = HttpResponse::Ok().json(AuthResponse {
+        success: true,
+        message: None,
+        user: Some(UserInfo {
+            id: user.id,
+            name: user.name,
+            is_admin: user.is_admin,
+            admin_level: user.admin_level,
+        }),
+    });
+
+    cookies::set_auth_cookies(&mut response, &access_token, &refresh_token);
+
+    Ok(response)
+}
+
 /// Configure the auth routes scope with CSRF protection.
 pub fn configure_auth_routes(cfg: &mut web::ServiceConfig) {
     // The JWT secret is loaded from the database at startup and shared via app_data.
@@ -749,14 +2464,42 @@ pub fn configure_auth_routes(cfg: &mut web::ServiceConfig) {
             .wrap(CsrfGuard)
             .route("/login", web::post().to(login))
             .route("/register", web::post().to(register))
+            .route("/captcha", web::post().to(captcha_challenge))
+            .route("/oidc/providers", web::get().to(oidc_providers))
+            .route("/oidc/{provider}/authorize", web::get().to(oidc_authorize))
+            .route("/oidc/{provider}/callback", web::get().to(oidc_callback))
+            .route("/magic-link/request", web::post().to(request_magic_link))
+            .route("/magic-link/complete", web::post().to(complete_magic_link))
             .route("/logout", web::post().to(logout))
             .route("/logout-all", web::post().to(logout_all))
+            .route("/sessions", web::get().to(list_sessions))
+            .route("/sessions/{id}/name", web::post().to(rename_session))
+            .route("/sessions/{id}/revoke", web::post().to(revoke_session))
+            .route("/tokens", web::get().to(list_api_tokens))
+            .route("/tokens", web::post().to(create_api_token))
+            .route("/tokens/{id}/revoke", web::post().to(revoke_api_token))
             .route("/refresh", web::post().to(refresh_token))
             .route("/change-password", web::post().to(change_password))
             .route("/password-reset/request", web::post().to(request_password_reset))
             .route("/password-reset/complete", web::post().to(complete_password_reset))
             .route("/email/verify", web::post().to(verify_email))
             .route("/email/request-verification", web::post().to(request_email_verification))
+            .route("/email/change", web::get().to(email_change_status))
+            .route("/email/change", web::post().to(request_email_change))
+            .route("/email/change/confirm", web::post().to(confirm_email_change))
+            .route("/email/change/cancel", web::post().to(cancel_email_change))
+            .route("/login/2fa", web::post().to(login_two_factor))
+            .route("/2fa", web::get().to(two_factor_status))
+            .route("/2fa/setup", web::post().to(two_factor_setup))
+            .route("/2fa/enable", web::post().to(two_factor_enable))
+            .route("/2fa/disable", web::post().to(two_factor_disable))
+            .route("/2fa/recovery-codes", web::post().to(regenerate_recovery_codes))
+            .route("/login/passkey/options", web::post().to(passkey_login_options))
+            .route("/login/passkey", web::post().to(passkey_login))
+            .route("/passkeys", web::get().to(list_passkeys))
+            .route("/passkeys/register/options", web::post().to(passkey_registration_options))
+            .route("/passkeys/register", web::post().to(register_passkey))
+            .route("/passkeys/{id}/delete", web::post().to(delete_passkey))
     );
 }
 
@@ -771,14 +2514,42 @@ pub fn configure_auth_routes_with_secret(jwt_secret: String) -> impl FnOnce(&mut
                 .wrap(crate::middleware::AuthMiddleware::new(jwt_secret))
                 .route("/login", web::post().to(login))
                 .route("/register", web::post().to(register))
+                .route("/captcha", web::post().to(captcha_challenge))
+                .route("/oidc/providers", web::get().to(oidc_providers))
+                .route("/oidc/{provider}/authorize", web::get().to(oidc_authorize))
+                .route("/oidc/{provider}/callback", web::get().to(oidc_callback))
+                .route("/magic-link/request", web::post().to(request_magic_link))
+                .route("/magic-link/complete", web::post().to(complete_magic_link))
                 .route("/logout", web::post().to(logout))
                 .route("/logout-all", web::post().to(logout_all))
+                .route("/sessions", web::get().to(list_sessions))
+                .route("/sessions/{id}/name", web::post().to(rename_session))
+                .route("/sessions/{id}/revoke", web::post().to(revoke_session))
+                .route("/tokens", web::get().to(list_api_tokens))
+                .route("/tokens", web::post().to(create_api_token))
+                .route("/tokens/{id}/revoke", web::post().to(revoke_api_token))
                 .route("/refresh", web::post().to(refresh_token))
                 .route("/change-password", web::post().to(change_password))
                 .route("/password-reset/request", web::post().to(request_password_reset))
                 .route("/password-reset/complete", web::post().to(complete_password_reset))
                 .route("/email/verify", web::post().to(verify_email))
                 .route("/email/request-verification", web::post().to(request_email_verification))
+                .route("/email/change", web::get().to(email_change_status))
+                .route("/email/change", web::post().to(request_email_change))
+                .route("/email/change/confirm", web::post().to(confirm_email_change))
+                .route("/email/change/cancel", web::post().to(cancel_email_change))
+                .route("/login/2fa", web::post().to(login_two_factor))
+                .route("/2fa", web::get().to(two_factor_status))
+                .route("/2fa/setup", web::post().to(two_factor_setup))
+                .route("/2fa/enable", web::post().to(two_factor_enable))
+                .route("/2fa/disable", web::post().to(two_factor_disable))
+                .route("/2fa/recovery-codes", web::post().to(regenerate_recovery_codes))
+                .route("/login/passkey/options", web::post().to(passkey_login_options))
+                .route("/login/passkey", web::post().to(passkey_login))
+                .route("/passkeys", web::get().to(list_passkeys))
+                .route("/passkeys/register/options", web::post().to(passkey_registration_options))
+                .route("/passkeys/register", web::post().to(register_passkey))
+                .route("/passkeys/{id}/delete", web::post().to(delete_passkey))
         );
     }
 }
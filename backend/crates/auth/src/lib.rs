@@ -3,23 +3,35 @@
 //! Implements the dual-token auth system:
 //! - Short-lived JWT access tokens (15 min) in httpOnly cookies
 //! - Long-lived refresh tokens (30 days) stored as hashes in auth_sessions
+//! - Optional TOTP two-factor authentication, checked between the password
+//!   and the session via a short-lived "MFA pending" token
+//! - Per-IP backoff and per-account lockout against password guessing
+//! - OpenID Connect single sign-on, linking provider identities to users
+//! - WebAuthn passkeys, for passwordless login or as a second factor
 //!
 //! This is a standalone crate that uses raw SQL queries via diesel::sql_query()
 //! to decouple from the tinyboards_db model layer.
 
 pub mod claims;
+pub mod captcha;
 pub mod cookies;
 pub mod errors;
 pub mod handlers;
+pub mod lockout;
 pub mod middleware;
+pub mod oidc;
 pub mod password;
 pub mod session;
 pub mod tokens;
+pub mod totp;
 pub mod types;
+pub mod user_agent;
+pub mod webauthn;
 
 // Re-export key types for convenience
 pub use errors::AuthError;
 pub use handlers::{configure_auth_routes, configure_auth_routes_with_secret};
 pub use middleware::{AuthMiddleware, AuthExt};
+pub use oidc::OidcProviders;
 pub use session::DbPool;
 pub use types::{AuthenticatedUser, UserRole};
@@ -0,0 +1,102 @@
+//! Brute-force protection for password logins.
+//!
+//! Two independent limits apply. Each client IP gets a few free failures per
+//! window, then has to wait an exponentially growing delay after each new
+//! failure. Each account is locked after a run of consecutive failures, and
+//! every further failure after the lock expires locks it again for twice as
+//! long. A successful login clears the account's run.
+//!
+//! Wrong two-factor codes count as failed logins too, and a correct password
+//! doesn't clear the run until the second factor is passed. Each MFA token
+//! is also refused after a few wrong codes.
+
+use chrono::NaiveDateTime;
+
+/// Window over which failures from one IP are counted
+pub const IP_WINDOW_MINUTES: i64 = 60;
+/// Failures an IP can make in the window before backoff starts
+const IP_FREE_FAILURES: i64 = 5;
+/// Longest delay an IP is made to wait between attempts
+const IP_MAX_DELAY_SECONDS: i64 = 15 * 60;
+/// Failures from an IP after which login also needs a captcha, if enabled
+pub const CAPTCHA_AFTER_FAILURES: i64 = 3;
+
+/// Consecutive failures before an account is locked
+pub const ACCOUNT_LOCKOUT_THRESHOLD: i32 = 10;
+/// Length of the first lockout; each further one doubles it
+const BASE_LOCKOUT_MINUTES: i64 = 15;
+const MAX_LOCKOUT_MINUTES: i64 = 24 * 60;
+
+/// Wrong codes an MFA token can be used for before it's refused
+pub const MFA_TOKEN_MAX_FAILURES: i32 = 3;
+
+/// Seconds an IP with `recent_failures` in the window must wait after its
+/// latest failure.
+pub fn ip_backoff_seconds(recent_failures: i64) -> i64 {
+    if recent_failures < IP_FREE_FAILURES {
+        return 0;
+    }
+    let exponent = (recent_failures - IP_FREE_FAILURES).min(20) as u32;
+    (1i64 << exponent).min(IP_MAX_DELAY_SECONDS)
+}
+
+/// Seconds until an IP may try again, or `None` if it can try now.
+pub fn ip_retry_after(
+    recent_failures: i64,
+    last_failed_at: Option<NaiveDateTime>,
+    now: NaiveDateTime,
+) -> Option<u64> {
+    let last_failed_at = last_failed_at?;
+    let delay = ip_backoff_seconds(recent_failures);
+    let remaining = (last_failed_at - now).num_seconds() + delay;
+    (remaining > 0).then_some(remaining as u64)
+}
+
+/// How long to lock an account after its `failed_attempts`th consecutive
+/// failure, or `None` if it stays unlocked.
+pub fn lockout_minutes(failed_attempts: i32) -> Option<i64> {
+    if failed_attempts < ACCOUNT_LOCKOUT_THRESHOLD {
+        return None;
+    }
+    let exponent = (failed_attempts - ACCOUNT_LOCKOUT_THRESHOLD).min(10) as u32;
+    Some((BASE_LOCKOUT_MINUTES << exponent).min(MAX_LOCKOUT_MINUTES))
+}
+
+/// Seconds until a lock expires, or `None` if the account isn't locked.
+pub fn locked_for(locked_until: Option<NaiveDateTime>, now: NaiveDateTime) -> Option<u64> {
+    let remaining = (locked_until? - now).num_seconds();
+    (remaining > 0).then_some(remaining as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn test_ip_backoff() {
+        assert_eq!(ip_backoff_seconds(0), 0);
+        assert_eq!(ip_backoff_seconds(IP_FREE_FAILURES - 1), 0);
+        assert_eq!(ip_backoff_seconds(IP_FREE_FAILURES), 1);
+        assert_eq!(ip_backoff_seconds(IP_FREE_FAILURES + 3), 8);
+        assert_eq!(ip_backoff_seconds(IP_FREE_FAILURES + 100), IP_MAX_DELAY_SECONDS);
+
+        let now = Utc::now().naive_utc();
+        assert_eq!(ip_retry_after(IP_FREE_FAILURES + 4, Some(now - Duration::seconds(10)), now), Some(6));
+        assert_eq!(ip_retry_after(IP_FREE_FAILURES + 4, Some(now - Duration::seconds(20)), now), None);
+        assert_eq!(ip_retry_after(0, None, now), None);
+    }
+
+    #[test]
+    fn test_account_lockout() {
+        assert_eq!(lockout_minutes(ACCOUNT_LOCKOUT_THRESHOLD - 1), None);
+        assert_eq!(lockout_minutes(ACCOUNT_LOCKOUT_THRESHOLD), Some(BASE_LOCKOUT_MINUTES));
+        assert_eq!(lockout_minutes(ACCOUNT_LOCKOUT_THRESHOLD + 1), Some(BASE_LOCKOUT_MINUTES * 2));
+        assert_eq!(lockout_minutes(ACCOUNT_LOCKOUT_THRESHOLD + 50), Some(MAX_LOCKOUT_MINUTES));
+
+        let now = Utc::now().naive_utc();
+        assert_eq!(locked_for(Some(now + Duration::seconds(90)), now), Some(90));
+        assert_eq!(locked_for(Some(now - Duration::seconds(1)), now), None);
+        assert_eq!(locked_for(None, now), None);
+    }
+}
@@ -0,0 +1,458 @@
+//! OpenID Connect single sign-on.
+//!
+//! Implements the authorization code flow with PKCE against any provider
+//! that publishes a discovery document at
+//! `<issuer>/.well-known/openid-configuration`. The provider's ID token is
+//! the only source of identity: its signature, issuer, audience, expiry and
+//! nonce are all checked before any of its claims are used.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Deserializer};
+use tinyboards_utils::settings::structs::{OidcProviderConfig, Settings};
+use url::Url;
+
+use crate::errors::AuthError;
+use crate::tokens;
+
+/// How long a user has to finish logging in at the provider
+pub const AUTH_REQUEST_TTL_MINUTES: i64 = 10;
+/// Where users land after logging in when they didn't ask for a page
+pub const DEFAULT_REDIRECT: &str = "/home";
+/// Longest username derived from provider claims, leaving room for a suffix
+const MAX_BASE_USERNAME_CHARS: usize = 24;
+
+/// The configured identity providers, shared with the handlers as app data.
+/// Apps that don't register it have single sign-on turned off.
+#[derive(Clone)]
+pub struct OidcProviders {
+    base_url: String,
+    providers: Vec<OidcProviderConfig>,
+    client: reqwest::Client,
+}
+
+impl OidcProviders {
+    /// `base_url` is the public URL of the site, which callback URLs are built on.
+    pub fn new(base_url: impl Into<String>, providers: Vec<OidcProviderConfig>, client: reqwest::Client) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            providers,
+            client,
+        }
+    }
+
+    pub fn from_settings(settings: &Settings, client: reqwest::Client) -> Self {
+        Self::new(settings.get_protocol_and_hostname(), settings.oidc.clone(), client)
+    }
+
+    pub fn list(&self) -> &[OidcProviderConfig] {
+        &self.providers
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OidcProviderConfig> {
+        self.providers.iter().find(|p| p.name == name)
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// The redirect URI to register with the provider.
+    pub fn redirect_uri(&self, provider: &OidcProviderConfig) -> String {
+        format!("{}/api/v2/auth/oidc/{}/callback", self.base_url, provider.name)
+    }
+}
+
+/// The parts of a provider's discovery document the login flow uses.
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+/// Claims read from a validated ID token.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// The email address, if the provider vouches for it.
+    pub fn verified_email(&self) -> Option<&str> {
+        self.email.as_deref().filter(|email| self.email_verified && !email.is_empty())
+    }
+}
+
+/// Some providers send `email_verified` as the string "true".
+fn bool_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match BoolOrString::deserialize(deserializer)? {
+        BoolOrString::Bool(value) => value,
+        BoolOrString::String(value) => value.eq_ignore_ascii_case("true"),
+    })
+}
+
+/// A random URL-safe value for `state`, `nonce` and the PKCE code verifier.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The S256 PKCE challenge for a code verifier (RFC 7636).
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(tokens::ring_free_sha256(code_verifier.as_bytes()))
+}
+
+/// Only same-site paths are accepted as post-login redirects; anything else
+/// goes to the default page.
+pub fn safe_redirect(target: Option<&str>) -> String {
+    match target {
+        Some(path)
+            if path.starts_with('/')
+                && !path.starts_with("//")
+                && !path.starts_with("/\\")
+                && !path.chars().any(|c| c.is_control()) =>
+        {
+            path.to_string()
+        }
+        _ => DEFAULT_REDIRECT.to_string(),
+    }
+}
+
+/// Fetch the provider's discovery document.
+pub async fn fetch_metadata(
+    client: &reqwest::Client,
+    provider: &OidcProviderConfig,
+) -> Result<ProviderMetadata, AuthError> {
+    let issuer = provider.issuer.trim_end_matches('/');
+    let url = format!("{}/.well-known/openid-configuration", issuer);
+    let metadata: ProviderMetadata = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| AuthError::SsoFailed(format!("Could not fetch {}: {}", url, e)))?
+        .json()
+        .await
+        .map_err(|e| AuthError::SsoFailed(format!("Invalid discovery document at {}: {}", url, e)))?;
+
+    if metadata.issuer.trim_end_matches('/') != issuer {
+        return Err(AuthError::SsoFailed(format!(
+            "Discovery document issuer {} doesn't match {}",
+            metadata.issuer, provider.issuer
+        )));
+    }
+    Ok(metadata)
+}
+
+/// Build the URL that sends the user to the provider to log in.
+pub fn authorization_url(
+    metadata: &ProviderMetadata,
+    provider: &OidcProviderConfig,
+    redirect_uri: &str,
+    state: &str,
+    nonce: &str,
+    code_challenge: &str,
+) -> Result<String, AuthError> {
+    let mut url = Url::parse(&metadata.authorization_endpoint)
+        .map_err(|e| AuthError::SsoFailed(format!("Invalid authorization endpoint: {}", e)))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &provider.client_id)
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("scope", &provider.scopes)
+        .append_pair("state", state)
+        .append_pair("nonce", nonce)
+        .append_pair("code_challenge", code_challenge)
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.into())
+}
+
+/// Exchange an authorization code for the provider's ID token.
+pub async fn exchange_code(
+    client: &reqwest::Client,
+    metadata: &ProviderMetadata,
+    provider: &OidcProviderConfig,
+    redirect_uri: &str,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String, AuthError> {
+    let response = client
+        .post(&metadata.token_endpoint)
+        .basic_auth(&provider.client_id, Some(&provider.client_secret))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", code_verifier),
+            ("client_id", provider.client_id.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| AuthError::SsoFailed(format!("Token request failed: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(AuthError::SsoFailed(format!("Token endpoint returned {}: {}", status, body)));
+    }
+
+    let tokens: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| AuthError::SsoFailed(format!("Invalid token response: {}", e)))?;
+    tokens
+        .id_token
+        .ok_or_else(|| AuthError::SsoFailed("Token response has no id_token".to_string()))
+}
+
+/// The algorithms `provider` accepts for ID tokens. The token names its own
+/// algorithm, so without this list a forged token could pick one the provider
+/// never uses, e.g. HS256 signed with a leaked client secret.
+fn allowed_algorithms(provider: &OidcProviderConfig) -> Result<Vec<Algorithm>, AuthError> {
+    provider
+        .id_token_signing_algs
+        .iter()
+        .map(|alg| {
+            alg.parse().map_err(|_| {
+                AuthError::SsoFailed(format!("Unknown ID token algorithm {} for {}", alg, provider.name))
+            })
+        })
+        .collect()
+}
+
+/// Check an ID token's signature and claims, and return the claims.
+///
+/// Only the provider's configured algorithms are accepted. HMAC-signed tokens
+/// are checked with the client secret; everything else against the
+/// provider's published keys.
+pub async fn validate_id_token(
+    client: &reqwest::Client,
+    metadata: &ProviderMetadata,
+    provider: &OidcProviderConfig,
+    id_token: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, AuthError> {
+    let invalid = |e: jsonwebtoken::errors::Error| AuthError::SsoFailed(format!("Invalid ID token: {}", e));
+    let header = decode_header(id_token).map_err(invalid)?;
+    if !allowed_algorithms(provider)?.contains(&header.alg) {
+        return Err(AuthError::SsoFailed(format!(
+            "ID token signed with {:?}, which {} isn't configured to use",
+            header.alg, provider.name
+        )));
+    }
+
+    let key = match header.alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            if provider.client_secret.is_empty() {
+                return Err(AuthError::SsoFailed("HMAC-signed ID token without a client secret".to_string()));
+            }
+            DecodingKey::from_secret(provider.client_secret.as_bytes())
+        }
+        _ => {
+            let jwks_uri = metadata
+                .jwks_uri
+                .as_deref()
+                .ok_or_else(|| AuthError::SsoFailed("Discovery document has no jwks_uri".to_string()))?;
+            let jwks: JwkSet = client
+                .get(jwks_uri)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| AuthError::SsoFailed(format!("Could not fetch {}: {}", jwks_uri, e)))?
+                .json()
+                .await
+                .map_err(|e| AuthError::SsoFailed(format!("Invalid key set at {}: {}", jwks_uri, e)))?;
+            let jwk = match header.kid.as_deref() {
+                Some(kid) => jwks.find(kid),
+                None => jwks.keys.first(),
+            }
+            .ok_or_else(|| AuthError::SsoFailed("No matching signing key".to_string()))?;
+            if jwk.common.algorithm.is_some_and(|alg| alg != header.alg) {
+                return Err(AuthError::SsoFailed("Signing key is for a different algorithm".to_string()));
+            }
+            DecodingKey::from_jwk(jwk).map_err(invalid)?
+        }
+    };
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[&provider.client_id]);
+    validation.set_issuer(&[&metadata.issuer]);
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(invalid)?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(AuthError::SsoFailed("ID token nonce doesn't match".to_string()));
+    }
+    Ok(claims)
+}
+
+/// A username for a new account, from the provider's preferred username,
+/// the email's local part or the display name, in that order.
+pub fn base_username(claims: &IdTokenClaims) -> String {
+    let source = [
+        claims.preferred_username.as_deref(),
+        claims.email.as_deref(),
+        claims.name.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|s| s.split('@').next().unwrap_or_default())
+    .find(|s| s.chars().any(|c| c.is_ascii_alphanumeric()))
+    .unwrap_or_default();
+
+    let cleaned: String = source
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut name = cleaned.trim_matches('_').to_string();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert_str(0, "user");
+    }
+    name.chars().take(MAX_BASE_USERNAME_CHARS).collect()
+}
+
+/// Usernames to try in turn until one is free.
+pub fn username_candidates(base: &str) -> Vec<String> {
+    let mut candidates = vec![base.to_string()];
+    candidates.extend((2..10).map(|n| format!("{}{}", base, n)));
+    candidates.extend((0..3).map(|_| format!("{}_{}", base, rand::random::<u16>() % 10_000)));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(preferred_username: Option<&str>, email: Option<&str>, name: Option<&str>) -> IdTokenClaims {
+        IdTokenClaims {
+            sub: "subject".to_string(),
+            nonce: None,
+            email: email.map(String::from),
+            email_verified: true,
+            preferred_username: preferred_username.map(String::from),
+            name: name.map(String::from),
+        }
+    }
+
+    fn provider(algs: &[&str]) -> OidcProviderConfig {
+        OidcProviderConfig {
+            name: "company".to_string(),
+            client_id: "client".to_string(),
+            client_secret: "a client secret".to_string(),
+            id_token_signing_algs: algs.iter().map(|alg| alg.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata {
+            issuer: "https://login.example.com".to_string(),
+            authorization_endpoint: "https://login.example.com/authorize".to_string(),
+            token_endpoint: "https://login.example.com/token".to_string(),
+            jwks_uri: None,
+        }
+    }
+
+    fn hs256_id_token(secret: &str) -> String {
+        let claims = serde_json::json!({
+            "iss": "https://login.example.com",
+            "aud": "client",
+            "sub": "subject",
+            "nonce": "nonce",
+            "exp": chrono::Utc::now().timestamp() + 300,
+        });
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(Algorithm::HS256),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    async fn validate(provider: &OidcProviderConfig, id_token: &str) -> Result<IdTokenClaims, AuthError> {
+        validate_id_token(&reqwest::Client::new(), &metadata(), provider, id_token, "nonce").await
+    }
+
+    #[tokio::test]
+    async fn test_id_token_algorithm_must_be_configured() {
+        let token = hs256_id_token("a client secret");
+
+        // The defaults only take keys from the provider's key set
+        assert!(matches!(validate(&provider(&["RS256", "ES256"]), &token).await, Err(AuthError::SsoFailed(_))));
+        assert!(matches!(validate(&provider(&[]), &token).await, Err(AuthError::SsoFailed(_))));
+
+        let claims = validate(&provider(&["HS256"]), &token).await.unwrap();
+        assert_eq!(claims.sub, "subject");
+    }
+
+    #[tokio::test]
+    async fn test_id_token_signed_with_another_secret_is_rejected() {
+        let token = hs256_id_token("someone else's secret");
+        assert!(matches!(validate(&provider(&["HS256"]), &token).await, Err(AuthError::SsoFailed(_))));
+    }
+
+    #[test]
+    fn test_default_algorithms_are_asymmetric() {
+        let defaults = OidcProviderConfig::default();
+        assert_eq!(allowed_algorithms(&defaults).unwrap(), [Algorithm::RS256, Algorithm::ES256]);
+        assert!(allowed_algorithms(&provider(&["HS257"])).is_err());
+    }
+
+    #[test]
+    fn test_pkce_challenge_rfc7636_vector() {
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(random_token().len(), 43);
+    }
+
+    #[test]
+    fn test_safe_redirect() {
+        assert_eq!(safe_redirect(Some("/b/general?sort=new")), "/b/general?sort=new");
+        assert_eq!(safe_redirect(Some("//evil.example")), DEFAULT_REDIRECT);
+        assert_eq!(safe_redirect(Some("/\\evil.example")), DEFAULT_REDIRECT);
+        assert_eq!(safe_redirect(Some("https://evil.example")), DEFAULT_REDIRECT);
+        assert_eq!(safe_redirect(None), DEFAULT_REDIRECT);
+    }
+
+    #[test]
+    fn test_base_username() {
+        let re = regex::Regex::new(r"^[A-Za-z][A-Za-z0-9_]{0,29}$").unwrap();
+
+        assert_eq!(base_username(&claims(Some("jane.doe"), None, None)), "jane_doe");
+        assert_eq!(base_username(&claims(None, Some("j.smith@corp.example"), None)), "j_smith");
+        assert_eq!(base_username(&claims(Some("—"), None, Some("42 Ops"))), "user42_Ops");
+        assert_eq!(base_username(&claims(None, None, None)), "user");
+
+        let long = base_username(&claims(Some(&"a".repeat(80)), None, None));
+        for candidate in username_candidates(&long) {
+            assert!(re.is_match(&candidate), "{} isn't a valid username", candidate);
+        }
+    }
+}
@@ -4,7 +4,7 @@ use diesel_async::RunQueryDsl;
 use uuid::Uuid;
 
 use crate::errors::AuthError;
-use crate::types::{AuthSessionRow, AuthUser, CreatedUser, EmailVerificationRow, JwtSecretRow, PasswordResetRow, SiteRegistrationInfo};
+use crate::types::{AccountLockoutRow, ApiTokenRow, ApiTokenScope, AuthSessionRow, AuthUser, CaptchaChallengeRow, CreatedUser, EmailChangeRow, EmailVerificationRow, IpLoginFailures, JwtSecretRow, OidcAuthRequestRow, PasskeyRow, PasswordResetRow, SiteCaptchaInfo, SiteRegistrationInfo, TotpRow, WebauthnChallengeRow};
 
 /// Type alias for the async connection pool.
 pub type DbPool = diesel_async::pooled_connection::bb8::Pool<diesel_async::AsyncPgConnection>;
@@ -189,9 +189,10 @@ pub async fn get_active_sessions(
 ) -> Result<Vec<AuthSessionRow>, AuthError> {
     let conn = &mut get_conn(pool).await?;
     sql_query(
-        "SELECT id, user_id, refresh_token_hash, user_agent, ip_address, last_used_at, expires_at, created_at
+        "SELECT id, user_id, refresh_token_hash, user_agent, ip_address, last_used_at, expires_at, created_at, name
          FROM auth_sessions
-         WHERE user_id = $1 AND expires_at > NOW()"
+         WHERE user_id = $1 AND expires_at > NOW()
+         ORDER BY COALESCE(last_used_at, created_at) DESC"
     )
     .bind::<diesel::sql_types::Uuid, _>(user_id)
     .get_results(conn)
@@ -199,6 +200,44 @@ pub async fn get_active_sessions(
     .map_err(|e| AuthError::DatabaseError(format!("Failed to get sessions: {}", e)))
 }
 
+/// Get a single active session by ID.
+pub async fn get_session(pool: &DbPool, session_id: Uuid) -> Result<Option<AuthSessionRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let result: Result<AuthSessionRow, _> = sql_query(
+        "SELECT id, user_id, refresh_token_hash, user_agent, ip_address, last_used_at, expires_at, created_at, name
+         FROM auth_sessions
+         WHERE id = $1 AND expires_at > NOW()"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(session_id)
+    .get_result(conn)
+    .await;
+
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to get session: {}", e))),
+    }
+}
+
+/// Set or clear the label on one of a user's sessions. Returns false if the
+/// session doesn't exist or belongs to someone else.
+pub async fn rename_session(
+    pool: &DbPool,
+    session_id: Uuid,
+    user_id: Uuid,
+    name: Option<&str>,
+) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let updated = sql_query("UPDATE auth_sessions SET name = $1 WHERE id = $2 AND user_id = $3")
+        .bind::<diesel::sql_types::Nullable<Text>, _>(name)
+        .bind::<diesel::sql_types::Uuid, _>(session_id)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to rename session: {}", e)))?;
+    Ok(updated > 0)
+}
+
 /// Rotate a session's refresh token (update hash, extend expiry).
 pub async fn rotate_session(
     pool: &DbPool,
@@ -219,20 +258,21 @@ pub async fn rotate_session(
     Ok(())
 }
 
-/// Delete a single session (logout).
+/// Delete a single session (logout or revocation). Returns false if no
+/// session with that ID belongs to the user.
 pub async fn delete_session(
     pool: &DbPool,
     session_id: Uuid,
     user_id: Uuid,
-) -> Result<(), AuthError> {
+) -> Result<bool, AuthError> {
     let conn = &mut get_conn(pool).await?;
-    sql_query("DELETE FROM auth_sessions WHERE id = $1 AND user_id = $2")
+    let deleted = sql_query("DELETE FROM auth_sessions WHERE id = $1 AND user_id = $2")
         .bind::<diesel::sql_types::Uuid, _>(session_id)
         .bind::<diesel::sql_types::Uuid, _>(user_id)
         .execute(conn)
         .await
         .map_err(|e| AuthError::DatabaseError(format!("Failed to delete session: {}", e)))?;
-    Ok(())
+    Ok(deleted > 0)
 }
 
 /// Delete ALL sessions for a user (logout all devices).
@@ -255,60 +295,85 @@ pub async fn cleanup_expired_sessions(pool: &DbPool) -> Result<usize, AuthError>
 }
 
 // ============================================================
-// Site configuration
+// Personal access tokens
 // ============================================================
 
-/// Get the site's registration mode.
-pub async fn get_registration_mode(pool: &DbPool) -> Result<SiteRegistrationInfo, AuthError> {
+/// Store a new personal access token. The token is marked as a bot token
+/// when the account is a bot account at the time it is created.
+pub async fn create_api_token(
+    pool: &DbPool,
+    user_id: Uuid,
+    name: &str,
+    token_hash: &str,
+    token_prefix: &str,
+    scope: ApiTokenScope,
+    expires_in_days: Option<i32>,
+) -> Result<ApiTokenRow, AuthError> {
     let conn = &mut get_conn(pool).await?;
     sql_query(
-        "SELECT registration_mode::text AS registration_mode, application_question
-         FROM site LIMIT 1"
+        "INSERT INTO api_tokens (user_id, name, token_hash, token_prefix, scope, is_bot, expires_at)
+         SELECT id, $2, $3, $4, $5, is_bot_account, NOW() + $6 * INTERVAL '1 day'
+         FROM users WHERE id = $1
+         RETURNING id, user_id, name, token_prefix, scope, is_bot, last_used_at, expires_at, created_at"
     )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(name)
+    .bind::<Text, _>(token_hash)
+    .bind::<Text, _>(token_prefix)
+    .bind::<Text, _>(scope.as_str())
+    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Integer>, _>(expires_in_days)
     .get_result(conn)
     .await
-    .map_err(|e| AuthError::DatabaseError(format!("Failed to get site config: {}", e)))
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create API token: {}", e)))
 }
 
-// ============================================================
-// Registration support
-// ============================================================
+/// A user's tokens that haven't expired, newest first.
+pub async fn get_api_tokens(pool: &DbPool, user_id: Uuid) -> Result<Vec<ApiTokenRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "SELECT id, user_id, name, token_prefix, scope, is_bot, last_used_at, expires_at, created_at
+         FROM api_tokens
+         WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > NOW())
+         ORDER BY created_at DESC"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_results(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to get API tokens: {}", e)))
+}
 
-/// Validate and consume a site invite code.
-pub async fn consume_invite(pool: &DbPool, invite_code: &str) -> Result<(), AuthError> {
+/// Get a token by ID.
+pub async fn get_api_token(pool: &DbPool, token_id: Uuid) -> Result<Option<ApiTokenRow>, AuthError> {
     let conn = &mut get_conn(pool).await?;
-    let affected = sql_query("DELETE FROM site_invites WHERE verification_code = $1")
-        .bind::<Text, _>(invite_code)
-        .execute(conn)
-        .await
-        .map_err(|e| AuthError::DatabaseError(format!("Failed to check invite: {}", e)))?;
+    let result: Result<ApiTokenRow, _> = sql_query(
+        "SELECT id, user_id, name, token_prefix, scope, is_bot, last_used_at, expires_at, created_at
+         FROM api_tokens WHERE id = $1"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(token_id)
+    .get_result(conn)
+    .await;
 
-    if affected == 0 {
-        return Err(AuthError::InvalidInvite);
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to get API token: {}", e))),
     }
-    Ok(())
 }
 
-/// Create a registration application.
-pub async fn create_application(
-    pool: &DbPool,
-    user_id: Uuid,
-    answer: &str,
-) -> Result<(), AuthError> {
+/// Delete one of a user's tokens.
+pub async fn delete_api_token(pool: &DbPool, token_id: Uuid, user_id: Uuid) -> Result<(), AuthError> {
     let conn = &mut get_conn(pool).await?;
-    sql_query(
-        "INSERT INTO registration_applications (user_id, answer) VALUES ($1, $2)"
-    )
-    .bind::<diesel::sql_types::Uuid, _>(user_id)
-    .bind::<Text, _>(answer)
-    .execute(conn)
-    .await
-    .map_err(|e| AuthError::DatabaseError(format!("Failed to create application: {}", e)))?;
+    sql_query("DELETE FROM api_tokens WHERE id = $1 AND user_id = $2")
+        .bind::<diesel::sql_types::Uuid, _>(token_id)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to delete API token: {}", e)))?;
     Ok(())
 }
 
-/// Check if user has a pending application.
-pub async fn has_pending_application(pool: &DbPool, user_id: Uuid) -> Result<bool, AuthError> {
+/// Number of unexpired tokens a user has.
+pub async fn count_api_tokens(pool: &DbPool, user_id: Uuid) -> Result<i64, AuthError> {
     let conn = &mut get_conn(pool).await?;
 
     #[derive(diesel::QueryableByName)]
@@ -318,139 +383,1121 @@ pub async fn has_pending_application(pool: &DbPool, user_id: Uuid) -> Result<boo
     }
 
     let row: CountRow = sql_query(
-        "SELECT COUNT(*) as count FROM registration_applications
-         WHERE user_id = $1 AND admin_id IS NULL AND deny_reason IS NULL"
+        "SELECT COUNT(*) AS count FROM api_tokens
+         WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > NOW())"
     )
     .bind::<diesel::sql_types::Uuid, _>(user_id)
     .get_result(conn)
     .await
-    .map_err(|e| AuthError::DatabaseError(format!("Failed to check application: {}", e)))?;
-
-    Ok(row.count > 0)
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to count API tokens: {}", e)))?;
+    Ok(row.count)
 }
 
 // ============================================================
-// Password reset
+// Failed logins and lockouts
 // ============================================================
 
-/// Create a password reset token (store hash in DB).
-pub async fn create_password_reset(
+/// Count failed logins from an IP in the last `window_minutes`.
+pub async fn get_ip_login_failures(
     pool: &DbPool,
-    user_id: Uuid,
-    token_hash: &str,
+    ip_address: &str,
+    window_minutes: i64,
+) -> Result<IpLoginFailures, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "SELECT COUNT(*) AS failures, MAX(created_at) AS last_failed_at
+         FROM login_failures
+         WHERE ip_address = $1 AND created_at > NOW() - make_interval(mins => $2)"
+    )
+    .bind::<Text, _>(ip_address)
+    .bind::<diesel::sql_types::Integer, _>(window_minutes as i32)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to count login failures: {}", e)))
+}
+
+/// Record a failed login. `user_id` is `None` when the name didn't match an account.
+pub async fn record_login_failure(
+    pool: &DbPool,
+    ip_address: &str,
+    username: &str,
+    user_id: Option<Uuid>,
 ) -> Result<(), AuthError> {
     let conn = &mut get_conn(pool).await?;
     sql_query(
-        "INSERT INTO password_resets (user_id, reset_token, expires_at)
-         VALUES ($1, $2, NOW() + INTERVAL '1 hour')"
+        "INSERT INTO login_failures (ip_address, username, user_id) VALUES ($1, $2, $3)"
     )
-    .bind::<diesel::sql_types::Uuid, _>(user_id)
-    .bind::<Text, _>(token_hash)
+    .bind::<Text, _>(ip_address)
+    .bind::<Text, _>(username)
+    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Uuid>, _>(user_id)
     .execute(conn)
     .await
-    .map_err(|e| AuthError::DatabaseError(format!("Failed to create reset token: {}", e)))?;
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to record login failure: {}", e)))?;
     Ok(())
 }
 
-/// Find valid (non-expired, non-used) password reset tokens for a user.
-pub async fn get_valid_password_resets(
+/// Get an account's run of failed logins, if it has one.
+pub async fn get_account_lockout(
     pool: &DbPool,
     user_id: Uuid,
-) -> Result<Vec<PasswordResetRow>, AuthError> {
+) -> Result<Option<AccountLockoutRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let rows: Vec<AccountLockoutRow> = sql_query(
+        "SELECT user_id, failed_attempts, locked_until, last_failed_at
+         FROM account_lockouts WHERE user_id = $1"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .load(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to get account lockout: {}", e)))?;
+    Ok(rows.into_iter().next())
+}
+
+/// Count another failed login for an account and return the new run length.
+/// A run with no failures for a day starts over.
+pub async fn increment_failed_logins(pool: &DbPool, user_id: Uuid) -> Result<i32, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct CountRow {
+        #[diesel(sql_type = diesel::sql_types::Integer)]
+        failed_attempts: i32,
+    }
+
+    let row: CountRow = sql_query(
+        "INSERT INTO account_lockouts (user_id, failed_attempts, last_failed_at)
+         VALUES ($1, 1, NOW())
+         ON CONFLICT (user_id) DO UPDATE SET
+             failed_attempts = CASE
+                 WHEN account_lockouts.last_failed_at < NOW() - INTERVAL '1 day' THEN 1
+                 ELSE account_lockouts.failed_attempts + 1
+             END,
+             last_failed_at = NOW()
+         RETURNING failed_attempts"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to record login failure: {}", e)))?;
+    Ok(row.failed_attempts)
+}
+
+/// Lock an account for `minutes` from now.
+pub async fn lock_account(pool: &DbPool, user_id: Uuid, minutes: i64) -> Result<(), AuthError> {
     let conn = &mut get_conn(pool).await?;
     sql_query(
-        "SELECT id, user_id, reset_token, created_at, expires_at, used_at
-         FROM password_resets
-         WHERE user_id = $1 AND used_at IS NULL AND expires_at > NOW()"
+        "UPDATE account_lockouts SET locked_until = NOW() + make_interval(mins => $2)
+         WHERE user_id = $1"
     )
     .bind::<diesel::sql_types::Uuid, _>(user_id)
-    .get_results(conn)
+    .bind::<diesel::sql_types::Integer, _>(minutes as i32)
+    .execute(conn)
     .await
-    .map_err(|e| AuthError::DatabaseError(format!("Failed to get reset tokens: {}", e)))
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to lock account: {}", e)))?;
+    Ok(())
 }
 
-/// Mark a password reset token as used.
-pub async fn mark_reset_used(pool: &DbPool, reset_id: Uuid) -> Result<(), AuthError> {
+/// Clear an account's failed logins and any lock, after a successful login.
+pub async fn clear_failed_logins(pool: &DbPool, user_id: Uuid) -> Result<(), AuthError> {
     let conn = &mut get_conn(pool).await?;
-    sql_query("UPDATE password_resets SET used_at = NOW() WHERE id = $1")
-        .bind::<diesel::sql_types::Uuid, _>(reset_id)
+    sql_query("DELETE FROM account_lockouts WHERE user_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
         .execute(conn)
         .await
-        .map_err(|e| AuthError::DatabaseError(format!("Failed to mark reset as used: {}", e)))?;
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to clear login failures: {}", e)))?;
     Ok(())
 }
 
-// ============================================================
-// Email verification
-// ============================================================
+#[derive(diesel::QueryableByName)]
+struct MfaFailuresRow {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    failures: i32,
+}
 
-/// Create an email verification token (store hash in DB).
-pub async fn create_email_verification(
+/// Count the wrong codes entered with an MFA token.
+pub async fn get_mfa_token_failures(pool: &DbPool, jti: Uuid) -> Result<i32, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let rows: Vec<MfaFailuresRow> = sql_query("SELECT failures FROM mfa_token_failures WHERE jti = $1")
+        .bind::<diesel::sql_types::Uuid, _>(jti)
+        .load(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to count MFA token failures: {}", e)))?;
+    Ok(rows.into_iter().next().map_or(0, |row| row.failures))
+}
+
+/// Count another wrong code against an MFA token expiring at `expires_at`
+/// (a UNIX timestamp) and return the new total.
+pub async fn record_mfa_token_failure(
     pool: &DbPool,
+    jti: Uuid,
     user_id: Uuid,
-    email: &str,
-    token_hash: &str,
+    expires_at: i64,
+) -> Result<i32, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    sql_query("DELETE FROM mfa_token_failures WHERE expires_at < NOW()")
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to clean up MFA token failures: {}", e)))?;
+
+    let row: MfaFailuresRow = sql_query(
+        "INSERT INTO mfa_token_failures (jti, user_id, failures, expires_at)
+         VALUES ($1, $2, 1, to_timestamp($3))
+         ON CONFLICT (jti) DO UPDATE SET failures = mfa_token_failures.failures + 1
+         RETURNING failures"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(jti)
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<diesel::sql_types::Double, _>(expires_at as f64)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to record MFA token failure: {}", e)))?;
+    Ok(row.failures)
+}
+
+/// Send a user a system notification.
+pub async fn create_system_notification(
+    pool: &DbPool,
+    user_id: Uuid,
+    body: &str,
 ) -> Result<(), AuthError> {
     let conn = &mut get_conn(pool).await?;
     sql_query(
-        "INSERT INTO email_verification (user_id, email, verification_code)
-         VALUES ($1, $2, $3)"
+        "INSERT INTO notifications (kind, recipient_user_id, body) VALUES ('system', $1, $2)"
     )
     .bind::<diesel::sql_types::Uuid, _>(user_id)
-    .bind::<Text, _>(email)
-    .bind::<Text, _>(token_hash)
+    .bind::<Text, _>(body)
     .execute(conn)
     .await
-    .map_err(|e| AuthError::DatabaseError(format!("Failed to create verification: {}", e)))?;
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create notification: {}", e)))?;
     Ok(())
 }
 
-/// Find valid (unverified) email verification tokens for a user.
-pub async fn get_pending_email_verifications(
+// ============================================================
+// Site configuration
+// ============================================================
+
+/// Get the site's registration mode.
+pub async fn get_registration_mode(pool: &DbPool) -> Result<SiteRegistrationInfo, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "SELECT registration_mode::text AS registration_mode, application_question
+         FROM site LIMIT 1"
+    )
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to get site config: {}", e)))
+}
+
+/// Whether the site allows logging in with an emailed link.
+pub async fn is_magic_link_enabled(pool: &DbPool) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct EnabledRow {
+        #[diesel(sql_type = diesel::sql_types::Bool)]
+        enabled: bool,
+    }
+
+    let row: EnabledRow = sql_query(
+        "SELECT COALESCE((SELECT magic_link_login_enabled FROM site LIMIT 1), false) AS enabled"
+    )
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to get site config: {}", e)))?;
+    Ok(row.enabled)
+}
+
+/// Get the site's captcha settings.
+pub async fn get_captcha_settings(pool: &DbPool) -> Result<SiteCaptchaInfo, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query("SELECT captcha_enabled, captcha_difficulty FROM site LIMIT 1")
+        .get_result(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to get site config: {}", e)))
+}
+
+// ============================================================
+// Captcha challenges
+// ============================================================
+
+/// Store a new captcha challenge, clearing out expired ones on the way.
+pub async fn create_captcha_challenge(
     pool: &DbPool,
-    user_id: Uuid,
-) -> Result<Vec<EmailVerificationRow>, AuthError> {
+    salt: &str,
+    difficulty: i32,
+    ttl_minutes: i64,
+) -> Result<CaptchaChallengeRow, AuthError> {
     let conn = &mut get_conn(pool).await?;
+
+    sql_query("DELETE FROM captcha_challenges WHERE expires_at < NOW()")
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to clean up captchas: {}", e)))?;
+
     sql_query(
-        "SELECT id, user_id, email, verification_code, created_at, verified_at
-         FROM email_verification
-         WHERE user_id = $1 AND verified_at IS NULL
-         ORDER BY created_at DESC"
+        "INSERT INTO captcha_challenges (salt, difficulty, expires_at)
+         VALUES ($1, $2, NOW() + make_interval(mins => $3))
+         RETURNING id, salt, difficulty, expires_at"
     )
-    .bind::<diesel::sql_types::Uuid, _>(user_id)
-    .get_results(conn)
+    .bind::<Text, _>(salt)
+    .bind::<diesel::sql_types::Integer, _>(difficulty)
+    .bind::<diesel::sql_types::Integer, _>(ttl_minutes as i32)
+    .get_result(conn)
     .await
-    .map_err(|e| AuthError::DatabaseError(format!("Failed to get verifications: {}", e)))
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create captcha: {}", e)))
 }
 
-/// Mark an email verification as complete.
-pub async fn mark_email_verified(pool: &DbPool, verification_id: Uuid) -> Result<(), AuthError> {
+/// Remove and return an unexpired captcha challenge. Challenges are single
+/// use, so a wrong answer also burns the challenge.
+pub async fn take_captcha_challenge(
+    pool: &DbPool,
+    challenge_id: Uuid,
+) -> Result<Option<CaptchaChallengeRow>, AuthError> {
     let conn = &mut get_conn(pool).await?;
-    sql_query("UPDATE email_verification SET verified_at = NOW() WHERE id = $1")
-        .bind::<diesel::sql_types::Uuid, _>(verification_id)
+    let rows: Vec<CaptchaChallengeRow> = sql_query(
+        "DELETE FROM captcha_challenges WHERE id = $1
+         RETURNING id, salt, difficulty, expires_at"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(challenge_id)
+    .load(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to check captcha: {}", e)))?;
+
+    Ok(rows
+        .into_iter()
+        .next()
+        .filter(|row| row.expires_at > chrono::Utc::now().naive_utc()))
+}
+
+// ============================================================
+// Single sign-on
+// ============================================================
+
+/// Store an in-flight single sign-on login, clearing out expired ones on the way.
+pub async fn create_oidc_auth_request(
+    pool: &DbPool,
+    state: &str,
+    provider: &str,
+    code_verifier: &str,
+    nonce: &str,
+    redirect_to: &str,
+    ttl_minutes: i64,
+) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    sql_query("DELETE FROM oidc_auth_requests WHERE expires_at < NOW()")
         .execute(conn)
         .await
-        .map_err(|e| AuthError::DatabaseError(format!("Failed to mark verified: {}", e)))?;
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to clean up SSO requests: {}", e)))?;
+
+    sql_query(
+        "INSERT INTO oidc_auth_requests (state, provider, code_verifier, nonce, redirect_to, expires_at)
+         VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(mins => $6))"
+    )
+    .bind::<Text, _>(state)
+    .bind::<Text, _>(provider)
+    .bind::<Text, _>(code_verifier)
+    .bind::<Text, _>(nonce)
+    .bind::<Text, _>(redirect_to)
+    .bind::<diesel::sql_types::Integer, _>(ttl_minutes as i32)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create SSO request: {}", e)))?;
     Ok(())
 }
 
-/// Find a user by email (for password reset lookups).
-pub async fn find_user_by_email(
+/// Remove and return an unexpired single sign-on login by its state.
+pub async fn take_oidc_auth_request(
     pool: &DbPool,
-    email: &str,
+    state: &str,
+) -> Result<Option<OidcAuthRequestRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let rows: Vec<OidcAuthRequestRow> = sql_query(
+        "DELETE FROM oidc_auth_requests WHERE state = $1
+         RETURNING provider, code_verifier, nonce, redirect_to, expires_at"
+    )
+    .bind::<Text, _>(state)
+    .load(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to check SSO request: {}", e)))?;
+
+    Ok(rows
+        .into_iter()
+        .next()
+        .filter(|row| row.expires_at > chrono::Utc::now().naive_utc()))
+}
+
+/// Find the user linked to a provider's subject.
+pub async fn get_user_by_identity(
+    pool: &DbPool,
+    provider: &str,
+    subject: &str,
 ) -> Result<Option<AuthUser>, AuthError> {
     let conn = &mut get_conn(pool).await?;
     let result: Result<AuthUser, _> = sql_query(format!(
-        "SELECT {} FROM users WHERE email = $1 LIMIT 1",
+        "SELECT {} FROM users
+         WHERE id = (SELECT user_id FROM user_identities WHERE provider = $1 AND subject = $2)",
         AUTH_USER_COLUMNS
     ))
-    .bind::<Text, _>(email)
+    .bind::<Text, _>(provider)
+    .bind::<Text, _>(subject)
     .get_result(conn)
     .await;
 
     match result {
         Ok(user) => Ok(Some(user)),
         Err(diesel::result::Error::NotFound) => Ok(None),
-        Err(e) => Err(AuthError::DatabaseError(format!("Failed to find user: {}", e))),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to find identity: {}", e))),
     }
 }
+
+/// Link a provider's subject to a user, or record another login if it
+/// already is.
+pub async fn upsert_user_identity(
+    pool: &DbPool,
+    user_id: Uuid,
+    provider: &str,
+    subject: &str,
+    email: Option<&str>,
+) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "INSERT INTO user_identities (user_id, provider, subject, email)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (provider, subject) DO UPDATE
+         SET email = COALESCE(EXCLUDED.email, user_identities.email), last_login_at = now()"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(provider)
+    .bind::<Text, _>(subject)
+    .bind::<diesel::sql_types::Nullable<Text>, _>(email)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to link identity: {}", e)))?;
+    Ok(())
+}
+
+// ============================================================
+// Registration support
+// ============================================================
+
+/// Validate and consume a site invite code.
+pub async fn consume_invite(pool: &DbPool, invite_code: &str) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let affected = sql_query("DELETE FROM site_invites WHERE verification_code = $1")
+        .bind::<Text, _>(invite_code)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to check invite: {}", e)))?;
+
+    if affected == 0 {
+        return Err(AuthError::InvalidInvite);
+    }
+    Ok(())
+}
+
+/// Create a registration application.
+pub async fn create_application(
+    pool: &DbPool,
+    user_id: Uuid,
+    answer: &str,
+) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "INSERT INTO registration_applications (user_id, answer) VALUES ($1, $2)"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(answer)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create application: {}", e)))?;
+    Ok(())
+}
+
+/// Check if user has a pending application.
+pub async fn has_pending_application(pool: &DbPool, user_id: Uuid) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct CountRow {
+        #[diesel(sql_type = diesel::sql_types::BigInt)]
+        count: i64,
+    }
+
+    let row: CountRow = sql_query(
+        "SELECT COUNT(*) as count FROM registration_applications
+         WHERE user_id = $1 AND admin_id IS NULL AND deny_reason IS NULL"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to check application: {}", e)))?;
+
+    Ok(row.count > 0)
+}
+
+// ============================================================
+// Password reset
+// ============================================================
+
+/// Create a password reset token (store hash in DB).
+pub async fn create_password_reset(
+    pool: &DbPool,
+    user_id: Uuid,
+    token_hash: &str,
+) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "INSERT INTO password_resets (user_id, reset_token, expires_at)
+         VALUES ($1, $2, NOW() + INTERVAL '1 hour')"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(token_hash)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create reset token: {}", e)))?;
+    Ok(())
+}
+
+/// Find valid (non-expired, non-used) password reset tokens for a user.
+pub async fn get_valid_password_resets(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<Vec<PasswordResetRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "SELECT id, user_id, reset_token, created_at, expires_at, used_at
+         FROM password_resets
+         WHERE user_id = $1 AND used_at IS NULL AND expires_at > NOW()"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_results(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to get reset tokens: {}", e)))
+}
+
+/// Mark a password reset token as used.
+pub async fn mark_reset_used(pool: &DbPool, reset_id: Uuid) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query("UPDATE password_resets SET used_at = NOW() WHERE id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(reset_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to mark reset as used: {}", e)))?;
+    Ok(())
+}
+
+// ============================================================
+// Magic links
+// ============================================================
+
+/// Store a login link token hash. Only the newest link works, so any the
+/// user hasn't used yet are dropped.
+pub async fn create_magic_link(pool: &DbPool, user_id: Uuid, token_hash: &str) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query("DELETE FROM magic_links WHERE user_id = $1 AND used_at IS NULL")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to clear login links: {}", e)))?;
+    sql_query("INSERT INTO magic_links (user_id, token) VALUES ($1, $2)")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<Text, _>(token_hash)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to create login link: {}", e)))?;
+    Ok(())
+}
+
+/// Whether a login link was sent to the user within the last `seconds`.
+pub async fn has_recent_magic_link(pool: &DbPool, user_id: Uuid, seconds: i32) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct RecentRow {
+        #[diesel(sql_type = diesel::sql_types::Bool)]
+        recent: bool,
+    }
+
+    let row: RecentRow = sql_query(
+        "SELECT EXISTS (
+             SELECT 1 FROM magic_links
+             WHERE user_id = $1 AND created_at > NOW() - $2 * INTERVAL '1 second'
+         ) AS recent"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<diesel::sql_types::Integer, _>(seconds)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to check login links: {}", e)))?;
+    Ok(row.recent)
+}
+
+/// Mark an unused, unexpired login link as used and return its user. In one
+/// statement, so two requests can't both use the same link.
+pub async fn use_magic_link(pool: &DbPool, token_hash: &str) -> Result<Option<Uuid>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct UsedRow {
+        #[diesel(sql_type = diesel::sql_types::Uuid)]
+        user_id: Uuid,
+    }
+
+    let result: Result<UsedRow, _> = sql_query(
+        "UPDATE magic_links SET used_at = NOW()
+         WHERE token = $1 AND used_at IS NULL AND expires_at > NOW()
+         RETURNING user_id"
+    )
+    .bind::<Text, _>(token_hash)
+    .get_result(conn)
+    .await;
+
+    match result {
+        Ok(row) => Ok(Some(row.user_id)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to use login link: {}", e))),
+    }
+}
+
+// ============================================================
+// Email verification
+// ============================================================
+
+/// Create an email verification token (store hash in DB).
+pub async fn create_email_verification(
+    pool: &DbPool,
+    user_id: Uuid,
+    email: &str,
+    token_hash: &str,
+) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "INSERT INTO email_verification (user_id, email, verification_code)
+         VALUES ($1, $2, $3)"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(email)
+    .bind::<Text, _>(token_hash)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create verification: {}", e)))?;
+    Ok(())
+}
+
+/// Find valid (unverified) email verification tokens for a user.
+pub async fn get_pending_email_verifications(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<Vec<EmailVerificationRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(
+        "SELECT id, user_id, email, verification_code, created_at, verified_at
+         FROM email_verification
+         WHERE user_id = $1 AND verified_at IS NULL
+         ORDER BY created_at DESC"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_results(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to get verifications: {}", e)))
+}
+
+/// Mark an email verification as complete.
+pub async fn mark_email_verified(pool: &DbPool, verification_id: Uuid) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query("UPDATE email_verification SET verified_at = NOW() WHERE id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(verification_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to mark verified: {}", e)))?;
+    Ok(())
+}
+
+/// Find a user by email (for password reset lookups).
+pub async fn find_user_by_email(
+    pool: &DbPool,
+    email: &str,
+) -> Result<Option<AuthUser>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let result: Result<AuthUser, _> = sql_query(format!(
+        "SELECT {} FROM users WHERE email = $1 LIMIT 1",
+        AUTH_USER_COLUMNS
+    ))
+    .bind::<Text, _>(email)
+    .get_result(conn)
+    .await;
+
+    match result {
+        Ok(user) => Ok(Some(user)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to find user: {}", e))),
+    }
+}
+
+// ============================================================
+// Email changes
+// ============================================================
+
+const EMAIL_CHANGE_COLUMNS: &str = "id, user_id, old_email, new_email, expires_at, created_at";
+
+/// Store a pending email change, replacing any the user already has.
+pub async fn create_email_change(
+    pool: &DbPool,
+    user_id: Uuid,
+    old_email: Option<&str>,
+    new_email: &str,
+    confirm_hash: &str,
+    cancel_hash: &str,
+) -> Result<EmailChangeRow, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(format!(
+        "INSERT INTO email_changes (user_id, old_email, new_email, confirm_token, cancel_token)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (user_id) DO UPDATE
+         SET old_email = EXCLUDED.old_email, new_email = EXCLUDED.new_email,
+             confirm_token = EXCLUDED.confirm_token, cancel_token = EXCLUDED.cancel_token,
+             expires_at = now() + INTERVAL '24 hours', created_at = now()
+         RETURNING {}",
+        EMAIL_CHANGE_COLUMNS
+    ))
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<diesel::sql_types::Nullable<Text>, _>(old_email)
+    .bind::<Text, _>(new_email)
+    .bind::<Text, _>(confirm_hash)
+    .bind::<Text, _>(cancel_hash)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create email change: {}", e)))
+}
+
+/// A user's pending email change, if it hasn't expired.
+pub async fn get_email_change(pool: &DbPool, user_id: Uuid) -> Result<Option<EmailChangeRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let result: Result<EmailChangeRow, _> = sql_query(format!(
+        "SELECT {} FROM email_changes WHERE user_id = $1 AND expires_at > NOW()",
+        EMAIL_CHANGE_COLUMNS
+    ))
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_result(conn)
+    .await;
+
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to get email change: {}", e))),
+    }
+}
+
+/// Apply the email change a confirmation token belongs to. The new address
+/// counts as verified, since the link could only be opened from its inbox,
+/// and unused verification links for the old address are dropped. Fails
+/// with `DuplicateUser` if another account took the address in the meantime.
+pub async fn confirm_email_change(pool: &DbPool, confirm_hash: &str) -> Result<EmailChangeRow, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(format!(
+        "WITH change AS (
+             DELETE FROM email_changes
+             WHERE confirm_token = $1 AND expires_at > NOW()
+             RETURNING {}
+         ), updated AS (
+             UPDATE users SET email = change.new_email, is_email_verified = true, updated_at = now()
+             FROM change WHERE users.id = change.user_id
+         ), stale AS (
+             DELETE FROM email_verification
+             WHERE user_id IN (SELECT user_id FROM change) AND verified_at IS NULL
+         )
+         SELECT * FROM change",
+        EMAIL_CHANGE_COLUMNS
+    ))
+    .bind::<Text, _>(confirm_hash)
+    .get_result(conn)
+    .await
+    .map_err(|e| match e {
+        diesel::result::Error::NotFound => AuthError::InvalidEmailChangeToken,
+        e if e.to_string().contains("users_email_key") => AuthError::DuplicateUser("email address".to_string()),
+        e => AuthError::DatabaseError(format!("Failed to confirm email change: {}", e)),
+    })
+}
+
+/// Drop the email change a cancel token belongs to. Expiry isn't checked:
+/// an expired change can't be confirmed, so cancelling it is harmless.
+pub async fn cancel_email_change(pool: &DbPool, cancel_hash: &str) -> Result<EmailChangeRow, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(format!(
+        "DELETE FROM email_changes WHERE cancel_token = $1 RETURNING {}",
+        EMAIL_CHANGE_COLUMNS
+    ))
+    .bind::<Text, _>(cancel_hash)
+    .get_result(conn)
+    .await
+    .map_err(|e| match e {
+        diesel::result::Error::NotFound => AuthError::InvalidEmailChangeToken,
+        e => AuthError::DatabaseError(format!("Failed to cancel email change: {}", e)),
+    })
+}
+
+/// Drop a user's pending email change. Returns whether there was one.
+pub async fn delete_email_change(pool: &DbPool, user_id: Uuid) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let affected = sql_query("DELETE FROM email_changes WHERE user_id = $1 AND expires_at > NOW()")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to delete email change: {}", e)))?;
+    Ok(affected > 0)
+}
+
+// ============================================================
+// Two-factor authentication
+// ============================================================
+
+/// Get a user's TOTP enrollment, enabled or pending.
+pub async fn get_totp(pool: &DbPool, user_id: Uuid) -> Result<Option<TotpRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let result: Result<TotpRow, _> = sql_query(
+        "SELECT user_id, secret, enabled_at, last_used_step FROM user_totp WHERE user_id = $1"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_result(conn)
+    .await;
+
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to get TOTP: {}", e))),
+    }
+}
+
+/// Store a new secret awaiting confirmation, replacing any earlier pending
+/// one. Returns false if TOTP is already enabled, which is left untouched.
+pub async fn save_pending_totp(pool: &DbPool, user_id: Uuid, secret: &str) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let updated = sql_query(
+        "INSERT INTO user_totp (user_id, secret) VALUES ($1, $2)
+         ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret, created_at = NOW()
+         WHERE user_totp.enabled_at IS NULL"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(secret)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to save TOTP secret: {}", e)))?;
+    Ok(updated > 0)
+}
+
+/// Turn on a pending enrollment once its first code checks out. Returns
+/// false if it was enabled concurrently.
+pub async fn enable_totp(pool: &DbPool, user_id: Uuid, step: i64) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let updated = sql_query(
+        "UPDATE user_totp SET enabled_at = NOW(), last_used_step = $2
+         WHERE user_id = $1 AND enabled_at IS NULL"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<diesel::sql_types::BigInt, _>(step)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to enable TOTP: {}", e)))?;
+    Ok(updated > 0)
+}
+
+/// Record the time step of an accepted code. Returns false if that step (or
+/// a later one) was already used, so two requests racing with the same code
+/// can't both succeed.
+pub async fn record_totp_step(pool: &DbPool, user_id: Uuid, step: i64) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let updated = sql_query(
+        "UPDATE user_totp SET last_used_step = $2
+         WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<diesel::sql_types::BigInt, _>(step)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to record TOTP use: {}", e)))?;
+    Ok(updated > 0)
+}
+
+/// Remove a user's TOTP enrollment and recovery codes.
+pub async fn delete_totp(pool: &DbPool, user_id: Uuid) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to delete recovery codes: {}", e)))?;
+    sql_query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to delete TOTP: {}", e)))?;
+    Ok(())
+}
+
+/// Replace all of a user's recovery codes with a new set of hashes.
+pub async fn replace_recovery_codes(
+    pool: &DbPool,
+    user_id: Uuid,
+    code_hashes: &[String],
+) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to delete recovery codes: {}", e)))?;
+    sql_query(
+        "INSERT INTO user_recovery_codes (user_id, code_hash)
+         SELECT $1, unnest($2::text[])"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<diesel::sql_types::Array<Text>, _>(code_hashes)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to save recovery codes: {}", e)))?;
+    Ok(())
+}
+
+/// Spend a recovery code. Returns false if it doesn't match an unused code.
+pub async fn use_recovery_code(pool: &DbPool, user_id: Uuid, code_hash: &str) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let updated = sql_query(
+        "UPDATE user_recovery_codes SET used_at = NOW()
+         WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(code_hash)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to use recovery code: {}", e)))?;
+    Ok(updated > 0)
+}
+
+/// Number of unused recovery codes a user has left.
+pub async fn count_recovery_codes(pool: &DbPool, user_id: Uuid) -> Result<i64, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct CountRow {
+        #[diesel(sql_type = diesel::sql_types::BigInt)]
+        count: i64,
+    }
+
+    let row: CountRow = sql_query(
+        "SELECT COUNT(*) AS count FROM user_recovery_codes WHERE user_id = $1 AND used_at IS NULL"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to count recovery codes: {}", e)))?;
+    Ok(row.count)
+}
+
+/// Whether site policy requires this user to use two-factor authentication:
+/// admins when `require_two_factor_for_admins` is set, and board moderators
+/// when `require_two_factor_for_mods` is set.
+pub async fn is_two_factor_required(pool: &DbPool, user: &AuthUser) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct RequiredRow {
+        #[diesel(sql_type = diesel::sql_types::Bool)]
+        required: bool,
+    }
+
+    let row: RequiredRow = sql_query(
+        "SELECT COALESCE((
+             SELECT (s.require_two_factor_for_admins AND $2)
+                 OR (s.require_two_factor_for_mods AND EXISTS (
+                     SELECT 1 FROM board_moderators
+                     WHERE user_id = $1 AND is_invite_accepted
+                 ))
+             FROM site s LIMIT 1
+         ), false) AS required"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(user.id)
+    .bind::<diesel::sql_types::Bool, _>(user.is_admin || user.admin_level > 0)
+    .get_result(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to check 2FA policy: {}", e)))?;
+    Ok(row.required)
+}
+
+// ============================================================
+// Passkeys (WebAuthn)
+// ============================================================
+
+/// Columns of webauthn_credentials, in `PasskeyRow` order.
+const PASSKEY_COLUMNS: &str =
+    "id, user_id, credential_id, public_key, sign_count, name, transports, last_used_at, created_at";
+
+/// Store the challenge for a ceremony, clearing out expired ones on the way.
+/// `purpose` is `register`, `login` or `second_factor`.
+pub async fn create_webauthn_challenge(
+    pool: &DbPool,
+    challenge: &str,
+    user_id: Option<Uuid>,
+    purpose: &str,
+    ttl_seconds: i64,
+) -> Result<(), AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    sql_query("DELETE FROM webauthn_challenges WHERE expires_at < NOW()")
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to clean up passkey challenges: {}", e)))?;
+
+    sql_query(
+        "INSERT INTO webauthn_challenges (challenge, user_id, purpose, expires_at)
+         VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))"
+    )
+    .bind::<Text, _>(challenge)
+    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Uuid>, _>(user_id)
+    .bind::<Text, _>(purpose)
+    .bind::<diesel::sql_types::Double, _>(ttl_seconds as f64)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to create passkey challenge: {}", e)))?;
+    Ok(())
+}
+
+/// Remove and return an unexpired challenge, so it can only answer one response.
+pub async fn take_webauthn_challenge(
+    pool: &DbPool,
+    challenge: &str,
+) -> Result<Option<WebauthnChallengeRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let rows: Vec<WebauthnChallengeRow> = sql_query(
+        "DELETE FROM webauthn_challenges WHERE challenge = $1
+         RETURNING user_id, purpose, expires_at"
+    )
+    .bind::<Text, _>(challenge)
+    .load(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to check passkey challenge: {}", e)))?;
+
+    Ok(rows
+        .into_iter()
+        .next()
+        .filter(|row| row.expires_at > chrono::Utc::now().naive_utc()))
+}
+
+/// Store a newly registered passkey.
+pub async fn create_passkey(
+    pool: &DbPool,
+    user_id: Uuid,
+    credential_id: &str,
+    public_key: &[u8],
+    sign_count: i64,
+    name: &str,
+    transports: &[String],
+) -> Result<PasskeyRow, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(format!(
+        "INSERT INTO webauthn_credentials (user_id, credential_id, public_key, sign_count, name, transports)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING {}",
+        PASSKEY_COLUMNS
+    ))
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .bind::<Text, _>(credential_id)
+    .bind::<diesel::sql_types::Bytea, _>(public_key)
+    .bind::<diesel::sql_types::BigInt, _>(sign_count)
+    .bind::<Text, _>(name)
+    .bind::<diesel::sql_types::Array<Text>, _>(transports)
+    .get_result(conn)
+    .await
+    .map_err(|e| match e {
+        e if e.to_string().contains("webauthn_credentials_credential_id_key") => AuthError::PasskeyAlreadyRegistered,
+        e => AuthError::DatabaseError(format!("Failed to save passkey: {}", e)),
+    })
+}
+
+/// A user's passkeys, oldest first.
+pub async fn get_passkeys(pool: &DbPool, user_id: Uuid) -> Result<Vec<PasskeyRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    sql_query(format!(
+        "SELECT {} FROM webauthn_credentials WHERE user_id = $1 ORDER BY created_at",
+        PASSKEY_COLUMNS
+    ))
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_results(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to get passkeys: {}", e)))
+}
+
+/// Find a passkey by its base64url credential ID.
+pub async fn get_passkey_by_credential_id(
+    pool: &DbPool,
+    credential_id: &str,
+) -> Result<Option<PasskeyRow>, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let result: Result<PasskeyRow, _> = sql_query(format!(
+        "SELECT {} FROM webauthn_credentials WHERE credential_id = $1",
+        PASSKEY_COLUMNS
+    ))
+    .bind::<Text, _>(credential_id)
+    .get_result(conn)
+    .await;
+
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(diesel::result::Error::NotFound) => Ok(None),
+        Err(e) => Err(AuthError::DatabaseError(format!("Failed to get passkey: {}", e))),
+    }
+}
+
+/// Record a use of a passkey with its new sign count. Returns false if the
+/// count changed since `previous_count` was read, so two requests racing
+/// with the same assertion can't both succeed.
+pub async fn record_passkey_use(
+    pool: &DbPool,
+    passkey_id: Uuid,
+    previous_count: i64,
+    sign_count: i64,
+) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let updated = sql_query(
+        "UPDATE webauthn_credentials SET sign_count = $3, last_used_at = NOW()
+         WHERE id = $1 AND sign_count = $2"
+    )
+    .bind::<diesel::sql_types::Uuid, _>(passkey_id)
+    .bind::<diesel::sql_types::BigInt, _>(previous_count)
+    .bind::<diesel::sql_types::BigInt, _>(sign_count)
+    .execute(conn)
+    .await
+    .map_err(|e| AuthError::DatabaseError(format!("Failed to record passkey use: {}", e)))?;
+    Ok(updated > 0)
+}
+
+/// Delete one of a user's passkeys. Returns false if it doesn't exist.
+pub async fn delete_passkey(pool: &DbPool, passkey_id: Uuid, user_id: Uuid) -> Result<bool, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+    let deleted = sql_query("DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2")
+        .bind::<diesel::sql_types::Uuid, _>(passkey_id)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .execute(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to delete passkey: {}", e)))?;
+    Ok(deleted > 0)
+}
+
+/// Number of passkeys a user has.
+pub async fn count_passkeys(pool: &DbPool, user_id: Uuid) -> Result<i64, AuthError> {
+    let conn = &mut get_conn(pool).await?;
+
+    #[derive(diesel::QueryableByName)]
+    struct CountRow {
+        #[diesel(sql_type = diesel::sql_types::BigInt)]
+        count: i64,
+    }
+
+    let row: CountRow = sql_query("SELECT COUNT(*) AS count FROM webauthn_credentials WHERE user_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .get_result(conn)
+        .await
+        .map_err(|e| AuthError::DatabaseError(format!("Failed to count passkeys: {}", e)))?;
+    Ok(row.count)
+}
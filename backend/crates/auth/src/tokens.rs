@@ -2,7 +2,7 @@ use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation}
 use rand::RngCore;
 use uuid::Uuid;
 
-use crate::claims::Claims;
+use crate::claims::{Claims, MfaPendingClaims, MFA_PENDING_PURPOSE};
 use crate::errors::AuthError;
 use crate::types::UserRole;
 
@@ -38,6 +38,33 @@ pub fn validate_access_token(token: &str, jwt_secret: &str) -> Result<Claims, Au
     Ok(token_data.claims)
 }
 
+/// Generate the token that carries a user from a correct password to the
+/// second factor (5-minute lifetime).
+pub fn create_mfa_token(user_id: Uuid, jwt_secret: &str) -> Result<String, AuthError> {
+    let claims = MfaPendingClaims::new(user_id);
+    let key = EncodingKey::from_secret(jwt_secret.as_bytes());
+    encode(&Header::default(), &claims, &key)
+        .map_err(|e| AuthError::TokenGenerationFailed(e.to_string()))
+}
+
+/// Validate an MFA pending token and return its claims.
+pub fn decode_mfa_token(token: &str, jwt_secret: &str) -> Result<MfaPendingClaims, AuthError> {
+    let key = DecodingKey::from_secret(jwt_secret.as_bytes());
+    let claims = decode::<MfaPendingClaims>(token, &key, &Validation::default())
+        .map_err(|_| AuthError::InvalidMfaToken)?
+        .claims;
+
+    if claims.purpose != MFA_PENDING_PURPOSE {
+        return Err(AuthError::InvalidMfaToken);
+    }
+    Ok(claims)
+}
+
+/// Validate an MFA pending token and return the user it was issued to.
+pub fn validate_mfa_token(token: &str, jwt_secret: &str) -> Result<Uuid, AuthError> {
+    decode_mfa_token(token, jwt_secret).map(|claims| claims.sub)
+}
+
 /// Generate a cryptographically random refresh token (hex-encoded).
 ///
 /// The raw token is sent to the client as a cookie.
@@ -67,6 +94,23 @@ pub fn verify_refresh_token(raw_token: &str, stored_hash: &str) -> bool {
     hash_refresh_token(raw_token) == stored_hash
 }
 
+/// Prefix of personal access tokens, so they can't be mistaken for a JWT
+/// and are easy to spot if one leaks into a log or a repository.
+pub const PERSONAL_TOKEN_PREFIX: &str = "tbp_";
+
+/// Generate a personal access token: `tbp_` followed by 32 random bytes,
+/// hex-encoded. Like refresh tokens, only the hash is stored.
+pub fn generate_personal_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", PERSONAL_TOKEN_PREFIX, hex::encode(bytes))
+}
+
+/// Hash a personal access token for storage and lookup.
+pub fn hash_personal_token(token: &str) -> String {
+    hash_refresh_token(token)
+}
+
 /// Generate a random token for password resets or email verification.
 pub fn generate_random_token() -> String {
     let mut bytes = [0u8; 32];
@@ -76,7 +120,7 @@ pub fn generate_random_token() -> String {
 
 /// Simple SHA-256 implementation without external ring dependency.
 /// Uses the built-in approach with manual computation.
-fn ring_free_sha256(data: &[u8]) -> [u8; 32] {
+pub(crate) fn ring_free_sha256(data: &[u8]) -> [u8; 32] {
     // Initial hash values (first 32 bits of fractional parts of square roots of first 8 primes)
     let mut h: [u32; 8] = [
         0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
@@ -210,6 +254,28 @@ mod tests {
         assert!(validate_access_token(&token, "secret2").is_err());
     }
 
+    #[test]
+    fn test_mfa_token_is_not_an_access_token() {
+        let user_id = Uuid::new_v4();
+        let secret = "test_jwt_secret_for_unit_tests";
+
+        let mfa_token = create_mfa_token(user_id, secret).expect("token creation should succeed");
+        assert_eq!(validate_mfa_token(&mfa_token, secret).unwrap(), user_id);
+        assert!(validate_mfa_token(&mfa_token, "secret2").is_err());
+        assert!(validate_access_token(&mfa_token, secret).is_err());
+
+        // Each token is counted on its own
+        let other = create_mfa_token(user_id, secret).expect("token creation should succeed");
+        assert_ne!(
+            decode_mfa_token(&mfa_token, secret).unwrap().jti,
+            decode_mfa_token(&other, secret).unwrap().jti
+        );
+
+        let access_token = create_access_token(user_id, UserRole::User, secret)
+            .expect("token creation should succeed");
+        assert!(validate_mfa_token(&access_token, secret).is_err());
+    }
+
     #[test]
     fn test_refresh_token_generation() {
         let token1 = generate_refresh_token();
@@ -232,6 +298,20 @@ mod tests {
         assert!(!verify_refresh_token("wrong_token", &hash));
     }
 
+    #[test]
+    fn test_personal_token_generation() {
+        let token = generate_personal_token();
+
+        assert!(token.starts_with(PERSONAL_TOKEN_PREFIX));
+        assert_eq!(token.len(), PERSONAL_TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, generate_personal_token());
+
+        // A personal token is never a valid access token
+        assert!(validate_access_token(&token, "test_jwt_secret_for_unit_tests").is_err());
+        assert_eq!(hash_personal_token(&token), hash_personal_token(&token));
+        assert_ne!(hash_personal_token(&token), hash_personal_token(&generate_personal_token()));
+    }
+
     #[test]
     fn test_sha256_known_vector() {
         // SHA-256 of empty string
@@ -52,6 +52,8 @@ pub struct AuthSessionRow {
     pub expires_at: NaiveDateTime,
     #[diesel(sql_type = Timestamptz)]
     pub created_at: NaiveDateTime,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub name: Option<String>,
 }
 
 /// Password reset row from the password_resets table.
@@ -88,6 +90,67 @@ pub struct EmailVerificationRow {
     pub verified_at: Option<NaiveDateTime>,
 }
 
+/// Pending email change row. The token columns hold hashes.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct EmailChangeRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub user_id: Uuid,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub old_email: Option<String>,
+    #[diesel(sql_type = Text)]
+    pub new_email: String,
+    #[diesel(sql_type = Timestamptz)]
+    pub expires_at: NaiveDateTime,
+    #[diesel(sql_type = Timestamptz)]
+    pub created_at: NaiveDateTime,
+}
+
+/// TOTP enrollment row from the user_totp table.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct TotpRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub user_id: Uuid,
+    /// Base32 shared secret
+    #[diesel(sql_type = Text)]
+    pub secret: String,
+    /// `None` while enrollment awaits its first code
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub enabled_at: Option<NaiveDateTime>,
+    /// Time step of the last accepted code, so it can't be replayed
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub last_used_step: Option<i64>,
+}
+
+impl TotpRow {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+}
+
+/// Recent failed logins from one IP.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct IpLoginFailures {
+    #[diesel(sql_type = BigInt)]
+    pub failures: i64,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub last_failed_at: Option<NaiveDateTime>,
+}
+
+/// Consecutive failed logins for an account, from the account_lockouts table.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct AccountLockoutRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub user_id: Uuid,
+    #[diesel(sql_type = Integer)]
+    pub failed_attempts: i32,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub locked_until: Option<NaiveDateTime>,
+    #[diesel(sql_type = Timestamptz)]
+    pub last_failed_at: NaiveDateTime,
+}
+
 /// JWT secret row from the secrets table.
 #[derive(Debug, Clone, diesel::QueryableByName)]
 pub struct JwtSecretRow {
@@ -104,6 +167,103 @@ pub struct SiteRegistrationInfo {
     pub application_question: Option<String>,
 }
 
+/// Captcha settings from the site table.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct SiteCaptchaInfo {
+    #[diesel(sql_type = Bool)]
+    pub captcha_enabled: bool,
+    #[diesel(sql_type = Text)]
+    pub captcha_difficulty: String,
+}
+
+/// Captcha challenge row from the captcha_challenges table.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct CaptchaChallengeRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub salt: String,
+    #[diesel(sql_type = Integer)]
+    pub difficulty: i32,
+    #[diesel(sql_type = Timestamptz)]
+    pub expires_at: NaiveDateTime,
+}
+
+/// In-flight single sign-on login from the oidc_auth_requests table.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct OidcAuthRequestRow {
+    #[diesel(sql_type = Text)]
+    pub provider: String,
+    #[diesel(sql_type = Text)]
+    pub code_verifier: String,
+    #[diesel(sql_type = Text)]
+    pub nonce: String,
+    #[diesel(sql_type = Text)]
+    pub redirect_to: String,
+    #[diesel(sql_type = Timestamptz)]
+    pub expires_at: NaiveDateTime,
+}
+
+/// Personal access token from the api_tokens table. The token hash is
+/// never loaded.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct ApiTokenRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub user_id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    #[diesel(sql_type = Text)]
+    pub token_prefix: String,
+    #[diesel(sql_type = Text)]
+    pub scope: String,
+    #[diesel(sql_type = Bool)]
+    pub is_bot: bool,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub last_used_at: Option<NaiveDateTime>,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub expires_at: Option<NaiveDateTime>,
+    #[diesel(sql_type = Timestamptz)]
+    pub created_at: NaiveDateTime,
+}
+
+/// WebAuthn credential from the webauthn_credentials table.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct PasskeyRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub user_id: Uuid,
+    /// Base64url credential ID
+    #[diesel(sql_type = Text)]
+    pub credential_id: String,
+    /// COSE_Key public key
+    #[diesel(sql_type = Bytea)]
+    pub public_key: Vec<u8>,
+    #[diesel(sql_type = BigInt)]
+    pub sign_count: i64,
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    #[diesel(sql_type = Array<Text>)]
+    pub transports: Vec<String>,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub last_used_at: Option<NaiveDateTime>,
+    #[diesel(sql_type = Timestamptz)]
+    pub created_at: NaiveDateTime,
+}
+
+/// A WebAuthn ceremony in progress, from the webauthn_challenges table.
+#[derive(Debug, Clone, diesel::QueryableByName)]
+pub struct WebauthnChallengeRow {
+    #[diesel(sql_type = Nullable<diesel::sql_types::Uuid>)]
+    pub user_id: Option<Uuid>,
+    #[diesel(sql_type = Text)]
+    pub purpose: String,
+    #[diesel(sql_type = Timestamptz)]
+    pub expires_at: NaiveDateTime,
+}
+
 /// Result of user creation (just the id and name).
 #[derive(Debug, Clone, diesel::QueryableByName)]
 pub struct CreatedUser {
@@ -121,6 +281,9 @@ pub struct CreatedUser {
 pub struct LoginRequest {
     pub username_or_email: String,
     pub password: String,
+    /// Required after repeated failures from the same IP when the site has
+    /// captcha enabled
+    pub captcha: Option<CaptchaSolution>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -131,6 +294,15 @@ pub struct RegisterRequest {
     pub password: String,
     pub invite_code: Option<String>,
     pub application_answer: Option<String>,
+    /// Required when the site has captcha enabled
+    pub captcha: Option<CaptchaSolution>,
+}
+
+/// A client's answer to a challenge from `POST /captcha`.
+#[derive(Debug, Deserialize)]
+pub struct CaptchaSolution {
+    pub id: Uuid,
+    pub nonce: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -149,12 +321,124 @@ pub struct PasswordResetRequest {
     pub email: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkComplete {
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PasswordResetComplete {
     pub token: String,
     pub new_password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LoginTwoFactorRequest {
+    pub mfa_token: String,
+    /// Code from the authenticator app
+    pub code: Option<String>,
+    /// One of the account's recovery codes, instead of `code`
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorSetupRequest {
+    /// Enroll during login, when the account is required to have 2FA.
+    /// Otherwise the session cookie identifies the user.
+    pub mfa_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorEnableRequest {
+    pub code: String,
+    /// Present when enrolling during login; enabling then also logs in
+    pub mfa_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorDisableRequest {
+    pub password: String,
+    /// Authenticator or recovery code
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterPasskeyRequest {
+    pub password: String,
+    /// Label to tell passkeys apart, e.g. "Phone" or "YubiKey"
+    pub name: String,
+    pub credential: crate::webauthn::RegistrationCredential,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeletePasskeyRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeyLoginOptionsRequest {
+    /// Use the passkey as the second step of a password login. Without it
+    /// the passkey logs in on its own.
+    pub mfa_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeyLoginRequest {
+    pub credential: crate::webauthn::AuthenticationCredential,
+    /// Must match the request for options
+    pub mfa_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionListQuery {
+    /// Another user's sessions (admins only)
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameSessionRequest {
+    /// New label; empty or missing clears it
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiTokenListQuery {
+    /// Another user's tokens (admins only)
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scope: ApiTokenScope,
+    /// Days until the token stops working; missing means it never expires
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcAuthorizeQuery {
+    /// Same-site path to return to after logging in
+    pub redirect: Option<String>,
+}
+
+/// What the provider sends back: `code` and `state`, or an `error`.
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct EmailVerifyRequest {
     pub token: String,
@@ -165,6 +449,24 @@ pub struct RequestEmailVerification {
     pub email: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RequestEmailChange {
+    pub new_email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmEmailChangeRequest {
+    pub token: String,
+}
+
+/// `token` comes from the cancel link sent to the old address. Without it,
+/// the logged-in user's own pending change is cancelled.
+#[derive(Debug, Deserialize)]
+pub struct CancelEmailChangeRequest {
+    pub token: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub success: bool,
@@ -174,6 +476,233 @@ pub struct AuthResponse {
     pub user: Option<UserInfo>,
 }
 
+/// Login response for an account with (or required to have) two-factor
+/// authentication. No session is created; `mfa_token` is exchanged at
+/// `/login/2fa`, or used to enroll when `enrollment_required` is set.
+#[derive(Debug, Serialize)]
+pub struct MfaRequiredResponse {
+    pub success: bool,
+    pub mfa_required: bool,
+    pub enrollment_required: bool,
+    /// The account has passkeys, which can be used for the second step
+    /// through `/login/passkey`
+    pub passkey: bool,
+    pub mfa_token: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub success: bool,
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI for QR codes
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+    /// Site policy requires 2FA for this account (admin or moderator)
+    pub required: bool,
+    pub recovery_codes_remaining: i64,
+    /// Registered passkeys, which also count as a second factor
+    pub passkeys: i64,
+}
+
+/// Freshly generated recovery codes. They're only ever shown once.
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub success: bool,
+    pub recovery_codes: Vec<String>,
+    /// Set when enabling 2FA completed a login
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserInfo>,
+}
+
+/// An active session as shown in the session list. The refresh token hash
+/// never leaves the server.
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub name: Option<String>,
+    /// Human-readable summary, e.g. "Firefox 128 on Windows"
+    pub device: String,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device_type: crate::user_agent::DeviceType,
+    pub ip_address: Option<String>,
+    pub created_at: String,
+    /// Last token refresh, so accurate to within the access token lifetime
+    pub last_seen_at: String,
+    pub expires_at: String,
+    /// The session making this request
+    pub current: bool,
+}
+
+impl SessionInfo {
+    /// `current_token_hash` is the hash of the caller's refresh cookie, if any.
+    pub fn from_row(row: AuthSessionRow, current_token_hash: Option<&str>) -> Self {
+        let device = crate::user_agent::parse(row.user_agent.as_deref().unwrap_or_default());
+        SessionInfo {
+            id: row.id,
+            name: row.name,
+            device: device.describe(),
+            browser: device.browser,
+            os: device.os,
+            device_type: device.device_type,
+            ip_address: row.ip_address,
+            created_at: row.created_at.and_utc().to_rfc3339(),
+            last_seen_at: row.last_used_at.unwrap_or(row.created_at).and_utc().to_rfc3339(),
+            expires_at: row.expires_at.and_utc().to_rfc3339(),
+            current: current_token_hash == Some(row.refresh_token_hash.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionListResponse {
+    pub sessions: Vec<SessionInfo>,
+}
+
+/// A personal access token as shown in the token list.
+#[derive(Debug, Serialize)]
+pub struct ApiTokenInfo {
+    pub id: Uuid,
+    pub name: String,
+    /// Start of the token, enough to tell tokens apart
+    pub token_prefix: String,
+    pub scope: String,
+    /// Created on a bot account
+    pub bot: bool,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+impl From<ApiTokenRow> for ApiTokenInfo {
+    fn from(row: ApiTokenRow) -> Self {
+        ApiTokenInfo {
+            id: row.id,
+            name: row.name,
+            token_prefix: row.token_prefix,
+            scope: row.scope,
+            bot: row.is_bot,
+            created_at: row.created_at.and_utc().to_rfc3339(),
+            last_used_at: row.last_used_at.map(|t| t.and_utc().to_rfc3339()),
+            expires_at: row.expires_at.map(|t| t.and_utc().to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTokenListResponse {
+    pub tokens: Vec<ApiTokenInfo>,
+}
+
+/// A newly created token. `token` is shown this once and can't be
+/// retrieved again.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiTokenResponse {
+    pub token: String,
+    pub api_token: ApiTokenInfo,
+}
+
+/// A registered passkey as shown in the passkey list.
+#[derive(Debug, Serialize)]
+pub struct PasskeyInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+impl From<PasskeyRow> for PasskeyInfo {
+    fn from(row: PasskeyRow) -> Self {
+        PasskeyInfo {
+            id: row.id,
+            name: row.name,
+            created_at: row.created_at.and_utc().to_rfc3339(),
+            last_used_at: row.last_used_at.map(|t| t.and_utc().to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasskeyListResponse {
+    pub passkeys: Vec<PasskeyInfo>,
+}
+
+/// Options for `navigator.credentials.create()`.
+#[derive(Debug, Serialize)]
+pub struct PasskeyCreationOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: crate::webauthn::CreationOptions,
+}
+
+/// Options for `navigator.credentials.get()`.
+#[derive(Debug, Serialize)]
+pub struct PasskeyRequestOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: crate::webauthn::RequestOptions,
+}
+
+/// An email change waiting for the new address to be confirmed.
+#[derive(Debug, Serialize)]
+pub struct PendingEmailChange {
+    pub new_email: String,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+impl From<EmailChangeRow> for PendingEmailChange {
+    fn from(row: EmailChangeRow) -> Self {
+        PendingEmailChange {
+            new_email: row.new_email,
+            created_at: row.created_at.and_utc().to_rfc3339(),
+            expires_at: row.expires_at.and_utc().to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailChangeStatusResponse {
+    pub pending: Option<PendingEmailChange>,
+}
+
+/// Whether the site wants a captcha, plus a fresh challenge when it does.
+#[derive(Debug, Serialize)]
+pub struct CaptchaResponse {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<CaptchaChallenge>,
+}
+
+/// Find a `nonce` such that SHA-256 of `salt` followed by `nonce` starts
+/// with `difficulty` zero bits.
+#[derive(Debug, Serialize)]
+pub struct CaptchaChallenge {
+    pub id: Uuid,
+    pub algorithm: &'static str,
+    pub salt: String,
+    pub difficulty: i32,
+    pub expires_at: String,
+}
+
+/// A single sign-on provider, as shown on the login page.
+#[derive(Debug, Serialize)]
+pub struct OidcProviderInfo {
+    pub name: String,
+    pub display_name: String,
+    /// Start the login by navigating here
+    pub authorize_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcProvidersResponse {
+    pub providers: Vec<OidcProviderInfo>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserInfo {
     pub id: Uuid,
@@ -220,3 +749,39 @@ impl UserRole {
         matches!(self, UserRole::Admin(_))
     }
 }
+
+/// What a personal access token may do. Each scope includes the ones before
+/// it, and none grants more than the account itself has.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiTokenScope {
+    /// Queries and subscriptions only
+    Read,
+    /// Acting as the user: posting, commenting, voting, messaging
+    Post,
+    /// Board moderation, on boards the user moderates
+    Moderate,
+    /// Site administration, up to the user's admin level
+    Admin,
+}
+
+impl ApiTokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTokenScope::Read => "read",
+            ApiTokenScope::Post => "post",
+            ApiTokenScope::Moderate => "moderate",
+            ApiTokenScope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "read" => Some(ApiTokenScope::Read),
+            "post" => Some(ApiTokenScope::Post),
+            "moderate" => Some(ApiTokenScope::Moderate),
+            "admin" => Some(ApiTokenScope::Admin),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Best-effort parsing of `User-Agent` headers into something a person can
+//! recognise in a session list ("Firefox 128 on Windows"). This only needs to
+//! tell a user's own devices apart, so it covers common browsers and platforms
+//! rather than every UA string in the wild.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceType {
+    Desktop,
+    Mobile,
+    Tablet,
+    Bot,
+    Unknown,
+}
+
+impl DeviceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceType::Desktop => "desktop",
+            DeviceType::Mobile => "mobile",
+            DeviceType::Tablet => "tablet",
+            DeviceType::Bot => "bot",
+            DeviceType::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceInfo {
+    /// Browser or client with its major version, e.g. "Firefox 128"
+    pub browser: Option<String>,
+    /// Operating system, e.g. "Windows" or "iOS"
+    pub os: Option<String>,
+    pub device_type: DeviceType,
+}
+
+impl DeviceInfo {
+    /// Short description such as "Firefox 128 on Windows".
+    pub fn describe(&self) -> String {
+        match (&self.browser, &self.os) {
+            (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+            (Some(browser), None) => browser.clone(),
+            (None, Some(os)) => os.clone(),
+            (None, None) => "Unknown device".to_string(),
+        }
+    }
+}
+
+/// Browser tokens, most specific first: Edge and Opera also claim to be
+/// Chrome, and almost everything claims to be Safari.
+const BROWSERS: &[(&str, &str)] = &[
+    ("Edg/", "Edge"),
+    ("EdgA/", "Edge"),
+    ("EdgiOS/", "Edge"),
+    ("OPR/", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("FxiOS/", "Firefox"),
+    ("Firefox/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Version/", "Safari"),
+    ("curl/", "curl"),
+    ("Wget/", "Wget"),
+];
+
+const BOT_MARKERS: &[&str] = &["bot", "crawler", "spider", "slurp"];
+
+pub fn parse(user_agent: &str) -> DeviceInfo {
+    let lower = user_agent.to_ascii_lowercase();
+
+    let os = if user_agent.contains("iPhone") || user_agent.contains("iPod") {
+        Some("iOS")
+    } else if user_agent.contains("iPad") {
+        Some("iPadOS")
+    } else if user_agent.contains("Android") {
+        Some("Android")
+    } else if user_agent.contains("Windows") {
+        Some("Windows")
+    } else if user_agent.contains("CrOS") {
+        Some("ChromeOS")
+    } else if user_agent.contains("Mac OS X") || user_agent.contains("Macintosh") {
+        Some("macOS")
+    } else if user_agent.contains("Linux") {
+        Some("Linux")
+    } else {
+        None
+    };
+
+    let browser = BROWSERS.iter().find_map(|(token, name)| {
+        let version = user_agent.split(token).nth(1)?;
+        let major: String = version.chars().take_while(|c| c.is_ascii_digit()).collect();
+        Some(if major.is_empty() {
+            name.to_string()
+        } else {
+            format!("{} {}", name, major)
+        })
+    });
+
+    let device_type = if BOT_MARKERS.iter().any(|m| lower.contains(m)) {
+        DeviceType::Bot
+    } else if user_agent.contains("iPad") || (os == Some("Android") && !user_agent.contains("Mobile")) {
+        DeviceType::Tablet
+    } else if user_agent.contains("Mobi") || user_agent.contains("iPhone") {
+        DeviceType::Mobile
+    } else if os.is_some() {
+        DeviceType::Desktop
+    } else {
+        DeviceType::Unknown
+    };
+
+    DeviceInfo {
+        browser,
+        os: os.map(str::to_string),
+        device_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_browsers() {
+        let firefox = parse("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:128.0) Gecko/20100101 Firefox/128.0");
+        assert_eq!(firefox.browser.as_deref(), Some("Firefox 128"));
+        assert_eq!(firefox.os.as_deref(), Some("Windows"));
+        assert_eq!(firefox.device_type, DeviceType::Desktop);
+        assert_eq!(firefox.describe(), "Firefox 128 on Windows");
+
+        let edge = parse("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0");
+        assert_eq!(edge.describe(), "Edge 126 on macOS");
+
+        let safari = parse("Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1");
+        assert_eq!(safari.describe(), "Safari 17 on iOS");
+        assert_eq!(safari.device_type, DeviceType::Mobile);
+
+        let chrome = parse("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36");
+        assert_eq!(chrome.describe(), "Chrome 126 on Android");
+        assert_eq!(chrome.device_type, DeviceType::Mobile);
+    }
+
+    #[test]
+    fn test_parse_other_clients() {
+        let tablet = parse("Mozilla/5.0 (Linux; Android 13; SM-X710) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36");
+        assert_eq!(tablet.device_type, DeviceType::Tablet);
+
+        let curl = parse("curl/8.5.0");
+        assert_eq!(curl.describe(), "curl 8");
+        assert_eq!(curl.device_type, DeviceType::Unknown);
+
+        let bot = parse("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)");
+        assert_eq!(bot.device_type, DeviceType::Bot);
+
+        assert_eq!(parse("").describe(), "Unknown device");
+    }
+}
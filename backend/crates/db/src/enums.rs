@@ -170,6 +170,10 @@ pg_enum! {
         PurgeBoard => b"purge_board",
         MarkNsfw => b"mark_nsfw",
         UnmarkNsfw => b"unmark_nsfw",
+        FilterPost => b"filter_post",
+        FlairPost => b"flair_post",
+        CommentPost => b"comment_post",
+        ReportPost => b"report_post",
     }
 }
 
@@ -1,4 +1,4 @@
-use crate::schema::{auth_sessions, email_verification, password_resets, secrets};
+use crate::schema::{api_tokens, auth_sessions, email_changes, email_verification, magic_links, password_resets, secrets};
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -19,6 +19,8 @@ pub struct AuthSession {
     pub last_used_at: Option<DateTime<Utc>>,
     pub expires_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    /// Label the user gave this session
+    pub name: Option<String>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -31,6 +33,29 @@ pub struct AuthSessionInsertForm {
     pub expires_at: DateTime<Utc>,
 }
 
+// ============================================================
+// api_tokens
+// ============================================================
+
+/// Personal access token. Created and revoked through the auth endpoints;
+/// the GraphQL layer only looks tokens up.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[diesel(table_name = api_tokens)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_hash: String,
+    pub token_prefix: String,
+    /// read, post, moderate or admin
+    pub scope: String,
+    /// Created on a bot account
+    pub is_bot: bool,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
 // ============================================================
 // secrets
 // ============================================================
@@ -87,3 +112,38 @@ pub struct EmailVerificationInsertForm {
     pub email: String,
     pub verification_code: String,
 }
+
+// ============================================================
+// email_changes
+// ============================================================
+
+/// Pending change of a user's email address, applied once the new address
+/// is confirmed.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[diesel(table_name = email_changes)]
+pub struct EmailChange {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub old_email: Option<String>,
+    pub new_email: String,
+    pub confirm_token: String,
+    pub cancel_token: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+// ============================================================
+// magic_links
+// ============================================================
+
+/// One-time passwordless login link.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[diesel(table_name = magic_links)]
+pub struct MagicLink {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
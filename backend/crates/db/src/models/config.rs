@@ -67,6 +67,10 @@ pub struct RateLimit {
     pub search_per_second: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub login: i32,
+    pub login_per_second: i32,
+    pub magic_link: i32,
+    pub magic_link_per_second: i32,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -79,12 +83,16 @@ pub struct RateLimitInsertForm {
     pub post_per_second: i32,
     pub register: i32,
     pub register_per_second: i32,
+    pub login: i32,
+    pub login_per_second: i32,
     pub image: i32,
     pub image_per_second: i32,
     pub comment: i32,
     pub comment_per_second: i32,
     pub search: i32,
     pub search_per_second: i32,
+    pub magic_link: i32,
+    pub magic_link_per_second: i32,
 }
 
 #[derive(Debug, Clone, AsChangeset)]
@@ -96,10 +104,14 @@ pub struct RateLimitUpdateForm {
     pub post_per_second: Option<i32>,
     pub register: Option<i32>,
     pub register_per_second: Option<i32>,
+    pub login: Option<i32>,
+    pub login_per_second: Option<i32>,
     pub image: Option<i32>,
     pub image_per_second: Option<i32>,
     pub comment: Option<i32>,
     pub comment_per_second: Option<i32>,
     pub search: Option<i32>,
     pub search_per_second: Option<i32>,
+    pub magic_link: Option<i32>,
+    pub magic_link_per_second: Option<i32>,
 }
@@ -19,6 +19,8 @@ pub struct PrivateMessage {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub thread_id: Option<Uuid>,
+    pub is_mod_note: bool,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -32,6 +34,8 @@ pub struct PrivateMessageInsertForm {
     pub body_html: String,
     pub is_read: bool,
     pub is_sender_hidden: bool,
+    pub thread_id: Option<Uuid>,
+    pub is_mod_note: bool,
 }
 
 #[derive(Debug, Clone, AsChangeset)]
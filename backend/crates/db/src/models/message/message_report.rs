@@ -0,0 +1,43 @@
+use crate::enums::DbReportStatus;
+use crate::schema::message_reports;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Queryable struct for the message_reports table.
+/// Field order matches schema.rs column order exactly.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = message_reports)]
+pub struct MessageReport {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub message_id: Uuid,
+    pub original_message_text: String,
+    pub reason: String,
+    pub status: DbReportStatus,
+    pub resolver_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Insert form for reporting a private message.
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = message_reports)]
+pub struct MessageReportInsertForm {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub message_id: Uuid,
+    pub original_message_text: String,
+    pub reason: String,
+    pub status: DbReportStatus,
+}
+
+/// Update form for modifying an existing message report (resolution).
+#[derive(Debug, Clone, AsChangeset, Default)]
+#[diesel(table_name = message_reports)]
+pub struct MessageReportUpdateForm {
+    pub status: Option<DbReportStatus>,
+    pub resolver_id: Option<Option<Uuid>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
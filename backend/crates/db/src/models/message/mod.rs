@@ -1 +1,3 @@
 pub mod message;
+pub mod message_report;
+pub mod modmail_thread;
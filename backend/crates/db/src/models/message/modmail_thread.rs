@@ -0,0 +1,41 @@
+use crate::schema::modmail_threads;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A modmail conversation between a user and a board's moderators.
+/// `status` is one of `new`, `in_progress` or `archived`.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = modmail_threads)]
+pub struct ModmailThread {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub user_id: Uuid,
+    pub subject: String,
+    pub status: String,
+    pub assigned_to: Option<Uuid>,
+    pub is_read_by_user: bool,
+    pub is_read_by_mods: bool,
+    pub last_message_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = modmail_threads)]
+pub struct ModmailThreadInsertForm {
+    pub board_id: Uuid,
+    pub user_id: Uuid,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, AsChangeset, Default)]
+#[diesel(table_name = modmail_threads)]
+pub struct ModmailThreadUpdateForm {
+    pub status: Option<String>,
+    pub assigned_to: Option<Option<Uuid>>,
+    pub is_read_by_user: Option<bool>,
+    pub is_read_by_mods: Option<bool>,
+    pub last_message_at: Option<DateTime<Utc>>,
+}
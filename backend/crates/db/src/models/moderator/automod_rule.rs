@@ -0,0 +1,49 @@
+use crate::schema::automod_rules;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Account that AutoModerator acts as. Its moderation log entries, comments
+/// and reports are attributed to it; nobody can log in to it.
+pub const AUTOMOD_USER_ID: Uuid = Uuid::from_u128(0xa070);
+
+/// Name given to the AutoModerator account when it's created.
+pub const AUTOMOD_USER_NAME: &str = "AutoModerator";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = automod_rules)]
+pub struct AutomodRule {
+    pub id: Uuid,
+    pub board_id: Option<Uuid>,
+    pub name: String,
+    pub conditions: serde_json::Value,
+    pub actions: serde_json::Value,
+    pub is_enabled: bool,
+    pub display_order: i32,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = automod_rules)]
+pub struct AutomodRuleInsertForm {
+    pub board_id: Option<Uuid>,
+    pub name: String,
+    pub conditions: serde_json::Value,
+    pub actions: serde_json::Value,
+    pub is_enabled: bool,
+    pub display_order: i32,
+    pub created_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, AsChangeset, Default)]
+#[diesel(table_name = automod_rules)]
+pub struct AutomodRuleUpdateForm {
+    pub name: Option<String>,
+    pub conditions: Option<serde_json::Value>,
+    pub actions: Option<serde_json::Value>,
+    pub is_enabled: Option<bool>,
+    pub display_order: Option<i32>,
+}
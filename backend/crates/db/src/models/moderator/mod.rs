@@ -1 +1,3 @@
+pub mod automod_rule;
 pub mod moderation_log;
+pub mod removal_reason;
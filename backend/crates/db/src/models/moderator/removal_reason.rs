@@ -0,0 +1,39 @@
+use crate::schema::removal_reasons;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = removal_reasons)]
+pub struct RemovalReason {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub title: String,
+    pub message: String,
+    pub rule: Option<String>,
+    pub display_order: i32,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = removal_reasons)]
+pub struct RemovalReasonInsertForm {
+    pub board_id: Uuid,
+    pub title: String,
+    pub message: String,
+    pub rule: Option<String>,
+    pub display_order: i32,
+    pub created_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, AsChangeset, Default)]
+#[diesel(table_name = removal_reasons)]
+pub struct RemovalReasonUpdateForm {
+    pub title: Option<String>,
+    pub message: Option<String>,
+    pub rule: Option<Option<String>>,
+    pub display_order: Option<i32>,
+}
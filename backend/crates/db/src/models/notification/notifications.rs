@@ -17,6 +17,8 @@ pub struct Notification {
     pub is_read: bool,
     pub created_at: DateTime<Utc>,
     pub actor_user_id: Option<Uuid>,
+    /// Text of a system notification
+    pub body: Option<String>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -29,4 +31,5 @@ pub struct NotificationInsertForm {
     pub message_id: Option<Uuid>,
     pub is_read: bool,
     pub actor_user_id: Option<Uuid>,
+    pub body: Option<String>,
 }
@@ -67,6 +67,9 @@ pub struct Site {
     pub updated_at: DateTime<Utc>,
     pub custom_css: Option<String>,
     pub custom_css_enabled: bool,
+    pub require_two_factor_for_admins: bool,
+    pub require_two_factor_for_mods: bool,
+    pub magic_link_login_enabled: bool,
 }
 
 /// Form for inserting a new site row.
@@ -180,4 +183,7 @@ pub struct SiteUpdateForm {
     pub default_board_mode: Option<DbBoardMode>,
     pub custom_css: Option<Option<String>>,
     pub custom_css_enabled: Option<bool>,
+    pub require_two_factor_for_admins: Option<bool>,
+    pub require_two_factor_for_mods: Option<bool>,
+    pub magic_link_login_enabled: Option<bool>,
 }
@@ -1,4 +1,7 @@
-use crate::schema::{uploads, content_uploads};
+use crate::schema::{
+    content_uploads, media_gc_runs, media_processing_jobs, storage_migration_objects,
+    storage_migrations, uploads,
+};
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -22,6 +25,12 @@ pub struct Upload {
     pub thumbnail_url: Option<String>,
     pub optimized_url: Option<String>,
     pub processing_status: String,
+    /// SHA-256 of the stored bytes; `None` for uploads made before deduplication
+    pub content_hash: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// Running time of a video or animation
+    pub duration_seconds: Option<f64>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -36,6 +45,10 @@ pub struct UploadInsertForm {
     pub thumbnail_url: Option<String>,
     pub optimized_url: Option<String>,
     pub processing_status: Option<String>,
+    pub content_hash: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_seconds: Option<f64>,
 }
 
 // ============================================================
@@ -61,3 +74,127 @@ pub struct ContentUploadInsertForm {
     pub comment_id: Option<Uuid>,
     pub position: Option<i32>,
 }
+
+// ============================================================
+// media_processing_jobs
+// ============================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, QueryableByName, Identifiable)]
+#[diesel(table_name = media_processing_jobs)]
+pub struct MediaProcessingJob {
+    pub id: Uuid,
+    pub upload_id: Uuid,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub run_at: DateTime<Utc>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = media_processing_jobs)]
+pub struct MediaProcessingJobInsertForm {
+    pub upload_id: Uuid,
+}
+
+// ============================================================
+// media_gc_runs
+// ============================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = media_gc_runs)]
+pub struct MediaGcRun {
+    pub id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub orphaned_uploads_deleted: i32,
+    pub orphaned_files_deleted: i32,
+    pub bytes_freed: i64,
+    pub missing_files: i32,
+    pub total_files: i64,
+    pub total_bytes: i64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = media_gc_runs)]
+pub struct MediaGcRunInsertForm {
+    pub started_at: DateTime<Utc>,
+    pub orphaned_uploads_deleted: i32,
+    pub orphaned_files_deleted: i32,
+    pub bytes_freed: i64,
+    pub missing_files: i32,
+    pub total_files: i64,
+    pub total_bytes: i64,
+    pub error: Option<String>,
+}
+
+// ============================================================
+// storage_migrations
+// ============================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = storage_migrations)]
+pub struct StorageMigration {
+    pub id: Uuid,
+    pub source_backend: String,
+    pub target_backend: String,
+    pub status: String,
+    pub total_objects: i32,
+    pub copied_objects: i32,
+    pub missing_objects: i32,
+    pub failed_objects: i32,
+    pub bytes_copied: i64,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = storage_migrations)]
+pub struct StorageMigrationInsertForm {
+    pub source_backend: String,
+    pub target_backend: String,
+}
+
+#[derive(Debug, Clone, Default, AsChangeset)]
+#[diesel(table_name = storage_migrations)]
+pub struct StorageMigrationUpdateForm {
+    pub status: Option<String>,
+    pub total_objects: Option<i32>,
+    pub copied_objects: Option<i32>,
+    pub missing_objects: Option<i32>,
+    pub failed_objects: Option<i32>,
+    pub bytes_copied: Option<i64>,
+    pub error: Option<Option<String>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<Option<DateTime<Utc>>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+#[diesel(table_name = storage_migration_objects)]
+pub struct StorageMigrationObject {
+    pub migration_id: Uuid,
+    pub key: String,
+    pub status: String,
+    pub size_bytes: i64,
+    pub checksum: Option<String>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable, AsChangeset)]
+#[diesel(table_name = storage_migration_objects)]
+#[diesel(treat_none_as_null = true)]
+pub struct StorageMigrationObjectForm {
+    pub migration_id: Uuid,
+    pub key: String,
+    pub status: String,
+    pub size_bytes: i64,
+    pub checksum: Option<String>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
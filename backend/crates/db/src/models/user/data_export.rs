@@ -0,0 +1,28 @@
+use crate::schema::data_exports;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// An account data export. `storage_key` and `expires_at` are set once the
+/// archive is ready.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = data_exports)]
+pub struct DataExport {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// pending, ready or failed
+    pub status: String,
+    pub storage_key: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = data_exports)]
+pub struct DataExportInsertForm {
+    pub user_id: Uuid,
+}
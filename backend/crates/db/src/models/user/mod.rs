@@ -1,3 +1,7 @@
+pub mod data_export;
 pub mod user;
+pub mod user_report;
 
+pub use data_export::*;
 pub use user::*;
+pub use user_report::*;
@@ -0,0 +1,51 @@
+use crate::enums::DbReportStatus;
+use crate::schema::user_reports;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Queryable struct for the user_reports table.
+/// Field order matches schema.rs column order exactly.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = user_reports)]
+pub struct UserReport {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub user_id: Uuid,
+    pub original_name: String,
+    pub original_display_name: Option<String>,
+    pub original_bio: Option<String>,
+    pub original_avatar: Option<String>,
+    pub original_signature: Option<String>,
+    pub reason: String,
+    pub status: DbReportStatus,
+    pub resolver_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Insert form for reporting a user's profile.
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = user_reports)]
+pub struct UserReportInsertForm {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub user_id: Uuid,
+    pub original_name: String,
+    pub original_display_name: Option<String>,
+    pub original_bio: Option<String>,
+    pub original_avatar: Option<String>,
+    pub original_signature: Option<String>,
+    pub reason: String,
+    pub status: DbReportStatus,
+}
+
+/// Update form for modifying an existing user report (resolution).
+#[derive(Debug, Clone, AsChangeset, Default)]
+#[diesel(table_name = user_reports)]
+pub struct UserReportUpdateForm {
+    pub status: Option<DbReportStatus>,
+    pub resolver_id: Option<Option<Uuid>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
@@ -1,5 +1,5 @@
-use crate::enums::DbWikiPermission;
-use crate::schema::{wiki_approved_contributors, wiki_page_revisions, wiki_pages};
+use crate::enums::{DbReportStatus, DbWikiPermission};
+use crate::schema::{wiki_approved_contributors, wiki_page_reports, wiki_page_revisions, wiki_pages};
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -112,3 +112,42 @@ pub struct WikiApprovedContributorInsertForm {
     pub user_id: Uuid,
     pub added_by: Uuid,
 }
+
+// ============================================================
+// wiki_page_reports
+// ============================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = wiki_page_reports)]
+pub struct WikiPageReport {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub page_id: Uuid,
+    pub original_page_title: String,
+    pub original_page_body: String,
+    pub reason: String,
+    pub status: DbReportStatus,
+    pub resolver_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = wiki_page_reports)]
+pub struct WikiPageReportInsertForm {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub page_id: Uuid,
+    pub original_page_title: String,
+    pub original_page_body: String,
+    pub reason: String,
+    pub status: DbReportStatus,
+}
+
+#[derive(Debug, Clone, AsChangeset, Default)]
+#[diesel(table_name = wiki_page_reports)]
+pub struct WikiPageReportUpdateForm {
+    pub status: Option<DbReportStatus>,
+    pub resolver_id: Option<Option<Uuid>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
@@ -131,6 +131,9 @@ diesel::table! {
         updated_at -> Timestamptz,
         custom_css -> Nullable<Text>,
         custom_css_enabled -> Bool,
+        require_two_factor_for_admins -> Bool,
+        require_two_factor_for_mods -> Bool,
+        magic_link_login_enabled -> Bool,
     }
 }
 
@@ -245,6 +248,9 @@ diesel::table! {
     }
 }
 
+// posts.search_vector and comments.search_vector are generated tsvector columns.
+// Diesel has no tsvector type, so they are left out here and queried through
+// `diesel::dsl::sql` fragments in the search resolver.
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::*;
@@ -546,6 +552,26 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        thread_id -> Nullable<Uuid>,
+        is_mod_note -> Bool,
+    }
+}
+
+diesel::table! {
+    modmail_threads (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 200]
+        subject -> Varchar,
+        #[max_length = 20]
+        status -> Varchar,
+        assigned_to -> Nullable<Uuid>,
+        is_read_by_user -> Bool,
+        is_read_by_mods -> Bool,
+        last_message_at -> Timestamptz,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -563,6 +589,7 @@ diesel::table! {
         is_read -> Bool,
         created_at -> Timestamptz,
         actor_user_id -> Nullable<Uuid>,
+        body -> Nullable<Text>,
     }
 }
 
@@ -602,6 +629,38 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    automod_rules (id) {
+        id -> Uuid,
+        board_id -> Nullable<Uuid>,
+        #[max_length = 100]
+        name -> Varchar,
+        conditions -> Jsonb,
+        actions -> Jsonb,
+        is_enabled -> Bool,
+        display_order -> Int4,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    removal_reasons (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        #[max_length = 100]
+        title -> Varchar,
+        message -> Text,
+        #[max_length = 200]
+        rule -> Nullable<Varchar>,
+        display_order -> Int4,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::*;
@@ -639,6 +698,100 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::*;
+
+    message_reports (id) {
+        id -> Uuid,
+        creator_id -> Uuid,
+        message_id -> Uuid,
+        original_message_text -> Text,
+        reason -> Text,
+        status -> ReportStatus,
+        resolver_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::*;
+
+    user_reports (id) {
+        id -> Uuid,
+        creator_id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 30]
+        original_name -> Varchar,
+        #[max_length = 30]
+        original_display_name -> Nullable<Varchar>,
+        original_bio -> Nullable<Text>,
+        original_avatar -> Nullable<Text>,
+        original_signature -> Nullable<Text>,
+        reason -> Text,
+        status -> ReportStatus,
+        resolver_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::*;
+
+    wiki_page_reports (id) {
+        id -> Uuid,
+        creator_id -> Uuid,
+        page_id -> Uuid,
+        #[max_length = 200]
+        original_page_title -> Varchar,
+        original_page_body -> Text,
+        reason -> Text,
+        status -> ReportStatus,
+        resolver_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    storage_migrations (id) {
+        id -> Uuid,
+        #[max_length = 20]
+        source_backend -> Varchar,
+        #[max_length = 20]
+        target_backend -> Varchar,
+        #[max_length = 20]
+        status -> Varchar,
+        total_objects -> Int4,
+        copied_objects -> Int4,
+        missing_objects -> Int4,
+        failed_objects -> Int4,
+        bytes_copied -> Int8,
+        error -> Nullable<Text>,
+        started_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        finished_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    storage_migration_objects (migration_id, key) {
+        migration_id -> Uuid,
+        key -> Text,
+        #[max_length = 20]
+        status -> Varchar,
+        size_bytes -> Int8,
+        #[max_length = 64]
+        checksum -> Nullable<Varchar>,
+        error -> Nullable<Text>,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     uploads (id) {
         id -> Uuid,
@@ -653,6 +806,11 @@ diesel::table! {
         optimized_url -> Nullable<Text>,
         #[max_length = 20]
         processing_status -> Varchar,
+        #[max_length = 64]
+        content_hash -> Nullable<Varchar>,
+        width -> Nullable<Int4>,
+        height -> Nullable<Int4>,
+        duration_seconds -> Nullable<Float8>,
     }
 }
 
@@ -913,6 +1071,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    magic_links (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token -> Text,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     password_resets (id) {
         id -> Uuid,
@@ -935,6 +1104,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    email_changes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        old_email -> Nullable<Text>,
+        new_email -> Text,
+        confirm_token -> Text,
+        cancel_token -> Text,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     registration_applications (id) {
         id -> Uuid,
@@ -954,6 +1136,51 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    media_gc_runs (id) {
+        id -> Uuid,
+        started_at -> Timestamptz,
+        finished_at -> Timestamptz,
+        orphaned_uploads_deleted -> Int4,
+        orphaned_files_deleted -> Int4,
+        bytes_freed -> Int8,
+        missing_files -> Int4,
+        total_files -> Int8,
+        total_bytes -> Int8,
+        error -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    data_exports (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 20]
+        status -> Varchar,
+        storage_key -> Nullable<Text>,
+        size_bytes -> Nullable<Int8>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        expires_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    media_processing_jobs (id) {
+        id -> Uuid,
+        upload_id -> Uuid,
+        #[max_length = 20]
+        status -> Varchar,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        run_at -> Timestamptz,
+        locked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     rate_limits (id) {
         id -> Uuid,
@@ -972,6 +1199,10 @@ diesel::table! {
         search_per_second -> Int4,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        login -> Int4,
+        login_per_second -> Int4,
+        magic_link -> Int4,
+        magic_link_per_second -> Int4,
     }
 }
 
@@ -985,6 +1216,128 @@ diesel::table! {
         last_used_at -> Nullable<Timestamptz>,
         expires_at -> Timestamptz,
         created_at -> Timestamptz,
+        name -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    api_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        token_hash -> Text,
+        token_prefix -> Text,
+        scope -> Text,
+        is_bot -> Bool,
+        last_used_at -> Nullable<Timestamptz>,
+        expires_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_totp (user_id) {
+        user_id -> Uuid,
+        secret -> Text,
+        enabled_at -> Nullable<Timestamptz>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    captcha_challenges (id) {
+        id -> Uuid,
+        salt -> Text,
+        difficulty -> Int4,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    login_failures (id) {
+        id -> Uuid,
+        ip_address -> Text,
+        username -> Text,
+        user_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    account_lockouts (user_id) {
+        user_id -> Uuid,
+        failed_attempts -> Int4,
+        locked_until -> Nullable<Timestamptz>,
+        last_failed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    mfa_token_failures (jti) {
+        jti -> Uuid,
+        user_id -> Uuid,
+        failures -> Int4,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_identities (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        provider -> Text,
+        subject -> Text,
+        email -> Nullable<Text>,
+        created_at -> Timestamptz,
+        last_login_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    oidc_auth_requests (state) {
+        state -> Text,
+        provider -> Text,
+        code_verifier -> Text,
+        nonce -> Text,
+        redirect_to -> Text,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    webauthn_credentials (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        credential_id -> Text,
+        public_key -> Bytea,
+        sign_count -> Int8,
+        name -> Text,
+        transports -> Array<Text>,
+        last_used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    webauthn_challenges (challenge) {
+        challenge -> Text,
+        user_id -> Nullable<Uuid>,
+        purpose -> Text,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
     }
 }
 
@@ -993,6 +1346,8 @@ diesel::table! {
 // ============================================================
 
 diesel::joinable!(auth_sessions -> users (user_id));
+diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(automod_rules -> boards (board_id));
 diesel::joinable!(board_aggregates -> boards (board_id));
 diesel::joinable!(board_blocks -> boards (board_id));
 diesel::joinable!(board_blocks -> users (user_id));
@@ -1017,15 +1372,21 @@ diesel::joinable!(comments -> posts (post_id));
 diesel::joinable!(content_uploads -> comments (comment_id));
 diesel::joinable!(content_uploads -> posts (post_id));
 diesel::joinable!(content_uploads -> uploads (upload_id));
+diesel::joinable!(data_exports -> users (user_id));
+diesel::joinable!(email_changes -> users (user_id));
 diesel::joinable!(email_verification -> users (user_id));
 diesel::joinable!(emoji_keywords -> emoji (emoji_id));
 diesel::joinable!(flair_aggregates -> flair_templates (flair_template_id));
 diesel::joinable!(flair_categories -> boards (board_id));
 diesel::joinable!(flair_templates -> boards (board_id));
 diesel::joinable!(flair_templates -> flair_categories (category_id));
+diesel::joinable!(media_processing_jobs -> uploads (upload_id));
+diesel::joinable!(message_reports -> private_messages (message_id));
 diesel::joinable!(moderation_log -> boards (board_id));
+diesel::joinable!(modmail_threads -> boards (board_id));
 diesel::joinable!(notification_settings -> users (user_id));
 diesel::joinable!(notifications -> private_messages (message_id));
+diesel::joinable!(magic_links -> users (user_id));
 diesel::joinable!(password_resets -> users (user_id));
 diesel::joinable!(post_aggregates -> posts (post_id));
 diesel::joinable!(post_flairs -> flair_templates (flair_template_id));
@@ -1039,6 +1400,7 @@ diesel::joinable!(post_votes -> posts (post_id));
 diesel::joinable!(post_votes -> users (user_id));
 diesel::joinable!(posts -> boards (board_id));
 diesel::joinable!(posts -> languages (language_id));
+diesel::joinable!(private_messages -> modmail_threads (thread_id));
 diesel::joinable!(rate_limits -> site (site_id));
 diesel::joinable!(reaction_aggregates -> comments (comment_id));
 diesel::joinable!(reaction_aggregates -> posts (post_id));
@@ -1046,9 +1408,11 @@ diesel::joinable!(reactions -> comments (comment_id));
 diesel::joinable!(reactions -> posts (post_id));
 diesel::joinable!(reactions -> users (user_id));
 diesel::joinable!(registration_applications -> users (user_id));
+diesel::joinable!(removal_reasons -> boards (board_id));
 diesel::joinable!(site_aggregates -> site (site_id));
 diesel::joinable!(site_languages -> languages (language_id));
 diesel::joinable!(site_languages -> site (site_id));
+diesel::joinable!(storage_migration_objects -> storage_migrations (migration_id));
 diesel::joinable!(uploads -> users (user_id));
 diesel::joinable!(user_aggregates -> users (user_id));
 diesel::joinable!(user_flair_filters -> boards (board_id));
@@ -1057,7 +1421,16 @@ diesel::joinable!(user_flairs -> boards (board_id));
 diesel::joinable!(user_flairs -> flair_templates (flair_template_id));
 diesel::joinable!(user_languages -> languages (language_id));
 diesel::joinable!(user_languages -> users (user_id));
+diesel::joinable!(user_recovery_codes -> users (user_id));
+diesel::joinable!(user_totp -> users (user_id));
+diesel::joinable!(login_failures -> users (user_id));
+diesel::joinable!(account_lockouts -> users (user_id));
+diesel::joinable!(mfa_token_failures -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
+diesel::joinable!(webauthn_challenges -> users (user_id));
+diesel::joinable!(webauthn_credentials -> users (user_id));
 diesel::joinable!(wiki_approved_contributors -> boards (board_id));
+diesel::joinable!(wiki_page_reports -> wiki_pages (page_id));
 diesel::joinable!(wiki_page_revisions -> users (editor_id));
 diesel::joinable!(wiki_page_revisions -> wiki_pages (page_id));
 diesel::joinable!(wiki_pages -> boards (board_id));
@@ -1067,7 +1440,10 @@ diesel::joinable!(wiki_pages -> boards (board_id));
 // ============================================================
 
 diesel::allow_tables_to_appear_in_same_query!(
+    account_lockouts,
+    api_tokens,
     auth_sessions,
+    automod_rules,
     board_aggregates,
     board_blocks,
     board_languages,
@@ -1076,12 +1452,15 @@ diesel::allow_tables_to_appear_in_same_query!(
     board_subscribers,
     board_user_bans,
     boards,
+    captcha_challenges,
     comment_aggregates,
     comment_reports,
     comment_saved,
     comment_votes,
     comments,
     content_uploads,
+    data_exports,
+    email_changes,
     email_verification,
     emoji,
     emoji_keywords,
@@ -1089,9 +1468,17 @@ diesel::allow_tables_to_appear_in_same_query!(
     flair_categories,
     flair_templates,
     languages,
+    login_failures,
+    magic_links,
+    media_gc_runs,
+    media_processing_jobs,
+    message_reports,
+    mfa_token_failures,
     moderation_log,
+    modmail_threads,
     notification_settings,
     notifications,
+    oidc_auth_requests,
     password_resets,
     post_aggregates,
     post_flairs,
@@ -1105,11 +1492,14 @@ diesel::allow_tables_to_appear_in_same_query!(
     reaction_aggregates,
     reactions,
     registration_applications,
+    removal_reasons,
     secrets,
     site,
     site_aggregates,
     site_invites,
     site_languages,
+    storage_migration_objects,
+    storage_migrations,
     uploads,
     user_aggregates,
     user_bans,
@@ -1117,9 +1507,16 @@ diesel::allow_tables_to_appear_in_same_query!(
     user_flair_filters,
     user_flairs,
     user_follows,
+    user_identities,
     user_languages,
+    user_recovery_codes,
+    user_reports,
+    user_totp,
     users,
+    webauthn_challenges,
+    webauthn_credentials,
     wiki_approved_contributors,
+    wiki_page_reports,
     wiki_page_revisions,
     wiki_pages,
 );
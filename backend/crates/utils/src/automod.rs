@@ -0,0 +1,410 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{content_filter::ContentFilter, TinyBoardsError};
+
+/// Post types a rule can be limited to.
+pub const POST_TYPES: [&str; 4] = ["text", "link", "image", "video"];
+
+const MAX_PATTERN_LENGTH: usize = 1000;
+const MAX_COMMENT_LENGTH: usize = 10000;
+const MAX_REPORT_LENGTH: usize = 500;
+/// Compiled size limit, so a pattern can't use a lot of memory
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// What a new post has to look like for an AutoModerator rule to act on it.
+/// Every condition that is set must hold; a list matches when any entry does.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomodConditions {
+    /// Case-insensitive regex searched for in the title
+    pub title_regex: Option<String>,
+    /// Case-insensitive regex searched for in the body
+    pub body_regex: Option<String>,
+    /// The post's link or a link in its body is on one of these domains or
+    /// their subdomains
+    pub domains: Vec<String>,
+    /// The author's account is younger than this many days
+    pub account_age_under_days: Option<i64>,
+    /// The author's combined post and comment score is below this
+    pub reputation_under: Option<i64>,
+    /// The author has one of these user flairs in the board
+    pub author_flair_ids: Vec<Uuid>,
+    /// One of `POST_TYPES`
+    pub post_types: Vec<String>,
+}
+
+/// What an AutoModerator rule does to a post it matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomodActions {
+    pub remove: bool,
+    /// Hide the post until a moderator approves it from the queue
+    pub filter: bool,
+    pub lock: bool,
+    pub mark_nsfw: bool,
+    /// Post flair template to apply
+    pub set_flair_id: Option<Uuid>,
+    /// Reply to the post with this comment
+    pub comment: Option<String>,
+    /// Report the post with this reason
+    pub report: Option<String>,
+}
+
+/// The parts of a post and its author that rules look at.
+#[derive(Debug, Clone)]
+pub struct AutomodSubject<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    pub url: Option<&'a str>,
+    /// One of `POST_TYPES`
+    pub post_type: &'a str,
+    pub account_age_days: i64,
+    pub reputation: i64,
+    pub author_flair_ids: &'a [Uuid],
+}
+
+fn compile(pattern: &str) -> Result<Regex, TinyBoardsError> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| TinyBoardsError::from_message(400, &format!("Invalid regex \"{}\": {}", pattern, e)))
+}
+
+/// Lowercased host without a trailing dot, or `None` if the URL has no host.
+fn url_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(url.host_str()?.trim_end_matches('.').to_lowercase())
+}
+
+fn host_on_domain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+impl AutomodConditions {
+    fn is_empty(&self) -> bool {
+        self.title_regex.is_none()
+            && self.body_regex.is_none()
+            && self.domains.is_empty()
+            && self.account_age_under_days.is_none()
+            && self.reputation_under.is_none()
+            && self.author_flair_ids.is_empty()
+            && self.post_types.is_empty()
+    }
+
+    /// Check the conditions before saving them. A rule without conditions
+    /// would act on every post, so at least one is required.
+    pub fn validate(&self) -> Result<(), TinyBoardsError> {
+        if self.is_empty() {
+            return Err(TinyBoardsError::from_message(400, "A rule needs at least one condition"));
+        }
+
+        for pattern in [&self.title_regex, &self.body_regex].into_iter().flatten() {
+            if pattern.is_empty() || pattern.len() > MAX_PATTERN_LENGTH {
+                return Err(TinyBoardsError::from_message(
+                    400,
+                    &format!("Patterns must be between 1 and {} characters", MAX_PATTERN_LENGTH),
+                ));
+            }
+            compile(pattern)?;
+        }
+
+        for domain in &self.domains {
+            let valid = !domain.is_empty()
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+            if !valid {
+                return Err(TinyBoardsError::from_message(
+                    400,
+                    &format!("\"{}\" isn't a domain name, like example.com", domain),
+                ));
+            }
+        }
+
+        if self.account_age_under_days.is_some_and(|days| days <= 0) {
+            return Err(TinyBoardsError::from_message(400, "Account age must be at least 1 day"));
+        }
+
+        if let Some(post_type) = self.post_types.iter().find(|t| !POST_TYPES.contains(&t.as_str())) {
+            return Err(TinyBoardsError::from_message(
+                400,
+                &format!("Unknown post type \"{}\"", post_type),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Lowercase domains so they compare with URL hosts.
+    pub fn normalize(mut self) -> Self {
+        self.domains = self
+            .domains
+            .into_iter()
+            .map(|d| d.trim().trim_end_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Whether the post meets every condition. A pattern that no longer
+    /// compiles never matches.
+    pub fn matches(&self, subject: &AutomodSubject) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        if let Some(ref pattern) = self.title_regex {
+            if !compile(pattern).is_ok_and(|re| re.is_match(subject.title)) {
+                return false;
+            }
+        }
+
+        if let Some(ref pattern) = self.body_regex {
+            if !compile(pattern).is_ok_and(|re| re.is_match(subject.body)) {
+                return false;
+            }
+        }
+
+        if !self.domains.is_empty() {
+            let urls = subject
+                .url
+                .map(str::to_string)
+                .into_iter()
+                .chain(ContentFilter::extract_urls_from_text(subject.body));
+            let on_domain = urls
+                .filter_map(|url| url_host(&url))
+                .any(|host| self.domains.iter().any(|d| host_on_domain(&host, d)));
+            if !on_domain {
+                return false;
+            }
+        }
+
+        if self
+            .account_age_under_days
+            .is_some_and(|days| subject.account_age_days >= days)
+        {
+            return false;
+        }
+
+        if self
+            .reputation_under
+            .is_some_and(|score| subject.reputation >= score)
+        {
+            return false;
+        }
+
+        if !self.author_flair_ids.is_empty()
+            && !subject
+                .author_flair_ids
+                .iter()
+                .any(|id| self.author_flair_ids.contains(id))
+        {
+            return false;
+        }
+
+        if !self.post_types.is_empty() && !self.post_types.iter().any(|t| t == subject.post_type) {
+            return false;
+        }
+
+        true
+    }
+}
+
+impl AutomodActions {
+    /// Check the actions before saving them.
+    pub fn validate(&self) -> Result<(), TinyBoardsError> {
+        let comment = self.comment.as_deref().map(str::trim);
+        let report = self.report.as_deref().map(str::trim);
+
+        let any = self.remove
+            || self.filter
+            || self.lock
+            || self.mark_nsfw
+            || self.set_flair_id.is_some()
+            || comment.is_some()
+            || report.is_some();
+        if !any {
+            return Err(TinyBoardsError::from_message(400, "A rule needs at least one action"));
+        }
+
+        if self.remove && self.filter {
+            return Err(TinyBoardsError::from_message(
+                400,
+                "A rule can either remove posts or send them to the approval queue, not both",
+            ));
+        }
+
+        if comment.is_some_and(|c| c.is_empty() || c.len() > MAX_COMMENT_LENGTH) {
+            return Err(TinyBoardsError::from_message(
+                400,
+                &format!("Comments must be between 1 and {} characters", MAX_COMMENT_LENGTH),
+            ));
+        }
+
+        if report.is_some_and(|r| r.len() < 3 || r.len() > MAX_REPORT_LENGTH) {
+            return Err(TinyBoardsError::from_message(
+                400,
+                &format!("Report reasons must be between 3 and {} characters", MAX_REPORT_LENGTH),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Short names of the actions, for dry runs.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.remove {
+            names.push("remove");
+        }
+        if self.filter {
+            names.push("filter");
+        }
+        if self.lock {
+            names.push("lock");
+        }
+        if self.mark_nsfw {
+            names.push("mark_nsfw");
+        }
+        if self.set_flair_id.is_some() {
+            names.push("set_flair");
+        }
+        if self.comment.is_some() {
+            names.push("comment");
+        }
+        if self.report.is_some() {
+            names.push("report");
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subject<'a>(title: &'a str, body: &'a str, url: Option<&'a str>) -> AutomodSubject<'a> {
+        AutomodSubject {
+            title,
+            body,
+            url,
+            post_type: if url.is_some() { "link" } else { "text" },
+            account_age_days: 30,
+            reputation: 100,
+            author_flair_ids: &[],
+        }
+    }
+
+    #[test]
+    fn test_empty_conditions_never_match() {
+        let conditions = AutomodConditions::default();
+        assert!(conditions.validate().is_err());
+        assert!(!conditions.matches(&subject("anything", "", None)));
+    }
+
+    #[test]
+    fn test_regex_is_case_insensitive() {
+        let conditions = AutomodConditions {
+            title_regex: Some(r"\bfree (money|crypto)\b".into()),
+            ..Default::default()
+        };
+        assert!(conditions.validate().is_ok());
+        assert!(conditions.matches(&subject("FREE Crypto inside", "", None)));
+        assert!(!conditions.matches(&subject("freedom", "", None)));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let conditions = AutomodConditions {
+            body_regex: Some("(unclosed".into()),
+            ..Default::default()
+        };
+        assert!(conditions.validate().is_err());
+        assert!(!conditions.matches(&subject("", "(unclosed", None)));
+    }
+
+    #[test]
+    fn test_domains_match_subdomains_and_body_links() {
+        let conditions = AutomodConditions {
+            domains: vec!["Example.com".into()],
+            ..Default::default()
+        }
+        .normalize();
+        assert!(conditions.validate().is_ok());
+        assert!(conditions.matches(&subject("", "", Some("https://example.com/a"))));
+        assert!(conditions.matches(&subject("", "", Some("https://www.example.com/a"))));
+        assert!(conditions.matches(&subject("", "see https://cdn.example.com/x", None)));
+        assert!(!conditions.matches(&subject("", "", Some("https://notexample.com/a"))));
+        assert!(!conditions.matches(&subject("", "", None)));
+    }
+
+    #[test]
+    fn test_all_conditions_must_hold() {
+        let conditions = AutomodConditions {
+            title_regex: Some("sale".into()),
+            account_age_under_days: Some(7),
+            reputation_under: Some(10),
+            ..Default::default()
+        };
+        let mut new_account = subject("big sale", "", None);
+        new_account.account_age_days = 2;
+        new_account.reputation = 0;
+        assert!(conditions.matches(&new_account));
+
+        new_account.reputation = 10;
+        assert!(!conditions.matches(&new_account));
+
+        assert!(!conditions.matches(&subject("big sale", "", None)));
+    }
+
+    #[test]
+    fn test_post_types_and_flairs() {
+        let flair = Uuid::new_v4();
+        let conditions = AutomodConditions {
+            author_flair_ids: vec![flair],
+            post_types: vec!["link".into()],
+            ..Default::default()
+        };
+        let mut link = subject("", "", Some("https://example.com"));
+        assert!(!conditions.matches(&link));
+
+        let flairs = [flair];
+        link.author_flair_ids = &flairs;
+        assert!(conditions.matches(&link));
+
+        let mut text = subject("", "", None);
+        text.author_flair_ids = &flairs;
+        assert!(!conditions.matches(&text));
+
+        let unknown = AutomodConditions {
+            post_types: vec!["poll".into()],
+            ..Default::default()
+        };
+        assert!(unknown.validate().is_err());
+    }
+
+    #[test]
+    fn test_actions_validation() {
+        assert!(AutomodActions::default().validate().is_err());
+        assert!(AutomodActions { remove: true, filter: true, ..Default::default() }
+            .validate()
+            .is_err());
+        assert!(AutomodActions { report: Some("no".into()), ..Default::default() }
+            .validate()
+            .is_err());
+
+        let actions = AutomodActions {
+            filter: true,
+            comment: Some("Your post is waiting for review.".into()),
+            ..Default::default()
+        };
+        assert!(actions.validate().is_ok());
+        assert_eq!(actions.names(), vec!["filter", "comment"]);
+    }
+}
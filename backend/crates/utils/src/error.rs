@@ -23,8 +23,9 @@ pub enum TinyBoardsError {
     #[error("Conflict: {0}")]
     Conflict(String),
 
-    #[error("Rate limited")]
-    RateLimited,
+    /// Carries the number of seconds until the request would be allowed.
+    #[error("Rate limit exceeded. Try again in {0} seconds.")]
+    RateLimited(u64),
 
     #[error("Database error: {0}")]
     Database(String),
@@ -41,7 +42,8 @@ struct ErrorResponse {
 
 impl TinyBoardsError {
     /// Convenience for migration: maps a numeric status code + message to the appropriate variant.
-    /// Prefer using typed variants directly in new code.
+    /// Prefer using typed variants directly in new code. There's no 429 here:
+    /// use `RateLimited` with the retry time the rate limiter returned.
     pub fn from_message(status: u16, msg: &str) -> Self {
         match status {
             400 => TinyBoardsError::BadRequest(msg.to_string()),
@@ -50,7 +52,6 @@ impl TinyBoardsError {
             404 => TinyBoardsError::NotFound(msg.to_string()),
             409 => TinyBoardsError::Conflict(msg.to_string()),
             422 => TinyBoardsError::Validation(vec![msg.to_string()]),
-            429 => TinyBoardsError::RateLimited,
             _ => TinyBoardsError::Internal(msg.to_string()),
         }
     }
@@ -72,7 +73,7 @@ impl actix_web::error::ResponseError for TinyBoardsError {
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
             Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
             Self::Conflict(_) => StatusCode::CONFLICT,
-            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
             Self::Database(_) | Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -94,6 +95,12 @@ impl actix_web::error::ResponseError for TinyBoardsError {
                     details: errors.clone(),
                 })
             }
+            Self::RateLimited(retry_after) => HttpResponse::build(status)
+                .insert_header(("Retry-After", retry_after.to_string()))
+                .json(ErrorResponse {
+                    error: self.to_string(),
+                    error_code: status.as_u16(),
+                }),
             _ => {
                 HttpResponse::build(status).json(ErrorResponse {
                     error: self.to_string(),
@@ -130,3 +137,16 @@ impl From<serde_json::Error> for TinyBoardsError {
         TinyBoardsError::Internal(format!("JSON error: {}", e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::ResponseError;
+
+    #[test]
+    fn test_rate_limited_sends_its_retry_after() {
+        let response = TinyBoardsError::RateLimited(42).error_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get("Retry-After").unwrap(), "42");
+    }
+}
@@ -7,7 +7,10 @@ pub mod utils;
 pub mod version;
 pub mod email;
 pub mod content_filter;
+pub mod automod;
+pub mod removal_reason;
 pub mod css_sanitizer;
+pub mod search_query;
 pub mod slug;
 
 pub use error::TinyBoardsError;
@@ -1,4 +1,6 @@
-use crate::{error::TinyBoardsError, utils::get_ip, IpAddr};
+use crate::{
+    error::TinyBoardsError, settings::structs::RateLimitSettings, utils::get_ip, IpAddr,
+};
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
 use futures::future::{ok, Ready};
 use rate_limiter::{RateLimitStorage, RateLimitType};
@@ -9,12 +11,18 @@ use std::{
     rc::Rc,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 use tokio::sync::{mpsc, mpsc::Sender, Mutex, OnceCell};
 use typed_builder::TypedBuilder;
+use uuid::Uuid;
 
 pub mod rate_limiter;
 
+/// How often idle buckets are dropped. The longest default interval is an
+/// hour, and a bucket idle for longer than its interval is full anyway.
+const BUCKET_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
 #[derive(Debug, Deserialize, Serialize, Clone, TypedBuilder)]
 pub struct RateLimitConfig {
     #[builder(default = 180)]
@@ -29,6 +37,10 @@ pub struct RateLimitConfig {
     pub register: i32,
     #[builder(default = 3600)]
     pub register_per_second: i32,
+    #[builder(default = 10)]
+    pub login: i32,
+    #[builder(default = 300)]
+    pub login_per_second: i32,
     #[builder(default = 6)]
     pub image: i32,
     #[builder(default = 3600)]
@@ -41,6 +53,33 @@ pub struct RateLimitConfig {
     pub search: i32,
     #[builder(default = 600)]
     pub search_per_second: i32,
+    #[builder(default = 3)]
+    pub magic_link: i32,
+    #[builder(default = 900)]
+    pub magic_link_per_second: i32,
+}
+
+impl From<&RateLimitSettings> for RateLimitConfig {
+    fn from(s: &RateLimitSettings) -> Self {
+        RateLimitConfig {
+            message: s.message,
+            message_per_second: s.message_per_second,
+            post: s.post,
+            post_per_second: s.post_per_second,
+            register: s.register,
+            register_per_second: s.register_per_second,
+            login: s.login,
+            login_per_second: s.login_per_second,
+            image: s.image,
+            image_per_second: s.image_per_second,
+            comment: s.comment,
+            comment_per_second: s.comment_per_second,
+            search: s.search,
+            search_per_second: s.search_per_second,
+            magic_link: s.magic_link,
+            magic_link_per_second: s.magic_link_per_second,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -79,11 +118,21 @@ impl RateLimitCell {
                         guard.rate_limit_config = r;
                     }
                 });
+                let rate_limit3 = rate_limit.clone();
+                tokio::spawn(async move {
+                    let mut interval = tokio::time::interval(BUCKET_CLEANUP_INTERVAL);
+                    loop {
+                        interval.tick().await;
+                        let mut guard = rate_limit3.lock().await;
+                        guard.rate_limiter.remove_idle_buckets(BUCKET_CLEANUP_INTERVAL);
+                    }
+                });
                 RateLimitCell { tx, rate_limit }
             })
             .await
     }
 
+    /// Replace the active limits, e.g. after an admin changed them.
     pub async fn send(&self, config: RateLimitConfig) -> Result<(), TinyBoardsError> {
         self.tx.send(config).await.map_err(|e| {
             TinyBoardsError::Internal(format!("Failed to send rate limit config: {}", e))
@@ -103,6 +152,10 @@ impl RateLimitCell {
         self.kind(RateLimitType::Register)
     }
 
+    pub fn login(&self) -> RateLimitedGuard {
+        self.kind(RateLimitType::Login)
+    }
+
     pub fn image(&self) -> RateLimitedGuard {
         self.kind(RateLimitType::Image)
     }
@@ -115,6 +168,10 @@ impl RateLimitCell {
         self.kind(RateLimitType::Search)
     }
 
+    pub fn magic_link(&self) -> RateLimitedGuard {
+        self.kind(RateLimitType::MagicLink)
+    }
+
     fn kind(&self, type_: RateLimitType) -> RateLimitedGuard {
         RateLimitedGuard {
             rate_limit: self.rate_limit.clone(),
@@ -131,6 +188,21 @@ pub struct RateLimitedMiddleware<S> {
 impl RateLimitedGuard {
     /// Returns true if the request passed the rate limit, false if it should be rejected.
     pub async fn check(self, ip_addr: IpAddr) -> bool {
+        self.check_request(&ip_addr, None).await.is_ok()
+    }
+
+    /// Check the limit for both the caller's IP and, if logged in, their account,
+    /// so neither switching networks nor sharing one helps get around it.
+    pub async fn check_request(
+        self,
+        ip_addr: &IpAddr,
+        user_id: Option<Uuid>,
+    ) -> Result<(), TinyBoardsError> {
+        let mut keys = vec![format!("ip:{}", ip_addr)];
+        if let Some(user_id) = user_id {
+            keys.push(format!("user:{}", user_id));
+        }
+
         let mut guard = self.rate_limit.lock().await;
         let rate_limit = &guard.rate_limit_config;
 
@@ -138,13 +210,17 @@ impl RateLimitedGuard {
             RateLimitType::Message => (rate_limit.message, rate_limit.message_per_second),
             RateLimitType::Post => (rate_limit.post, rate_limit.post_per_second),
             RateLimitType::Register => (rate_limit.register, rate_limit.register_per_second),
+            RateLimitType::Login => (rate_limit.login, rate_limit.login_per_second),
             RateLimitType::Image => (rate_limit.image, rate_limit.image_per_second),
             RateLimitType::Comment => (rate_limit.comment, rate_limit.comment_per_second),
             RateLimitType::Search => (rate_limit.search, rate_limit.search_per_second),
+            RateLimitType::MagicLink => (rate_limit.magic_link, rate_limit.magic_link_per_second),
         };
         let limiter = &mut guard.rate_limiter;
 
-        limiter.check_rate_limit_full(self.type_, &ip_addr, kind, interval)
+        limiter
+            .check_rate_limit_full(self.type_, &keys, kind, interval)
+            .map_err(TinyBoardsError::RateLimited)
     }
 }
 
@@ -189,15 +265,68 @@ where
         let service = self.service.clone();
 
         Box::pin(async move {
-            if rate_limited.check(ip_addr).await {
-                service.call(req).await
-            } else {
-                let (http_req, _) = req.into_parts();
-                Ok(ServiceResponse::from_err(
-                    TinyBoardsError::RateLimited,
-                    http_req,
-                ))
+            match rate_limited.check_request(&ip_addr, None).await {
+                Ok(()) => service.call(req).await,
+                Err(e) => {
+                    let (http_req, _) = req.into_parts();
+                    Ok(ServiceResponse::from_err(e, http_req))
+                }
             }
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn guard(config: RateLimitConfig, type_: RateLimitType) -> RateLimitedGuard {
+        RateLimitedGuard {
+            rate_limit: Arc::new(Mutex::new(RateLimit {
+                rate_limiter: Default::default(),
+                rate_limit_config: config,
+            })),
+            type_,
+        }
+    }
+
+    fn retry_after(guard: &RateLimitedGuard, attempts: usize) -> Option<u64> {
+        let ip = IpAddr("1.2.3.4".to_string());
+        let mut result = None;
+        for _ in 0..attempts {
+            if let Err(e) = block_on(guard.clone().check_request(&ip, None)) {
+                match e {
+                    TinyBoardsError::RateLimited(secs) => result = Some(secs),
+                    other => panic!("unexpected error: {}", other),
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_retry_after_comes_from_the_bucket_that_fired() {
+        let config = RateLimitConfig::builder()
+            .post(2)
+            .post_per_second(300)
+            .register(3)
+            .register_per_second(3600)
+            .build();
+
+        // 2 posts per 300s regain one every 150s
+        let post = guard(config.clone(), RateLimitType::Post);
+        assert_eq!(retry_after(&post, 3), Some(150));
+
+        // 3 registrations per hour regain one every 1200s
+        let register = guard(config, RateLimitType::Register);
+        assert_eq!(retry_after(&register, 4), Some(1200));
+    }
+
+    #[test]
+    fn test_allows_requests_within_the_limit() {
+        let config = RateLimitConfig::builder().search(5).search_per_second(60).build();
+        let search = guard(config, RateLimitType::Search);
+        assert_eq!(retry_after(&search, 5), None);
+    }
+}
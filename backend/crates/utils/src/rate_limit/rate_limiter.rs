@@ -1,7 +1,8 @@
-use crate::IpAddr;
-use std::{collections::HashMap, time::Instant};
-use strum::IntoEnumIterator;
-use strum_macros::{EnumIter, AsRefStr};
+use std::{
+  collections::HashMap,
+  time::{Duration, Instant},
+};
+use strum_macros::{AsRefStr, EnumIter};
 use tracing::debug;
 
 #[derive(Debug, Clone)]
@@ -10,87 +11,170 @@ struct RateLimitBucket {
   allowance: f64,
 }
 
+impl RateLimitBucket {
+  fn full(rate: i32, now: Instant) -> Self {
+    RateLimitBucket {
+      last_checked: now,
+      allowance: f64::from(rate),
+    }
+  }
+
+  /// Top the bucket up for the time passed since it was last checked.
+  fn refill(&mut self, now: Instant, rate: i32, per: i32) {
+    let time_passed = now.duration_since(self.last_checked).as_secs_f64();
+    self.last_checked = now;
+    self.allowance = (self.allowance + time_passed * refill_rate(rate, per)).min(f64::from(rate));
+  }
+}
+
+/// Allowance regained per second.
+fn refill_rate(rate: i32, per: i32) -> f64 {
+  f64::from(rate.max(0)) / f64::from(per.max(1))
+}
+
 #[derive(Eq, PartialEq, Hash, Debug, EnumIter, Copy, Clone, AsRefStr)]
 pub(crate) enum RateLimitType {
   Message,
   Register,
+  Login,
   Post,
   Image,
   Comment,
   Search,
+  MagicLink,
 }
 
-/// Rate limiting based on rate type and IP addr
+/// Rate limiting based on rate type and a caller key (an IP address or a user).
 #[derive(Debug, Clone, Default)]
 pub struct RateLimitStorage {
-  buckets: HashMap<RateLimitType, HashMap<IpAddr, RateLimitBucket>>,
+  buckets: HashMap<RateLimitType, HashMap<String, RateLimitBucket>>,
 }
 
 impl RateLimitStorage {
-  fn insert_ip(&mut self, ip: &IpAddr) {
-    for rate_limit_type in RateLimitType::iter() {
-      if self.buckets.get(&rate_limit_type).is_none() {
-        self.buckets.insert(rate_limit_type, HashMap::new());
-      }
-
-      if let Some(bucket) = self.buckets.get_mut(&rate_limit_type) {
-        if bucket.get(ip).is_none() {
-          bucket.insert(
-            ip.clone(),
-            RateLimitBucket {
-              last_checked: Instant::now(),
-              allowance: -2f64,
-            },
-          );
-        }
-      }
-    }
+  /// Check the bucket for every key and consume one request from each if all of
+  /// them have allowance left. Otherwise nothing is consumed and the number of
+  /// seconds until the request would be allowed is returned.
+  pub(super) fn check_rate_limit_full(
+    &mut self,
+    type_: RateLimitType,
+    keys: &[String],
+    rate: i32,
+    per: i32,
+  ) -> Result<(), u64> {
+    self.check_at(type_, keys, rate, per, Instant::now())
   }
 
-  /// Returns true if the request passed the rate limit, false if it failed and should be rejected.
-  #[allow(clippy::float_cmp)]
-  pub(super) fn check_rate_limit_full(
+  fn check_at(
     &mut self,
     type_: RateLimitType,
-    ip: &IpAddr,
+    keys: &[String],
     rate: i32,
     per: i32,
-  ) -> bool {
-    self.insert_ip(ip);
-    if let Some(bucket) = self.buckets.get_mut(&type_) {
-      if let Some(rate_limit) = bucket.get_mut(ip) {
-        let current = Instant::now();
-        let time_passed = current.duration_since(rate_limit.last_checked).as_secs() as f64;
-
-        // The initial value
-        if rate_limit.allowance == -2f64 {
-          rate_limit.allowance = f64::from(rate);
-        };
-
-        rate_limit.last_checked = current;
-        rate_limit.allowance += time_passed * (f64::from(rate) / f64::from(per));
-        if rate_limit.allowance > f64::from(rate) {
-          rate_limit.allowance = f64::from(rate);
-        }
-
-        if rate_limit.allowance < 1.0 {
-          debug!(
-            "Rate limited type: {}, IP: {}, time_passed: {}, allowance: {}",
-            type_.as_ref(),
-            ip,
-            time_passed,
-            rate_limit.allowance
-          );
-          false
-        } else {
-          rate_limit.allowance -= 1.0;
-          true
-        }
+    now: Instant,
+  ) -> Result<(), u64> {
+    let buckets = self.buckets.entry(type_).or_default();
+
+    let mut lowest = f64::MAX;
+    for key in keys {
+      let bucket = buckets
+        .entry(key.clone())
+        .or_insert_with(|| RateLimitBucket::full(rate, now));
+      bucket.refill(now, rate, per);
+      lowest = lowest.min(bucket.allowance);
+    }
+
+    if lowest < 1.0 {
+      let per_second = refill_rate(rate, per);
+      let retry_after = if per_second > 0.0 {
+        ((1.0 - lowest) / per_second).ceil() as u64
       } else {
-        true
+        u64::from(per.max(1).unsigned_abs())
+      };
+      debug!(
+        "Rate limited type: {}, keys: {:?}, allowance: {}, retry after: {}s",
+        type_.as_ref(),
+        keys,
+        lowest,
+        retry_after
+      );
+      return Err(retry_after.max(1));
+    }
+
+    for key in keys {
+      if let Some(bucket) = buckets.get_mut(key) {
+        bucket.allowance -= 1.0;
       }
-    } else {
-      true
     }
+    Ok(())
   }
-}
\ No newline at end of file
+
+  /// Drop buckets that haven't been touched for `max_idle`. An idle bucket has
+  /// refilled completely, so forgetting it doesn't change any outcome.
+  pub(super) fn remove_idle_buckets(&mut self, max_idle: Duration) {
+    let now = Instant::now();
+    for buckets in self.buckets.values_mut() {
+      buckets.retain(|_, bucket| now.duration_since(bucket.last_checked) < max_idle);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keys(list: &[&str]) -> Vec<String> {
+    list.iter().map(|k| k.to_string()).collect()
+  }
+
+  #[test]
+  fn test_allows_up_to_rate_then_blocks() {
+    let mut storage = RateLimitStorage::default();
+    let now = Instant::now();
+    let ip = keys(&["ip:1.2.3.4"]);
+
+    for _ in 0..3 {
+      assert!(storage.check_at(RateLimitType::Post, &ip, 3, 60, now).is_ok());
+    }
+    assert_eq!(storage.check_at(RateLimitType::Post, &ip, 3, 60, now), Err(20));
+  }
+
+  #[test]
+  fn test_refills_over_time() {
+    let mut storage = RateLimitStorage::default();
+    let now = Instant::now();
+    let ip = keys(&["ip:1.2.3.4"]);
+
+    assert!(storage.check_at(RateLimitType::Post, &ip, 1, 10, now).is_ok());
+    assert!(storage.check_at(RateLimitType::Post, &ip, 1, 10, now).is_err());
+
+    let later = now + Duration::from_secs(10);
+    assert!(storage.check_at(RateLimitType::Post, &ip, 1, 10, later).is_ok());
+  }
+
+  #[test]
+  fn test_user_limit_applies_across_ips() {
+    let mut storage = RateLimitStorage::default();
+    let now = Instant::now();
+
+    assert!(storage
+      .check_at(RateLimitType::Comment, &keys(&["ip:a", "user:1"]), 1, 60, now)
+      .is_ok());
+    assert!(storage
+      .check_at(RateLimitType::Comment, &keys(&["ip:b", "user:1"]), 1, 60, now)
+      .is_err());
+    // The failed attempt didn't consume anything from the fresh IP's bucket.
+    assert!(storage
+      .check_at(RateLimitType::Comment, &keys(&["ip:b"]), 1, 60, now)
+      .is_ok());
+  }
+
+  #[test]
+  fn test_types_are_independent() {
+    let mut storage = RateLimitStorage::default();
+    let now = Instant::now();
+    let ip = keys(&["ip:1.2.3.4"]);
+
+    assert!(storage.check_at(RateLimitType::Post, &ip, 1, 60, now).is_ok());
+    assert!(storage.check_at(RateLimitType::Search, &ip, 1, 60, now).is_ok());
+  }
+}
@@ -0,0 +1,89 @@
+/// What the placeholders in a removal reason's message stand for.
+pub struct RemovalMessageContext<'a> {
+    /// Name of the removed content's author
+    pub author: &'a str,
+    /// Name of the board the content was removed from
+    pub board: &'a str,
+    /// Board rule the reason enforces
+    pub rule: Option<&'a str>,
+}
+
+/// Used for `{rule}` when the reason isn't tied to a specific rule.
+const DEFAULT_RULE: &str = "the board rules";
+
+/// Fill in `{author}`, `{board}` and `{rule}` in a removal reason's message.
+/// Replacement happens in one pass, so placeholders inside the substituted
+/// values (a user named `{board}`, say) are left alone, as are unknown ones.
+pub fn render_removal_message(template: &str, context: &RemovalMessageContext) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start..];
+        let value = match after.find('}').map(|end| &after[1..end]) {
+            Some("author") => Some(context.author),
+            Some("board") => Some(context.board),
+            Some("rule") => Some(context.rule.unwrap_or(DEFAULT_RULE)),
+            _ => None,
+        };
+        match value {
+            Some(value) => {
+                rendered.push_str(value);
+                rest = &after[after.find('}').unwrap_or(0) + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &after[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> RemovalMessageContext<'static> {
+        RemovalMessageContext {
+            author: "alice",
+            board: "rust",
+            rule: Some("Rule 2: Stay on topic"),
+        }
+    }
+
+    #[test]
+    fn test_render_replaces_placeholders() {
+        let rendered = render_removal_message(
+            "Hi {author}, your post in b/{board} was removed for breaking {rule}.",
+            &context(),
+        );
+        assert_eq!(
+            rendered,
+            "Hi alice, your post in b/rust was removed for breaking Rule 2: Stay on topic."
+        );
+    }
+
+    #[test]
+    fn test_render_without_rule() {
+        let ctx = RemovalMessageContext { rule: None, ..context() };
+        assert_eq!(render_removal_message("See {rule}", &ctx), "See the board rules");
+    }
+
+    #[test]
+    fn test_render_keeps_unknown_placeholders() {
+        assert_eq!(
+            render_removal_message("{author} {unknown} {board", &context()),
+            "alice {unknown} {board"
+        );
+    }
+
+    #[test]
+    fn test_render_does_not_expand_substituted_values() {
+        let ctx = RemovalMessageContext { author: "{board}", ..context() };
+        assert_eq!(render_removal_message("{author} in {board}", &ctx), "{board} in rust");
+    }
+}
@@ -0,0 +1,264 @@
+/// Marker emitted by `ts_headline` at the start of a matched fragment.
+pub const HEADLINE_START: &str = "{{{";
+/// Marker emitted by `ts_headline` at the end of a matched fragment.
+pub const HEADLINE_STOP: &str = "}}}";
+
+/// Options passed to `ts_headline` for body snippets.
+pub const HEADLINE_OPTIONS: &str =
+    "StartSel={{{, StopSel=}}}, MaxWords=35, MinWords=15, MaxFragments=2, FragmentDelimiter=\" … \"";
+
+/// Options passed to `ts_headline` for titles, which are short enough to highlight in full.
+pub const HEADLINE_TITLE_OPTIONS: &str = "StartSel={{{, StopSel=}}}, HighlightAll=true";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Word(String),
+    Phrase(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Clause {
+    negated: bool,
+    term: Term,
+}
+
+/// Convert a user search string into a `to_tsquery` expression.
+///
+/// Supported syntax:
+/// * `word another` — all words must match
+/// * `"exact phrase"` — words must appear next to each other
+/// * `-word` / `-"phrase"` — exclude matches
+/// * `cats OR dogs` — either side may match
+///
+/// Everything that isn't a letter or digit is treated as a word separator, so the
+/// output is always a well-formed tsquery regardless of input. Returns `None` when
+/// the query has no positive terms, since a pure exclusion would scan every row.
+pub fn to_tsquery(input: &str) -> Option<String> {
+    let groups = parse(input);
+
+    let has_positive = groups
+        .iter()
+        .any(|group| group.iter().any(|clause| !clause.negated));
+    if !has_positive {
+        return None;
+    }
+
+    let rendered: Vec<String> = groups
+        .iter()
+        .map(|group| {
+            let parts: Vec<String> = group.iter().map(render_clause).collect();
+            if parts.len() > 1 {
+                format!("({})", parts.join(" | "))
+            } else {
+                parts.join("")
+            }
+        })
+        .collect();
+
+    Some(rendered.join(" & "))
+}
+
+/// Turn raw `ts_headline` output into safe HTML with `<mark>` highlighting.
+///
+/// Headlines are generated from the markdown source, so everything is escaped
+/// before the highlight markers are swapped for tags.
+pub fn render_headline(raw: &str) -> String {
+    let mut escaped = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+        .replace(HEADLINE_START, "<mark>")
+        .replace(HEADLINE_STOP, "</mark>")
+}
+
+fn render_clause(clause: &Clause) -> String {
+    let term = match &clause.term {
+        Term::Word(w) => w.clone(),
+        Term::Phrase(words) if words.len() == 1 => words[0].clone(),
+        Term::Phrase(words) => format!("({})", words.join(" <-> ")),
+    };
+
+    if clause.negated {
+        format!("!{}", term)
+    } else {
+        term
+    }
+}
+
+/// Split the input into AND-ed groups of OR-ed clauses.
+fn parse(input: &str) -> Vec<Vec<Clause>> {
+    let mut groups: Vec<Vec<Clause>> = Vec::new();
+    let mut pending_or = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut negated = false;
+        if c == '-' {
+            chars.next();
+            match chars.peek() {
+                Some(next) if !next.is_whitespace() => negated = true,
+                _ => continue,
+            }
+        }
+
+        let raw = if chars.peek() == Some(&'"') {
+            chars.next();
+            let mut phrase = String::new();
+            for ch in chars.by_ref() {
+                if ch == '"' {
+                    break;
+                }
+                phrase.push(ch);
+            }
+            RawTerm::Quoted(phrase)
+        } else {
+            let mut word = String::new();
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() || ch == '"' {
+                    break;
+                }
+                word.push(ch);
+                chars.next();
+            }
+            RawTerm::Bare(word)
+        };
+
+        if let RawTerm::Bare(ref w) = raw {
+            if !negated && w == "OR" {
+                pending_or = !groups.is_empty();
+                continue;
+            }
+        }
+
+        let words = lexemes(raw.text());
+        let term = match words.len() {
+            0 => continue,
+            1 if !raw.is_quoted() => Term::Word(words[0].clone()),
+            // Hyphenated or punctuated words behave like short phrases.
+            _ => Term::Phrase(words),
+        };
+
+        let clause = Clause { negated, term };
+        match groups.last_mut() {
+            Some(group) if pending_or => group.push(clause),
+            _ => groups.push(vec![clause]),
+        }
+        pending_or = false;
+    }
+
+    groups
+}
+
+enum RawTerm {
+    Bare(String),
+    Quoted(String),
+}
+
+impl RawTerm {
+    fn text(&self) -> &str {
+        match self {
+            RawTerm::Bare(s) | RawTerm::Quoted(s) => s,
+        }
+    }
+
+    fn is_quoted(&self) -> bool {
+        matches!(self, RawTerm::Quoted(_))
+    }
+}
+
+fn lexemes(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_words_are_anded() {
+        assert_eq!(to_tsquery("rust async").as_deref(), Some("rust & async"));
+    }
+
+    #[test]
+    fn test_phrase() {
+        assert_eq!(
+            to_tsquery("\"borrow checker\" rust").as_deref(),
+            Some("(borrow <-> checker) & rust")
+        );
+    }
+
+    #[test]
+    fn test_exclusion() {
+        assert_eq!(to_tsquery("rust -python").as_deref(), Some("rust & !python"));
+        assert_eq!(
+            to_tsquery("rust -\"python snake\"").as_deref(),
+            Some("rust & !(python <-> snake)")
+        );
+    }
+
+    #[test]
+    fn test_or() {
+        assert_eq!(
+            to_tsquery("cats OR dogs food").as_deref(),
+            Some("(cats | dogs) & food")
+        );
+        assert_eq!(to_tsquery("a OR b OR c").as_deref(), Some("(a | b | c)"));
+    }
+
+    #[test]
+    fn test_lowercase_or_is_a_word() {
+        assert_eq!(to_tsquery("this or that").as_deref(), Some("this & or & that"));
+    }
+
+    #[test]
+    fn test_leading_or_is_ignored() {
+        assert_eq!(to_tsquery("OR rust").as_deref(), Some("rust"));
+    }
+
+    #[test]
+    fn test_only_exclusions_is_rejected() {
+        assert_eq!(to_tsquery("-rust -python"), None);
+        assert_eq!(to_tsquery("  "), None);
+        assert_eq!(to_tsquery("!!! ---"), None);
+    }
+
+    #[test]
+    fn test_special_characters_cannot_break_query() {
+        assert_eq!(
+            to_tsquery("foo' & bar:* | (baz)").as_deref(),
+            Some("foo & bar & baz")
+        );
+        assert_eq!(to_tsquery("e-mail").as_deref(), Some("(e <-> mail)"));
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        assert_eq!(to_tsquery("\"hello world").as_deref(), Some("(hello <-> world)"));
+    }
+
+    #[test]
+    fn test_render_headline_escapes_html() {
+        let raw = "<script>{{{alert}}}</script> & more";
+        assert_eq!(
+            render_headline(raw),
+            "&lt;script&gt;<mark>alert</mark>&lt;/script&gt; &amp; more"
+        );
+    }
+}
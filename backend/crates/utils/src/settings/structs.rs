@@ -20,6 +20,9 @@ pub struct Settings {
   #[default(None)]
   #[doku(example = "Some(Default::default())")]
   pub email: Option<EmailConfig>,
+  /// OpenID Connect identity providers users can log in with
+  #[default(Vec::new())]
+  pub oidc: Vec<OidcProviderConfig>,
   /// Parameters to configure how media uploads are stored on the instance
   #[default(Default::default())]
   pub media: MediaConfig,
@@ -88,6 +91,12 @@ pub struct RateLimitSettings {
   /// Interval length for registration limit, in seconds
   #[default(3600)]
   pub register_per_second: i32,
+  /// Maximum number of login attempts in interval
+  #[default(10)]
+  pub login: i32,
+  /// Interval length for login limit, in seconds
+  #[default(300)]
+  pub login_per_second: i32,
   /// Maximum number of image uploads in interval
   #[default(6)]
   pub image: i32,
@@ -100,11 +109,18 @@ pub struct RateLimitSettings {
   /// Interval length for comment limit, in seconds
   #[default(600)]
   pub comment_per_second: i32,
+  /// Maximum number of searches in interval
   #[default(60)]
   pub search: i32,
   /// Interval length for search limit, in seconds
   #[default(600)]
   pub search_per_second: i32,
+  /// Maximum number of login link requests in interval
+  #[default(3)]
+  pub magic_link: i32,
+  /// Interval length for login link limit, in seconds
+  #[default(900)]
+  pub magic_link_per_second: i32,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, SmartDefault, Document)]
@@ -173,6 +189,31 @@ pub struct MediaConfig {
   /// maximum file size in megabytes for site icons
   #[default(1)]
   pub max_site_icon_size_mb: u32,
+  /// periodically delete uploads and stored files that nothing references
+  #[default(true)]
+  pub gc_enabled: bool,
+  /// hours an upload may stay unreferenced before garbage collection deletes it
+  #[default(24)]
+  pub gc_grace_period_hours: u32,
+  /// seconds a signed media URL stays valid (media on private instances)
+  #[default(3600)]
+  pub signed_url_ttl_seconds: u32,
+  /// background workers optimizing uploaded images; 0 leaves the queue to other instances
+  #[default(2)]
+  pub processing_workers: u32,
+  /// ffmpeg binary used to transcode animated GIFs and extract video poster frames;
+  /// without it GIFs are served as uploaded and videos get no poster
+  #[default("ffmpeg")]
+  pub ffmpeg_path: String,
+  /// ffprobe binary used to read video dimensions and duration
+  #[default("ffprobe")]
+  pub ffprobe_path: String,
+  /// what animated GIFs are converted to: "webp" (animated WebP) or "mp4"
+  #[default("webp")]
+  pub gif_transcode_format: String,
+  /// seconds a single ffmpeg/ffprobe run may take before it is killed
+  #[default(120)]
+  pub transcode_timeout_seconds: u32,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, SmartDefault, Document)]
@@ -189,6 +230,10 @@ pub struct StorageConfig {
   pub azure: Option<AzureConfig>,
   /// Google Cloud Storage configuration
   pub gcs: Option<GcsConfig>,
+  /// Serve files the backend doesn't have from `media.media_path`. Needed after switching
+  /// backends until `tinyboards_server migrate-storage --from local` has completed.
+  #[default(true)]
+  pub local_fallback: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Document)]
@@ -263,6 +308,45 @@ pub struct CaptchaConfig {
   pub difficulty: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, SmartDefault, Document)]
+#[serde(default)]
+pub struct OidcProviderConfig {
+  /// Short identifier used in callback URLs, e.g. `/api/v2/auth/oidc/<name>/callback`
+  #[default("")]
+  #[doku(example = "company")]
+  pub name: String,
+  /// Label shown on the login button
+  #[default("")]
+  #[doku(example = "Company SSO")]
+  pub display_name: String,
+  /// Issuer URL; `<issuer>/.well-known/openid-configuration` must serve the discovery document
+  #[default("")]
+  #[doku(example = "https://login.example.com")]
+  pub issuer: String,
+  /// Client ID registered with the provider
+  #[default("")]
+  pub client_id: String,
+  /// Client secret registered with the provider
+  #[default("")]
+  pub client_secret: String,
+  /// Space-separated scopes to request
+  #[default("openid email profile")]
+  pub scopes: String,
+  /// Create an account on first login, subject to the site's registration mode
+  #[default(true)]
+  pub auto_provision: bool,
+  /// Attach a first-time login to an existing account with the same verified email.
+  /// Only enable this for providers that are trusted to verify email ownership
+  #[default(false)]
+  pub link_by_email: bool,
+  /// Signing algorithms accepted for ID tokens. Asymmetric ones are checked against the
+  /// provider's published keys; HS256 uses the client secret, so only list it for
+  /// providers that sign that way
+  #[default(vec!["RS256".to_string(), "ES256".to_string()])]
+  #[doku(example = "RS256")]
+  pub id_token_signing_algs: Vec<String>,
+}
+
 // #[derive(Debug, Deserialize, Serialize, Clone, SmartDefault, Document)]
 // #[serde(default)]
 // pub struct PictrsConfig {
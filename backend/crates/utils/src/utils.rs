@@ -4,17 +4,35 @@ use itertools::Itertools;
 use once_cell::sync::Lazy;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use regex::Regex;
+use std::net::{Ipv6Addr, SocketAddr};
 
+/// The client address that rate limits and login backoff are keyed on.
+/// IPv6 clients are keyed on their /64, since a single host is usually
+/// handed the whole prefix and could otherwise rotate through it.
 pub fn get_ip(conn_info: &ConnectionInfo) -> IpAddr {
-    IpAddr(
-        conn_info
-            .realip_remote_addr()
-            .unwrap_or("127.0.0.1:12345")
-            .split(':')
-            .next()
-            .unwrap_or("127.0.0.1")
-            .to_string(),
-    )
+    IpAddr(ip_key(conn_info.realip_remote_addr().unwrap_or("127.0.0.1")))
+}
+
+/// Strip the port, if any, from `addr` (`1.2.3.4:80`, `[2001:db8::1]:80`
+/// or a bare address) and reduce IPv6 addresses to their /64. Anything
+/// that doesn't parse, like a bogus forwarded header, is kept as it is.
+fn ip_key(addr: &str) -> String {
+    let ip = addr
+        .parse::<SocketAddr>()
+        .map(|socket| socket.ip())
+        .or_else(|_| addr.trim_start_matches('[').trim_end_matches(']').parse());
+
+    match ip {
+        Ok(std::net::IpAddr::V4(v4)) => v4.to_string(),
+        Ok(std::net::IpAddr::V6(v6)) => match v6.to_ipv4_mapped() {
+            Some(v4) => v4.to_string(),
+            None => {
+                let [a, b, c, d, ..] = v6.segments();
+                format!("{}/64", Ipv6Addr::new(a, b, c, d, 0, 0, 0, 0))
+            }
+        },
+        Err(_) => addr.to_string(),
+    }
 }
 
 static MENTIONS_REGEX: Lazy<Regex> =
@@ -467,3 +485,28 @@ pub fn extract_img_file_name(i_url: &str) -> Option<String> {
 
     Some(img_file_name.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_key_strips_ports() {
+        assert_eq!(ip_key("203.0.113.7:54321"), "203.0.113.7");
+        assert_eq!(ip_key("203.0.113.7"), "203.0.113.7");
+        assert_eq!(ip_key("[::ffff:203.0.113.7]:443"), "203.0.113.7");
+    }
+
+    #[test]
+    fn test_ip_key_groups_ipv6_by_64() {
+        assert_eq!(ip_key("2001:db8:1:2:3:4:5:6"), "2001:db8:1:2::/64");
+        assert_eq!(ip_key("[2001:db8:1:2::9]:8080"), "2001:db8:1:2::/64");
+        assert_eq!(ip_key("[2001:db8::1]"), "2001:db8::/64");
+        assert_ne!(ip_key("2001:db8:1:2::1"), ip_key("2001:db8:1:3::1"));
+    }
+
+    #[test]
+    fn test_ip_key_keeps_unparseable_addresses() {
+        assert_eq!(ip_key("unknown"), "unknown");
+    }
+}
@@ -1,18 +1,26 @@
 use actix_web::*;
-use async_graphql::dataloader::DataLoader;
-use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use async_graphql::{dataloader::DataLoader, Data};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
 //use tinyboards_api::{Perform, PerformUpload};
-use tinyboards_api::{context::TinyBoardsContext, utils::auth::get_user_from_header_opt};
-use tinyboards_api::{LoggedInUser, MasterKey, PostgresLoader, Settings as GQLSettings};
+use tinyboards_api::{
+    context::TinyBoardsContext,
+    utils::auth::{get_auth_from_header_opt, get_user_from_header_opt},
+};
+use tinyboards_api::{LoggedInUser, MasterKey, PostgresLoader, SessionTokenHash, Settings as GQLSettings, TokenScope};
+use tinyboards_auth::{cookies::REFRESH_COOKIE_NAME, tokens::hash_refresh_token};
+use tinyboards_db::models::user::User;
+use tinyboards_utils::utils::get_ip;
 use crate::media_handler;
 
 pub fn graphql_config(cfg: &mut web::ServiceConfig) {
     cfg.route("/api/v2/graphql", web::post().to(perform_graphql));
+    cfg.route("/api/v2/graphql/ws", web::get().to(graphql_subscription));
 }
 
 pub fn media_files_config(cfg: &mut web::ServiceConfig) {
     // Serve media files through OpenDAL storage backend (works for all backends: fs, s3, azure, gcs)
     cfg.route("/media/{filename:.*}", web::get().to(media_handler::serve_media));
+    cfg.route("/media/{filename:.*}", web::head().to(media_handler::serve_media));
 }
 
 pub fn health_check_config(cfg: &mut web::ServiceConfig) {
@@ -49,14 +57,23 @@ async fn perform_graphql(
 ) -> Result<GraphQLResponse> {
     let auth_header = get_auth(&http_request);
 
-    let logged_in_user =
-        get_user_from_header_opt(context.pool(), context.master_key(), auth_header.as_deref()).await?;
+    let (logged_in_user, token_scope) =
+        match get_auth_from_header_opt(context.pool(), context.master_key(), auth_header.as_deref()).await? {
+            Some((user, scope)) => (Some(user), scope),
+            None => (None, None),
+        };
 
     let my_user_id = match logged_in_user {
         Some(ref v) => v.id,
         None => uuid::Uuid::nil(),
     };
 
+    let session_token_hash = http_request
+        .cookie(REFRESH_COOKIE_NAME)
+        .map(|cookie| hash_refresh_token(cookie.value()));
+    // Resolved up front so the connection info borrow isn't held across the await
+    let ip = get_ip(&http_request.connection_info());
+
     Ok(context
         .schema()
         .execute(
@@ -64,9 +81,14 @@ async fn perform_graphql(
                 .into_inner()
                 .data(LoggedInUser::from(logged_in_user))
                 .data(MasterKey::from(context.master_key().jwt_secret.clone()))
+                .data(SessionTokenHash::from(session_token_hash))
+                .data(TokenScope::from(token_scope))
                 .data(GQLSettings::from(context.settings()))
                 .data(context.pool().clone())
                 .data(context.storage().clone())
+                .data(context.events().clone())
+                .data(context.rate_limit_cell().clone())
+                .data(ip)
                 .data(DataLoader::new(
                     PostgresLoader::new(context.pool(), my_user_id),
                     tokio::spawn,
@@ -75,3 +97,63 @@ async fn perform_graphql(
         .await
         .into())
 }
+
+/// Per-connection data for websocket subscriptions, mirroring what `perform_graphql` attaches to each request.
+fn subscription_data(context: &TinyBoardsContext, logged_in_user: Option<User>) -> Data {
+    let my_user_id = match logged_in_user {
+        Some(ref v) => v.id,
+        None => uuid::Uuid::nil(),
+    };
+
+    let mut data = Data::default();
+    data.insert(LoggedInUser::from(logged_in_user));
+    data.insert(MasterKey::from(context.master_key().jwt_secret.clone()));
+    data.insert(GQLSettings::from(context.settings()));
+    data.insert(context.pool().clone());
+    data.insert(context.storage().clone());
+    data.insert(context.events().clone());
+    data.insert(DataLoader::new(
+        PostgresLoader::new(context.pool(), my_user_id),
+        tokio::spawn,
+    ));
+    data
+}
+
+async fn graphql_subscription(
+    context: web::Data<TinyBoardsContext>,
+    http_request: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse> {
+    // Browsers can't set headers on websocket upgrades, so the tb_access cookie
+    // is the usual path. Clients without cookies may instead send
+    // `{"Authorization": "Bearer ..."}` as the connection_init payload.
+    let auth_header = get_auth(&http_request);
+    let logged_in_user =
+        get_user_from_header_opt(context.pool(), context.master_key(), auth_header.as_deref()).await?;
+
+    let init_context = context.clone();
+
+    GraphQLSubscription::new(context.schema().clone())
+        .with_data(subscription_data(&context, logged_in_user))
+        .on_connection_init(move |payload| async move {
+            let auth = payload
+                .get("Authorization")
+                .or_else(|| payload.get("authorization"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+
+            match auth {
+                Some(auth) => {
+                    let user = get_user_from_header_opt(
+                        init_context.pool(),
+                        init_context.master_key(),
+                        Some(&auth),
+                    )
+                    .await?;
+                    Ok(subscription_data(&init_context, user))
+                }
+                None => Ok(Data::default()),
+            }
+        })
+        .start(&http_request, payload)
+}
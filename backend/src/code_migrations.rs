@@ -3,7 +3,10 @@ use diesel::sql_query;
 use diesel_async::RunQueryDsl;
 use tinyboards_db::{
     enums::*,
-    models::user::user::{User, UserInsertForm},
+    models::{
+        moderator::automod_rule::{AUTOMOD_USER_ID, AUTOMOD_USER_NAME},
+        user::user::{User, UserInsertForm},
+    },
     schema::{site, users},
     utils::DbPool,
 };
@@ -41,6 +44,29 @@ async fn initialize_core_database_records(pool: &DbPool) -> Result<(), TinyBoard
         .await
         .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to insert JWT secret"))?;
 
+    // Insert the AutoModerator account if it doesn't exist. Its password is
+    // random and thrown away, so nobody can log in to it. If someone already
+    // registered the name, it gets a suffix.
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+    let passhash = argon2::Argon2::default()
+        .hash_password(Uuid::new_v4().to_string().as_bytes(), &SaltString::generate(&mut OsRng))
+        .map_err(|e| TinyBoardsError::from_message(500, &format!("Password hash error: {}", e)))?
+        .to_string();
+
+    sql_query(format!(
+        "INSERT INTO users (id, name, display_name, passhash, is_bot_account, is_application_accepted) \
+         SELECT '{id}', \
+         CASE WHEN EXISTS (SELECT 1 FROM users WHERE name = '{name}') \
+         THEN '{name}_' || substr(md5(random()::text), 1, 6) ELSE '{name}' END, \
+         '{name}', '{passhash}', true, true \
+         WHERE NOT EXISTS (SELECT 1 FROM users WHERE id = '{id}')",
+        id = AUTOMOD_USER_ID,
+        name = AUTOMOD_USER_NAME,
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to insert AutoModerator account"))?;
+
     info!("Core database records initialized");
     Ok(())
 }
@@ -198,8 +224,23 @@ async fn initialize_site_and_admin_user(
             .await
             .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to insert site aggregates"))?;
 
-        // Initialize default rate limits for the site
-        sql_query("INSERT INTO rate_limits (id, site_id) SELECT gen_random_uuid(), id FROM site LIMIT 1 ON CONFLICT DO NOTHING")
+        // Seed the site's rate limits from the config file; admins adjust them afterwards
+        let limits = settings.rate_limit.clone().unwrap_or_default();
+        sql_query(format!(
+            "INSERT INTO rate_limits (id, site_id, message, message_per_second, post, post_per_second, \
+             register, register_per_second, login, login_per_second, image, image_per_second, \
+             comment, comment_per_second, search, search_per_second, magic_link, magic_link_per_second) \
+             SELECT gen_random_uuid(), id, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {} \
+             FROM site LIMIT 1 ON CONFLICT DO NOTHING",
+            limits.message, limits.message_per_second,
+            limits.post, limits.post_per_second,
+            limits.register, limits.register_per_second,
+            limits.login, limits.login_per_second,
+            limits.image, limits.image_per_second,
+            limits.comment, limits.comment_per_second,
+            limits.search, limits.search_per_second,
+            limits.magic_link, limits.magic_link_per_second,
+        ))
             .execute(&mut conn)
             .await
             .map_err(|e| TinyBoardsError::from_error_message(e, 500, "Failed to insert rate limits"))?;
@@ -4,6 +4,7 @@ pub mod code_migrations;
 pub mod media_handler;
 pub mod root_span_builder;
 pub mod scheduled_tasks;
+pub mod storage_migration;
 #[cfg(feature = "console")]
 pub mod telemetry;
 
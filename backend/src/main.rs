@@ -12,6 +12,9 @@ use reqwest_tracing::TracingMiddleware;
 use std::{thread, time::Duration};
 use tinyboards_api::{
     context::TinyBoardsContext,
+    events::EventBus,
+    storage::processing_queue,
+    utils::rate_limit::load_rate_limit_config,
     utils::request::build_user_agent,
 };
 use tinyboards_api::gen_schema;
@@ -19,13 +22,13 @@ use tinyboards_db::{
     models::auth::Secret,
     utils::{build_db_pool, get_db_url, run_migrations},
 };
-use tinyboards_auth::configure_auth_routes_with_secret;
+use tinyboards_auth::{configure_auth_routes_with_secret, OidcProviders};
 use tinyboards_server::{
     api_routes, code_migrations::run_advanced_migrations, init_logging,
-    root_span_builder::QuieterRootSpanBuilder, scheduled_tasks,
+    root_span_builder::QuieterRootSpanBuilder, scheduled_tasks, storage_migration,
 };
 use tinyboards_utils::utils::ensure_upload_directories;
-use tinyboards_utils::{error::TinyBoardsError, settings::SETTINGS};
+use tinyboards_utils::{error::TinyBoardsError, rate_limit::RateLimitCell, settings::SETTINGS};
 use tracing_actix_web::TracingLogger;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
@@ -53,6 +56,14 @@ async fn main() -> Result<(), TinyBoardsError> {
     // run advanced migrations
     run_advanced_migrations(&pool, &settings).await?;
 
+    // `tinyboards_server migrate-storage --from <backend>` copies media and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some(storage_migration::COMMAND) {
+        let migrate_args = storage_migration::parse_args(&args[1..])
+            .map_err(|usage| TinyBoardsError::from_message(400, &usage))?;
+        return storage_migration::run(&pool, &settings, migrate_args).await;
+    }
+
     // ensure upload directories exist
     let media_path = settings.get_media_path();
     ensure_upload_directories(&media_path).await.map_err(|e| {
@@ -67,9 +78,19 @@ async fn main() -> Result<(), TinyBoardsError> {
 
     tracing::info!("Storage backend initialized: {:?}", storage.backend_type());
 
+    let events = EventBus::new();
+
+    // Optimize uploaded images and videos in the background
+    processing_queue::spawn_workers(pool.clone(), storage.clone(), events.clone(), &settings.media);
+
     let db_url = get_db_url(Some(&settings));
+    let task_pool = pool.clone();
+    let task_storage = storage.clone();
+    let task_media = settings.media.clone();
+    let runtime = tokio::runtime::Handle::current();
     thread::spawn(move || {
-        scheduled_tasks::setup(db_url).expect("Couldn't setup scheduled tasks");
+        scheduled_tasks::setup(db_url, task_pool, task_storage, task_media, runtime)
+            .expect("Couldn't setup scheduled tasks");
     });
 
     // init the secret
@@ -106,8 +127,13 @@ async fn main() -> Result<(), TinyBoardsError> {
         backoff_exponent: 2,
     };
 
+    let rate_limit_config = load_rate_limit_config(&pool).await?;
+    let rate_limit_cell = RateLimitCell::new(rate_limit_config).await;
+
     let graphql_schema = gen_schema();
 
+    let oidc_providers = OidcProviders::from_settings(&settings, reqwest_client.clone());
+
     let client: ClientWithMiddleware = ClientBuilder::new(reqwest_client.clone())
         .with(TracingMiddleware::default())
         .with(RetryTransientMiddleware::new_with_policy(retry_policy))
@@ -121,7 +147,8 @@ async fn main() -> Result<(), TinyBoardsError> {
             settings.clone(),
             secret.clone(),
             storage.clone(),
-            //rate_limit_cell.clone(),
+            events.clone(),
+            rate_limit_cell.clone(),
             graphql_schema.clone(),
         );
 
@@ -163,8 +190,10 @@ async fn main() -> Result<(), TinyBoardsError> {
             .app_data(payload_config)
             .app_data(json_config)
             .app_data(Data::new(context))
-            // Share the pool directly for the auth REST handlers
+            // Share the pool, rate limiter and SSO providers directly for the auth REST handlers
             .app_data(Data::new(pool.clone()))
+            .app_data(Data::new(rate_limit_cell.clone()))
+            .app_data(Data::new(oidc_providers.clone()))
             // Health check endpoint
             .configure(api_routes::health_check_config)
             // GraphQL
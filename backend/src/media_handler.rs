@@ -1,14 +1,48 @@
+use actix_files::NamedFile;
+use actix_web::body::SizedStream;
+use actix_web::http::header::{self, EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch};
+use actix_web::http::Method;
 use actix_web::{web, HttpRequest, HttpResponse, Result};
-use actix_web::http::header;
-use tinyboards_api::context::TinyBoardsContext;
-use tinyboards_utils::error::TinyBoardsError;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
 use std::path::PathBuf;
-use tokio::fs;
+use std::time::SystemTime;
+use tinyboards_api::{
+    context::TinyBoardsContext,
+    storage::{export::EXPORTS_PREFIX, signing::verify_media_signature},
+};
+use tinyboards_auth::{cookies::ACCESS_COOKIE_NAME, tokens::validate_access_token};
+use tinyboards_db::{schema::site, utils::get_conn};
+use tinyboards_utils::error::TinyBoardsError;
+
+/// Cache policy for keys that are never rewritten in place.
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Anything else may change, so caches must revalidate (cheap with ETags).
+const REVALIDATE_CACHE_CONTROL: &str = "public, no-cache";
+/// Private-instance variants: browsers may cache, shared caches must not.
+const PRIVATE_IMMUTABLE_CACHE_CONTROL: &str = "private, max-age=31536000, immutable";
+const PRIVATE_REVALIDATE_CACHE_CONTROL: &str = "private, no-cache";
+
+/// `?expires=<unix time>&sig=<hex hmac>` from `storage::signing`
+#[derive(Deserialize)]
+struct MediaSignature {
+    expires: i64,
+    sig: String,
+}
 
 /// Serves media files from the configured storage backend.
-/// Tries the active storage backend first, then falls back to the local filesystem
-/// so that files uploaded before a backend migration are still accessible.
-/// Supports HTTP Range requests for video seeking.
+/// Tries the active storage backend first, then (unless `storage.local_fallback` is
+/// off) falls back to the local filesystem so that files uploaded before a backend
+/// switch are still accessible until `migrate-storage` has copied them over.
+///
+/// The body is streamed from storage with ranged reads, so neither full downloads
+/// nor Range requests (video seeking) buffer the whole object in memory. Responses
+/// carry `ETag`/`Last-Modified` and conditional requests are answered with 304.
+///
+/// On a private instance every request needs a session or a signed URL.
+/// Data export archives always need their signed URL.
 pub async fn serve_media(
     context: web::Data<TinyBoardsContext>,
     req: HttpRequest,
@@ -21,79 +55,317 @@ pub async fn serve_media(
 
     tracing::debug!("Serving media file: {}", storage_key);
 
-    // Try the configured storage backend first
-    let data = match context.storage().read(storage_key).await {
-        Ok(data) => data,
-        Err(_) => {
-            // Storage backend didn't have it — check local filesystem as fallback.
-            // This handles the migration case where old files live on disk but the
-            // active backend has been switched to S3/Wasabi/etc.
-            let media_path = context.settings().get_media_path();
-            let local_path = PathBuf::from(&media_path).join(storage_key);
-
-            tracing::debug!("File not in storage backend, trying local path: {:?}", local_path);
-            fs::read(&local_path).await.map_err(|_| {
-                tracing::warn!("File not found in storage backend or local filesystem: {}", storage_key);
-                TinyBoardsError::from_message(404, "File not found")
-            })?
+    // Exports are personal data: serve them like private media, and only to
+    // whoever has the link
+    if storage_key.starts_with(EXPORTS_PREFIX) && !has_valid_signature(&context, &req, storage_key) {
+        return Err(TinyBoardsError::from_message(403, "This download link is invalid or has expired."));
+    }
+    let is_private = storage_key.starts_with(EXPORTS_PREFIX) || is_private_instance(&context).await?;
+    if is_private && !is_authorized(&context, &req, storage_key) {
+        return Err(TinyBoardsError::from_message(
+            403,
+            "This is a private instance. Log in or use a signed URL.",
+        ));
+    }
+
+    let storage = context.storage();
+    let metadata = match storage.stat(storage_key).await {
+        Ok(metadata) => metadata,
+        Err(_) if context.settings().storage.local_fallback => {
+            return serve_local_fallback(&context, &req, storage_key, is_private).await
         }
+        Err(e) => return Err(e),
     };
 
+    let total_len = metadata.content_length();
     let content_type = get_content_type(storage_key);
-    let total_len = data.len();
-
-    // Parse Range header for partial content (needed for video seeking)
-    if let Some(range_header) = req.headers().get(header::RANGE) {
-        if let Ok(range_str) = range_header.to_str() {
-            if let Some(range) = parse_range(range_str, total_len) {
-                let (start, end) = range;
-                let slice = data[start..=end].to_vec();
-                return Ok(HttpResponse::PartialContent()
-                    .content_type(content_type)
-                    .insert_header((header::CONTENT_LENGTH, (end - start + 1).to_string()))
-                    .insert_header((header::ACCEPT_RANGES, "bytes"))
-                    .insert_header((
-                        header::CONTENT_RANGE,
-                        format!("bytes {}-{}/{}", start, end, total_len),
-                    ))
-                    .body(slice));
-            }
+    let cache_control = cache_control(storage_key, is_private);
+    let etag = metadata
+        .etag()
+        .map(parse_etag)
+        .unwrap_or_else(|| fallback_etag(total_len, metadata.last_modified()));
+    let last_modified = metadata
+        .last_modified()
+        .map(|t| HttpDate::from(SystemTime::from(t)));
+
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header((header::ETAG, etag.to_string()))
+        .insert_header((header::CACHE_CONTROL, cache_control))
+        .insert_header((header::ACCEPT_RANGES, "bytes"));
+    if let Some(last_modified) = last_modified {
+        response.insert_header((header::LAST_MODIFIED, last_modified.to_string()));
+    }
+
+    if is_not_modified(&req, &etag, metadata.last_modified()) {
+        return Ok(response
+            .status(actix_web::http::StatusCode::NOT_MODIFIED)
+            .finish());
+    }
+
+    // Parse Range header for partial content (needed for video seeking).
+    // A stale If-Range means the client's partial copy is outdated, so send it all.
+    let range_header = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|h| h.to_str().ok())
+        .filter(|_| if_range_matches(&req, &etag));
+
+    let range = range_header.map_or(RangeRequest::Ignore, |r| parse_range(r, total_len));
+    let (start, end) = match range {
+        RangeRequest::Partial(start, end) => {
+            response
+                .status(actix_web::http::StatusCode::PARTIAL_CONTENT)
+                .insert_header((
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, total_len),
+                ));
+            (start, end)
+        }
+        RangeRequest::NotSatisfiable => {
+            return Ok(HttpResponse::RangeNotSatisfiable()
+                .insert_header((header::CONTENT_RANGE, format!("bytes */{}", total_len)))
+                .finish());
+        }
+        RangeRequest::Ignore if total_len == 0 => {
+            return Ok(response.content_type(content_type).finish());
+        }
+        RangeRequest::Ignore => (0, total_len - 1),
+    };
+
+    let body_len = end - start + 1;
+    response.content_type(content_type);
+
+    if req.method() == Method::HEAD {
+        return Ok(response
+            .insert_header((header::CONTENT_LENGTH, body_len.to_string()))
+            .finish());
+    }
+
+    let stream = storage
+        .read_range_stream(storage_key, start..end + 1)
+        .await?;
+    Ok(response.body(SizedStream::new(body_len, stream)))
+}
+
+/// Storage backend didn't have it — check local filesystem as fallback.
+/// This handles the migration case where old files live on disk but the
+/// active backend has been switched to S3/Wasabi/etc. `NamedFile` streams
+/// from disk and handles ranges and conditional requests itself.
+async fn serve_local_fallback(
+    context: &TinyBoardsContext,
+    req: &HttpRequest,
+    storage_key: &str,
+    is_private: bool,
+) -> Result<HttpResponse, TinyBoardsError> {
+    let media_path = context.settings().get_media_path();
+    let local_path = PathBuf::from(&media_path).join(storage_key);
+
+    tracing::debug!(
+        "File not in storage backend, trying local path: {:?}",
+        local_path
+    );
+    let file = NamedFile::open_async(&local_path).await.map_err(|_| {
+        tracing::warn!(
+            "File not found in storage backend or local filesystem: {}",
+            storage_key
+        );
+        TinyBoardsError::from_message(404, "File not found")
+    })?;
+
+    let mut response = file.into_response(req);
+    if let Ok(value) = cache_control(storage_key, is_private).parse() {
+        response.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+    Ok(response)
+}
+
+async fn is_private_instance(context: &TinyBoardsContext) -> Result<bool, TinyBoardsError> {
+    let conn = &mut get_conn(context.pool()).await?;
+    Ok(site::table
+        .select(site::is_private)
+        .first::<bool>(conn)
+        .await
+        .optional()
+        .map_err(|e| TinyBoardsError::Database(e.to_string()))?
+        .unwrap_or(false))
+}
+
+/// The request carries an unexpired signature for this key.
+fn has_valid_signature(context: &TinyBoardsContext, req: &HttpRequest, storage_key: &str) -> bool {
+    let secret = &context.master_key().jwt_secret;
+    match web::Query::<MediaSignature>::from_query(req.query_string()) {
+        Ok(signature) => verify_media_signature(storage_key, signature.expires, &signature.sig, secret),
+        Err(_) => false,
+    }
+}
+
+/// A valid signature for this key, or a valid access token in the
+/// `Authorization` header or the `tb_access` cookie.
+fn is_authorized(context: &TinyBoardsContext, req: &HttpRequest, storage_key: &str) -> bool {
+    if has_valid_signature(context, req, storage_key) {
+        return true;
+    }
+
+    let secret = &context.master_key().jwt_secret;
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| req.cookie(ACCESS_COOKIE_NAME).map(|c| c.value().to_string()));
+
+    match token {
+        Some(token) => validate_access_token(&token, secret).is_ok(),
+        None => false,
+    }
+}
+
+fn cache_control(key: &str, is_private: bool) -> &'static str {
+    match (cache_control_for_key(key), is_private) {
+        (IMMUTABLE_CACHE_CONTROL, true) => PRIVATE_IMMUTABLE_CACHE_CONTROL,
+        (_, true) => PRIVATE_REVALIDATE_CACHE_CONTROL,
+        (policy, false) => policy,
+    }
+}
+
+/// Backends return ETags quoted (`"abc"` or `W/"abc"`); some return them bare.
+fn parse_etag(raw: &str) -> EntityTag {
+    raw.parse()
+        .unwrap_or_else(|_| EntityTag::new_strong(raw.trim_matches('"').to_string()))
+}
+
+/// Validator for backends without ETags (e.g. the local filesystem), built from
+/// the size and modification time like nginx does. It has to be strong: a
+/// client resuming a download sends it back in `If-Range`, which only accepts
+/// strong validators.
+fn fallback_etag(len: u64, modified: Option<DateTime<Utc>>) -> EntityTag {
+    let modified = modified.map(|t| t.timestamp()).unwrap_or_default();
+    EntityTag::new_strong(format!("{:x}-{:x}", len, modified))
+}
+
+/// `If-None-Match` takes precedence over `If-Modified-Since` (RFC 9110 §13.2.2).
+fn is_not_modified(req: &HttpRequest, etag: &EntityTag, modified: Option<DateTime<Utc>>) -> bool {
+    if req.headers().contains_key(header::IF_NONE_MATCH) {
+        return match IfNoneMatch::parse(req) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(etag)),
+            Err(_) => false,
+        };
+    }
+
+    match (IfModifiedSince::parse(req), modified) {
+        (Ok(IfModifiedSince(since)), Some(modified)) => {
+            // HTTP dates have one-second precision
+            modified.timestamp() <= DateTime::<Utc>::from(SystemTime::from(since)).timestamp()
+        }
+        _ => false,
+    }
+}
+
+/// `If-Range` only allows a partial response if the strong validator still matches.
+fn if_range_matches(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req
+        .headers()
+        .get(header::IF_RANGE)
+        .and_then(|h| h.to_str().ok())
+    {
+        Some(value) => value
+            .parse::<EntityTag>()
+            .map(|tag| tag.strong_eq(etag))
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
+/// Uploaded files get a fresh random (or content-hash) name and are never
+/// overwritten, so they can be cached forever.
+fn cache_control_for_key(key: &str) -> &'static str {
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let stem = stem.strip_suffix("_thumb").unwrap_or(stem);
+
+    let is_content_hash = stem.len() == 64 && stem.chars().all(|c| c.is_ascii_hexdigit());
+    // generate_secure_filename: `{name}_{unix timestamp}_{18 random alphanumerics}`
+    let is_generated = match stem.rsplitn(3, '_').collect::<Vec<_>>().as_slice() {
+        [random, timestamp, _] => {
+            random.len() == 18
+                && random.chars().all(|c| c.is_ascii_alphanumeric())
+                && !timestamp.is_empty()
+                && timestamp.chars().all(|c| c.is_ascii_digit())
         }
+        _ => false,
+    };
+
+    if is_content_hash || is_generated {
+        IMMUTABLE_CACHE_CONTROL
+    } else {
+        REVALIDATE_CACHE_CONTROL
     }
+}
 
-    Ok(HttpResponse::Ok()
-        .content_type(content_type)
-        .insert_header((header::CONTENT_LENGTH, total_len.to_string()))
-        .insert_header((header::ACCEPT_RANGES, "bytes"))
-        .body(data))
+/// What to do with a `Range` header.
+#[derive(Debug, PartialEq, Eq)]
+enum RangeRequest {
+    /// Send `start..=end` as 206.
+    Partial(u64, u64),
+    /// Valid, but none of it is inside the file: 416.
+    NotSatisfiable,
+    /// Malformed, another unit, or several ranges (we don't send multipart
+    /// responses). RFC 9110 §14.2 says to ignore it and send the whole file.
+    Ignore,
 }
 
-/// Parse a simple "bytes=start-end" range header.
-/// Returns (start, end) inclusive, or None if the range is invalid.
-fn parse_range(range_str: &str, total: usize) -> Option<(usize, usize)> {
-    let range_str = range_str.strip_prefix("bytes=")?;
-    let mut parts = range_str.splitn(2, '-');
-    let start_str = parts.next()?.trim();
-    let end_str = parts.next()?.trim();
+/// Parse a single "bytes=start-end" range against a file of `total` bytes.
+fn parse_range(range_str: &str, total: u64) -> RangeRequest {
+    let Some(spec) = range_str.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignore;
+    };
+    let Some((start_str, end_str)) = spec.split_once('-') else {
+        return RangeRequest::Ignore;
+    };
+    // `split_once` leaves any further ranges in `end_str`, where they fail to
+    // parse, so multi-range requests are ignored too.
+    let (start_str, end_str) = (start_str.trim(), end_str.trim());
 
     if start_str.is_empty() {
         // Suffix range: bytes=-500 means last 500 bytes
-        let suffix_len: usize = end_str.parse().ok()?;
-        let start = total.saturating_sub(suffix_len);
-        Some((start, total - 1))
-    } else {
-        let start: usize = start_str.parse().ok()?;
-        let end = if end_str.is_empty() {
-            total - 1
-        } else {
-            end_str.parse::<usize>().ok()?.min(total - 1)
+        let Some(suffix_len) = parse_position(end_str) else {
+            return RangeRequest::Ignore;
         };
-        if start <= end && start < total {
-            Some((start, end))
-        } else {
-            None
+        if suffix_len == 0 || total == 0 {
+            return RangeRequest::NotSatisfiable;
+        }
+        return RangeRequest::Partial(total.saturating_sub(suffix_len), total - 1);
+    }
+
+    let Some(start) = parse_position(start_str) else {
+        return RangeRequest::Ignore;
+    };
+    let end = if end_str.is_empty() {
+        None
+    } else {
+        match parse_position(end_str) {
+            Some(end) if end >= start => Some(end),
+            _ => return RangeRequest::Ignore,
         }
+    };
+
+    if start >= total {
+        return RangeRequest::NotSatisfiable;
+    }
+    let last = total - 1;
+    RangeRequest::Partial(start, end.map_or(last, |end| end.min(last)))
+}
+
+/// Digits only: `u64::from_str` would also take a leading `+`.
+fn parse_position(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
     }
+    s.parse().ok()
 }
 
 /// Determine content type from file extension
@@ -130,3 +402,109 @@ fn get_content_type(path: &str) -> &'static str {
         _ => "application/octet-stream",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        use RangeRequest::*;
+
+        assert_eq!(parse_range("bytes=0-99", 1000), Partial(0, 99));
+        assert_eq!(parse_range("bytes=500-", 1000), Partial(500, 999));
+        assert_eq!(parse_range("bytes=-100", 1000), Partial(900, 999));
+        assert_eq!(parse_range("bytes=-5000", 1000), Partial(0, 999));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Partial(900, 999));
+        assert_eq!(parse_range("bytes=1000-", 1000), NotSatisfiable);
+        assert_eq!(parse_range("bytes=0-0", 0), NotSatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), NotSatisfiable);
+    }
+
+    #[test]
+    fn test_parse_range_ignores_what_it_cant_serve() {
+        for header in [
+            "items=0-1",
+            "bytes=",
+            "bytes=abc",
+            "bytes=a-b",
+            "bytes=+1-5",
+            "bytes=-",
+            "bytes=5-3",
+            "bytes=0-1,5-9",
+            "bytes=-5,0-1",
+        ] {
+            assert_eq!(parse_range(header, 1000), RangeRequest::Ignore, "{}", header);
+        }
+    }
+
+    #[test]
+    fn test_if_range_accepts_the_fallback_etag() {
+        let modified = DateTime::from_timestamp(1_700_000_000, 0);
+        let etag = fallback_etag(1000, modified);
+
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((header::IF_RANGE, etag.to_string()))
+            .to_http_request();
+        assert!(if_range_matches(&req, &etag));
+
+        let stale = fallback_etag(999, modified);
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((header::IF_RANGE, stale.to_string()))
+            .to_http_request();
+        assert!(!if_range_matches(&req, &etag));
+
+        let weak = EntityTag::new_weak(etag.tag().to_string());
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((header::IF_RANGE, weak.to_string()))
+            .to_http_request();
+        assert!(!if_range_matches(&req, &etag));
+    }
+
+    #[test]
+    fn test_cache_control_for_key() {
+        assert_eq!(
+            cache_control_for_key("images/cat_1700000000_aB3dE5fG7hJ9kL1mN2.webp"),
+            IMMUTABLE_CACHE_CONTROL
+        );
+        assert_eq!(
+            cache_control_for_key("images/upload_1700000000_aB3dE5fG7hJ9kL1mN2_thumb.webp"),
+            IMMUTABLE_CACHE_CONTROL
+        );
+        assert_eq!(
+            cache_control_for_key(&format!("images/{}.png", "a".repeat(64))),
+            IMMUTABLE_CACHE_CONTROL
+        );
+        assert_eq!(
+            cache_control_for_key("default_avatar.png"),
+            REVALIDATE_CACHE_CONTROL
+        );
+        assert_eq!(
+            cache_control_for_key("site/logo.png"),
+            REVALIDATE_CACHE_CONTROL
+        );
+    }
+
+    #[test]
+    fn test_private_cache_control() {
+        assert_eq!(
+            cache_control("images/cat_1700000000_aB3dE5fG7hJ9kL1mN2.webp", true),
+            PRIVATE_IMMUTABLE_CACHE_CONTROL
+        );
+        assert_eq!(cache_control("site/logo.png", true), PRIVATE_REVALIDATE_CACHE_CONTROL);
+        assert_eq!(cache_control("site/logo.png", false), REVALIDATE_CACHE_CONTROL);
+    }
+
+    #[test]
+    fn test_parse_etag() {
+        assert_eq!(
+            parse_etag("\"abc\""),
+            EntityTag::new_strong("abc".to_string())
+        );
+        assert_eq!(
+            parse_etag("W/\"abc\""),
+            EntityTag::new_weak("abc".to_string())
+        );
+        assert_eq!(parse_etag("abc"), EntityTag::new_strong("abc".to_string()));
+    }
+}
@@ -4,11 +4,21 @@ use clokwerk::{Scheduler, TimeUnits};
 use chrono::{Datelike, NaiveDate};
 use diesel::{sql_query, PgConnection, Connection, RunQueryDsl};
 use std::{thread, time::Duration};
-use tinyboards_utils::error::TinyBoardsError;
+use tinyboards_api::storage::{export::cleanup_expired_exports, maintenance::run_media_gc, StorageBackend};
+use tinyboards_db::utils::DbPool;
+use tinyboards_utils::{error::TinyBoardsError, settings::structs::MediaConfig};
+use tokio::runtime::Handle;
 use tracing::{info, error};
 
-/// Schedules various cleanup tasks for Tinyboards in a background thread
-pub fn setup(db_url: String) -> Result<(), TinyBoardsError> {
+/// Schedules various cleanup tasks for Tinyboards in a background thread.
+/// Jobs that need the async storage backend are run on `runtime`.
+pub fn setup(
+    db_url: String,
+    pool: DbPool,
+    storage: StorageBackend,
+    media: MediaConfig,
+    runtime: Handle,
+) -> Result<(), TinyBoardsError> {
     let mut scheduler = Scheduler::new();
     let mut frequent_scheduler = Scheduler::new();
 
@@ -84,12 +94,17 @@ pub fn setup(db_url: String) -> Result<(), TinyBoardsError> {
     let mut conn4 = PgConnection::establish(&db_url)
         .map_err(|e| TinyBoardsError::from_message(500, &e.to_string()))?;
 
-    // Hourly cleanup of expired sessions, password resets, and old notifications
+    // Hourly cleanup of expired sessions, API tokens, password resets, login failures, and old notifications
     scheduler
     .every(TimeUnits::hour(1))
     .run(move || {
         cleanup_expired_sessions(&mut conn4);
+        cleanup_expired_api_tokens(&mut conn4);
         cleanup_expired_password_resets(&mut conn4);
+        cleanup_expired_email_changes(&mut conn4);
+        cleanup_expired_magic_links(&mut conn4);
+        cleanup_expired_webauthn_challenges(&mut conn4);
+        cleanup_old_login_failures(&mut conn4);
         cleanup_old_read_notifications(&mut conn4);
     });
 
@@ -106,6 +121,29 @@ pub fn setup(db_url: String) -> Result<(), TinyBoardsError> {
         ensure_partitions(&mut conn5);
     });
 
+    // Hourly: delete data export archives past their expiry
+    let (export_pool, export_storage, export_runtime) = (pool.clone(), storage.clone(), runtime.clone());
+    scheduler
+    .every(TimeUnits::hour(1))
+    .run(move || {
+        match export_runtime.block_on(cleanup_expired_exports(&export_pool, &export_storage)) {
+            Ok(count) if count > 0 => info!("Removed {} expired data exports", count),
+            Ok(_) => {}
+            Err(e) => error!("Failed to clean up expired data exports: {}", e),
+        }
+    });
+
+    // Daily: delete uploads and stored files that nothing references
+    if media.gc_enabled {
+        scheduler
+        .every(TimeUnits::day(1))
+        .run(move || {
+            if let Err(e) = runtime.block_on(run_media_gc(&pool, &storage, &media)) {
+                error!("Failed to run media garbage collection: {}", e);
+            }
+        });
+    }
+
     // Manually run the scheduler in an event loop
     loop {
         scheduler.run_pending();
@@ -186,6 +224,19 @@ fn cleanup_expired_sessions(conn: &mut PgConnection) {
     }
 }
 
+/// Remove expired personal access tokens
+fn cleanup_expired_api_tokens(conn: &mut PgConnection) {
+    let stmt = "DELETE FROM api_tokens WHERE expires_at < now()";
+    match sql_query(stmt).execute(conn) {
+        Ok(count) => {
+            if count > 0 {
+                info!("Removed {} expired API tokens", count);
+            }
+        }
+        Err(e) => error!("Failed to clean up expired API tokens: {}", e)
+    }
+}
+
 /// Remove expired password reset tokens
 fn cleanup_expired_password_resets(conn: &mut PgConnection) {
     let stmt = "DELETE FROM password_resets WHERE expires_at < now()";
@@ -199,6 +250,66 @@ fn cleanup_expired_password_resets(conn: &mut PgConnection) {
     }
 }
 
+/// Remove email changes that were never confirmed
+fn cleanup_expired_email_changes(conn: &mut PgConnection) {
+    let stmt = "DELETE FROM email_changes WHERE expires_at < now()";
+    match sql_query(stmt).execute(conn) {
+        Ok(count) => {
+            if count > 0 {
+                info!("Removed {} expired email changes", count);
+            }
+        }
+        Err(e) => error!("Failed to clean up expired email changes: {}", e)
+    }
+}
+
+/// Remove expired login links, used or not
+fn cleanup_expired_magic_links(conn: &mut PgConnection) {
+    let stmt = "DELETE FROM magic_links WHERE expires_at < now()";
+    match sql_query(stmt).execute(conn) {
+        Ok(count) => {
+            if count > 0 {
+                info!("Removed {} expired login links", count);
+            }
+        }
+        Err(e) => error!("Failed to clean up expired login links: {}", e)
+    }
+}
+
+/// Remove passkey challenges that were never answered
+fn cleanup_expired_webauthn_challenges(conn: &mut PgConnection) {
+    let stmt = "DELETE FROM webauthn_challenges WHERE expires_at < now()";
+    match sql_query(stmt).execute(conn) {
+        Ok(count) => {
+            if count > 0 {
+                info!("Removed {} expired passkey challenges", count);
+            }
+        }
+        Err(e) => error!("Failed to clean up expired passkey challenges: {}", e)
+    }
+}
+
+/// Forget failed logins and lockout runs that no longer affect anything.
+/// Backoff only looks back an hour and runs reset after a day without failures.
+fn cleanup_old_login_failures(conn: &mut PgConnection) {
+    let stmt = "DELETE FROM login_failures WHERE created_at < now() - INTERVAL '1 day'";
+    match sql_query(stmt).execute(conn) {
+        Ok(count) => {
+            if count > 0 {
+                info!("Removed {} old login failures", count);
+            }
+        }
+        Err(e) => error!("Failed to clean up login failures: {}", e)
+    }
+
+    let stmt = "DELETE FROM account_lockouts \
+                WHERE last_failed_at < now() - INTERVAL '1 day' \
+                AND (locked_until IS NULL OR locked_until < now())";
+    if let Err(e) = sql_query(stmt).execute(conn) {
+        error!("Failed to clean up account lockouts: {}", e);
+    }
+}
+
 /// Delete read notifications older than 90 days
 fn cleanup_old_read_notifications(conn: &mut PgConnection) {
     let stmt = "DELETE FROM notifications WHERE is_read = true AND created_at < now() - INTERVAL '90 days'";
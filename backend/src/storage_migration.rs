@@ -0,0 +1,105 @@
+use tinyboards_api::storage::migration::{migrate_storage, MigrationProgress, ObjectStatus};
+use tinyboards_db::utils::DbPool;
+use tinyboards_utils::{error::TinyBoardsError, settings::structs::Settings};
+
+pub const COMMAND: &str = "migrate-storage";
+
+const USAGE: &str = "usage: tinyboards_server migrate-storage --from <fs|local|s3|azure|gcs> [--to <backend>]";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MigrateStorageArgs {
+    pub from: String,
+    /// Defaults to the configured `storage.backend`
+    pub to: Option<String>,
+}
+
+/// Parse the arguments after `migrate-storage`.
+pub fn parse_args(args: &[String]) -> Result<MigrateStorageArgs, String> {
+    let mut from = None;
+    let mut to = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = match arg.split_once('=') {
+            Some((_, value)) => Some(value.to_string()),
+            None => args.next().cloned(),
+        };
+        match arg.split('=').next() {
+            Some("--from") => from = value,
+            Some("--to") => to = value,
+            _ => return Err(format!("unknown argument `{}`\n{}", arg, USAGE)),
+        }
+    }
+
+    match from {
+        Some(from) => Ok(MigrateStorageArgs { from, to }),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+/// Copy all referenced media into the configured backend and print progress.
+/// Safe to interrupt; running it again picks up where it stopped.
+pub async fn run(
+    pool: &DbPool,
+    settings: &Settings,
+    args: MigrateStorageArgs,
+) -> Result<(), TinyBoardsError> {
+    let to = args
+        .to
+        .or_else(|| settings.storage.backend.clone())
+        .unwrap_or_else(|| "fs".to_string());
+
+    println!("Migrating media from `{}` to `{}`", args.from, to);
+
+    let migration = migrate_storage(pool, settings, &args.from, &to, |p: &MigrationProgress| {
+        match p.status {
+            ObjectStatus::Copied => {}
+            ObjectStatus::Missing => println!("missing: {}", p.key),
+            ObjectStatus::Failed => println!("failed:  {} ({})", p.key, p.error.unwrap_or("unknown error")),
+        }
+        if p.done.is_multiple_of(100) || p.done == p.total {
+            println!("[{}/{}]", p.done, p.total);
+        }
+    })
+    .await?;
+
+    println!(
+        "Migration {}: {} copied ({} bytes), {} missing, {} failed",
+        migration.status,
+        migration.copied_objects,
+        migration.bytes_copied,
+        migration.missing_objects,
+        migration.failed_objects
+    );
+
+    if migration.failed_objects > 0 {
+        println!("Run the command again to retry failed objects.");
+    } else {
+        println!("Once the server is running on `{}`, `storage.local_fallback` can be set to false.", to);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse_args(&args(&["--from", "local"])),
+            Ok(MigrateStorageArgs { from: "local".to_string(), to: None })
+        );
+        assert_eq!(
+            parse_args(&args(&["--from=fs", "--to", "s3"])),
+            Ok(MigrateStorageArgs { from: "fs".to_string(), to: Some("s3".to_string()) })
+        );
+        assert!(parse_args(&args(&["--to", "s3"])).is_err());
+        assert!(parse_args(&args(&["--from", "fs", "--force"])).is_err());
+    }
+}
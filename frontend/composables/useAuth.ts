@@ -1,7 +1,10 @@
 import { computed, ref } from 'vue'
 import { useAuthStore } from '~/stores/auth'
+import { useSiteStore } from '~/stores/site'
 import { useGraphQL } from '~/composables/useGraphQL'
 import { useToast } from '~/composables/useToast'
+import { useCaptcha } from '~/composables/useCaptcha'
+import { getPasskey, type RequestOptionsJSON } from '~/utils/webauthn'
 import type { User } from '~/types/generated'
 import type { LoginInput, RegisterInput, AuthRestResponse, RegisterRestResponse } from '~/types/api'
 
@@ -50,6 +53,8 @@ export function useAuth () {
   const store = useAuthStore()
   const loading = ref(false)
   const error = ref<{ message: string } | null>(null)
+  /** Second login step, set when the password was accepted but 2FA is needed */
+  const mfa = ref<{ token: string; enrollmentRequired: boolean; passkey: boolean; message: string } | null>(null)
 
   const user = computed(() => store.user)
   const isLoggedIn = computed(() => store.isLoggedIn)
@@ -95,9 +100,20 @@ export function useAuth () {
         body: {
           username_or_email: input.usernameOrEmail,
           password: input.password,
+          captcha: input.captcha,
         },
       })
 
+      if (data.mfa_required && data.mfa_token) {
+        mfa.value = {
+          token: data.mfa_token,
+          enrollmentRequired: data.enrollment_required ?? false,
+          passkey: data.passkey ?? false,
+          message: data.message ?? '',
+        }
+        return false
+      }
+
       if (!data.success) {
         error.value = { message: data.message ?? 'Login failed' }
         toast.error(data.message ?? 'Login failed')
@@ -108,6 +124,47 @@ export function useAuth () {
       await fetchMe()
       toast.success('Logged in')
       return true
+    } catch (err: unknown) {
+      const fetchError = err as { data?: { error?: string }; statusCode?: number; statusMessage?: string }
+      // After a few failed attempts the server wants a captcha too: solve one and retry
+      if (fetchError.statusCode === 400 && !input.captcha && useSiteStore().captchaEnabled) {
+        const captcha = await useCaptcha().solve().catch(() => null)
+        if (captcha) {
+          return await login({ ...input, captcha })
+        }
+      }
+      const msg = fetchError.data?.error ?? fetchError.statusMessage ?? 'Login failed'
+      error.value = { message: msg }
+      toast.error(msg)
+      return false
+    } finally {
+      loading.value = false
+    }
+  }
+
+  /**
+   * Finish a login that needs a second factor, with a code from the
+   * authenticator app or a recovery code.
+   */
+  async function loginWithTwoFactor (code: string, isRecoveryCode = false): Promise<boolean> {
+    if (!mfa.value) {
+      return false
+    }
+    loading.value = true
+    error.value = null
+    const toast = useToast()
+
+    try {
+      await $fetch<AuthRestResponse>('/api/auth/login-2fa', {
+        method: 'POST',
+        body: {
+          mfa_token: mfa.value.token,
+          ...(isRecoveryCode ? { recovery_code: code } : { code }),
+        },
+      })
+
+      await completeLogin()
+      return true
     } catch (err: unknown) {
       const fetchError = err as { data?: { error?: string }; statusMessage?: string }
       const msg = fetchError.data?.error ?? fetchError.statusMessage ?? 'Login failed'
@@ -119,6 +176,51 @@ export function useAuth () {
     }
   }
 
+  /**
+   * Log in with a passkey: on its own, or as the second factor when a
+   * password login is waiting for one.
+   */
+  async function loginWithPasskey (): Promise<boolean> {
+    loading.value = true
+    error.value = null
+    const toast = useToast()
+    const mfaToken = mfa.value?.token
+
+    try {
+      const options = await $fetch<{ publicKey: RequestOptionsJSON }>('/api/auth/login-passkey-options', {
+        method: 'POST',
+        body: { mfa_token: mfaToken },
+      })
+      const credential = await getPasskey(options.publicKey)
+      await $fetch<AuthRestResponse>('/api/auth/login-passkey', {
+        method: 'POST',
+        body: { credential, mfa_token: mfaToken },
+      })
+
+      await completeLogin()
+      return true
+    } catch (err: unknown) {
+      // Closing the browser's passkey prompt isn't an error worth shouting about
+      if (err instanceof DOMException && err.name === 'NotAllowedError') {
+        return false
+      }
+      const fetchError = err as { data?: { error?: string }; statusMessage?: string }
+      const msg = fetchError.data?.error ?? fetchError.statusMessage ?? 'Passkey login failed'
+      error.value = { message: msg }
+      toast.error(msg)
+      return false
+    } finally {
+      loading.value = false
+    }
+  }
+
+  /** Load the user once the auth cookies are set (e.g. after enrolling in 2FA during login). */
+  async function completeLogin (): Promise<void> {
+    mfa.value = null
+    await fetchMe()
+    useToast().success('Logged in')
+  }
+
   async function register (input: RegisterInput): Promise<boolean> {
     loading.value = true
     error.value = null
@@ -134,6 +236,7 @@ export function useAuth () {
           email: input.email,
           invite_code: input.inviteCode,
           application_answer: input.applicationAnswer,
+          captcha: input.captcha,
         },
       })
 
@@ -189,8 +292,12 @@ export function useAuth () {
     isAdmin,
     loading,
     error,
+    mfa,
     fetchMe,
     login,
+    loginWithTwoFactor,
+    loginWithPasskey,
+    completeLogin,
     register,
     logout,
   }
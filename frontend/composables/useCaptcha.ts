@@ -0,0 +1,61 @@
+import { ref } from 'vue'
+import type { Ref } from 'vue'
+import type { CaptchaChallenge, CaptchaResponse, CaptchaSolution } from '~/types/api'
+
+interface UseCaptchaReturn {
+  solving: Ref<boolean>
+  solve: () => Promise<CaptchaSolution | null>
+}
+
+/** Hashes computed concurrently per round, to amortise the async overhead of WebCrypto */
+const BATCH_SIZE = 256
+
+function leadingZeroBits (bytes: Uint8Array): number {
+  let count = 0
+  for (const byte of bytes) {
+    if (byte === 0) {
+      count += 8
+      continue
+    }
+    return count + Math.clz32(byte) - 24
+  }
+  return count
+}
+
+async function findNonce (challenge: CaptchaChallenge): Promise<string> {
+  const encoder = new TextEncoder()
+  for (let start = 0; ; start += BATCH_SIZE) {
+    const nonces = Array.from({ length: BATCH_SIZE }, (_, i) => String(start + i))
+    const digests = await Promise.all(nonces.map(nonce =>
+      crypto.subtle.digest('SHA-256', encoder.encode(challenge.salt + nonce)),
+    ))
+    const found = digests.findIndex(d => leadingZeroBits(new Uint8Array(d)) >= challenge.difficulty)
+    if (found !== -1) return nonces[found]
+  }
+}
+
+/**
+ * Composable for the site's built-in proof-of-work captcha.
+ *
+ * `solve()` fetches a challenge and searches for a nonce in the browser. It
+ * resolves to `null` when the site doesn't have captcha enabled, and throws if
+ * the challenge can't be fetched. Each solution can be submitted only once.
+ */
+export function useCaptcha (): UseCaptchaReturn {
+  const solving = ref(false)
+
+  async function solve (): Promise<CaptchaSolution | null> {
+    const data = await $fetch<CaptchaResponse>('/api/auth/captcha', { method: 'POST' })
+    if (!data.enabled || !data.challenge) return null
+
+    solving.value = true
+    try {
+      const nonce = await findNonce(data.challenge)
+      return { id: data.challenge.id, nonce }
+    } finally {
+      solving.value = false
+    }
+  }
+
+  return { solving, solve }
+}
@@ -73,7 +73,7 @@ export function useFileUpload (): UseFileUploadReturn {
   async function uploadFile (file: File): Promise<string | null> {
     const query = `
       mutation UploadFile($file: Upload!) {
-        uploadFile(file: $file)
+        uploadFile(file: $file) { url }
       }
     `
     const variables = { file: null } as Record<string, unknown>
@@ -83,7 +83,7 @@ export function useFileUpload (): UseFileUploadReturn {
       { file: ['variables.file'] },
       { file },
     )
-    return (result?.uploadFile as string) ?? null
+    return (result?.uploadFile as { url: string } | undefined)?.url ?? null
   }
 
   async function executeWithFile (
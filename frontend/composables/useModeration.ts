@@ -28,6 +28,14 @@ interface CommentReportView {
   updatedAt: string
 }
 
+export type RemovalMessageDelivery = 'COMMENT' | 'PRIVATE_MESSAGE' | 'NOTIFICATION'
+
+// A predefined removal reason picked in a remove dialog, and how to tell the author
+export interface RemovalReasonChoice {
+  removalReasonId: string | null
+  sendMessage: RemovalMessageDelivery | null
+}
+
 const POST_REPORTS_QUERY = `
   query GetPostReports($boardId: ID, $statusFilter: String, $limit: Int, $offset: Int) {
     getPostReports(boardId: $boardId, statusFilter: $statusFilter, limit: $limit, offset: $offset) {
@@ -45,8 +53,8 @@ const COMMENT_REPORTS_QUERY = `
 `
 
 const REMOVE_POST_MUTATION = `
-  mutation RemovePost($postId: ID!, $reason: String) {
-    removePost(postId: $postId, reason: $reason) { id isRemoved }
+  mutation RemovePost($postId: ID!, $reason: String, $removalReasonId: ID, $sendMessage: RemovalMessageDelivery) {
+    removePost(postId: $postId, reason: $reason, removalReasonId: $removalReasonId, sendMessage: $sendMessage) { id isRemoved }
   }
 `
 
@@ -57,8 +65,8 @@ const RESTORE_POST_MUTATION = `
 `
 
 const REMOVE_COMMENT_MUTATION = `
-  mutation RemoveComment($commentId: ID!, $reason: String) {
-    removeComment(commentId: $commentId, reason: $reason) { id isRemoved }
+  mutation RemoveComment($commentId: ID!, $reason: String, $removalReasonId: ID, $sendMessage: RemovalMessageDelivery) {
+    removeComment(commentId: $commentId, reason: $reason, removalReasonId: $removalReasonId, sendMessage: $sendMessage) { id isRemoved }
   }
 `
 
@@ -158,10 +166,17 @@ export function useModeration () {
     }
   }
 
-  async function removePost (postId: string, reason?: string): Promise<boolean> {
+  async function removePost (postId: string, reason?: string, choice?: RemovalReasonChoice): Promise<boolean> {
     const toast = useToast()
     const { execute: exec, error: mutError } = useGraphQL()
-    const result = await exec(REMOVE_POST_MUTATION, { variables: { postId, reason: reason ?? null } })
+    const result = await exec(REMOVE_POST_MUTATION, {
+      variables: {
+        postId,
+        reason: reason ?? null,
+        removalReasonId: choice?.removalReasonId ?? null,
+        sendMessage: choice?.removalReasonId ? choice.sendMessage : null,
+      },
+    })
     if (result) { toast.success('Post removed'); return true }
     toast.error(mutError.value?.message ?? 'Failed to remove post')
     return false
@@ -176,10 +191,17 @@ export function useModeration () {
     return false
   }
 
-  async function removeComment (commentId: string, reason?: string): Promise<boolean> {
+  async function removeComment (commentId: string, reason?: string, choice?: RemovalReasonChoice): Promise<boolean> {
     const toast = useToast()
     const { execute: exec, error: mutError } = useGraphQL()
-    const result = await exec(REMOVE_COMMENT_MUTATION, { variables: { commentId, reason: reason ?? null } })
+    const result = await exec(REMOVE_COMMENT_MUTATION, {
+      variables: {
+        commentId,
+        reason: reason ?? null,
+        removalReasonId: choice?.removalReasonId ?? null,
+        sendMessage: choice?.removalReasonId ? choice.sendMessage : null,
+      },
+    })
     if (result) { toast.success('Comment removed'); return true }
     toast.error(mutError.value?.message ?? 'Failed to remove comment')
     return false
@@ -221,7 +243,7 @@ export function useModeration () {
     return false
   }
 
-  async function resolveReport (reportId: string, reportType: 'post' | 'comment'): Promise<boolean> {
+  async function resolveReport (reportId: string, reportType: 'post' | 'comment' | 'wiki_page' | 'user' | 'message'): Promise<boolean> {
     const toast = useToast()
     const { execute: exec, error: mutError } = useGraphQL()
     const result = await exec(RESOLVE_REPORT_MUTATION, { variables: { reportId, reportType } })
@@ -230,7 +252,7 @@ export function useModeration () {
     return false
   }
 
-  async function dismissReport (reportId: string, reportType: 'post' | 'comment'): Promise<boolean> {
+  async function dismissReport (reportId: string, reportType: 'post' | 'comment' | 'wiki_page' | 'user' | 'message'): Promise<boolean> {
     const toast = useToast()
     const { execute: exec, error: mutError } = useGraphQL()
     const result = await exec(DISMISS_REPORT_MUTATION, { variables: { reportId, reportType } })
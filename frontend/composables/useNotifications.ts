@@ -27,6 +27,7 @@ export interface NotificationCommentContext {
 export interface NotificationMessageContext {
   id: string
   body: string
+  threadId: string | null
 }
 
 export interface Notification {
@@ -41,6 +42,7 @@ export interface Notification {
   post: NotificationPostContext | null
   comment: NotificationCommentContext | null
   message: NotificationMessageContext | null
+  body: string | null
 }
 
 interface UnreadCount {
@@ -58,7 +60,8 @@ const NOTIFICATIONS_QUERY = `
       actor { id name displayName avatar }
       post { id title boardName boardId }
       comment { id body postId postTitle boardName }
-      message { id body }
+      message { id body threadId }
+      body
     }
   }
 `
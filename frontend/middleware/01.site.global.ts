@@ -33,6 +33,7 @@ const SITE_QUERY = `
       defaultBoardMode
       customCss
       customCssEnabled
+      magicLinkLoginEnabled
     }
   }
 `
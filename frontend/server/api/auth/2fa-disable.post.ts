@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/2fa-disable
+ * Proxies to backend POST /api/v2/auth/2fa/disable.
+ * Requires authentication (access_token cookie).
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/2fa/disable')
+  setResponseStatus(event, status)
+  return data
+})
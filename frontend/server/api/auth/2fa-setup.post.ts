@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/2fa-setup
+ * Proxies to backend POST /api/v2/auth/2fa/setup.
+ * Requires authentication, or the MFA token when enrolling during login.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/2fa/setup')
+  setResponseStatus(event, status)
+  return data
+})
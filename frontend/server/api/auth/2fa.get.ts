@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * GET /api/auth/2fa
+ * Proxies to backend GET /api/v2/auth/2fa.
+ * Requires authentication (access_token cookie).
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/2fa', { method: 'GET' })
+  setResponseStatus(event, status)
+  return data
+})
@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/captcha
+ * Proxies to backend POST /api/v2/auth/captcha.
+ * Returns a proof-of-work challenge when the site has captcha enabled.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/captcha', { includeBody: false })
+  setResponseStatus(event, status)
+  return data
+})
@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/email-change-cancel
+ * Proxies to backend POST /api/v2/auth/email/change/cancel.
+ * Takes the token from the cancel link, or none to cancel your own pending change.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/email/change/cancel')
+  setResponseStatus(event, status)
+  return data
+})
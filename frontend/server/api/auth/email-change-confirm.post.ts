@@ -0,0 +1,12 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/email-change-confirm
+ * Proxies to backend POST /api/v2/auth/email/change/confirm.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/email/change/confirm')
+  setResponseStatus(event, status)
+  return data
+})
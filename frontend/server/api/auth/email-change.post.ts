@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/email-change
+ * Proxies to backend POST /api/v2/auth/email/change.
+ * Requires authentication (access_token cookie) and the current password.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/email/change')
+  setResponseStatus(event, status)
+  return data
+})
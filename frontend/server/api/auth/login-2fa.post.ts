@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/login-2fa
+ * Proxies to backend POST /api/v2/auth/login/2fa.
+ * Exchanges the MFA token from login and a code for auth cookies.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/login/2fa')
+  setResponseStatus(event, status)
+  return data
+})
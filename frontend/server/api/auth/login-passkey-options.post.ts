@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/login-passkey-options
+ * Proxies to backend POST /api/v2/auth/login/passkey/options.
+ * Returns options for navigator.credentials.get(), for the MFA token's account if one is given.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/login/passkey/options')
+  setResponseStatus(event, status)
+  return data
+})
@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/login-passkey
+ * Proxies to backend POST /api/v2/auth/login/passkey.
+ * Exchanges a signed passkey assertion (and the MFA token, as a second factor) for auth cookies.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/login/passkey')
+  setResponseStatus(event, status)
+  return data
+})
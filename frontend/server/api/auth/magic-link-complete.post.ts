@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/magic-link-complete
+ * Proxies to backend POST /api/v2/auth/magic-link/complete.
+ * Forwards cookies both directions — the backend sets httpOnly auth cookies.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/magic-link/complete')
+  setResponseStatus(event, status)
+  return data
+})
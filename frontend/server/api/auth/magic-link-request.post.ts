@@ -0,0 +1,12 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/magic-link-request
+ * Proxies to backend POST /api/v2/auth/magic-link/request.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/magic-link/request')
+  setResponseStatus(event, status)
+  return data
+})
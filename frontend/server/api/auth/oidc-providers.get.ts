@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * GET /api/auth/oidc-providers
+ * Proxies to backend GET /api/v2/auth/oidc/providers.
+ * Lists the single sign-on providers shown on the login page.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/oidc/providers', { method: 'GET' })
+  setResponseStatus(event, status)
+  return data
+})
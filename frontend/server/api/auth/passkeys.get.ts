@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * GET /api/auth/passkeys
+ * Proxies to backend GET /api/v2/auth/passkeys.
+ * Requires authentication (access_token cookie).
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/passkeys', { method: 'GET' })
+  setResponseStatus(event, status)
+  return data
+})
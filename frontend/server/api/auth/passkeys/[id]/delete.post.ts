@@ -0,0 +1,14 @@
+import { defineEventHandler, getRouterParam, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/passkeys/:id/delete
+ * Proxies to backend POST /api/v2/auth/passkeys/:id/delete.
+ * Requires authentication and the current password.
+ */
+export default defineEventHandler(async (event) => {
+  const id = encodeURIComponent(getRouterParam(event, 'id') ?? '')
+  const { status, data } = await proxyAuthRequest(event, `/passkeys/${id}/delete`)
+  setResponseStatus(event, status)
+  return data
+})
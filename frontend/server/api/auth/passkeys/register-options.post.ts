@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/passkeys/register-options
+ * Proxies to backend POST /api/v2/auth/passkeys/register/options.
+ * Requires authentication. Returns options for navigator.credentials.create().
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/passkeys/register/options', { includeBody: false })
+  setResponseStatus(event, status)
+  return data
+})
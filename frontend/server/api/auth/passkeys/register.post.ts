@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/passkeys/register
+ * Proxies to backend POST /api/v2/auth/passkeys/register.
+ * Requires authentication and the current password. Stores the new passkey from the authenticator's response.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/passkeys/register')
+  setResponseStatus(event, status)
+  return data
+})
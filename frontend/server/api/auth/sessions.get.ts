@@ -0,0 +1,17 @@
+import { defineEventHandler, getQuery, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * GET /api/auth/sessions
+ * Proxies to backend GET /api/v2/auth/sessions.
+ * Requires authentication. Admins may pass ?user_id= to list another user's sessions.
+ */
+export default defineEventHandler(async (event) => {
+  const { user_id: userId } = getQuery(event)
+  const path = typeof userId === 'string' && userId
+    ? `/sessions?user_id=${encodeURIComponent(userId)}`
+    : '/sessions'
+  const { status, data } = await proxyAuthRequest(event, path, { method: 'GET' })
+  setResponseStatus(event, status)
+  return data
+})
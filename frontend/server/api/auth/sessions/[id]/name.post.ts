@@ -0,0 +1,14 @@
+import { defineEventHandler, getRouterParam, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/sessions/:id/name
+ * Proxies to backend POST /api/v2/auth/sessions/:id/name.
+ * Requires authentication (access_token cookie).
+ */
+export default defineEventHandler(async (event) => {
+  const id = encodeURIComponent(getRouterParam(event, 'id') ?? '')
+  const { status, data } = await proxyAuthRequest(event, `/sessions/${id}/name`)
+  setResponseStatus(event, status)
+  return data
+})
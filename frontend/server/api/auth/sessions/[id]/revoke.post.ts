@@ -0,0 +1,14 @@
+import { defineEventHandler, getRouterParam, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/sessions/:id/revoke
+ * Proxies to backend POST /api/v2/auth/sessions/:id/revoke.
+ * Requires authentication. Admins may revoke other users' sessions.
+ */
+export default defineEventHandler(async (event) => {
+  const id = encodeURIComponent(getRouterParam(event, 'id') ?? '')
+  const { status, data } = await proxyAuthRequest(event, `/sessions/${id}/revoke`, { includeBody: false })
+  setResponseStatus(event, status)
+  return data
+})
@@ -0,0 +1,17 @@
+import { defineEventHandler, getQuery, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * GET /api/auth/tokens
+ * Proxies to backend GET /api/v2/auth/tokens.
+ * Requires authentication. Admins may pass ?user_id= to list another user's tokens.
+ */
+export default defineEventHandler(async (event) => {
+  const { user_id: userId } = getQuery(event)
+  const path = typeof userId === 'string' && userId
+    ? `/tokens?user_id=${encodeURIComponent(userId)}`
+    : '/tokens'
+  const { status, data } = await proxyAuthRequest(event, path, { method: 'GET' })
+  setResponseStatus(event, status)
+  return data
+})
@@ -0,0 +1,13 @@
+import { defineEventHandler, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/tokens
+ * Proxies to backend POST /api/v2/auth/tokens.
+ * Requires authentication (access_token cookie). The response holds the new token.
+ */
+export default defineEventHandler(async (event) => {
+  const { status, data } = await proxyAuthRequest(event, '/tokens')
+  setResponseStatus(event, status)
+  return data
+})
@@ -0,0 +1,14 @@
+import { defineEventHandler, getRouterParam, setResponseStatus } from 'h3'
+import { proxyAuthRequest } from '~/server/utils/authProxy'
+
+/**
+ * POST /api/auth/tokens/:id/revoke
+ * Proxies to backend POST /api/v2/auth/tokens/:id/revoke.
+ * Requires authentication. Admins may revoke other users' tokens.
+ */
+export default defineEventHandler(async (event) => {
+  const id = encodeURIComponent(getRouterParam(event, 'id') ?? '')
+  const { status, data } = await proxyAuthRequest(event, `/tokens/${id}/revoke`, { includeBody: false })
+  setResponseStatus(event, status)
+  return data
+})
@@ -9,7 +9,7 @@ import { readBody, appendResponseHeader, getHeader } from 'h3'
 export async function proxyAuthRequest (
   event: H3Event,
   path: string,
-  options?: { includeBody?: boolean },
+  options?: { includeBody?: boolean; method?: 'GET' | 'POST' },
 ): Promise<{ status: number; data: unknown }> {
   const config = useRuntimeConfig()
   const backendUrl = `${config.internalApiHost}/api/v2/auth${path}`
@@ -38,11 +38,11 @@ export async function proxyAuthRequest (
   }
 
   const fetchOptions: RequestInit = {
-    method: 'POST',
+    method: options?.method ?? 'POST',
     headers,
   }
 
-  if (options?.includeBody !== false) {
+  if (options?.includeBody !== false && fetchOptions.method !== 'GET') {
     const body = await readBody(event)
     if (body) {
       fetchOptions.body = JSON.stringify(body)
@@ -28,6 +28,7 @@ export const useSiteStore = defineStore('site', () => {
   const boardCreationAdminOnly = computed(() => site.value?.boardCreationAdminOnly ?? false)
   const requireEmailVerification = computed(() => site.value?.requireEmailVerification ?? false)
   const isPrivate = computed(() => site.value?.isPrivate ?? false)
+  const magicLinkLoginEnabled = computed(() => site.value?.magicLinkLoginEnabled ?? false)
 
   // Theme colors
   const primaryColor = computed(() => site.value?.primaryColor ?? null)
@@ -63,6 +64,7 @@ export const useSiteStore = defineStore('site', () => {
     boardCreationAdminOnly,
     requireEmailVerification,
     isPrivate,
+    magicLinkLoginEnabled,
     primaryColor,
     secondaryColor,
     hoverColor,
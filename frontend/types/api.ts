@@ -29,6 +29,7 @@ export interface PaginatedResult<T> {
 export type LoginInput = {
   usernameOrEmail: string
   password: string
+  captcha?: CaptchaSolution
 }
 
 export type RegisterInput = {
@@ -38,6 +39,36 @@ export type RegisterInput = {
   email?: string
   inviteCode?: string
   applicationAnswer?: string
+  captcha?: CaptchaSolution
+}
+
+export type CaptchaChallenge = {
+  id: string
+  algorithm: 'sha256'
+  salt: string
+  difficulty: number
+  expires_at: string
+}
+
+export type CaptchaResponse = {
+  enabled: boolean
+  challenge?: CaptchaChallenge
+}
+
+export type CaptchaSolution = {
+  id: string
+  nonce: string
+}
+
+export type OidcProvider = {
+  name: string
+  display_name: string
+  /** Navigate here (not fetch) to start logging in */
+  authorize_url: string
+}
+
+export type OidcProvidersResponse = {
+  providers: OidcProvider[]
 }
 
 export type AuthRestResponse = {
@@ -49,6 +80,33 @@ export type AuthRestResponse = {
     is_admin: boolean
     admin_level: number
   } | null
+  /** Set when the password was right but a second factor is needed */
+  mfa_required?: boolean
+  /** The account must set up two-factor authentication before logging in */
+  enrollment_required?: boolean
+  /** The account has a passkey it can use as the second factor */
+  passkey?: boolean
+  mfa_token?: string
+}
+
+export type TwoFactorStatus = {
+  enabled: boolean
+  required: boolean
+  recovery_codes_remaining: number
+  /** Passkeys also count as a second factor */
+  passkeys: number
+}
+
+export type TwoFactorSetupResponse = {
+  success: boolean
+  secret: string
+  otpauth_uri: string
+}
+
+export type RecoveryCodesResponse = {
+  success: boolean
+  recovery_codes: string[]
+  user?: AuthRestResponse['user']
 }
 
 export type RegisterRestResponse = {
@@ -63,3 +121,57 @@ export type RegisterRestResponse = {
   } | null
   message?: string | null
 }
+
+export type AuthSessionInfo = {
+  id: string
+  name: string | null
+  /** e.g. "Firefox 128 on Windows" */
+  device: string
+  browser: string | null
+  os: string | null
+  device_type: 'desktop' | 'mobile' | 'tablet' | 'bot' | 'unknown'
+  ip_address: string | null
+  created_at: string
+  last_seen_at: string
+  expires_at: string
+  /** The session making the request */
+  current: boolean
+}
+
+export type ApiTokenScope = 'read' | 'post' | 'moderate' | 'admin'
+
+export type ApiTokenInfo = {
+  id: string
+  name: string
+  /** Start of the token, e.g. "tbp_1a2b3c4d" */
+  token_prefix: string
+  scope: ApiTokenScope
+  /** Created on a bot account */
+  bot: boolean
+  created_at: string
+  last_used_at: string | null
+  expires_at: string | null
+}
+
+export type CreatedApiTokenResponse = {
+  /** The full token; only returned once */
+  token: string
+  api_token: ApiTokenInfo
+}
+
+export type PasskeyInfo = {
+  id: string
+  name: string
+  created_at: string
+  last_used_at: string | null
+}
+
+export type PendingEmailChange = {
+  new_email: string
+  created_at: string
+  expires_at: string
+}
+
+export type EmailChangeStatusResponse = {
+  pending: PendingEmailChange | null
+}
@@ -341,6 +341,20 @@ export type EmojiScope =
   | 'Board'
   | 'Site';
 
+export type FileUpload = {
+  __typename?: 'FileUpload';
+  createdAt: Scalars['String']['output'];
+  durationSeconds?: Maybe<Scalars['Float']['output']>;
+  height?: Maybe<Scalars['Int']['output']>;
+  id: Scalars['ID']['output'];
+  optimizedUrl?: Maybe<Scalars['String']['output']>;
+  processingStatus: UploadProcessingStatus;
+  sizeBytes: Scalars['Int']['output'];
+  thumbnailUrl?: Maybe<Scalars['String']['output']>;
+  url: Scalars['String']['output'];
+  width?: Maybe<Scalars['Int']['output']>;
+};
+
 export type FilterMode =
   | 'exclude'
   | 'include';
@@ -480,6 +494,7 @@ export type LocalSite = {
   isPrivate: Scalars['Boolean']['output'];
   isSiteSetup: Scalars['Boolean']['output'];
   legalInformation?: Maybe<Scalars['String']['output']>;
+  magicLinkLoginEnabled: Scalars['Boolean']['output'];
   name: Scalars['String']['output'];
   primaryColor: Scalars['String']['output'];
   registrationMode: Scalars['String']['output'];
@@ -616,7 +631,7 @@ export type Mutation = {
   updateSettings: UserSettings;
   updateSiteConfig: LocalSite;
   updateUserBoardCreationApproval: User;
-  uploadFile: Scalars['String']['output'];
+  uploadFile: FileUpload;
   voteOnComment: Comment;
   voteOnPost: Post;
 };
@@ -1778,6 +1793,7 @@ export type UpdateSettingsInput = {
   editorMode?: InputMaybe<Scalars['String']['input']>;
   email?: InputMaybe<Scalars['String']['input']>;
   interfaceLanguage?: InputMaybe<Scalars['String']['input']>;
+  isBotAccount?: InputMaybe<Scalars['Boolean']['input']>;
   isEmailNotificationsEnabled?: InputMaybe<Scalars['Boolean']['input']>;
   showBots?: InputMaybe<Scalars['Boolean']['input']>;
   showNsfw?: InputMaybe<Scalars['Boolean']['input']>;
@@ -1811,6 +1827,7 @@ export type UpdateSiteConfigInput = {
   isPrivate?: InputMaybe<Scalars['Boolean']['input']>;
   legalInformation?: InputMaybe<Scalars['String']['input']>;
   linkFilterEnabled?: InputMaybe<Scalars['Boolean']['input']>;
+  magicLinkLoginEnabled?: InputMaybe<Scalars['Boolean']['input']>;
   name?: InputMaybe<Scalars['String']['input']>;
   primaryColor?: InputMaybe<Scalars['String']['input']>;
   registrationMode?: InputMaybe<Scalars['String']['input']>;
@@ -1825,6 +1842,12 @@ export type UpdateSiteConfigInput = {
   wordFilterEnabled?: InputMaybe<Scalars['Boolean']['input']>;
 };
 
+export type UploadProcessingStatus =
+  | 'DONE'
+  | 'FAILED'
+  | 'PENDING'
+  | 'PROCESSING';
+
 export type User = {
   __typename?: 'User';
   adminLevel: Scalars['Int']['output'];
@@ -1876,6 +1899,7 @@ export type UserSettings = {
   email?: Maybe<Scalars['String']['output']>;
   id: Scalars['ID']['output'];
   interfaceLanguage: Scalars['String']['output'];
+  isBotAccount: Scalars['Boolean']['output'];
   isEmailNotificationsEnabled: Scalars['Boolean']['output'];
   isEmailVerified: Scalars['Boolean']['output'];
   name: Scalars['String']['output'];
@@ -0,0 +1,95 @@
+/**
+ * Helpers for passkeys (WebAuthn). The server sends ceremony options and
+ * expects credentials back with binary fields base64url-encoded; the
+ * browser API works with ArrayBuffers.
+ */
+
+type CredentialDescriptorJSON = { type: 'public-key'; id: string; transports?: string[] }
+
+export type CreationOptionsJSON = Omit<PublicKeyCredentialCreationOptions, 'challenge' | 'user' | 'excludeCredentials'> & {
+  challenge: string
+  user: { id: string; name: string; displayName: string }
+  excludeCredentials?: CredentialDescriptorJSON[]
+}
+
+export type RequestOptionsJSON = Omit<PublicKeyCredentialRequestOptions, 'challenge' | 'allowCredentials'> & {
+  challenge: string
+  allowCredentials?: CredentialDescriptorJSON[]
+}
+
+export function base64urlToBuffer (value: string): ArrayBuffer {
+  const base64 = value.replace(/-/g, '+').replace(/_/g, '/')
+  const binary = atob(base64.padEnd(Math.ceil(base64.length / 4) * 4, '='))
+  const bytes = new Uint8Array(binary.length)
+  for (let i = 0; i < binary.length; i++) {
+    bytes[i] = binary.charCodeAt(i)
+  }
+  return bytes.buffer
+}
+
+export function bufferToBase64url (buffer: ArrayBuffer): string {
+  let binary = ''
+  for (const byte of new Uint8Array(buffer)) {
+    binary += String.fromCharCode(byte)
+  }
+  return btoa(binary).replace(/\+/g, '-').replace(/\//g, '_').replace(/=+$/, '')
+}
+
+/** Whether this browser can use passkeys at all. */
+export function passkeysSupported (): boolean {
+  return import.meta.client && typeof window.PublicKeyCredential === 'function'
+}
+
+function toDescriptors (list?: CredentialDescriptorJSON[]): PublicKeyCredentialDescriptor[] | undefined {
+  return list?.map(c => ({ ...c, id: base64urlToBuffer(c.id), transports: c.transports as AuthenticatorTransport[] }))
+}
+
+/** Run `navigator.credentials.create()` and encode the result for the server. */
+export async function createPasskey (options: CreationOptionsJSON): Promise<Record<string, unknown>> {
+  const credential = await navigator.credentials.create({
+    publicKey: {
+      ...options,
+      challenge: base64urlToBuffer(options.challenge),
+      user: { ...options.user, id: base64urlToBuffer(options.user.id) },
+      excludeCredentials: toDescriptors(options.excludeCredentials),
+    },
+  }) as PublicKeyCredential | null
+  if (!credential) {
+    throw new Error('No passkey was created')
+  }
+
+  const response = credential.response as AuthenticatorAttestationResponse
+  return {
+    rawId: bufferToBase64url(credential.rawId),
+    response: {
+      clientDataJSON: bufferToBase64url(response.clientDataJSON),
+      attestationObject: bufferToBase64url(response.attestationObject),
+      transports: response.getTransports?.() ?? [],
+    },
+  }
+}
+
+/** Run `navigator.credentials.get()` and encode the result for the server. */
+export async function getPasskey (options: RequestOptionsJSON): Promise<Record<string, unknown>> {
+  const credential = await navigator.credentials.get({
+    publicKey: {
+      ...options,
+      challenge: base64urlToBuffer(options.challenge),
+      allowCredentials: toDescriptors(options.allowCredentials),
+    },
+  }) as PublicKeyCredential | null
+  if (!credential) {
+    throw new Error('No passkey was used')
+  }
+
+  const response = credential.response as AuthenticatorAssertionResponse
+  return {
+    rawId: bufferToBase64url(credential.rawId),
+    response: {
+      clientDataJSON: bufferToBase64url(response.clientDataJSON),
+      authenticatorData: bufferToBase64url(response.authenticatorData),
+      signature: bufferToBase64url(response.signature),
+      userHandle: response.userHandle ? bufferToBase64url(response.userHandle) : null,
+    },
+  }
+}
@@ -0,0 +1,3 @@
+ALTER TABLE rate_limits
+    DROP COLUMN login_per_second,
+    DROP COLUMN login;
@@ -0,0 +1,4 @@
+-- Separate bucket for login attempts, which previously had no limit
+ALTER TABLE rate_limits
+    ADD COLUMN login            INT NOT NULL DEFAULT 10,
+    ADD COLUMN login_per_second INT NOT NULL DEFAULT 300;
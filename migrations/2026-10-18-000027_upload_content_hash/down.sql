@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_uploads_file_path;
+DROP INDEX IF EXISTS idx_uploads_content_hash;
+
+ALTER TABLE uploads DROP COLUMN content_hash;
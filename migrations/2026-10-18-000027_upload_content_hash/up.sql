@@ -0,0 +1,6 @@
+-- Uploads are stored under the SHA-256 of their (processed) bytes, so identical
+-- files share one blob. Each uploads row is a reference to it.
+ALTER TABLE uploads ADD COLUMN content_hash VARCHAR(64);
+
+CREATE INDEX idx_uploads_content_hash ON uploads (content_hash) WHERE content_hash IS NOT NULL;
+CREATE INDEX idx_uploads_file_path ON uploads (file_path);
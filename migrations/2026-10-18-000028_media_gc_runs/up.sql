@@ -0,0 +1,15 @@
+-- Results of the scheduled media garbage collection, shown to admins
+CREATE TABLE media_gc_runs (
+    id                          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    started_at                  TIMESTAMPTZ NOT NULL,
+    finished_at                 TIMESTAMPTZ NOT NULL DEFAULT now(),
+    orphaned_uploads_deleted    INT NOT NULL DEFAULT 0,
+    orphaned_files_deleted      INT NOT NULL DEFAULT 0,
+    bytes_freed                 BIGINT NOT NULL DEFAULT 0,
+    missing_files               INT NOT NULL DEFAULT 0,
+    total_files                 BIGINT NOT NULL DEFAULT 0,
+    total_bytes                 BIGINT NOT NULL DEFAULT 0,
+    error                       TEXT
+);
+
+CREATE INDEX idx_media_gc_runs_started ON media_gc_runs (started_at DESC);
@@ -0,0 +1,32 @@
+-- Copies of media between storage backends (`tinyboards_server migrate-storage`).
+-- Per-object rows make an interrupted migration resumable.
+CREATE TABLE storage_migrations (
+    id                  UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source_backend      VARCHAR(20) NOT NULL,
+    target_backend      VARCHAR(20) NOT NULL,
+    -- running, completed or failed
+    status              VARCHAR(20) NOT NULL DEFAULT 'running',
+    total_objects       INT NOT NULL DEFAULT 0,
+    copied_objects      INT NOT NULL DEFAULT 0,
+    missing_objects     INT NOT NULL DEFAULT 0,
+    failed_objects      INT NOT NULL DEFAULT 0,
+    bytes_copied        BIGINT NOT NULL DEFAULT 0,
+    error               TEXT,
+    started_at          TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT now(),
+    finished_at         TIMESTAMPTZ
+);
+
+CREATE INDEX idx_storage_migrations_backends ON storage_migrations (source_backend, target_backend, started_at DESC);
+
+CREATE TABLE storage_migration_objects (
+    migration_id    UUID NOT NULL REFERENCES storage_migrations(id) ON DELETE CASCADE,
+    key             TEXT NOT NULL,
+    -- copied, missing (not in the source) or failed
+    status          VARCHAR(20) NOT NULL,
+    size_bytes      BIGINT NOT NULL DEFAULT 0,
+    checksum        VARCHAR(64),
+    error           TEXT,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (migration_id, key)
+);
@@ -0,0 +1,10 @@
+DROP TABLE IF EXISTS media_processing_jobs;
+
+DROP INDEX IF EXISTS idx_uploads_processing_status;
+ALTER TABLE uploads DROP CONSTRAINT IF EXISTS uploads_processing_status_check;
+UPDATE uploads SET processing_status = 'complete' WHERE processing_status IN ('done', 'processing');
+ALTER TABLE uploads ALTER COLUMN processing_status SET DEFAULT 'pending';
+ALTER TABLE uploads ADD CONSTRAINT uploads_processing_status_check
+    CHECK (processing_status IN ('pending', 'complete', 'failed'));
+CREATE INDEX idx_uploads_processing_status ON uploads (processing_status)
+    WHERE processing_status = 'pending';
@@ -0,0 +1,30 @@
+-- Uploads are now optimized by a background worker, so processing_status
+-- gains a 'processing' state and 'complete' becomes 'done'. Nothing ever
+-- processed the rows left 'pending' by the old default, so close them out.
+ALTER TABLE uploads DROP CONSTRAINT IF EXISTS uploads_processing_status_check;
+UPDATE uploads SET processing_status = 'done' WHERE processing_status IN ('complete', 'pending');
+ALTER TABLE uploads ALTER COLUMN processing_status SET DEFAULT 'done';
+ALTER TABLE uploads ADD CONSTRAINT uploads_processing_status_check
+    CHECK (processing_status IN ('pending', 'processing', 'done', 'failed'));
+
+DROP INDEX IF EXISTS idx_uploads_processing_status;
+CREATE INDEX idx_uploads_processing_status ON uploads (processing_status)
+    WHERE processing_status IN ('pending', 'processing');
+
+-- Durable queue for the upload processing worker. A job is deleted once its
+-- upload is done; failed jobs are kept with the last error.
+CREATE TABLE media_processing_jobs (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    upload_id   UUID NOT NULL UNIQUE REFERENCES uploads(id) ON DELETE CASCADE,
+    status      VARCHAR(20) NOT NULL DEFAULT 'pending'
+                CHECK (status IN ('pending', 'processing', 'failed')),
+    attempts    INT NOT NULL DEFAULT 0,
+    last_error  TEXT,
+    run_at      TIMESTAMPTZ NOT NULL DEFAULT now(),
+    locked_at   TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_media_processing_jobs_due ON media_processing_jobs (run_at)
+    WHERE status = 'pending';
@@ -0,0 +1,4 @@
+ALTER TABLE uploads
+    DROP COLUMN IF EXISTS duration_seconds,
+    DROP COLUMN IF EXISTS height,
+    DROP COLUMN IF EXISTS width;
@@ -0,0 +1,7 @@
+-- Dimensions of the stored image or video and, for video and animations,
+-- the running time. Filled in when the upload is processed; NULL for
+-- uploads made earlier and for files that aren't media.
+ALTER TABLE uploads
+    ADD COLUMN width INT,
+    ADD COLUMN height INT,
+    ADD COLUMN duration_seconds DOUBLE PRECISION;
@@ -0,0 +1,6 @@
+ALTER TABLE site
+    DROP COLUMN IF EXISTS require_two_factor_for_mods,
+    DROP COLUMN IF EXISTS require_two_factor_for_admins;
+
+DROP TABLE IF EXISTS user_recovery_codes;
+DROP TABLE IF EXISTS user_totp;
@@ -0,0 +1,26 @@
+-- TOTP two-factor authentication. A row with enabled_at NULL is an
+-- enrollment that hasn't been confirmed with a first code yet.
+CREATE TABLE user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    enabled_at TIMESTAMPTZ,
+    -- Time step of the last accepted code, so codes can't be replayed
+    last_used_step BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- One-time recovery codes, stored as SHA-256 hashes.
+CREATE TABLE user_recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_user_recovery_codes_unused ON user_recovery_codes (user_id, code_hash)
+    WHERE used_at IS NULL;
+
+ALTER TABLE site
+    ADD COLUMN require_two_factor_for_admins BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN require_two_factor_for_mods BOOLEAN NOT NULL DEFAULT false;
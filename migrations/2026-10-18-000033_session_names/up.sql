@@ -0,0 +1,2 @@
+-- Optional label a user gives a session ("Work laptop") in the session list.
+ALTER TABLE auth_sessions ADD COLUMN name TEXT;
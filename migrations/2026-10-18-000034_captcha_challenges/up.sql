@@ -0,0 +1,12 @@
+-- Self-hosted proof-of-work captcha. Each challenge is single use: it is
+-- deleted when a solution is checked, whether or not the solution is right.
+CREATE TABLE captcha_challenges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    salt TEXT NOT NULL,
+    -- Required number of leading zero bits in SHA-256(salt || nonce)
+    difficulty INTEGER NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_captcha_challenges_expires_at ON captcha_challenges (expires_at);
@@ -0,0 +1,4 @@
+ALTER TABLE notifications DROP COLUMN IF EXISTS body;
+
+DROP TABLE IF EXISTS account_lockouts;
+DROP TABLE IF EXISTS login_failures;
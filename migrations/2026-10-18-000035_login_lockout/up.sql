@@ -0,0 +1,29 @@
+-- Failed password logins, kept for a day for per-IP backoff and the admin
+-- "hot IPs" view. `username` is what was typed, so attempts against
+-- accounts that don't exist are tracked too.
+CREATE TABLE login_failures (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ip_address TEXT NOT NULL,
+    username TEXT NOT NULL,
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_login_failures_ip ON login_failures (ip_address, created_at);
+CREATE INDEX idx_login_failures_created_at ON login_failures (created_at);
+
+-- Consecutive failed logins per account. The row is removed on a
+-- successful login.
+CREATE TABLE account_lockouts (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMPTZ,
+    last_failed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_account_lockouts_locked_until ON account_lockouts (locked_until)
+    WHERE locked_until IS NOT NULL;
+
+-- Text for system notifications, which don't point at a post, comment or
+-- message.
+ALTER TABLE notifications ADD COLUMN body TEXT;
@@ -0,0 +1,19 @@
+-- Personal access tokens, sent as `Authorization: Bearer tbp_...`. Only the
+-- SHA-256 of the token is stored; `token_prefix` keeps the first few
+-- characters so users can tell their tokens apart. Each scope includes the
+-- ones before it (read < post < moderate < admin). `is_bot` records whether
+-- the account was a bot account when the token was created.
+CREATE TABLE api_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    token_prefix TEXT NOT NULL,
+    scope TEXT NOT NULL CHECK (scope IN ('read', 'post', 'moderate', 'admin')),
+    is_bot BOOLEAN NOT NULL DEFAULT false,
+    last_used_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_api_tokens_user_id ON api_tokens (user_id);
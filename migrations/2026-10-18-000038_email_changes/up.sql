@@ -0,0 +1,14 @@
+-- Pending email address changes. The new address gets a confirmation link
+-- and the old one a cancel link; `users.email` only changes once the new
+-- address is confirmed. Only the SHA-256 of each token is stored, and a
+-- user has at most one pending change.
+CREATE TABLE email_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    old_email TEXT,
+    new_email TEXT NOT NULL,
+    confirm_token TEXT NOT NULL UNIQUE,
+    cancel_token TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL DEFAULT (now() + INTERVAL '24 hours'),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
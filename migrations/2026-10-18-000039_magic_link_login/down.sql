@@ -0,0 +1,8 @@
+ALTER TABLE rate_limits
+    DROP COLUMN IF EXISTS magic_link_per_second,
+    DROP COLUMN IF EXISTS magic_link;
+
+ALTER TABLE site
+    DROP COLUMN IF EXISTS magic_link_login_enabled;
+
+DROP TABLE IF EXISTS magic_links;
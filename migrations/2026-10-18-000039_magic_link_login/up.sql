@@ -0,0 +1,20 @@
+-- One-time login links for passwordless login. Like password_resets, only
+-- the SHA-256 of the token is stored.
+CREATE TABLE magic_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL DEFAULT (now() + INTERVAL '15 minutes'),
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_magic_links_user_id ON magic_links (user_id);
+
+ALTER TABLE site
+    ADD COLUMN magic_link_login_enabled BOOLEAN NOT NULL DEFAULT false;
+
+-- Separate bucket for login link requests, since each one sends an email
+ALTER TABLE rate_limits
+    ADD COLUMN magic_link            INT NOT NULL DEFAULT 3,
+    ADD COLUMN magic_link_per_second INT NOT NULL DEFAULT 900;
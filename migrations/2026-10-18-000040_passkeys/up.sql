@@ -0,0 +1,27 @@
+-- WebAuthn credentials (passkeys and security keys). `credential_id` is
+-- base64url and `public_key` is the COSE_Key the authenticator registered.
+-- `sign_count` is the authenticator's signature counter, which has to go
+-- up with every use unless the authenticator doesn't keep one.
+CREATE TABLE webauthn_credentials (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    credential_id TEXT NOT NULL UNIQUE,
+    public_key BYTEA NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    name TEXT NOT NULL,
+    transports TEXT[] NOT NULL DEFAULT '{}',
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_webauthn_credentials_user_id ON webauthn_credentials (user_id);
+
+-- Challenges for ceremonies in progress. Each can be used once. `user_id`
+-- is NULL for passwordless logins, where the passkey picks the account.
+CREATE TABLE webauthn_challenges (
+    challenge TEXT PRIMARY KEY,
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL CHECK (purpose IN ('register', 'login', 'second_factor')),
+    expires_at TIMESTAMPTZ NOT NULL DEFAULT now() + INTERVAL '5 minutes',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
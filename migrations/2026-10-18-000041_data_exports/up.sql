@@ -0,0 +1,19 @@
+-- Account data exports. A pending export is being built; a ready one has
+-- an archive under `exports/` in storage until `expires_at`, after which the
+-- archive and the row are deleted.
+CREATE TABLE data_exports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'ready', 'failed')),
+    storage_key TEXT,
+    size_bytes BIGINT,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_data_exports_user_id ON data_exports (user_id, created_at DESC);
+
+-- At most one export per account is built at a time
+CREATE UNIQUE INDEX idx_data_exports_one_pending ON data_exports (user_id) WHERE status = 'pending';
@@ -0,0 +1,2 @@
+-- PostgreSQL does not support removing enum values; they are left in place.
+DROP TABLE automod_rules;
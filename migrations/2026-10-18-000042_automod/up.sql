@@ -0,0 +1,25 @@
+-- AutoModerator rules. `board_id` is NULL for site-wide rules, which run
+-- before a board's own. `conditions` and `actions` are the JSON forms of
+-- `AutomodConditions` and `AutomodActions` in tinyboards_utils::automod.
+CREATE TABLE automod_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    board_id UUID REFERENCES boards(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    conditions JSONB NOT NULL DEFAULT '{}',
+    actions JSONB NOT NULL DEFAULT '{}',
+    is_enabled BOOLEAN NOT NULL DEFAULT true,
+    display_order INT NOT NULL DEFAULT 0,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+SELECT add_updated_at_trigger('automod_rules');
+
+CREATE INDEX idx_automod_rules_board_id ON automod_rules (board_id, display_order);
+
+-- Actions AutoModerator takes that moderators had no log entry for
+ALTER TYPE moderation_action ADD VALUE 'filter_post';
+ALTER TYPE moderation_action ADD VALUE 'flair_post';
+ALTER TYPE moderation_action ADD VALUE 'comment_post';
+ALTER TYPE moderation_action ADD VALUE 'report_post';
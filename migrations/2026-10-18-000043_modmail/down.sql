@@ -0,0 +1,2 @@
+ALTER TABLE private_messages DROP COLUMN is_mod_note, DROP COLUMN thread_id;
+DROP TABLE modmail_threads;
@@ -0,0 +1,29 @@
+-- Modmail: conversations between a user and a board's moderation team.
+-- Messages live in `private_messages` with `recipient_board_id` set and
+-- `thread_id` pointing here. Replies from moderators are sent as the board
+-- (`is_sender_hidden`), and mod notes are only shown to moderators.
+CREATE TABLE modmail_threads (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    subject VARCHAR(200) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'new' CHECK (status IN ('new', 'in_progress', 'archived')),
+    assigned_to UUID REFERENCES users(id) ON DELETE SET NULL,
+    is_read_by_user BOOLEAN NOT NULL DEFAULT true,
+    is_read_by_mods BOOLEAN NOT NULL DEFAULT false,
+    last_message_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+SELECT add_updated_at_trigger('modmail_threads');
+
+CREATE INDEX idx_modmail_threads_board ON modmail_threads (board_id, status, last_message_at DESC);
+CREATE INDEX idx_modmail_threads_user ON modmail_threads (user_id, last_message_at DESC);
+
+ALTER TABLE private_messages
+    ADD COLUMN thread_id UUID REFERENCES modmail_threads(id) ON DELETE CASCADE,
+    ADD COLUMN is_mod_note BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX idx_private_messages_thread ON private_messages (thread_id, created_at)
+    WHERE thread_id IS NOT NULL;
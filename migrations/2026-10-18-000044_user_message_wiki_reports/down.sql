@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS wiki_page_reports CASCADE;
+DROP TABLE IF EXISTS message_reports CASCADE;
+DROP TABLE IF EXISTS user_reports CASCADE;
@@ -0,0 +1,71 @@
+-- Reports for user profiles, private messages and wiki pages. Like post and
+-- comment reports, each keeps a snapshot of what was reported so edits or
+-- deletions don't change what the moderators review.
+
+-- ============================================================
+-- user_reports (handled by site admins)
+-- ============================================================
+
+CREATE TABLE user_reports (
+    id                      UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    creator_id              UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_id                 UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    original_name           VARCHAR(30) NOT NULL,
+    original_display_name   VARCHAR(30),
+    original_bio            TEXT,
+    original_avatar         TEXT,
+    original_signature      TEXT,
+    reason                  TEXT NOT NULL,
+    status                  report_status NOT NULL DEFAULT 'pending',
+    resolver_id             UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+SELECT add_updated_at_trigger('user_reports');
+
+CREATE INDEX idx_user_reports_user ON user_reports (user_id);
+CREATE INDEX idx_user_reports_status ON user_reports (status) WHERE status = 'pending';
+
+-- ============================================================
+-- message_reports (handled by site admins)
+-- ============================================================
+
+CREATE TABLE message_reports (
+    id                      UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    creator_id              UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message_id              UUID NOT NULL REFERENCES private_messages(id) ON DELETE CASCADE,
+    original_message_text   TEXT NOT NULL,
+    reason                  TEXT NOT NULL,
+    status                  report_status NOT NULL DEFAULT 'pending',
+    resolver_id             UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+SELECT add_updated_at_trigger('message_reports');
+
+CREATE INDEX idx_message_reports_message ON message_reports (message_id);
+CREATE INDEX idx_message_reports_status ON message_reports (status) WHERE status = 'pending';
+
+-- ============================================================
+-- wiki_page_reports (handled by the board's moderators)
+-- ============================================================
+
+CREATE TABLE wiki_page_reports (
+    id                  UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    creator_id          UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    page_id             UUID NOT NULL REFERENCES wiki_pages(id) ON DELETE CASCADE,
+    original_page_title VARCHAR(200) NOT NULL,
+    original_page_body  TEXT NOT NULL,
+    reason              TEXT NOT NULL,
+    status              report_status NOT NULL DEFAULT 'pending',
+    resolver_id         UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+SELECT add_updated_at_trigger('wiki_page_reports');
+
+CREATE INDEX idx_wiki_page_reports_page ON wiki_page_reports (page_id);
+CREATE INDEX idx_wiki_page_reports_status ON wiki_page_reports (status) WHERE status = 'pending';
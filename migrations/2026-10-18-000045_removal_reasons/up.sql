@@ -0,0 +1,18 @@
+-- Predefined removal reasons a board's moderators pick from when removing a
+-- post or comment. `message` is markdown with {author}, {board} and {rule}
+-- placeholders; `rule` is the board rule the reason enforces, if any.
+CREATE TABLE removal_reasons (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    title VARCHAR(100) NOT NULL,
+    message TEXT NOT NULL,
+    rule VARCHAR(200),
+    display_order INT NOT NULL DEFAULT 0,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+SELECT add_updated_at_trigger('removal_reasons');
+
+CREATE INDEX idx_removal_reasons_board_id ON removal_reasons (board_id, display_order);
@@ -0,0 +1,12 @@
+-- Wrong codes entered with each MFA token (the token issued after a correct
+-- password on an account with two-factor authentication). A token is refused
+-- after a few, so a password only buys a handful of guesses at a time. Rows
+-- are kept until the token would have expired anyway.
+CREATE TABLE mfa_token_failures (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    failures INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_mfa_token_failures_expires_at ON mfa_token_failures (expires_at);